@@ -0,0 +1,247 @@
+use apollo_vault::error::ContractError;
+use apollo_vault::migrations::migrate_contract;
+use astroport_vault::contract::{
+    execute_receive, execute_vault_extension, init_vault, query_vault_extension, reply_vault,
+    AstroportVault,
+};
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult,
+};
+use cw2::set_contract_version;
+use cw_vault_standard::msg::{VaultInfoResponse, VaultStandardInfoResponse};
+
+use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use crate::vault_token::TokenFactoryDenom;
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:astroport-tokenfactory-vault";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const VAULT_STANDARD_VERSION: u16 = 1;
+const VAULT_STANDARD_EXTENSIONS: [&str; 0] = [];
+
+pub type AstroportTokenFactoryVaultContract<'a> = AstroportVault<'a, TokenFactoryDenom>;
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    // Any fee of the tokenfactory module for creating the denom is paid from the
+    // funds sent with this message
+    let vault_token = TokenFactoryDenom::new(
+        env.contract.address.to_string(),
+        msg.vault_token_subdenom,
+        msg.token_factory,
+    );
+
+    init_vault(
+        deps,
+        msg.admin,
+        msg.pool,
+        msg.generator,
+        msg.astro_token,
+        msg.config,
+        vault_token,
+        None,
+    )
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    let contract = AstroportTokenFactoryVaultContract::default();
+
+    match msg {
+        ExecuteMsg::Deposit { amount, recipient } => {
+            contract.execute_deposit(deps, env, &info, amount, recipient)
+        }
+        ExecuteMsg::Redeem { recipient, amount } => {
+            contract.execute_redeem(deps, env, &info, amount, recipient)
+        }
+        ExecuteMsg::VaultExtension(msg) => execute_vault_extension(&contract, deps, env, info, msg),
+        ExecuteMsg::Receive(cw20_msg) => execute_receive(&contract, deps, env, info, cw20_msg),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    let contract = AstroportTokenFactoryVaultContract::default();
+    let base_vault = &contract.base_vault;
+    match msg {
+        QueryMsg::VaultStandardInfo {} => to_binary(&VaultStandardInfoResponse {
+            version: VAULT_STANDARD_VERSION,
+            extensions: VAULT_STANDARD_EXTENSIONS
+                .iter()
+                .map(|&s| s.into())
+                .collect(),
+        }),
+        QueryMsg::Info {} => {
+            let vault_token = base_vault.vault_token.load(deps.storage)?;
+            let base_token = base_vault.base_token.load(deps.storage)?;
+            to_binary(&VaultInfoResponse {
+                base_token: base_token.to_string(),
+                vault_token: vault_token.to_string(),
+            })
+        }
+        QueryMsg::PreviewDeposit { amount } => {
+            to_binary(&base_vault.query_simulate_deposit(deps, amount)?)
+        }
+        QueryMsg::PreviewRedeem { amount } => {
+            to_binary(&base_vault.query_simulate_withdraw(deps, amount)?)
+        }
+        QueryMsg::TotalAssets {} => to_binary(&base_vault.query_total_assets(deps)?),
+        QueryMsg::TotalVaultTokenSupply {} => {
+            to_binary(&base_vault.query_total_vault_token_supply(deps)?)
+        }
+        QueryMsg::ConvertToShares { amount } => {
+            to_binary(&base_vault.query_simulate_deposit(deps, amount)?)
+        }
+        QueryMsg::ConvertToAssets { amount } => {
+            to_binary(&base_vault.query_simulate_withdraw(deps, amount)?)
+        }
+        QueryMsg::VaultExtension(msg) => query_vault_extension(&contract, deps, env, msg),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(_deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, ContractError> {
+    reply_vault(&AstroportTokenFactoryVaultContract::default(), reply)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let contract = AstroportTokenFactoryVaultContract::default();
+    migrate_contract(
+        deps,
+        &env,
+        CONTRACT_NAME,
+        CONTRACT_VERSION,
+        contract.migration_steps(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{AssetInfo, AssetInfoUnchecked};
+    use apollo_vault::state::ConfigUnchecked;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{
+        coin, Addr, ContractResult, CosmosMsg, Decimal, SystemResult, Uint128, WasmQuery,
+    };
+    use cw_dex_router::helpers::CwDexRouterBase;
+    use cw_vault_token::VaultToken;
+    use liquidity_helper::LiquidityHelperBase;
+
+    use super::*;
+    use crate::vault_token::TokenFactory;
+
+    const PAIR: &str = "pair";
+    const LP_TOKEN: &str = "lp_token";
+
+    #[test]
+    fn test_instantiate() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        // The pair is queried when creating the pool
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { contract_addr, .. } if contract_addr == PAIR => {
+                SystemResult::Ok(ContractResult::Ok(Binary::from(
+                    br#"{
+                        "asset_infos": [
+                            {"native_token": {"denom": "inj"}},
+                            {"native_token": {"denom": "uatom"}}
+                        ],
+                        "contract_addr": "pair",
+                        "liquidity_token": "lp_token",
+                        "pair_type": {"xyk": {}}
+                    }"#
+                    .to_vec(),
+                )))
+            }
+            query => panic!("Unexpected query {:?}", query),
+        });
+
+        let msg = InstantiateMsg {
+            admin: "admin".to_string(),
+            pool: PAIR.to_string(),
+            config: ConfigUnchecked {
+                performance_fee: Decimal::percent(5),
+                treasury: "treasury".to_string(),
+                router: CwDexRouterBase("router".to_string()),
+                reward_assets: vec![],
+                reward_liquidation_target: AssetInfoUnchecked::Native("inj".to_string()),
+                force_withdraw_whitelist: vec![],
+                liquidity_helper: LiquidityHelperBase("liquidity_helper".to_string()),
+                early_exit_penalty: Decimal::zero(),
+                early_exit_penalty_decay: false,
+                early_exit_penalty_to_treasury: false,
+                auto_withdraw_keeper_fee: Decimal::zero(),
+                min_unlock_amount: Uint128::zero(),
+                max_unlocking_positions_per_owner: 0,
+                redemption_buffer_ratio: Decimal::zero(),
+                instant_redemption_fee: Decimal::zero(),
+            },
+            token_factory: TokenFactory::Injective,
+            vault_token_subdenom: "vault-token".to_string(),
+            generator: "generator".to_string(),
+            astro_token: "astro".to_string(),
+        };
+        let res = instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[coin(1_000_000_000_000_000_000, "inj")]),
+            msg,
+        )
+        .unwrap();
+
+        // The vault token is created with the tokenfactory module of the chain
+        let denom = format!("factory/{}/vault-token", env.contract.address);
+        assert_eq!(res.messages.len(), 1);
+        assert!(matches!(
+            &res.messages[0].msg,
+            CosmosMsg::Stargate { type_url, .. }
+                if type_url == "/injective.tokenfactory.v1beta1.MsgCreateDenom"
+        ));
+
+        let contract = AstroportTokenFactoryVaultContract::default();
+        let vault_token = contract.base_vault.vault_token.load(&deps.storage).unwrap();
+        assert_eq!(vault_token.to_string(), denom);
+        assert_eq!(
+            contract.base_vault.base_token.load(&deps.storage).unwrap(),
+            AssetInfo::Cw20(Addr::unchecked(LP_TOKEN))
+        );
+        assert_eq!(
+            contract.staking.load(&deps.storage).unwrap().generator_addr,
+            Addr::unchecked("generator")
+        );
+
+        // The supply of the vault token is read with the bank query
+        assert_eq!(
+            vault_token.query_total_supply(deps.as_ref()).unwrap(),
+            Uint128::zero()
+        );
+
+        let res: VaultInfoResponse =
+            cosmwasm_std::from_binary(&query(deps.as_ref(), env, QueryMsg::Info {}).unwrap())
+                .unwrap();
+        assert_eq!(
+            res,
+            VaultInfoResponse {
+                base_token: LP_TOKEN.to_string(),
+                vault_token: denom,
+            }
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use apollo_vault::msg::{ExtensionExecuteMsg, ExtensionQueryMsg};
+use apollo_vault::state::ConfigUnchecked;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::Uint128;
+use cw20::Cw20ReceiveMsg;
+use cw_vault_standard::VaultStandardQueryMsg;
+
+use crate::vault_token::TokenFactory;
+
+/// ExecuteMsg for the Astroport tokenfactory vault. Accepts the messages of the
+/// vault standard, with the same JSON encoding, as well as the `Receive` hook
+/// of the cw20 base token.
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Deposit base tokens into the vault. The cw20 base token must be
+    /// approved for the vault to transfer `amount`. Alternatively, the base
+    /// tokens can be sent to the vault with a `Receive` hook.
+    Deposit {
+        amount: Uint128,
+        recipient: Option<String>,
+    },
+    /// Redeem vault tokens for base tokens. The vault tokens must be sent with
+    /// the message.
+    Redeem {
+        recipient: Option<String>,
+        amount: Uint128,
+    },
+    /// Messages of the vault extensions.
+    VaultExtension(ExtensionExecuteMsg),
+    /// The `Receive` hook called by the cw20 base token when it is sent to the
+    /// vault. The `msg` of the `Cw20ReceiveMsg` must be an
+    /// [`apollo_vault::msg::Cw20HookMsg`].
+    Receive(Cw20ReceiveMsg),
+}
+
+pub type QueryMsg = VaultStandardQueryMsg<ExtensionQueryMsg>;
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// Address that is allowed to update config.
+    pub admin: String,
+    /// The address of the pool that this vault will autocompound.
+    pub pool: String,
+    /// Configurable parameters for the contract.
+    pub config: ConfigUnchecked,
+    /// The tokenfactory module of the chain that the native vault token is
+    /// created with.
+    pub token_factory: TokenFactory,
+    /// The subdenom that will be used for the native vault token, e.g.
+    /// the denom of the vault token will be:
+    /// "factory/{vault_contract}/{vault_token_subdenom}".
+    pub vault_token_subdenom: String,
+    /// Astroport Generator contract address for the base token
+    pub generator: String,
+    /// Astro token contract address
+    pub astro_token: String,
+}
+
+#[cw_serde]
+pub struct MigrateMsg {}
@@ -0,0 +1,403 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    attr, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, Event, MessageInfo, Response,
+    StdError, StdResult, Uint128,
+};
+use cw_vault_token::{
+    Burn, CwTokenResponse, CwTokenResult, Instantiate, Mint, Receive, VaultToken,
+};
+use osmosis_std::types::cosmos::base::v1beta1::Coin as CoinMsg;
+use osmosis_std::types::osmosis::tokenfactory::v1beta1::{MsgBurn, MsgCreateDenom, MsgMint};
+use std::fmt::Display;
+
+/// The tokenfactory module of the chain that the vault is deployed on. The
+/// modules of these chains are forks of the Osmosis tokenfactory module, which
+/// may register their messages under a different protobuf package. All of
+/// them encode `MsgCreateDenom` with `sender = 1` and `subdenom = 2`, and
+/// `MsgMint` and `MsgBurn` with `sender = 1` and `amount = 2`. Fields that
+/// were added to the messages in later versions of the modules, such as the
+/// denom metadata of Injective's `MsgCreateDenom` or the mint-to and
+/// burn-from addresses, are left unset, in which case the modules use the
+/// sender.
+#[cw_serde]
+pub enum TokenFactory {
+    /// The tokenfactory module of Osmosis.
+    Osmosis,
+    /// The tokenfactory module of Neutron, which keeps the protobuf package of
+    /// the Osmosis module, e.g. `/osmosis.tokenfactory.v1beta1.MsgMint`.
+    Neutron,
+    /// The tokenfactory module of Injective, which registers its messages
+    /// under its own package, e.g. `/injective.tokenfactory.v1beta1.MsgMint`.
+    Injective,
+}
+
+impl TokenFactory {
+    /// Returns the protobuf package of the messages of the tokenfactory module.
+    pub fn proto_package(&self) -> &str {
+        match self {
+            TokenFactory::Osmosis | TokenFactory::Neutron => "osmosis.tokenfactory.v1beta1",
+            TokenFactory::Injective => "injective.tokenfactory.v1beta1",
+        }
+    }
+
+    /// Returns a stargate message of the tokenfactory module. `msg_name` is the
+    /// name of the protobuf message, e.g. `MsgMint`.
+    fn stargate_msg(&self, msg_name: &str, value: Binary) -> CosmosMsg {
+        CosmosMsg::Stargate {
+            type_url: format!("/{}.{}", self.proto_package(), msg_name),
+            value,
+        }
+    }
+}
+
+/// Representation of a native token created using the tokenfactory module of
+/// the chain the vault is deployed on. The denom of the token will be
+/// `factory/{owner}/{subdenom}`. If this token has not yet been created, the
+/// `instantiate` function must first be called and its response included in the
+/// transaction.
+///
+/// This is the same as [`cw_vault_token::osmosis::OsmosisDenom`], except that
+/// the messages are sent to the configured [`TokenFactory`] and the total
+/// supply is read with the bank query of CosmWasm instead of a stargate query.
+#[cw_serde]
+pub struct TokenFactoryDenom {
+    /// Creator and owner of the denom. Only this address can mint and burn
+    /// tokens.
+    pub owner: String,
+    /// The subdenom of the token. All tokens created using the tokenfactory
+    /// have the format `factory/{owner}/{subdenom}`.
+    pub subdenom: String,
+    /// The tokenfactory module that the denom is created with.
+    pub token_factory: TokenFactory,
+}
+
+impl TokenFactoryDenom {
+    /// Creates a new [`TokenFactoryDenom`].
+    pub const fn new(owner: String, subdenom: String, token_factory: TokenFactory) -> Self {
+        Self {
+            owner,
+            subdenom,
+            token_factory,
+        }
+    }
+
+    fn coin_msg(&self, amount: Uint128) -> Option<CoinMsg> {
+        Some(CoinMsg {
+            denom: self.to_string(),
+            amount: amount.to_string(),
+        })
+    }
+}
+
+impl Display for TokenFactoryDenom {
+    /// Returns the full denom of the token, in the format
+    /// `factory/{owner}/{subdenom}`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "factory/{}/{}", self.owner, self.subdenom)
+    }
+}
+
+impl VaultToken for TokenFactoryDenom {
+    fn query_balance<A: Into<String>>(&self, deps: Deps, address: A) -> CwTokenResult<Uint128> {
+        Ok(deps
+            .querier
+            .query_balance(address, self.to_string())?
+            .amount)
+    }
+
+    fn query_total_supply(&self, deps: Deps) -> CwTokenResult<Uint128> {
+        Ok(deps.querier.query_supply(self.to_string())?.amount)
+    }
+}
+
+impl Mint for TokenFactoryDenom {
+    fn mint(
+        &self,
+        _deps: DepsMut,
+        env: &Env,
+        recipient: &Addr,
+        amount: Uint128,
+    ) -> CwTokenResponse {
+        let mint_msg = self.token_factory.stargate_msg(
+            "MsgMint",
+            MsgMint {
+                sender: env.contract.address.to_string(),
+                amount: self.coin_msg(amount),
+            }
+            .into(),
+        );
+
+        let event = Event::new("apollo/vaults/tokenfactory").add_attributes(vec![
+            attr("action", "mint"),
+            attr("denom", self.to_string()),
+            attr("amount", amount),
+            attr("recipient", recipient),
+        ]);
+
+        Ok(Response::new()
+            .add_message(mint_msg)
+            .add_message(BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: vec![Coin {
+                    denom: self.to_string(),
+                    amount,
+                }],
+            })
+            .add_event(event))
+    }
+}
+
+impl Burn for TokenFactoryDenom {
+    fn burn(&self, _deps: DepsMut, env: &Env, amount: Uint128) -> CwTokenResponse {
+        let burn_msg = self.token_factory.stargate_msg(
+            "MsgBurn",
+            MsgBurn {
+                sender: env.contract.address.to_string(),
+                amount: self.coin_msg(amount),
+            }
+            .into(),
+        );
+
+        let event = Event::new("apollo/vaults/tokenfactory").add_attributes(vec![
+            attr("action", "burn"),
+            attr("denom", self.to_string()),
+            attr("amount", amount),
+        ]);
+
+        Ok(Response::new().add_message(burn_msg).add_event(event))
+    }
+}
+
+impl Instantiate for TokenFactoryDenom {
+    fn instantiate(&self, _deps: DepsMut, _init_info: Option<Binary>) -> CwTokenResponse {
+        let init_msg = self.token_factory.stargate_msg(
+            "MsgCreateDenom",
+            MsgCreateDenom {
+                sender: self.owner.clone(),
+                subdenom: self.subdenom.clone(),
+            }
+            .into(),
+        );
+
+        let event = Event::new("apollo/vaults/tokenfactory").add_attributes(vec![
+            attr("action", "create_denom"),
+            attr("denom", self.to_string()),
+        ]);
+
+        Ok(Response::new().add_message(init_msg).add_event(event))
+    }
+}
+
+impl Receive for TokenFactoryDenom {
+    fn receive(
+        &self,
+        _deps: DepsMut,
+        _env: &Env,
+        info: &MessageInfo,
+        amount: Uint128,
+    ) -> StdResult<()> {
+        let required = Coin {
+            denom: self.to_string(),
+            amount,
+        };
+        if !info.funds.contains(&required) {
+            return Err(StdError::generic_err(format!(
+                "Expected to receive {}",
+                required
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::coin;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+
+    use super::*;
+
+    const OWNER: &str = "vault";
+    const SUBDENOM: &str = "vault-token";
+    const DENOM: &str = "factory/vault/vault-token";
+
+    fn vault_token(token_factory: TokenFactory) -> TokenFactoryDenom {
+        TokenFactoryDenom::new(OWNER.to_string(), SUBDENOM.to_string(), token_factory)
+    }
+
+    /// Returns the protobuf encoding of a length-delimited field.
+    fn proto_field(tag: u8, value: &[u8]) -> Vec<u8> {
+        [&[tag << 3 | 2, value.len() as u8][..], value].concat()
+    }
+
+    /// Returns the protobuf encoding of a `MsgMint` or `MsgBurn` with the
+    /// fields `sender = 1` and `amount = 2`.
+    fn proto_mint_or_burn(sender: &str, amount: u128) -> Vec<u8> {
+        let coin = [
+            proto_field(1, DENOM.as_bytes()),
+            proto_field(2, amount.to_string().as_bytes()),
+        ]
+        .concat();
+        [proto_field(1, sender.as_bytes()), proto_field(2, &coin)].concat()
+    }
+
+    fn stargate_msg(res: &Response, index: usize) -> (String, Binary) {
+        match &res.messages[index].msg {
+            CosmosMsg::Stargate { type_url, value } => (type_url.clone(), value.clone()),
+            msg => panic!("Expected a stargate message, got {:?}", msg),
+        }
+    }
+
+    #[test]
+    fn test_denom() {
+        assert_eq!(vault_token(TokenFactory::Osmosis).to_string(), DENOM);
+        assert_eq!(vault_token(TokenFactory::Injective).to_string(), DENOM);
+    }
+
+    #[test]
+    fn test_instantiate() {
+        for (token_factory, type_url) in [
+            (
+                TokenFactory::Osmosis,
+                "/osmosis.tokenfactory.v1beta1.MsgCreateDenom",
+            ),
+            (
+                TokenFactory::Neutron,
+                "/osmosis.tokenfactory.v1beta1.MsgCreateDenom",
+            ),
+            (
+                TokenFactory::Injective,
+                "/injective.tokenfactory.v1beta1.MsgCreateDenom",
+            ),
+        ] {
+            let mut deps = mock_dependencies();
+            let res = vault_token(token_factory)
+                .instantiate(deps.as_mut(), None)
+                .unwrap();
+
+            // MsgCreateDenom has the fields `sender = 1` and `subdenom = 2` in all
+            // of the modules
+            assert_eq!(res.messages.len(), 1);
+            assert_eq!(
+                stargate_msg(&res, 0),
+                (
+                    type_url.to_string(),
+                    Binary::from(
+                        [
+                            proto_field(1, OWNER.as_bytes()),
+                            proto_field(2, SUBDENOM.as_bytes()),
+                        ]
+                        .concat()
+                    )
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_mint() {
+        for (token_factory, type_url) in [
+            (
+                TokenFactory::Osmosis,
+                "/osmosis.tokenfactory.v1beta1.MsgMint",
+            ),
+            (
+                TokenFactory::Neutron,
+                "/osmosis.tokenfactory.v1beta1.MsgMint",
+            ),
+            (
+                TokenFactory::Injective,
+                "/injective.tokenfactory.v1beta1.MsgMint",
+            ),
+        ] {
+            let mut deps = mock_dependencies();
+            let env = mock_env();
+            let recipient = Addr::unchecked("recipient");
+            let res = vault_token(token_factory)
+                .mint(deps.as_mut(), &env, &recipient, Uint128::new(100))
+                .unwrap();
+
+            // The tokens are minted to the vault and then sent to the recipient
+            assert_eq!(res.messages.len(), 2);
+            assert_eq!(
+                stargate_msg(&res, 0),
+                (
+                    type_url.to_string(),
+                    Binary::from(proto_mint_or_burn(env.contract.address.as_str(), 100))
+                )
+            );
+            assert_eq!(
+                res.messages[1].msg,
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: recipient.to_string(),
+                    amount: vec![coin(100, DENOM)],
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_burn() {
+        for (token_factory, type_url) in [
+            (
+                TokenFactory::Osmosis,
+                "/osmosis.tokenfactory.v1beta1.MsgBurn",
+            ),
+            (
+                TokenFactory::Neutron,
+                "/osmosis.tokenfactory.v1beta1.MsgBurn",
+            ),
+            (
+                TokenFactory::Injective,
+                "/injective.tokenfactory.v1beta1.MsgBurn",
+            ),
+        ] {
+            let mut deps = mock_dependencies();
+            let env = mock_env();
+            let res = vault_token(token_factory)
+                .burn(deps.as_mut(), &env, Uint128::new(100))
+                .unwrap();
+
+            assert_eq!(res.messages.len(), 1);
+            assert_eq!(
+                stargate_msg(&res, 0),
+                (
+                    type_url.to_string(),
+                    Binary::from(proto_mint_or_burn(env.contract.address.as_str(), 100))
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_receive() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let vault_token = vault_token(TokenFactory::Injective);
+
+        vault_token
+            .receive(
+                deps.as_mut(),
+                &env,
+                &mock_info("sender", &[coin(100, "uinj"), coin(100, DENOM)]),
+                Uint128::new(100),
+            )
+            .unwrap();
+
+        // The vault tokens must be sent with the message
+        for funds in [vec![], vec![coin(99, DENOM)], vec![coin(100, "uinj")]] {
+            let err = vault_token
+                .receive(
+                    deps.as_mut(),
+                    &env,
+                    &mock_info("sender", &funds),
+                    Uint128::new(100),
+                )
+                .unwrap_err();
+            assert_eq!(
+                err,
+                StdError::generic_err(format!("Expected to receive 100{}", DENOM))
+            );
+        }
+    }
+}
@@ -1,9 +1,15 @@
+use apollo_vault::migrations::migrate_contract;
 use apollo_vault::msg::{ApolloExtensionQueryMsg, ExtensionExecuteMsg, ExtensionQueryMsg};
+use apollo_vault::state::ConfigUnchecked;
 use apollo_vault::AutocompoundingVault;
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
-use cw2::{get_contract_version, set_contract_version};
+use cosmwasm_std::{
+    from_binary, to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult,
+    SubMsgResponse, SubMsgResult, Uint128,
+};
+use cw2::set_contract_version;
+use cw20::Cw20ReceiveMsg;
 use cw20_base::allowances::{
     execute_decrease_allowance, execute_increase_allowance, execute_send_from,
     execute_transfer_from, query_allowance,
@@ -18,11 +24,15 @@ use cw_dex::astroport::{AstroportPool, AstroportStaking};
 use cw_dex::traits::Pool;
 use cw_vault_standard::msg::{VaultInfoResponse, VaultStandardInfoResponse};
 use cw_vault_token::cw4626::Cw4626;
-use semver::Version;
+use cw_vault_token::VaultToken;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::msg::{ExecuteMsg, GeneratorQueryMsg, InstantiateMsg, QueryMsg};
 use apollo_vault::error::ContractError;
-use apollo_vault::msg::{ApolloExtensionExecuteMsg, CallbackMsg};
+use apollo_vault::msg::{
+    ApolloExtensionExecuteMsg, CallbackMsg, Cw20HookMsg, CALLBACK_DATA_REPLY_ID,
+};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:astroport-vault";
@@ -31,8 +41,10 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 const VAULT_STANDARD_VERSION: u16 = 1;
 const VAULT_STANDARD_EXTENSIONS: [&str; 2] = ["cw4626", "cw20"];
 
-pub type AstroportVaultContract<'a> =
-    AutocompoundingVault<'a, AstroportStaking, AstroportPool, Cw4626>;
+/// An autocompounding vault for Astroport with the vault token `V`.
+pub type AstroportVault<'a, V> = AutocompoundingVault<'a, AstroportStaking, AstroportPool, V>;
+
+pub type AstroportVaultContract<'a> = AstroportVault<'a, Cw4626>;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -43,20 +55,49 @@ pub fn instantiate(
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-    let contract = AstroportVaultContract::default();
+    let vault_token = Cw4626::new(&env);
+
+    init_vault(
+        deps,
+        msg.admin,
+        msg.pool,
+        msg.generator,
+        msg.astro_token,
+        msg.config,
+        vault_token,
+        Some(to_binary(&msg.init_info)?),
+    )
+}
 
-    let admin_addr = deps.api.addr_validate(&msg.admin)?;
-    let config = msg.config.check(deps.as_ref())?;
+/// Initializes an Astroport vault with the vault token `vault_token`, staking
+/// the LP tokens of `pool` in the Astroport generator `generator`.
+#[allow(clippy::too_many_arguments)]
+pub fn init_vault<V>(
+    deps: DepsMut,
+    admin: String,
+    pool: String,
+    generator: String,
+    astro_token: String,
+    config: ConfigUnchecked,
+    vault_token: V,
+    init_info: Option<Binary>,
+) -> Result<Response, ContractError>
+where
+    V: VaultToken + Serialize + DeserializeOwned,
+{
+    let contract = AstroportVault::<V>::default();
+
+    let admin_addr = deps.api.addr_validate(&admin)?;
+    let config = config.check(deps.as_ref())?;
 
     // Create the pool object
-    let pool = AstroportPool::new(deps.as_ref(), deps.api.addr_validate(&msg.pool)?)?;
+    let pool = AstroportPool::new(deps.as_ref(), deps.api.addr_validate(&pool)?)?;
 
     let staking = AstroportStaking {
         lp_token_addr: deps.api.addr_validate(&pool.lp_token().to_string())?,
-        generator_addr: deps.api.addr_validate(&msg.generator)?,
-        astro_addr: deps.api.addr_validate(&msg.astro_token)?,
+        generator_addr: deps.api.addr_validate(&generator)?,
+        astro_addr: deps.api.addr_validate(&astro_token)?,
     };
-    let vault_token = Cw4626::new(&env);
 
     contract.init(
         deps,
@@ -65,7 +106,7 @@ pub fn instantiate(
         staking,
         config,
         vault_token,
-        Some(to_binary(&msg.init_info)?),
+        init_info,
     )
 }
 
@@ -135,50 +176,96 @@ pub fn execute(
         ExecuteMsg::Redeem { recipient, amount } => {
             contract.execute_redeem(deps, env, &info, amount, recipient)
         }
-        ExecuteMsg::VaultExtension(msg) => match msg {
-            ExtensionExecuteMsg::Callback(msg) => {
-                // Assert that only the contract itself can call this
-                if info.sender != env.contract.address {
-                    return Err(ContractError::Unauthorized {});
-                }
+        ExecuteMsg::VaultExtension(msg) => execute_vault_extension(&contract, deps, env, info, msg),
+        ExecuteMsg::Receive(cw20_msg) => execute_receive(&contract, deps, env, info, cw20_msg),
+    }
+}
 
-                match msg {
-                    CallbackMsg::SellRewards {} => {
-                        contract.execute_callback_sell_rewards(deps, env, info)
-                    }
-                    CallbackMsg::ProvideLiquidity {} => {
-                        contract.execute_callback_provide_liquidity(deps, env, info)
-                    }
-                    CallbackMsg::Stake {
-                        base_token_balance_before,
-                    } => contract.execute_callback_stake(deps, env, base_token_balance_before),
-                    CallbackMsg::MintVaultToken { amount, recipient } => {
-                        contract.execute_callback_mint_vault_token(deps, env, amount, recipient)
-                    }
-                    CallbackMsg::Redeem { amount, recipient } => {
-                        contract.execute_callback_redeem(deps, env, amount, recipient)
-                    }
-                }
+/// Handles the messages of the vault extensions of an Astroport vault.
+pub fn execute_vault_extension<V>(
+    contract: &AstroportVault<V>,
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExtensionExecuteMsg,
+) -> Result<Response, ContractError>
+where
+    V: VaultToken + Serialize + DeserializeOwned,
+{
+    match msg {
+        ExtensionExecuteMsg::Callback(msg) => {
+            // Assert that only the contract itself can call this
+            if info.sender != env.contract.address {
+                return Err(ContractError::Unauthorized {});
             }
-            ExtensionExecuteMsg::Apollo(msg) => match msg {
-                ApolloExtensionExecuteMsg::UpdateConfig { updates } => {
-                    contract.execute_update_config(deps, info, updates)
-                }
 
-                ApolloExtensionExecuteMsg::UpdateAdmin { address } => {
-                    contract.execute_update_admin(deps, info, address)
+            match msg {
+                CallbackMsg::SellRewards {} => {
+                    contract.execute_callback_sell_rewards(deps, env, info)
+                }
+                CallbackMsg::ProvideLiquidity {} => {
+                    contract.execute_callback_provide_liquidity(deps, env, info)
                 }
-                ApolloExtensionExecuteMsg::AcceptAdminTransfer {} => {
-                    contract.execute_accept_admin_transfer(deps, info)
+                CallbackMsg::Stake {
+                    base_token_balance_before,
+                } => contract.execute_callback_stake(deps, env, base_token_balance_before),
+                CallbackMsg::MintVaultToken { amount, recipient } => {
+                    contract.execute_callback_mint_vault_token(deps, env, amount, recipient)
                 }
-                ApolloExtensionExecuteMsg::DropAdminTransfer {} => {
-                    contract.execute_drop_admin_transfer(deps, info)
+                CallbackMsg::Redeem { amount, recipient } => {
+                    contract.execute_callback_redeem(deps, env, amount, recipient)
                 }
-            },
+            }
+        }
+        ExtensionExecuteMsg::Apollo(msg) => match msg {
+            ApolloExtensionExecuteMsg::UpdateConfig { updates } => {
+                contract.execute_update_config(deps, env, info, updates)
+            }
+
+            ApolloExtensionExecuteMsg::UpdateAdmin {
+                address,
+                expiration,
+            } => contract.execute_update_admin(deps, env, info, address, expiration),
+            ApolloExtensionExecuteMsg::AcceptAdminTransfer {} => {
+                contract.execute_accept_admin_transfer(deps, env, info)
+            }
+            ApolloExtensionExecuteMsg::RenounceAdmin { expiration } => {
+                contract.execute_renounce_admin(deps, env, info, expiration)
+            }
+            ApolloExtensionExecuteMsg::ConfirmRenounceAdmin {} => {
+                contract.execute_confirm_renounce_admin(deps, env, info)
+            }
+            ApolloExtensionExecuteMsg::DropAdminTransfer {} => {
+                contract.execute_drop_admin_transfer(deps, env, info)
+            }
         },
     }
 }
 
+/// Handles base tokens sent to the vault with the `Send` message of a cw20.
+/// `info.sender` is the cw20 contract and is checked to be the base token.
+pub fn execute_receive<V>(
+    contract: &AstroportVault<V>,
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError>
+where
+    V: VaultToken + Serialize + DeserializeOwned,
+{
+    match from_binary(&cw20_msg.msg)? {
+        Cw20HookMsg::Deposit { recipient } => contract.execute_receive_deposit(
+            deps,
+            env,
+            &info,
+            cw20_msg.sender,
+            cw20_msg.amount,
+            recipient,
+        ),
+    }
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     let contract = AstroportVaultContract::default();
@@ -215,11 +302,7 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::ConvertToAssets { amount } => {
             to_binary(&base_vault.query_simulate_withdraw(deps, amount)?)
         }
-        QueryMsg::VaultExtension(msg) => match msg {
-            ExtensionQueryMsg::Apollo(apollo_msg) => match apollo_msg {
-                ApolloExtensionQueryMsg::State {} => to_binary(&contract.query_state(deps, env)?),
-            },
-        },
+        QueryMsg::VaultExtension(msg) => query_vault_extension(&contract, deps, env, msg),
         QueryMsg::Balance { address } => {
             to_binary(&base_vault.query_vault_token_balance(deps, address)?)
         }
@@ -240,17 +323,236 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     }
 }
 
+/// Handles the queries of the vault extensions of an Astroport vault.
+pub fn query_vault_extension<V>(
+    contract: &AstroportVault<V>,
+    deps: Deps,
+    env: Env,
+    msg: ExtensionQueryMsg,
+) -> StdResult<Binary>
+where
+    V: VaultToken + Serialize + DeserializeOwned,
+{
+    match msg {
+        ExtensionQueryMsg::Apollo(apollo_msg) => match apollo_msg {
+            ApolloExtensionQueryMsg::State {} => to_binary(&contract.query_state(deps, env)?),
+            ApolloExtensionQueryMsg::Config {} => to_binary(&contract.query_config(deps)?),
+            ApolloExtensionQueryMsg::Admin {} => to_binary(&contract.query_admin(deps)?),
+            ApolloExtensionQueryMsg::PendingAdminTransfer {} => {
+                to_binary(&contract.query_pending_admin_transfer(deps)?)
+            }
+            ApolloExtensionQueryMsg::ForceWithdrawWhitelist {} => {
+                to_binary(&contract.query_force_withdraw_whitelist(deps)?)
+            }
+            ApolloExtensionQueryMsg::ContractVersion {} => {
+                to_binary(&contract.query_contract_version(deps)?)
+            }
+            ApolloExtensionQueryMsg::RewardRoutes {} => {
+                to_binary(&contract.query_reward_routes(deps)?)
+            }
+            ApolloExtensionQueryMsg::Solvency {} => {
+                // Astroport vaults have no unlocking period, so no base
+                // tokens are unlocking in the generator
+                let staking = contract.staking.load(deps.storage)?;
+                let staked: Uint128 = deps.querier.query_wasm_smart(
+                    staking.generator_addr,
+                    &GeneratorQueryMsg::Deposit {
+                        lp_token: staking.lp_token_addr.to_string(),
+                        user: env.contract.address.to_string(),
+                    },
+                )?;
+                to_binary(&contract.query_solvency(deps, env, staked, Uint128::zero())?)
+            }
+            ApolloExtensionQueryMsg::ChangeLog { start_after, limit } => {
+                to_binary(&contract.query_change_log(deps, start_after, limit)?)
+            }
+        },
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(_deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, ContractError> {
+    reply_vault(&AstroportVaultContract::default(), reply)
+}
+
+/// Handles the replies to the submessages of an Astroport vault.
+pub fn reply_vault<V>(contract: &AstroportVault<V>, reply: Reply) -> Result<Response, ContractError>
+where
+    V: VaultToken + Serialize + DeserializeOwned,
+{
+    if let SubMsgResult::Ok(SubMsgResponse {
+        data: Some(b),
+        events: _,
+    }) = reply.result
+    {
+        match reply.id {
+            CALLBACK_DATA_REPLY_ID => contract.reply_callback_data(b),
+            id => Err(ContractError::UnknownReplyId(id)),
+        }
+    } else {
+        Ok(Response::default())
+    }
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
-    let version: Version = CONTRACT_VERSION.parse()?;
-    let storage_version: Version = get_contract_version(deps.storage)?.version.parse()?;
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let contract = AstroportVaultContract::default();
+    migrate_contract(
+        deps,
+        &env,
+        CONTRACT_NAME,
+        CONTRACT_VERSION,
+        contract.migration_steps(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::AssetInfo;
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::{
+        Addr, ContractResult, CosmosMsg, OwnedDeps, SystemResult, WasmMsg, WasmQuery,
+    };
+
+    use super::*;
+
+    const BASE_TOKEN: &str = "base_token";
+    const GENERATOR: &str = "generator";
+    const CW20_SENDER: &str = "cw20_sender";
+    const RECIPIENT: &str = "recipient";
+    const AMOUNT: u128 = 1_000_000;
+
+    fn setup() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        let contract = AstroportVaultContract::default();
+        contract
+            .base_vault
+            .base_token
+            .save(
+                &mut deps.storage,
+                &AssetInfo::Cw20(Addr::unchecked(BASE_TOKEN)),
+            )
+            .unwrap();
+        contract
+            .staking
+            .save(
+                &mut deps.storage,
+                &AstroportStaking {
+                    lp_token_addr: Addr::unchecked(BASE_TOKEN),
+                    generator_addr: Addr::unchecked(GENERATOR),
+                    astro_addr: Addr::unchecked("astro"),
+                },
+            )
+            .unwrap();
+
+        // The received base tokens are already part of the vault balance, and
+        // the generator has no pending rewards
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { contract_addr, .. } if contract_addr == BASE_TOKEN => {
+                SystemResult::Ok(ContractResult::Ok(
+                    to_binary(&cw20::BalanceResponse {
+                        balance: Uint128::new(AMOUNT),
+                    })
+                    .unwrap(),
+                ))
+            }
+            _ => SystemResult::Ok(ContractResult::Ok(Binary::from(
+                br#"{"pending":"0","pending_on_proxy":null}"#.to_vec(),
+            ))),
+        });
+        deps
+    }
+
+    fn receive_msg(recipient: Option<String>) -> ExecuteMsg {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: CW20_SENDER.to_string(),
+            amount: Uint128::new(AMOUNT),
+            msg: to_binary(&Cw20HookMsg::Deposit { recipient }).unwrap(),
+        })
+    }
+
+    /// Returns the amount and recipient of the `MintVaultToken` callback in the
+    /// response.
+    fn minted_vault_tokens(res: &Response) -> (Uint128, Addr) {
+        res.messages
+            .iter()
+            .find_map(|sub_msg| match &sub_msg.msg {
+                CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => match from_binary(msg) {
+                    Ok(ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Callback(
+                        CallbackMsg::MintVaultToken { amount, recipient },
+                    ))) => Some((amount, recipient)),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_receive_deposit() {
+        let mut deps = setup();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(BASE_TOKEN, &[]),
+            receive_msg(Some(RECIPIENT.to_string())),
+        )
+        .unwrap();
+
+        assert_eq!(
+            minted_vault_tokens(&res),
+            (Uint128::new(AMOUNT), Addr::unchecked(RECIPIENT))
+        );
+    }
+
+    #[test]
+    fn test_receive_deposit_defaults_recipient_to_cw20_sender() {
+        let mut deps = setup();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(BASE_TOKEN, &[]),
+            receive_msg(None),
+        )
+        .unwrap();
+
+        assert_eq!(
+            minted_vault_tokens(&res),
+            (Uint128::new(AMOUNT), Addr::unchecked(CW20_SENDER))
+        );
+    }
+
+    #[test]
+    fn test_receive_deposit_of_other_cw20_fails() {
+        let mut deps = setup();
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("other_cw20", &[]),
+            receive_msg(None),
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, ContractError::InvalidDepositAsset {}));
+    }
 
-    // migrate only if newer
-    if storage_version < version {
-        set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    #[test]
+    fn test_receive_is_tagged() {
+        let msg: ExecuteMsg = from_binary(&Binary::from(
+            br#"{"receive":{"sender":"cw20_sender","amount":"1","msg":""}}"#.to_vec(),
+        ))
+        .unwrap();
+        assert!(matches!(msg, ExecuteMsg::Receive(_)));
 
-        // If state structure changed in any contract version in the way migration is needed, it
-        // should occur here
+        let msg: ExecuteMsg = from_binary(&Binary::from(
+            br#"{"deposit":{"amount":"1","recipient":null}}"#.to_vec(),
+        ))
+        .unwrap();
+        assert!(matches!(msg, ExecuteMsg::Deposit { .. }));
     }
-    Ok(Response::default())
 }
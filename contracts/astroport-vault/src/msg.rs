@@ -1,10 +1,82 @@
 use apollo_vault::msg::{ExtensionExecuteMsg, ExtensionQueryMsg};
 use apollo_vault::state::ConfigUnchecked;
 use cosmwasm_schema::cw_serde;
-use cw_vault_standard::extensions::cw4626::{Cw4626ExecuteMsg, Cw4626QueryMsg};
+use cosmwasm_std::{Binary, Uint128};
+use cw20::{Cw20ReceiveMsg, Expiration, Logo};
+use cw_vault_standard::extensions::cw4626::Cw4626QueryMsg;
 use cw_vault_token::cw4626::Cw4626InstantiateMsg;
 
-pub type ExecuteMsg = Cw4626ExecuteMsg<ExtensionExecuteMsg>;
+/// ExecuteMsg for the Astroport vault. Accepts the messages of the cw4626 vault
+/// standard, with the same JSON encoding, as well as the `Receive` hook of the
+/// cw20 base token.
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Transfer is a base message to move vault tokens to another account
+    /// without triggering actions.
+    Transfer { recipient: String, amount: Uint128 },
+    /// Send is a base message to transfer vault tokens to a contract and
+    /// trigger an action on the receiving contract.
+    Send {
+        contract: String,
+        amount: Uint128,
+        msg: Binary,
+    },
+    /// Allows spender to access an additional amount of the sender's vault
+    /// tokens.
+    IncreaseAllowance {
+        spender: String,
+        amount: Uint128,
+        expires: Option<Expiration>,
+    },
+    /// Lowers the spender's access to the sender's vault tokens.
+    DecreaseAllowance {
+        spender: String,
+        amount: Uint128,
+        expires: Option<Expiration>,
+    },
+    /// Transfers `amount` vault tokens from `owner` to `recipient` using the
+    /// allowance of the sender.
+    TransferFrom {
+        owner: String,
+        recipient: String,
+        amount: Uint128,
+    },
+    /// Sends `amount` vault tokens from `owner` to `contract` using the
+    /// allowance of the sender, triggering an action on the receiving
+    /// contract.
+    SendFrom {
+        owner: String,
+        contract: String,
+        amount: Uint128,
+        msg: Binary,
+    },
+    /// Updates the marketing info of the vault token.
+    UpdateMarketing {
+        project: Option<String>,
+        description: Option<String>,
+        marketing: Option<String>,
+    },
+    /// Uploads a logo for the vault token.
+    UploadLogo(Logo),
+    /// Deposit base tokens into the vault. The cw20 base token must be
+    /// approved for the vault to transfer `amount`. Alternatively, the base
+    /// tokens can be sent to the vault with a `Receive` hook.
+    Deposit {
+        amount: Uint128,
+        recipient: Option<String>,
+    },
+    /// Redeem `amount` vault tokens for base tokens.
+    Redeem {
+        recipient: Option<String>,
+        amount: Uint128,
+    },
+    /// Messages of the vault extensions.
+    VaultExtension(ExtensionExecuteMsg),
+    /// The `Receive` hook called by the cw20 base token when it is sent to the
+    /// vault. The `msg` of the `Cw20ReceiveMsg` must be an
+    /// [`apollo_vault::msg::Cw20HookMsg`].
+    Receive(Cw20ReceiveMsg),
+}
 
 pub type QueryMsg = Cw4626QueryMsg<ExtensionQueryMsg>;
 #[cw_serde]
@@ -22,3 +94,11 @@ pub struct InstantiateMsg {
     /// Astro token contract address
     pub astro_token: String,
 }
+
+/// The query message of the Astroport generator used to read the base tokens
+/// staked by the vault.
+#[cw_serde]
+pub enum GeneratorQueryMsg {
+    /// Returns the amount of `lp_token` deposited by `user` as a `Uint128`.
+    Deposit { lp_token: String, user: String },
+}
@@ -0,0 +1,256 @@
+use std::str::FromStr;
+
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    attr, to_binary, Binary, Deps, DepsMut, Env, Event, MessageInfo, Reply, Response, StdResult,
+    SubMsgResponse, SubMsgResult, Uint128,
+};
+use cw2::set_contract_version;
+use cw_vault_standard::msg::{VaultInfoResponse, VaultStandardInfoResponse};
+use cw_vault_token::osmosis::OsmosisDenom;
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{
+    MsgAddToPositionResponse, MsgCreatePositionResponse, MsgWithdrawPositionResponse,
+};
+
+use crate::error::ContractError;
+use crate::execute::{
+    COMPOUND_ADD_TO_POSITION_REPLY_ID, DEPOSIT_ADD_TO_POSITION_REPLY_ID,
+    DEPOSIT_CREATE_POSITION_REPLY_ID, REBALANCE_CREATE_POSITION_REPLY_ID,
+    REDEEM_WITHDRAW_POSITION_REPLY_ID, SWAP_REPLY_ID,
+};
+use crate::msg::{
+    CallbackMsg, ExecuteMsg, ExtensionExecuteMsg, ExtensionQueryMsg, InstantiateMsg, MigrateMsg,
+    QueryMsg,
+};
+use crate::state::Position;
+use crate::ClVault;
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:osmosis-cl-vault";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Constants passed to VaultStandardInfo query
+const VAULT_STANDARD_VERSION: u16 = 1;
+const VAULT_STANDARD_EXTENSIONS: [&str; 0] = [];
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    let contract = ClVault::default();
+
+    let admin_addr = deps.api.addr_validate(&msg.admin)?;
+    let config = msg.config.check(deps.as_ref())?;
+
+    // Validate that 10 osmo for vault token creation are sent
+    let osmo_amount = info
+        .funds
+        .iter()
+        .find(|coin| coin.denom == "uosmo")
+        .map(|coin| coin.amount)
+        .unwrap_or_default();
+    if osmo_amount < Uint128::new(10_000_000) {
+        return Err(ContractError::from(
+            "A minimum of 10_000_000 uosmo must be sent to create the vault token",
+        ));
+    }
+
+    // The position is created by the first deposit
+    let position = Position {
+        pool_id: msg.pool_id,
+        position_id: None,
+        lower_tick: msg.lower_tick,
+        upper_tick: msg.upper_tick,
+    };
+
+    let vault_token = OsmosisDenom::new(env.contract.address.to_string(), msg.vault_token_subdenom);
+
+    contract.init(deps, admin_addr, position, config, vault_token)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    let contract = ClVault::default();
+
+    match msg {
+        ExecuteMsg::Deposit { amount, recipient } => {
+            contract.execute_deposit(deps, env, &info, amount, recipient)
+        }
+        ExecuteMsg::Redeem { recipient, amount } => {
+            contract.execute_redeem(deps, env, &info, amount, recipient)
+        }
+        ExecuteMsg::VaultExtension(msg) => match msg {
+            ExtensionExecuteMsg::Compound {} => contract.compound(deps, &env),
+            ExtensionExecuteMsg::Rebalance {
+                lower_tick,
+                upper_tick,
+            } => contract.execute_rebalance(deps, env, &info, lower_tick, upper_tick),
+            ExtensionExecuteMsg::UpdateConfig { updates } => {
+                contract.execute_update_config(deps, info, updates)
+            }
+            ExtensionExecuteMsg::UpdateAdmin { address } => {
+                let address = deps.api.addr_validate(&address)?;
+                Ok(contract
+                    .admin
+                    .execute_update_admin(deps, info, Some(address))?)
+            }
+            ExtensionExecuteMsg::Callback(msg) => {
+                // Assert that only the contract itself can call this
+                if info.sender != env.contract.address {
+                    return Err(ContractError::Unauthorized {});
+                }
+
+                match msg {
+                    CallbackMsg::SellRewards {} => {
+                        contract.execute_callback_sell_rewards(deps, env)
+                    }
+                    CallbackMsg::SwapToPositionRatio {} => {
+                        contract.execute_callback_swap_to_position_ratio(deps, env)
+                    }
+                    CallbackMsg::AddToPosition {} => {
+                        contract.execute_callback_add_to_position(deps, env)
+                    }
+                    CallbackMsg::CreatePosition {} => {
+                        contract.execute_callback_create_position(deps, env)
+                    }
+                    CallbackMsg::Deposit {} => contract.execute_callback_deposit(deps, env),
+                    CallbackMsg::Redeem { amount, recipient } => {
+                        contract.execute_callback_redeem(deps, env, amount, recipient)
+                    }
+                }
+            }
+        },
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    let contract = ClVault::default();
+    let base_vault = &contract.base_vault;
+
+    match msg {
+        QueryMsg::VaultStandardInfo {} => to_binary(&VaultStandardInfoResponse {
+            version: VAULT_STANDARD_VERSION,
+            extensions: VAULT_STANDARD_EXTENSIONS
+                .iter()
+                .map(|&s| s.into())
+                .collect(),
+        }),
+        QueryMsg::Info {} => {
+            // The base token is the liquidity of the position, which is
+            // represented by the pool assets
+            let vault_token = base_vault.vault_token.load(deps.storage)?;
+            let pool = contract.query_pool(deps)?;
+
+            to_binary(&VaultInfoResponse {
+                base_token: format!("{}/{}", pool.token0, pool.token1),
+                vault_token: vault_token.to_string(),
+            })
+        }
+        QueryMsg::PreviewDeposit { amount } => {
+            to_binary(&base_vault.query_simulate_deposit(deps, amount)?)
+        }
+        QueryMsg::PreviewRedeem { amount } => {
+            to_binary(&base_vault.query_simulate_withdraw(deps, amount)?)
+        }
+        QueryMsg::TotalAssets {} => to_binary(&base_vault.query_total_assets(deps)?),
+        QueryMsg::TotalVaultTokenSupply {} => {
+            to_binary(&base_vault.query_total_vault_token_supply(deps)?)
+        }
+        QueryMsg::ConvertToShares { amount } => {
+            to_binary(&base_vault.query_simulate_deposit(deps, amount)?)
+        }
+        QueryMsg::ConvertToAssets { amount } => {
+            to_binary(&base_vault.query_simulate_withdraw(deps, amount)?)
+        }
+        QueryMsg::VaultExtension(msg) => match msg {
+            ExtensionQueryMsg::State {} => to_binary(&contract.query_state(deps)?),
+        },
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, reply: Reply) -> Result<Response, ContractError> {
+    let contract = ClVault::default();
+
+    // Failures of these messages are ignored, so that they can be retried on
+    // the next compound
+    if let SubMsgResult::Err(error) = &reply.result {
+        return match reply.id {
+            COMPOUND_ADD_TO_POSITION_REPLY_ID | SWAP_REPLY_ID => Ok(Response::new().add_event(
+                Event::new("apollo/vaults/osmosis_cl_vault").add_attributes(vec![
+                    attr("action", "reply_ignored_error"),
+                    attr("reply_id", reply.id.to_string()),
+                    attr("error", error),
+                ]),
+            )),
+            id => Err(ContractError::UnknownReplyId(id)),
+        };
+    }
+
+    if let SubMsgResult::Ok(SubMsgResponse {
+        data: Some(b),
+        events: _,
+    }) = reply.result
+    {
+        match reply.id {
+            COMPOUND_ADD_TO_POSITION_REPLY_ID => {
+                let res: MsgAddToPositionResponse = b.try_into()?;
+                contract.update_position(deps, res.position_id)?;
+                Ok(Response::new())
+            }
+            REBALANCE_CREATE_POSITION_REPLY_ID => {
+                let res: MsgCreatePositionResponse = b.try_into()?;
+                contract.update_position(deps, res.position_id)?;
+                Ok(Response::new())
+            }
+            DEPOSIT_CREATE_POSITION_REPLY_ID => {
+                let res: MsgCreatePositionResponse = b.try_into()?;
+                contract.reply_deposit(
+                    deps,
+                    env,
+                    res.position_id,
+                    Uint128::from_str(&res.amount0)?,
+                    Uint128::from_str(&res.amount1)?,
+                )
+            }
+            DEPOSIT_ADD_TO_POSITION_REPLY_ID => {
+                let res: MsgAddToPositionResponse = b.try_into()?;
+                contract.reply_deposit(
+                    deps,
+                    env,
+                    res.position_id,
+                    Uint128::from_str(&res.amount0)?,
+                    Uint128::from_str(&res.amount1)?,
+                )
+            }
+            REDEEM_WITHDRAW_POSITION_REPLY_ID => {
+                let res: MsgWithdrawPositionResponse = b.try_into()?;
+                contract.reply_redeem(
+                    deps,
+                    Uint128::from_str(&res.amount0)?,
+                    Uint128::from_str(&res.amount1)?,
+                )
+            }
+            id => Err(ContractError::UnknownReplyId(id)),
+        }
+    } else {
+        Ok(Response::default())
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::default())
+}
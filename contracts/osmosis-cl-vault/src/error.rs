@@ -0,0 +1,74 @@
+use cosmwasm_std::{Coin, OverflowError, StdError, Uint128};
+use cw_controllers::AdminError;
+use cw_vault_token::CwTokenError;
+use thiserror::Error;
+
+/// Concentrated liquidity vault errors
+#[allow(missing_docs)]
+#[derive(Error, Debug)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    CwTokenError(#[from] CwTokenError),
+
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
+    #[error("{0}")]
+    AdminError(#[from] AdminError),
+
+    #[error("{0}")]
+    Generic(String),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Invalid asset deposited. Only the pool assets {expected:?} can be deposited, got {actual:?}")]
+    InvalidDepositAsset {
+        expected: Vec<String>,
+        actual: Vec<Coin>,
+    },
+
+    #[error("Invalid tick range [{lower_tick}, {upper_tick}). The lower tick must be below the upper tick and both must be multiples of the tick spacing {tick_spacing}")]
+    InvalidTickRange {
+        lower_tick: i64,
+        upper_tick: i64,
+        tick_spacing: u64,
+    },
+
+    #[error("Invalid reward liquidation target. Reward liquidation target must be one of the pool assets. Expected one of: {expected:?}. Got {actual}")]
+    InvalidRewardLiquidationTarget {
+        expected: Vec<String>,
+        actual: String,
+    },
+
+    #[error("Cannot rebalance while the current tick {current_tick} is in the range of the position [{lower_tick}, {upper_tick})")]
+    PositionInRange {
+        current_tick: i64,
+        lower_tick: i64,
+        upper_tick: i64,
+    },
+
+    #[error("Deposit added {actual} liquidity, less than the minimum of {min}")]
+    InsufficientLiquidity { min: Uint128, actual: Uint128 },
+
+    #[error("The vault has no position")]
+    NoPosition {},
+
+    #[error("Unknown reply ID: {0}")]
+    UnknownReplyId(u64),
+}
+
+impl From<String> for ContractError {
+    fn from(val: String) -> Self {
+        ContractError::Generic(val)
+    }
+}
+
+impl From<&str> for ContractError {
+    fn from(val: &str) -> Self {
+        ContractError::Generic(val.into())
+    }
+}
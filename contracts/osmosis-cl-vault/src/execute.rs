@@ -0,0 +1,709 @@
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use cosmwasm_std::{
+    attr, Addr, BankMsg, Coin, Decimal, Decimal256, DepsMut, Env, Event, MessageInfo, Response,
+    StdResult, SubMsg, Uint128,
+};
+use cw_vault_token::VaultToken;
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{
+    MsgAddToPosition, MsgCollectIncentives, MsgCollectSpreadRewards, MsgCreatePosition,
+    MsgWithdrawPosition,
+};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{MsgSwapExactAmountIn, SwapAmountInRoute};
+
+use crate::error::ContractError;
+use crate::math::{
+    dec_to_uint_floor, parse_dec, swap_for_position_ratio, tick_to_price, RatioSwap,
+};
+use crate::msg::CallbackMsg;
+use crate::state::{ConfigUpdates, PendingDeposit};
+use crate::vault::{assert_pool_asset, assert_valid_tick_range, query_position_liquidity};
+use crate::ClVault;
+
+/// Reply ID of the `MsgAddToPosition` that compounds rewards into the
+/// position. Failures are ignored, since the rewards can be compounded later.
+pub const COMPOUND_ADD_TO_POSITION_REPLY_ID: u64 = 1;
+/// Reply ID of the `MsgCreatePosition` that recreates the position when
+/// rebalancing.
+pub const REBALANCE_CREATE_POSITION_REPLY_ID: u64 = 2;
+/// Reply ID of the `MsgCreatePosition` of a deposit into a vault without a
+/// position.
+pub const DEPOSIT_CREATE_POSITION_REPLY_ID: u64 = 3;
+/// Reply ID of the `MsgAddToPosition` of a deposit.
+pub const DEPOSIT_ADD_TO_POSITION_REPLY_ID: u64 = 4;
+/// Reply ID of the `MsgWithdrawPosition` of a redemption.
+pub const REDEEM_WITHDRAW_POSITION_REPLY_ID: u64 = 5;
+/// Reply ID of the swap into the ratio of the position. Failures are ignored,
+/// since the balances are swapped again on the next compound.
+pub const SWAP_REPLY_ID: u64 = 6;
+
+impl ClVault<'_> {
+    /// Collect the spread rewards and incentives of the position and add them
+    /// to the position. Does nothing if the vault has no position.
+    ///
+    /// Adding to a position creates a new position in Osmosis, which resets
+    /// the time the position has been joined for incentives with an uptime
+    /// requirement.
+    pub fn compound(&self, deps: DepsMut, env: &Env) -> Result<Response, ContractError> {
+        let position = self.position.load(deps.storage)?;
+        let position_id = match position.position_id {
+            Some(position_id) => position_id,
+            None => return Ok(Response::new()),
+        };
+
+        let event = Event::new("apollo/vaults/osmosis_cl_vault").add_attributes(vec![
+            attr("action", "compound"),
+            attr("position_id", position_id.to_string()),
+        ]);
+
+        Ok(self
+            .collect_rewards(env, position_id)?
+            .add_message(CallbackMsg::SwapToPositionRatio {}.into_cosmos_msg(env)?)
+            .add_message(CallbackMsg::AddToPosition {}.into_cosmos_msg(env)?)
+            .add_event(event))
+    }
+
+    /// Returns a response that collects the spread rewards and incentives of
+    /// the position, then sells them with `CallbackMsg::SellRewards`.
+    fn collect_rewards(&self, env: &Env, position_id: u64) -> StdResult<Response> {
+        let sender = env.contract.address.to_string();
+        let collect_spread_rewards = MsgCollectSpreadRewards {
+            position_ids: vec![position_id],
+            sender: sender.clone(),
+        };
+        let collect_incentives = MsgCollectIncentives {
+            position_ids: vec![position_id],
+            sender,
+        };
+
+        Ok(Response::new()
+            .add_message(collect_spread_rewards)
+            .add_message(collect_incentives)
+            .add_message(CallbackMsg::SellRewards {}.into_cosmos_msg(env)?))
+    }
+
+    /// Deposit the pool assets sent in `info.funds` into the position. This
+    /// will first compound the pending rewards.
+    ///
+    /// ## Arguments
+    /// - `min_liquidity`: The minimum amount of liquidity that must be added to
+    ///   the position.
+    /// - `recipient`: Optional address to receive the vault tokens. If None,
+    ///   the `info.sender` will be used instead.
+    pub fn execute_deposit(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: &MessageInfo,
+        min_liquidity: Uint128,
+        recipient: Option<String>,
+    ) -> Result<Response, ContractError> {
+        let pool = self.query_pool(deps.as_ref())?;
+        let pool_denoms = vec![pool.token0, pool.token1];
+        if info.funds.is_empty()
+            || info
+                .funds
+                .iter()
+                .any(|coin| !pool_denoms.contains(&coin.denom))
+        {
+            return Err(ContractError::InvalidDepositAsset {
+                expected: pool_denoms,
+                actual: info.funds.clone(),
+            });
+        }
+
+        // Unwrap recipient or use caller's address
+        let recipient =
+            recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+
+        // Reserve the deposited funds, so that they are not compounded
+        self.pending_deposit.save(
+            deps.storage,
+            &PendingDeposit {
+                recipient: recipient.clone(),
+                funds: info.funds.clone(),
+                min_liquidity,
+            },
+        )?;
+
+        let event = Event::new("apollo/vaults/osmosis_cl_vault").add_attributes(vec![
+            attr("action", "deposit"),
+            attr("recipient", recipient),
+            attr("min_liquidity", min_liquidity),
+        ]);
+
+        Ok(self
+            .compound(deps.branch(), &env)?
+            .add_message(CallbackMsg::Deposit {}.into_cosmos_msg(&env)?)
+            .add_event(event))
+    }
+
+    /// Redeem vault tokens for the pool assets of the liquidity they represent.
+    /// This will first compound the pending rewards. The vault tokens must be
+    /// sent in the `info.funds` field.
+    ///
+    /// ## Arguments
+    /// - `vault_token_amount`: Amount of vault tokens to redeem.
+    /// - `recipient`: Optional address to receive the pool assets. If None, the
+    ///   `info.sender` will be used instead.
+    pub fn execute_redeem(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: &MessageInfo,
+        vault_token_amount: Uint128,
+        recipient: Option<String>,
+    ) -> Result<Response, ContractError> {
+        let vault_token = self.base_vault.vault_token.load(deps.storage)?;
+
+        // Validate that the vault tokens were received
+        vault_token.receive(deps.branch(), &env, info, vault_token_amount)?;
+
+        // Unwrap recipient or use caller's address
+        let recipient =
+            recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+
+        let event = Event::new("apollo/vaults/osmosis_cl_vault").add_attributes(vec![
+            attr("action", "redeem"),
+            attr("recipient", recipient.clone()),
+            attr("amount", vault_token_amount),
+        ]);
+
+        Ok(self
+            .compound(deps, &env)?
+            .add_message(
+                CallbackMsg::Redeem {
+                    amount: vault_token_amount,
+                    recipient,
+                }
+                .into_cosmos_msg(&env)?,
+            )
+            .add_event(event))
+    }
+
+    /// Withdraw the position and create a new one in the range
+    /// [`lower_tick`, `upper_tick`). The rewards of the old position are
+    /// collected and added to the new position. Can only be called by the
+    /// admin or the keeper while the current tick of the pool is outside of
+    /// the range of the position.
+    pub fn execute_rebalance(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: &MessageInfo,
+        lower_tick: i64,
+        upper_tick: i64,
+    ) -> Result<Response, ContractError> {
+        let config = self.config.load(deps.storage)?;
+        if !self.admin.is_admin(deps.as_ref(), &info.sender)? && !config.is_keeper(&info.sender) {
+            return Err(ContractError::Unauthorized {});
+        }
+
+        let pool = self.query_pool(deps.as_ref())?;
+        assert_valid_tick_range(&pool, lower_tick, upper_tick)?;
+
+        let mut position = self.position.load(deps.storage)?;
+        if position.is_in_range(pool.current_tick) {
+            return Err(ContractError::PositionInRange {
+                current_tick: pool.current_tick,
+                lower_tick: position.lower_tick,
+                upper_tick: position.upper_tick,
+            });
+        }
+
+        let event = Event::new("apollo/vaults/osmosis_cl_vault").add_attributes(vec![
+            attr("action", "rebalance"),
+            attr("current_tick", pool.current_tick.to_string()),
+            attr("old_lower_tick", position.lower_tick.to_string()),
+            attr("old_upper_tick", position.upper_tick.to_string()),
+            attr("lower_tick", lower_tick.to_string()),
+            attr("upper_tick", upper_tick.to_string()),
+        ]);
+
+        let old_position_id = position.position_id;
+        position.lower_tick = lower_tick;
+        position.upper_tick = upper_tick;
+        position.position_id = None;
+        self.position.save(deps.storage, &position)?;
+
+        // Without a position there is no liquidity to move
+        let position_id = match old_position_id {
+            Some(position_id) => position_id,
+            None => return Ok(Response::new().add_event(event)),
+        };
+
+        // Collect the rewards, withdraw all liquidity and create the new
+        // position with the withdrawn assets and the rewards
+        let withdraw_position = MsgWithdrawPosition {
+            position_id,
+            sender: env.contract.address.to_string(),
+            liquidity_amount: query_position_liquidity(&deps.querier, position_id)?.to_string(),
+        };
+
+        Ok(self
+            .collect_rewards(&env, position_id)?
+            .add_message(withdraw_position)
+            .add_message(CallbackMsg::SwapToPositionRatio {}.into_cosmos_msg(&env)?)
+            .add_message(CallbackMsg::CreatePosition {}.into_cosmos_msg(&env)?)
+            .add_event(event))
+    }
+
+    /// Update the configuration of the vault. Can only be called by the admin.
+    pub fn execute_update_config(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        updates: ConfigUpdates,
+    ) -> Result<Response, ContractError> {
+        self.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+        let config = self
+            .config
+            .load(deps.storage)?
+            .update(deps.as_ref(), updates.clone())?;
+        assert_pool_asset(
+            &self.query_pool(deps.as_ref())?,
+            &config.reward_liquidation_target,
+        )?;
+        self.config.save(deps.storage, &config)?;
+
+        let event = Event::new("apollo/vaults/osmosis_cl_vault").add_attributes(vec![
+            attr("action", "update_config"),
+            attr("updates", format!("{:?}", updates)),
+        ]);
+
+        Ok(Response::new().add_event(event))
+    }
+
+    /// Sends the performance fee of the collected rewards to the treasury and
+    /// sells all reward assets that are not pool assets for the reward
+    /// liquidation target.
+    pub fn execute_callback_sell_rewards(
+        &self,
+        deps: DepsMut,
+        env: Env,
+    ) -> Result<Response, ContractError> {
+        let cfg = self.config.load(deps.storage)?;
+        let pool = self.query_pool(deps.as_ref())?;
+        let pool_assets = vec![
+            AssetInfo::Native(pool.token0),
+            AssetInfo::Native(pool.token1),
+        ];
+
+        // AssetList of reward tokens collected from performance fees
+        let mut reward_asset_balances_to_treasury = AssetList::new();
+
+        let reward_assets_to_sell: AssetList = cfg
+            .reward_assets
+            .into_iter()
+            .map(|x| {
+                // Take performance fee from each reward asset. Deposited pool
+                // assets are not rewards.
+                let balance = match &x {
+                    AssetInfo::Native(denom) => self.free_balance(deps.as_ref(), &env, denom)?,
+                    AssetInfo::Cw20(_) => {
+                        x.query_balance(&deps.querier, env.contract.address.clone())?
+                    }
+                };
+                let balance_after_fee = balance * (Decimal::one() - cfg.performance_fee);
+                reward_asset_balances_to_treasury.add(&Asset::new(
+                    x.clone(),
+                    balance.checked_sub(balance_after_fee)?,
+                ))?;
+                Ok(Asset::new(x, balance_after_fee))
+            })
+            .collect::<StdResult<Vec<_>>>()?
+            .into_iter()
+            .filter(|x| x.amount != Uint128::zero()) // Filter out assets with 0 balance
+            // Pool assets are added to the position directly
+            .filter(|x| !pool_assets.contains(&x.info))
+            .collect::<Vec<_>>()
+            .into();
+
+        // Send performance fees to treasury
+        let mut msgs = reward_asset_balances_to_treasury
+            .into_iter()
+            .filter(|x| x.amount != Uint128::zero()) // Filter out assets with 0 balance
+            .map(|x| x.transfer_msg(cfg.treasury.to_string()))
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let mut event = Event::new("apollo/vaults/osmosis_cl_vault")
+            .add_attribute("action", "execute_callback_sell_rewards");
+        if reward_asset_balances_to_treasury.len() > 0 {
+            event = event.add_attribute(
+                "reward_asset_balances_to_treasury",
+                reward_asset_balances_to_treasury.to_string(),
+            );
+        }
+
+        // Swap all other reward assets
+        if reward_assets_to_sell.len() > 0 {
+            let mut swap_msgs = cfg.router.basket_liquidate_msgs(
+                reward_assets_to_sell.clone(),
+                &cfg.reward_liquidation_target,
+                None,
+                None,
+            )?;
+            msgs.append(&mut swap_msgs);
+            event = event.add_attribute("reward_assets_to_sell", reward_assets_to_sell.to_string());
+        }
+
+        Ok(Response::new().add_messages(msgs).add_event(event))
+    }
+
+    /// Swaps the pool assets held by the contract, excluding a pending
+    /// deposit, into the ratio of the position at the current price of the
+    /// pool. The minimum output of the swap is the output at the current price
+    /// minus `max_swap_slippage`.
+    pub fn execute_callback_swap_to_position_ratio(
+        &self,
+        deps: DepsMut,
+        env: Env,
+    ) -> Result<Response, ContractError> {
+        let cfg = self.config.load(deps.storage)?;
+        let pool = self.query_pool(deps.as_ref())?;
+        let position = self.position.load(deps.storage)?;
+
+        let amount0 = self.free_balance(deps.as_ref(), &env, &pool.token0)?;
+        let amount1 = self.free_balance(deps.as_ref(), &env, &pool.token1)?;
+        let sqrt_price = parse_dec(&pool.current_sqrt_price)?;
+        let swap = swap_for_position_ratio(
+            amount0,
+            amount1,
+            sqrt_price,
+            tick_to_price(position.lower_tick)?.sqrt(),
+            tick_to_price(position.upper_tick)?.sqrt(),
+        )?;
+
+        let price = sqrt_price * sqrt_price;
+        let (token_in, token_out_denom, expected_out) = match swap {
+            None => return Ok(Response::new()),
+            Some(RatioSwap::ZeroForOne(amount)) => (
+                Coin::new(amount.u128(), pool.token0),
+                pool.token1,
+                Decimal256::from_ratio(amount, 1u8) * price,
+            ),
+            Some(RatioSwap::OneForZero(amount)) if !price.is_zero() => (
+                Coin::new(amount.u128(), pool.token1),
+                pool.token0,
+                Decimal256::from_ratio(amount, 1u8) / price,
+            ),
+            Some(RatioSwap::OneForZero(_)) => return Ok(Response::new()),
+        };
+        let max_slippage = Decimal256::from(cfg.max_swap_slippage);
+        let token_out_min_amount =
+            dec_to_uint_floor(expected_out * (Decimal256::one() - max_slippage))?;
+
+        // Dust would be swapped for nothing
+        if token_out_min_amount.is_zero() {
+            return Ok(Response::new());
+        }
+
+        let event = Event::new("apollo/vaults/osmosis_cl_vault").add_attributes(vec![
+            attr("action", "execute_callback_swap_to_position_ratio"),
+            attr("token_in", token_in.to_string()),
+            attr("token_out_min_amount", token_out_min_amount),
+        ]);
+
+        let swap_msg = MsgSwapExactAmountIn {
+            sender: env.contract.address.to_string(),
+            routes: vec![SwapAmountInRoute {
+                pool_id: position.pool_id,
+                token_out_denom,
+            }],
+            token_in: Some(token_in.into()),
+            token_out_min_amount: token_out_min_amount.to_string(),
+        };
+
+        Ok(Response::new()
+            .add_submessage(SubMsg::reply_on_error(swap_msg, SWAP_REPLY_ID))
+            .add_event(event))
+    }
+
+    /// Adds the pool assets held by the contract, excluding a pending deposit,
+    /// to the position.
+    pub fn execute_callback_add_to_position(
+        &self,
+        deps: DepsMut,
+        env: Env,
+    ) -> Result<Response, ContractError> {
+        let pool = self.query_pool(deps.as_ref())?;
+        let position_id = match self.position.load(deps.storage)?.position_id {
+            Some(position_id) => position_id,
+            None => return Ok(Response::new()),
+        };
+
+        let amount0 = self.free_balance(deps.as_ref(), &env, &pool.token0)?;
+        let amount1 = self.free_balance(deps.as_ref(), &env, &pool.token1)?;
+        if amount0.is_zero() && amount1.is_zero() {
+            return Ok(Response::new());
+        }
+
+        let event = Event::new("apollo/vaults/osmosis_cl_vault").add_attributes(vec![
+            attr("action", "execute_callback_add_to_position"),
+            attr("amount0", amount0),
+            attr("amount1", amount1),
+        ]);
+
+        let add_to_position = MsgAddToPosition {
+            position_id,
+            sender: env.contract.address.to_string(),
+            amount0: amount0.to_string(),
+            amount1: amount1.to_string(),
+            token_min_amount0: "0".to_string(),
+            token_min_amount1: "0".to_string(),
+        };
+
+        // Compounding must not block deposits and redemptions, so a failure is
+        // handled in the reply and the rewards are compounded later
+        Ok(Response::new()
+            .add_submessage(SubMsg::reply_always(
+                add_to_position,
+                COMPOUND_ADD_TO_POSITION_REPLY_ID,
+            ))
+            .add_event(event))
+    }
+
+    /// Creates a new position in the range of the vault with all pool assets
+    /// held by the contract. Called when rebalancing.
+    pub fn execute_callback_create_position(
+        &self,
+        deps: DepsMut,
+        env: Env,
+    ) -> Result<Response, ContractError> {
+        let pool = self.query_pool(deps.as_ref())?;
+        let position = self.position.load(deps.storage)?;
+        let tokens_provided = self.free_pool_balances(deps.as_ref(), &env, &pool)?;
+
+        let event = Event::new("apollo/vaults/osmosis_cl_vault").add_attributes(vec![
+            attr("action", "execute_callback_create_position"),
+            attr("tokens_provided", format!("{:?}", tokens_provided)),
+        ]);
+
+        let create_position = MsgCreatePosition {
+            pool_id: position.pool_id,
+            sender: env.contract.address.to_string(),
+            lower_tick: position.lower_tick,
+            upper_tick: position.upper_tick,
+            tokens_provided: tokens_provided.into_iter().map(Into::into).collect(),
+            token_min_amount0: "0".to_string(),
+            token_min_amount1: "0".to_string(),
+        };
+
+        Ok(Response::new()
+            .add_submessage(SubMsg::reply_on_success(
+                create_position,
+                REBALANCE_CREATE_POSITION_REPLY_ID,
+            ))
+            .add_event(event))
+    }
+
+    /// Adds the pending deposit to the position, or creates the position if
+    /// the vault has none. The vault tokens are minted when handling the reply.
+    pub fn execute_callback_deposit(
+        &self,
+        deps: DepsMut,
+        env: Env,
+    ) -> Result<Response, ContractError> {
+        let pool = self.query_pool(deps.as_ref())?;
+        let position = self.position.load(deps.storage)?;
+        let deposit = self.pending_deposit.load(deps.storage)?;
+        let sender = env.contract.address.to_string();
+
+        let msg = match position.position_id {
+            Some(position_id) => SubMsg::reply_on_success(
+                MsgAddToPosition {
+                    position_id,
+                    sender,
+                    amount0: deposit.amount_of(&pool.token0).to_string(),
+                    amount1: deposit.amount_of(&pool.token1).to_string(),
+                    token_min_amount0: "0".to_string(),
+                    token_min_amount1: "0".to_string(),
+                },
+                DEPOSIT_ADD_TO_POSITION_REPLY_ID,
+            ),
+            None => SubMsg::reply_on_success(
+                MsgCreatePosition {
+                    pool_id: position.pool_id,
+                    sender,
+                    lower_tick: position.lower_tick,
+                    upper_tick: position.upper_tick,
+                    tokens_provided: deposit.funds.into_iter().map(Into::into).collect(),
+                    token_min_amount0: "0".to_string(),
+                    token_min_amount1: "0".to_string(),
+                },
+                DEPOSIT_CREATE_POSITION_REPLY_ID,
+            ),
+        };
+
+        Ok(Response::new().add_submessage(msg))
+    }
+
+    /// Burns `vault_token_amount` vault tokens and withdraws the liquidity they
+    /// represent from the position. The withdrawn pool assets are sent to
+    /// `recipient` when handling the reply.
+    pub fn execute_callback_redeem(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        vault_token_amount: Uint128,
+        recipient: Addr,
+    ) -> Result<Response, ContractError> {
+        let mut position = self.position.load(deps.storage)?;
+        let position_id = position.position_id.ok_or(ContractError::NoPosition {})?;
+        let vault_token_supply = self
+            .base_vault
+            .query_total_vault_token_supply(deps.as_ref())?;
+
+        // Burn vault tokens and get the amount of liquidity to withdraw
+        let (liquidity, burn_res) = self.base_vault.burn_vault_tokens_for_base_tokens(
+            deps.branch(),
+            &env,
+            vault_token_amount,
+        )?;
+
+        // The last redemption withdraws the whole position, including the
+        // liquidity lost to rounding, which deletes the position
+        let liquidity_amount = if vault_token_amount == vault_token_supply {
+            position.position_id = None;
+            self.position.save(deps.storage, &position)?;
+            query_position_liquidity(&deps.querier, position_id)?
+        } else {
+            Decimal256::from_ratio(liquidity, 1u8)
+        };
+        if liquidity_amount.is_zero() {
+            return Err(ContractError::from(
+                "Redeem amount is too small to withdraw any liquidity",
+            ));
+        }
+
+        self.pending_redeem_recipient
+            .save(deps.storage, &recipient)?;
+
+        let event = Event::new("apollo/vaults/osmosis_cl_vault").add_attributes(vec![
+            attr("action", "execute_callback_redeem"),
+            attr("recipient", recipient),
+            attr("vault_token_amount", vault_token_amount),
+            attr("liquidity_amount", liquidity_amount.to_string()),
+        ]);
+
+        let withdraw_position = MsgWithdrawPosition {
+            position_id,
+            sender: env.contract.address.to_string(),
+            liquidity_amount: liquidity_amount.to_string(),
+        };
+
+        Ok(burn_res
+            .add_submessage(SubMsg::reply_on_success(
+                withdraw_position,
+                REDEEM_WITHDRAW_POSITION_REPLY_ID,
+            ))
+            .add_event(event))
+    }
+
+    /// Handles the reply of a deposit that changed the position to
+    /// `position_id`. Mints vault tokens for the added liquidity and refunds
+    /// the deposited funds that were not used.
+    pub fn reply_deposit(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        position_id: u64,
+        used_amount0: Uint128,
+        used_amount1: Uint128,
+    ) -> Result<Response, ContractError> {
+        let deposit = self.pending_deposit.load(deps.storage)?;
+        self.pending_deposit.remove(deps.storage);
+
+        let vault_token = self.base_vault.vault_token.load(deps.storage)?;
+        let vault_token_supply = vault_token.query_total_supply(deps.as_ref())?;
+        let liquidity_before = self
+            .base_vault
+            .total_staked_base_tokens
+            .load(deps.storage)?;
+        let liquidity_after = self.update_position(deps.branch(), position_id)?;
+        let liquidity_added = liquidity_after.checked_sub(liquidity_before)?;
+        if liquidity_added < deposit.min_liquidity {
+            return Err(ContractError::InsufficientLiquidity {
+                min: deposit.min_liquidity,
+                actual: liquidity_added,
+            });
+        }
+
+        let vault_tokens = self.base_vault.calculate_vault_tokens(
+            liquidity_added,
+            liquidity_before,
+            vault_token_supply,
+        )?;
+
+        // Refund the part of the deposit that did not fit the ratio of the
+        // position
+        let pool = self.query_pool(deps.as_ref())?;
+        let refund = [(pool.token0, used_amount0), (pool.token1, used_amount1)]
+            .into_iter()
+            .map(|(denom, used)| {
+                Ok(Coin {
+                    amount: deposit.amount_of(&denom).checked_sub(used)?,
+                    denom,
+                })
+            })
+            .collect::<Result<Vec<_>, ContractError>>()?
+            .into_iter()
+            .filter(|coin| !coin.amount.is_zero())
+            .collect::<Vec<_>>();
+
+        let event = Event::new("apollo/vaults/osmosis_cl_vault").add_attributes(vec![
+            attr("action", "reply_deposit"),
+            attr("recipient", deposit.recipient.to_string()),
+            attr("liquidity_added", liquidity_added),
+            attr("mint_amount", vault_tokens),
+            attr("refund", format!("{:?}", refund)),
+        ]);
+
+        let mut res = vault_token
+            .mint(deps, &env, &deposit.recipient, vault_tokens)?
+            .add_event(event);
+        if !refund.is_empty() {
+            res = res.add_message(BankMsg::Send {
+                to_address: deposit.recipient.to_string(),
+                amount: refund,
+            });
+        }
+
+        Ok(res)
+    }
+
+    /// Handles the reply of the withdrawal of a redemption, sending the
+    /// withdrawn pool assets to the recipient.
+    pub fn reply_redeem(
+        &self,
+        deps: DepsMut,
+        amount0: Uint128,
+        amount1: Uint128,
+    ) -> Result<Response, ContractError> {
+        let recipient = self.pending_redeem_recipient.load(deps.storage)?;
+        self.pending_redeem_recipient.remove(deps.storage);
+
+        let pool = self.query_pool(deps.as_ref())?;
+        let mut coins = vec![
+            Coin::new(amount0.u128(), pool.token0),
+            Coin::new(amount1.u128(), pool.token1),
+        ];
+        coins.retain(|coin| !coin.amount.is_zero());
+        coins.sort_by(|a, b| a.denom.cmp(&b.denom));
+
+        let event = Event::new("apollo/vaults/osmosis_cl_vault").add_attributes(vec![
+            attr("action", "reply_redeem"),
+            attr("recipient", recipient.to_string()),
+            attr("amount", format!("{:?}", coins)),
+        ]);
+
+        let res = Response::new().add_event(event);
+        if coins.is_empty() {
+            return Ok(res);
+        }
+        Ok(res.add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: coins,
+        }))
+    }
+}
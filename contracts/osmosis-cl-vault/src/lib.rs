@@ -0,0 +1,13 @@
+#[macro_use]
+extern crate derive_builder;
+
+pub mod contract;
+pub mod error;
+pub mod execute;
+pub mod math;
+pub mod msg;
+pub mod query;
+pub mod state;
+pub mod vault;
+
+pub use crate::vault::ClVault;
@@ -0,0 +1,190 @@
+use std::str::FromStr;
+
+use cosmwasm_std::{Decimal256, StdError, StdResult, Uint128, Uint256};
+
+/// The exponent of the additive increment between ticks at a price of one.
+const EXPONENT_AT_PRICE_ONE: i64 = -6;
+
+/// The number of ticks after which the additive increment between ticks grows
+/// by a factor of ten, i.e. `9 * 10^(-EXPONENT_AT_PRICE_ONE)`.
+const TICKS_PER_GEOMETRIC_EXPONENT: i64 = 9_000_000;
+
+/// Direction and amount of a swap between the two pool assets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RatioSwap {
+    /// Swap the given amount of token0 for token1.
+    ZeroForOne(Uint128),
+    /// Swap the given amount of token1 for token0.
+    OneForZero(Uint128),
+}
+
+/// Returns the price of token0 in token1 at `tick`, following the geometric
+/// tick spacing of Osmosis concentrated liquidity pools. Prices that are
+/// smaller than the precision of `Decimal256` are rounded down.
+pub fn tick_to_price(tick: i64) -> StdResult<Decimal256> {
+    if tick == 0 {
+        return Ok(Decimal256::one());
+    }
+
+    // Division truncates towards zero, as in the Osmosis implementation
+    let geometric_exponent_delta = tick / TICKS_PER_GEOMETRIC_EXPONENT;
+    let mut exponent_at_current_tick = EXPONENT_AT_PRICE_ONE + geometric_exponent_delta;
+    if tick < 0 {
+        exponent_at_current_tick -= 1;
+    }
+    let num_additive_ticks = tick - geometric_exponent_delta * TICKS_PER_GEOMETRIC_EXPONENT;
+
+    let base = pow_ten(geometric_exponent_delta)?;
+    let additive = pow_ten(exponent_at_current_tick)?
+        .checked_mul(Decimal256::from_ratio(
+            num_additive_ticks.unsigned_abs(),
+            1u8,
+        ))
+        .map_err(StdError::overflow)?;
+
+    if num_additive_ticks >= 0 {
+        base.checked_add(additive).map_err(StdError::overflow)
+    } else {
+        base.checked_sub(additive).map_err(StdError::overflow)
+    }
+}
+
+/// Returns `10^exponent` as a `Decimal256`.
+fn pow_ten(exponent: i64) -> StdResult<Decimal256> {
+    let power = 10u128
+        .checked_pow(exponent.unsigned_abs() as u32)
+        .ok_or_else(|| StdError::generic_err(format!("10^{} is out of range", exponent)))?;
+
+    if exponent >= 0 {
+        Ok(Decimal256::from_ratio(power, 1u8))
+    } else {
+        Ok(Decimal256::from_ratio(1u8, power))
+    }
+}
+
+/// Parses a decimal string from the chain, which may have more fractional
+/// digits than `Decimal256` supports, e.g. the 36 decimals of the sqrt price of
+/// a pool. Extra fractional digits are truncated.
+pub fn parse_dec(value: &str) -> StdResult<Decimal256> {
+    let places = Decimal256::DECIMAL_PLACES as usize;
+    match value.split_once('.') {
+        Some((whole, fractional)) if fractional.len() > places => {
+            Decimal256::from_str(&format!("{}.{}", whole, &fractional[..places]))
+        }
+        _ => Decimal256::from_str(value),
+    }
+}
+
+/// Converts a decimal to a `Uint128`, rounding down.
+pub fn dec_to_uint_floor(value: Decimal256) -> StdResult<Uint128> {
+    let floor = value.atomics() / Uint256::from(10u128.pow(Decimal256::DECIMAL_PLACES));
+    Uint128::try_from(floor).map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+/// Returns the swap that converts balances of `amount0` token0 and `amount1`
+/// token1 into the ratio of a position between `lower_sqrt_price` and
+/// `upper_sqrt_price` at the current `sqrt_price` of the pool. The price impact
+/// and spread factor of the swap are ignored, so a small part of the balances
+/// may be left over when adding them to the position. Returns `None` if no swap
+/// is needed.
+pub fn swap_for_position_ratio(
+    amount0: Uint128,
+    amount1: Uint128,
+    sqrt_price: Decimal256,
+    lower_sqrt_price: Decimal256,
+    upper_sqrt_price: Decimal256,
+) -> StdResult<Option<RatioSwap>> {
+    // Below the range the position consists only of token0 and above the
+    // range only of token1
+    let swap = if sqrt_price <= lower_sqrt_price {
+        RatioSwap::OneForZero(amount1)
+    } else if sqrt_price >= upper_sqrt_price {
+        RatioSwap::ZeroForOne(amount0)
+    } else {
+        // The amounts of token0 and token1 per unit of liquidity in the range
+        let token0_per_liquidity =
+            (upper_sqrt_price - sqrt_price) / (sqrt_price * upper_sqrt_price);
+        let token1_per_liquidity = sqrt_price - lower_sqrt_price;
+        let price = sqrt_price * sqrt_price;
+
+        // The total value of the balances in token1, split so that the value of
+        // token0 matches the ratio of the position
+        let amount0 = Decimal256::from_ratio(amount0, 1u8);
+        let value = amount0 * price + Decimal256::from_ratio(amount1, 1u8);
+        let target_amount0 =
+            value * token0_per_liquidity / (token0_per_liquidity * price + token1_per_liquidity);
+
+        if amount0 > target_amount0 {
+            RatioSwap::ZeroForOne(dec_to_uint_floor(amount0 - target_amount0)?)
+        } else {
+            let amount_in = dec_to_uint_floor((target_amount0 - amount0) * price)?;
+            RatioSwap::OneForZero(amount_in.min(amount1))
+        }
+    };
+
+    match swap {
+        RatioSwap::ZeroForOne(amount) | RatioSwap::OneForZero(amount) if amount.is_zero() => {
+            Ok(None)
+        }
+        swap => Ok(Some(swap)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(0, "1" ; "price one")]
+    #[test_case(1, "1.000001" ; "one tick above price one")]
+    #[test_case(-1, "0.9999999" ; "one tick below price one")]
+    #[test_case(100_000, "1.1" ; "positive additive ticks")]
+    #[test_case(-100_000, "0.99" ; "negative additive ticks")]
+    #[test_case(1_000_000, "2" ; "price two")]
+    #[test_case(9_000_000, "10" ; "next geometric exponent")]
+    #[test_case(18_000_000, "100" ; "second geometric exponent")]
+    #[test_case(-9_000_000, "0.1" ; "previous geometric exponent")]
+    #[test_case(-9_000_001, "0.09999999" ; "below previous geometric exponent")]
+    fn test_tick_to_price(tick: i64, expected: &str) {
+        assert_eq!(
+            tick_to_price(tick).unwrap(),
+            Decimal256::from_str(expected).unwrap()
+        );
+    }
+
+    #[test_case("1", "1" ; "integer")]
+    #[test_case("1.5", "1.5" ; "few decimals")]
+    #[test_case("1.000000000000000000000000000000000001", "1" ; "truncates big dec")]
+    #[test_case("0.123456789012345678999", "0.123456789012345678" ; "truncates extra digits")]
+    fn test_parse_dec(value: &str, expected: &str) {
+        assert_eq!(
+            parse_dec(value).unwrap(),
+            Decimal256::from_str(expected).unwrap()
+        );
+    }
+
+    #[test_case(100, 0, "1", Some(RatioSwap::ZeroForOne(Uint128::new(50))) ; "only token0 in range")]
+    #[test_case(0, 100, "1", Some(RatioSwap::OneForZero(Uint128::new(50))) ; "only token1 in range")]
+    #[test_case(50, 50, "1", None ; "balanced in range")]
+    #[test_case(100, 100, "0.5", Some(RatioSwap::OneForZero(Uint128::new(100))) ; "below range")]
+    #[test_case(100, 100, "2", Some(RatioSwap::ZeroForOne(Uint128::new(100))) ; "above range")]
+    #[test_case(0, 100, "2", None ; "above range with only token1")]
+    fn test_swap_for_position_ratio(
+        amount0: u128,
+        amount1: u128,
+        sqrt_price: &str,
+        expected: Option<RatioSwap>,
+    ) {
+        // A range from sqrt price 0.5 to 2 holds equal amounts of both assets at
+        // a sqrt price of 1
+        let swap = swap_for_position_ratio(
+            Uint128::new(amount0),
+            Uint128::new(amount1),
+            Decimal256::from_str(sqrt_price).unwrap(),
+            Decimal256::from_str("0.5").unwrap(),
+            Decimal256::from_str("2").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(swap, expected);
+    }
+}
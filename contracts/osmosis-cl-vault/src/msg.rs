@@ -0,0 +1,134 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{to_binary, Addr, CosmosMsg, Env, StdResult, Uint128, WasmMsg};
+use cw_vault_standard::{VaultStandardExecuteMsg, VaultStandardQueryMsg};
+use cw_vault_token::osmosis::OsmosisDenom;
+
+use crate::state::{Config, ConfigUnchecked, ConfigUpdates, Position};
+
+/// ExecuteMsg for the concentrated liquidity vault. The base tokens of the
+/// vault are units of liquidity of its position. The pool assets of a
+/// `Deposit` are sent in `info.funds` and its `amount` is the minimum amount of
+/// liquidity that must be added to the position. A `Redeem` sends the pool
+/// assets withdrawn from the position to the recipient.
+pub type ExecuteMsg = VaultStandardExecuteMsg<ExtensionExecuteMsg>;
+
+/// QueryMsg for the concentrated liquidity vault.
+pub type QueryMsg = VaultStandardQueryMsg<ExtensionQueryMsg>;
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// Address that is allowed to update config and rebalance the position.
+    pub admin: String,
+    /// The ID of the concentrated liquidity pool that this vault will provide
+    /// liquidity to.
+    pub pool_id: u64,
+    /// The lower tick of the initial range of the position.
+    pub lower_tick: i64,
+    /// The upper tick of the initial range of the position.
+    pub upper_tick: i64,
+    /// Configurable parameters for the contract.
+    pub config: ConfigUnchecked,
+    /// The subdenom that will be used for the native vault token, e.g.
+    /// the denom of the vault token will be:
+    /// "factory/{vault_contract}/{vault_token_subdenom}".
+    pub vault_token_subdenom: String,
+}
+
+/// Extension execute messages for the concentrated liquidity vault
+#[cw_serde]
+pub enum ExtensionExecuteMsg {
+    /// Collect the spread rewards and incentives of the position and add them
+    /// to the position.
+    Compound {},
+    /// Withdraw the position and create a new one in the range
+    /// [`lower_tick`, `upper_tick`). Can only be called by the admin or the
+    /// keeper, and only when the current tick of the pool has left the range
+    /// of the position.
+    Rebalance {
+        /// The lower tick of the new range.
+        lower_tick: i64,
+        /// The upper tick of the new range.
+        upper_tick: i64,
+    },
+    /// Update the configuration of the vault.
+    UpdateConfig {
+        /// The config updates.
+        updates: ConfigUpdates,
+    },
+    /// Update the admin of the vault.
+    UpdateAdmin {
+        /// The new admin address.
+        address: String,
+    },
+    /// Execute a callback message.
+    Callback(CallbackMsg),
+}
+
+/// Callback messages for the concentrated liquidity vault. These can only be
+/// called by the contract itself.
+#[cw_serde]
+pub enum CallbackMsg {
+    /// Take the performance fee from the collected rewards and sell the reward
+    /// assets that are not pool assets.
+    SellRewards {},
+    /// Swap the pool assets held by the contract into the ratio of the
+    /// position.
+    SwapToPositionRatio {},
+    /// Add the pool assets held by the contract to the position.
+    AddToPosition {},
+    /// Create a new position in the range of the vault with the pool assets
+    /// held by the contract.
+    CreatePosition {},
+    /// Add the pending deposit to the position.
+    Deposit {},
+    /// Burn `amount` vault tokens and withdraw the liquidity they represent
+    /// to `recipient`.
+    Redeem {
+        /// Amount of vault tokens to redeem.
+        amount: Uint128,
+        /// The address to receive the withdrawn pool assets.
+        recipient: Addr,
+    },
+}
+
+impl CallbackMsg {
+    /// Convert the callback message to a [`CosmosMsg`]. The message will be
+    /// formatted as a `Callback` extension in a [`VaultStandardExecuteMsg`].
+    pub fn into_cosmos_msg(&self, env: &Env) -> StdResult<CosmosMsg> {
+        Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_binary(&VaultStandardExecuteMsg::VaultExtension(
+                ExtensionExecuteMsg::Callback(self.clone()),
+            ))?,
+            funds: vec![],
+        }))
+    }
+}
+
+/// Extension query messages for the concentrated liquidity vault
+#[cw_serde]
+pub enum ExtensionQueryMsg {
+    /// Returns the current state of the vault. Response: [`StateResponse`].
+    State {},
+}
+
+/// Response struct containing information about the current state of the vault.
+#[cw_serde]
+pub struct StateResponse {
+    /// The admin address. `None` if the admin is not set.
+    pub admin: Option<Addr>,
+    /// The config of the vault.
+    pub config: Config,
+    /// The position managed by the vault.
+    pub position: Position,
+    /// The liquidity of the position, rounded down. These are the base tokens
+    /// of the vault.
+    pub total_liquidity: Uint128,
+    /// The vault token.
+    pub vault_token: OsmosisDenom,
+    /// The total supply of the vault token.
+    pub vault_token_supply: Uint128,
+}
+
+#[cw_serde]
+pub struct MigrateMsg {}
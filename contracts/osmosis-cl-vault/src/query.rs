@@ -0,0 +1,31 @@
+use cosmwasm_std::{Deps, StdResult};
+use cw_vault_token::VaultToken;
+
+use crate::msg::StateResponse;
+use crate::ClVault;
+
+impl ClVault<'_> {
+    /// Returns the current state of the vault.
+    pub fn query_state(&self, deps: Deps) -> StdResult<StateResponse> {
+        let admin = self.admin.get(deps)?;
+        let total_liquidity = self
+            .base_vault
+            .total_staked_base_tokens
+            .load(deps.storage)?;
+
+        let vault_token = self.base_vault.vault_token.load(deps.storage)?;
+        let vault_token_supply = vault_token.query_total_supply(deps)?;
+
+        let config = self.config.load(deps.storage)?;
+        let position = self.position.load(deps.storage)?;
+
+        Ok(StateResponse {
+            admin,
+            config,
+            position,
+            total_liquidity,
+            vault_token,
+            vault_token_supply,
+        })
+    }
+}
@@ -0,0 +1,161 @@
+use apollo_cw_asset::{AssetInfo, AssetInfoBase};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Coin, Decimal, Deps, StdError, StdResult, Uint128};
+use cw_dex_router::helpers::CwDexRouterBase;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+//--------------------------------------------------------------------------------------------------
+// Config
+//--------------------------------------------------------------------------------------------------
+
+/// Base config struct for the contract.
+#[cw_serde]
+#[derive(Builder)]
+#[builder(derive(Serialize, Deserialize, Debug, PartialEq, JsonSchema))]
+pub struct ConfigBase<T> {
+    /// Percentage of the collected rewards to be charged as performance fee
+    pub performance_fee: Decimal,
+    /// Account to receive fee payments
+    pub treasury: T,
+    /// Router used to sell reward assets that are not pool assets
+    pub router: CwDexRouterBase<T>,
+    /// The assets that are collected as spread rewards and incentives that the
+    /// vault will compound into the position. The pool assets should be
+    /// included, since spread rewards are paid in them.
+    pub reward_assets: Vec<AssetInfoBase<T>>,
+    /// The asset to which we should swap reward assets that are not pool
+    /// assets before adding them to the position. Must be one of the pool
+    /// assets.
+    pub reward_liquidation_target: AssetInfoBase<T>,
+    /// Address that is allowed to rebalance the position besides the admin.
+    pub keeper: Option<T>,
+    /// Maximum slippage when swapping the pool assets into the ratio of the
+    /// position, relative to the current price of the pool.
+    pub max_swap_slippage: Decimal,
+}
+
+/// Config with non-validated addresses.
+pub type ConfigUnchecked = ConfigBase<String>;
+/// Config with validated addresses.
+pub type Config = ConfigBase<Addr>;
+/// Config updates struct containing same fields as Config, but all fields are
+/// optional.
+pub type ConfigUpdates = ConfigBaseBuilder<String>;
+
+/// Merges the old config with a new partial config.
+impl Config {
+    /// Updates the existing config with the new config updates. If a field is
+    /// `None` in the `updates` then the old config is kept, else it is updated
+    /// to the new value.
+    pub fn update(self, deps: Deps, updates: ConfigUpdates) -> StdResult<Config> {
+        ConfigUnchecked {
+            performance_fee: updates.performance_fee.unwrap_or(self.performance_fee),
+            treasury: updates.treasury.unwrap_or_else(|| self.treasury.into()),
+            router: updates.router.unwrap_or_else(|| self.router.into()),
+            reward_assets: updates
+                .reward_assets
+                .unwrap_or_else(|| self.reward_assets.into_iter().map(Into::into).collect()),
+            reward_liquidation_target: updates
+                .reward_liquidation_target
+                .unwrap_or_else(|| self.reward_liquidation_target.into()),
+            keeper: updates
+                .keeper
+                .unwrap_or_else(|| self.keeper.map(Into::into)),
+            max_swap_slippage: updates.max_swap_slippage.unwrap_or(self.max_swap_slippage),
+        }
+        .check(deps)
+    }
+
+    /// Returns true if `addr` is the keeper.
+    pub fn is_keeper(&self, addr: &Addr) -> bool {
+        self.keeper.as_ref() == Some(addr)
+    }
+}
+
+impl ConfigUnchecked {
+    /// Constructs a Config from the unchecked config, validating all addresses.
+    pub fn check(&self, deps: Deps) -> StdResult<Config> {
+        if self.performance_fee > Decimal::one() {
+            return Err(StdError::generic_err(
+                "Performance fee cannot be greater than 100%",
+            ));
+        }
+        if self.max_swap_slippage > Decimal::one() {
+            return Err(StdError::generic_err(
+                "Max swap slippage cannot be greater than 100%",
+            ));
+        }
+
+        let reward_assets: Vec<AssetInfo> = self
+            .reward_assets
+            .iter()
+            .map(|x| x.check(deps.api))
+            .collect::<StdResult<_>>()?;
+        let router = self.router.check(deps.api)?;
+        let reward_liquidation_target = self.reward_liquidation_target.check(deps.api)?;
+
+        Ok(Config {
+            performance_fee: self.performance_fee,
+            treasury: deps.api.addr_validate(&self.treasury)?,
+            reward_assets,
+            reward_liquidation_target,
+            router,
+            keeper: self
+                .keeper
+                .as_ref()
+                .map(|x| deps.api.addr_validate(x))
+                .transpose()?,
+            max_swap_slippage: self.max_swap_slippage,
+        })
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// State
+//--------------------------------------------------------------------------------------------------
+
+/// The concentrated liquidity position managed by the vault.
+#[cw_serde]
+pub struct Position {
+    /// The ID of the concentrated liquidity pool.
+    pub pool_id: u64,
+    /// The ID of the position. `None` if the vault has no liquidity. The ID
+    /// changes every time liquidity is added to the position.
+    pub position_id: Option<u64>,
+    /// The lower tick of the position's range.
+    pub lower_tick: i64,
+    /// The upper tick of the position's range.
+    pub upper_tick: i64,
+}
+
+impl Position {
+    /// Returns true if `tick` is within the range of the position, i.e. if the
+    /// position is earning spread rewards at `tick`.
+    pub fn is_in_range(&self, tick: i64) -> bool {
+        self.lower_tick <= tick && tick < self.upper_tick
+    }
+}
+
+/// A deposit that is waiting for the pending rewards to be compounded before
+/// it is added to the position.
+#[cw_serde]
+pub struct PendingDeposit {
+    /// The address that will receive the vault tokens.
+    pub recipient: Addr,
+    /// The pool assets that were deposited. These are not compounded.
+    pub funds: Vec<Coin>,
+    /// The minimum amount of liquidity that must be added to the position.
+    pub min_liquidity: Uint128,
+}
+
+impl PendingDeposit {
+    /// Returns the deposited amount of `denom`.
+    pub fn amount_of(&self, denom: &str) -> Uint128 {
+        self.funds
+            .iter()
+            .filter(|coin| coin.denom == denom)
+            .map(|coin| coin.amount)
+            .sum()
+    }
+}
@@ -0,0 +1,213 @@
+use apollo_cw_asset::AssetInfo;
+use base_vault::BaseVault;
+use cosmwasm_std::{
+    Addr, Binary, Coin, Decimal256, Deps, DepsMut, Env, QuerierWrapper, Response, StdError,
+    StdResult, Uint128,
+};
+use cw_controllers::Admin;
+use cw_storage_plus::Item;
+use cw_vault_token::osmosis::OsmosisDenom;
+use cw_vault_token::VaultToken;
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{
+    ConcentratedliquidityQuerier, Pool,
+};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::PoolmanagerQuerier;
+
+use crate::error::ContractError;
+use crate::math::{dec_to_uint_floor, parse_dec};
+use crate::state::{Config, PendingDeposit, Position};
+
+/// ClVault is a wrapper around BaseVault that manages a single concentrated
+/// liquidity position. The base tokens of the vault are units of liquidity of
+/// the position, so vault tokens represent a share of the position.
+pub struct ClVault<'a> {
+    /// The base vault implementation. Its `total_staked_base_tokens` is the
+    /// liquidity of the position, rounded down. The `base_token` item is not
+    /// used, since liquidity is not a token.
+    pub base_vault: BaseVault<'a, OsmosisDenom>,
+
+    /// The concentrated liquidity position managed by the vault.
+    pub position: Item<'a, Position>,
+
+    /// Configuration for this vault
+    pub config: Item<'a, Config>,
+
+    /// The admin address that is allowed to update the config.
+    pub admin: Admin<'a>,
+
+    /// Temporary storage of a deposit while the pending rewards are
+    /// compounded.
+    pub pending_deposit: Item<'a, PendingDeposit>,
+
+    /// Temporary storage of the recipient of a redemption until the position
+    /// has been withdrawn from.
+    pub pending_redeem_recipient: Item<'a, Addr>,
+}
+
+impl Default for ClVault<'_> {
+    fn default() -> Self {
+        Self {
+            base_vault: BaseVault::default(),
+            position: Item::new("position"),
+            config: Item::new("config"),
+            admin: Admin::new("admin"),
+            pending_deposit: Item::new("pending_deposit"),
+            pending_redeem_recipient: Item::new("pending_redeem_recipient"),
+        }
+    }
+}
+
+impl ClVault<'_> {
+    /// Save values for all of the Items in the struct and instantiate the
+    /// vault token.
+    pub fn init(
+        &self,
+        mut deps: DepsMut,
+        admin: Addr,
+        position: Position,
+        config: Config,
+        vault_token: OsmosisDenom,
+    ) -> Result<Response, ContractError> {
+        let pool = query_cl_pool(&deps.querier, position.pool_id)?;
+        assert_valid_tick_range(&pool, position.lower_tick, position.upper_tick)?;
+        assert_pool_asset(&pool, &config.reward_liquidation_target)?;
+
+        self.position.save(deps.storage, &position)?;
+        self.config.save(deps.storage, &config)?;
+        self.admin.set(deps.branch(), Some(admin))?;
+
+        self.base_vault
+            .vault_token
+            .save(deps.storage, &vault_token)?;
+        self.base_vault
+            .total_staked_base_tokens
+            .save(deps.storage, &Uint128::zero())?;
+
+        Ok(vault_token.instantiate(deps, None)?)
+    }
+
+    /// Returns the concentrated liquidity pool of the vault.
+    pub fn query_pool(&self, deps: Deps) -> StdResult<Pool> {
+        let position = self.position.load(deps.storage)?;
+        query_cl_pool(&deps.querier, position.pool_id)
+    }
+
+    /// Returns the contract's balance of `denom`, excluding the funds of a
+    /// pending deposit.
+    pub fn free_balance(&self, deps: Deps, env: &Env, denom: &str) -> StdResult<Uint128> {
+        let balance = deps
+            .querier
+            .query_balance(&env.contract.address, denom)?
+            .amount;
+        let reserved = self
+            .pending_deposit
+            .may_load(deps.storage)?
+            .map(|deposit| deposit.amount_of(denom))
+            .unwrap_or_default();
+
+        Ok(balance.saturating_sub(reserved))
+    }
+
+    /// Returns the contract's balances of the pool assets, excluding the funds
+    /// of a pending deposit, as coins with a non-zero amount sorted by denom.
+    pub fn free_pool_balances(&self, deps: Deps, env: &Env, pool: &Pool) -> StdResult<Vec<Coin>> {
+        let mut denoms = [pool.token0.clone(), pool.token1.clone()];
+        denoms.sort();
+
+        Ok(denoms
+            .into_iter()
+            .map(|denom| {
+                Ok(Coin {
+                    amount: self.free_balance(deps, env, &denom)?,
+                    denom,
+                })
+            })
+            .collect::<StdResult<Vec<_>>>()?
+            .into_iter()
+            .filter(|coin| !coin.amount.is_zero())
+            .collect())
+    }
+
+    /// Saves the ID of the vault's position after it has been created or
+    /// changed, and updates the total liquidity to the liquidity of the
+    /// position. Returns the new total liquidity.
+    pub fn update_position(
+        &self,
+        deps: DepsMut,
+        position_id: u64,
+    ) -> Result<Uint128, ContractError> {
+        let mut position = self.position.load(deps.storage)?;
+        position.position_id = Some(position_id);
+        self.position.save(deps.storage, &position)?;
+
+        let liquidity = dec_to_uint_floor(query_position_liquidity(&deps.querier, position_id)?)?;
+        self.base_vault
+            .total_staked_base_tokens
+            .save(deps.storage, &liquidity)?;
+
+        Ok(liquidity)
+    }
+}
+
+/// Returns the concentrated liquidity pool with ID `pool_id`. Returns an error
+/// if the pool is not a concentrated liquidity pool.
+pub fn query_cl_pool(querier: &QuerierWrapper, pool_id: u64) -> StdResult<Pool> {
+    let pool = PoolmanagerQuerier::new(querier)
+        .pool(pool_id)?
+        .pool
+        .ok_or_else(|| StdError::generic_err(format!("Pool {} not found", pool_id)))?;
+
+    if pool.type_url != Pool::TYPE_URL {
+        return Err(StdError::generic_err(format!(
+            "Pool {} is not a concentrated liquidity pool",
+            pool_id
+        )));
+    }
+
+    Pool::try_from(Binary::from(pool.value))
+}
+
+/// Returns the liquidity of the position with ID `position_id`.
+pub fn query_position_liquidity(
+    querier: &QuerierWrapper,
+    position_id: u64,
+) -> StdResult<Decimal256> {
+    let position = ConcentratedliquidityQuerier::new(querier)
+        .position_by_id(position_id)?
+        .position
+        .and_then(|breakdown| breakdown.position)
+        .ok_or_else(|| StdError::generic_err(format!("Position {} not found", position_id)))?;
+
+    parse_dec(&position.liquidity)
+}
+
+/// Asserts that the range [`lower_tick`, `upper_tick`) is non-empty and that
+/// both ticks are multiples of the tick spacing of the pool.
+pub fn assert_valid_tick_range(
+    pool: &Pool,
+    lower_tick: i64,
+    upper_tick: i64,
+) -> Result<(), ContractError> {
+    let tick_spacing = pool.tick_spacing as i64;
+    if lower_tick >= upper_tick || lower_tick % tick_spacing != 0 || upper_tick % tick_spacing != 0
+    {
+        return Err(ContractError::InvalidTickRange {
+            lower_tick,
+            upper_tick,
+            tick_spacing: pool.tick_spacing,
+        });
+    }
+    Ok(())
+}
+
+/// Asserts that `asset` is one of the assets of the pool.
+pub fn assert_pool_asset(pool: &Pool, asset: &AssetInfo) -> Result<(), ContractError> {
+    let pool_assets = vec![pool.token0.clone(), pool.token1.clone()];
+    match asset {
+        AssetInfo::Native(denom) if pool_assets.contains(denom) => Ok(()),
+        _ => Err(ContractError::InvalidRewardLiquidationTarget {
+            expected: pool_assets,
+            actual: asset.to_string(),
+        }),
+    }
+}
@@ -0,0 +1,422 @@
+use std::str::FromStr;
+
+use apollo_cw_asset::AssetInfoUnchecked;
+use cosmwasm_std::{Binary, Coin, Decimal, Empty, Uint128};
+use cw_dex_router::helpers::CwDexRouterUnchecked;
+use osmosis_cl_vault::msg::{
+    ExecuteMsg, ExtensionExecuteMsg, ExtensionQueryMsg, InstantiateMsg, QueryMsg, StateResponse,
+};
+use osmosis_cl_vault::state::ConfigUnchecked;
+use osmosis_std::types::cosmos::bank::v1beta1::QueryBalanceRequest;
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{
+    CreateConcentratedLiquidityPoolsProposal, MsgCreatePosition, MsgCreatePositionResponse, Pool,
+    PoolRecord,
+};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSwapExactAmountIn, MsgSwapExactAmountInResponse, NumPoolsRequest, NumPoolsResponse,
+    PoolRequest, PoolResponse, SwapAmountInRoute,
+};
+use osmosis_test_tube::{
+    Account, Bank, GovWithAppAccess, Module, OsmosisTestApp, Runner, SigningAccount, Wasm,
+};
+
+const UATOM: &str = "uatom";
+const UOSMO: &str = "uosmo";
+const PERFORMANCE_FEE: Decimal = Decimal::raw(5 * 10u128.pow(16)); // 5%
+const TICK_SPACING: u64 = 100;
+const MIN_TICK: i64 = -108_000_000;
+const MAX_TICK: i64 = 342_000_000;
+/// The range of the vault position is between a price of 0.9 and 2
+const LOWER_TICK: i64 = -1_000_000;
+const UPPER_TICK: i64 = 1_000_000;
+const VAULT_WASM_PATH: &str = "target/wasm32-unknown-unknown/release/osmosis_cl_vault.wasm";
+const ROUTER_WASM_PATH: &str = "../osmosis-vault/tests/artifacts/cw_dex_router_osmosis.wasm";
+
+/// Set up the concentrated liquidity pool and the contracts needed to run the
+/// tests. The pool starts at a price of 1 with full range liquidity.
+/// Returns (String,u64) of (vault_addr, pool_id)
+pub fn setup_test(app: &OsmosisTestApp, accs: &[SigningAccount]) -> (String, u64) {
+    let wasm = Wasm::new(app);
+
+    let admin = &accs[0];
+    let treasury = &accs[2];
+    let keeper = &accs[5];
+
+    // Create the concentrated liquidity pool through governance
+    GovWithAppAccess::new(app)
+        .propose_and_execute(
+            CreateConcentratedLiquidityPoolsProposal::TYPE_URL.to_string(),
+            CreateConcentratedLiquidityPoolsProposal {
+                title: "Create uatom/uosmo pool".to_string(),
+                description: "Create a concentrated liquidity uatom/uosmo pool".to_string(),
+                pool_records: vec![PoolRecord {
+                    denom0: UATOM.to_string(),
+                    denom1: UOSMO.to_string(),
+                    tick_spacing: TICK_SPACING,
+                    spread_factor: "0.003".to_string(),
+                    ..Default::default()
+                }],
+            },
+            admin.address(),
+            false,
+            admin,
+        )
+        .unwrap();
+    let pool_id = app
+        .query::<_, NumPoolsResponse>(
+            "/osmosis.poolmanager.v1beta1.Query/NumPools",
+            &NumPoolsRequest {},
+        )
+        .unwrap()
+        .num_pools;
+    println!("Pool ID: {}", pool_id);
+
+    // Provide full range liquidity, which sets the price of the pool to 1
+    app.execute::<_, MsgCreatePositionResponse>(
+        MsgCreatePosition {
+            pool_id,
+            sender: admin.address(),
+            lower_tick: MIN_TICK,
+            upper_tick: MAX_TICK,
+            tokens_provided: vec![
+                Coin::new(1_000_000_000_000, UATOM).into(),
+                Coin::new(1_000_000_000_000, UOSMO).into(),
+            ],
+            token_min_amount0: "0".to_string(),
+            token_min_amount1: "0".to_string(),
+        },
+        MsgCreatePosition::TYPE_URL,
+        admin,
+    )
+    .unwrap();
+
+    // Upload wasm files
+    let store_code = |path: &str| {
+        wasm.store_code(&std::fs::read(path).unwrap(), None, admin)
+            .unwrap()
+            .data
+            .code_id
+    };
+    let router_code_id = store_code(ROUTER_WASM_PATH);
+    let vault_code_id = store_code(VAULT_WASM_PATH);
+
+    // Instantiate CwDexRouter. The spread rewards are paid in the pool assets,
+    // so no paths are needed.
+    let cw_dex_router = wasm
+        .instantiate(
+            router_code_id,
+            &Empty {},
+            None,
+            Some("cw-dex-router"),
+            &[],
+            admin,
+        )
+        .unwrap()
+        .data
+        .address;
+
+    let config = ConfigUnchecked {
+        performance_fee: PERFORMANCE_FEE,
+        treasury: treasury.address(),
+        router: CwDexRouterUnchecked::new(&cw_dex_router),
+        reward_assets: vec![
+            AssetInfoUnchecked::Native(UATOM.to_string()),
+            AssetInfoUnchecked::Native(UOSMO.to_string()),
+        ],
+        reward_liquidation_target: AssetInfoUnchecked::Native(UOSMO.to_string()),
+        keeper: Some(keeper.address()),
+        max_swap_slippage: Decimal::percent(5),
+    };
+
+    // Instantiate osmosis cl vault contract
+    let vault_addr = wasm
+        .instantiate(
+            vault_code_id,
+            &InstantiateMsg {
+                admin: admin.address(),
+                pool_id,
+                lower_tick: LOWER_TICK,
+                upper_tick: UPPER_TICK,
+                config,
+                vault_token_subdenom: "osmosis-cl-vault".to_string(),
+            },
+            Some(&admin.address()),
+            Some("osmosis-cl-vault"),
+            // 10 OSMO needed to create vault token
+            &[Coin::new(10_000_000, UOSMO)],
+            admin,
+        )
+        .unwrap()
+        .data
+        .address;
+
+    println!(" ------ Addresses -------");
+    println!("admin: {}", admin.address());
+    println!("treasury: {}", treasury.address());
+    println!("keeper: {}", keeper.address());
+
+    println!(" ------ Contracts -------");
+    println!("Vault: {}", vault_addr);
+    println!("CwDexRouter: {}", cw_dex_router);
+    println!("-----------------------------------");
+
+    (vault_addr, pool_id)
+}
+
+#[test]
+pub fn test_osmosis_cl_vault_functionality() {
+    let app = OsmosisTestApp::default();
+    let accs = app
+        .init_accounts(
+            &[
+                Coin::new(1_000_000_000_000_000_000_000_000, UATOM),
+                Coin::new(1_000_000_000_000_000_000_000_000, UOSMO),
+            ],
+            6,
+        )
+        .unwrap();
+
+    let treasury = &accs[2];
+    let user1 = &accs[3];
+    let user2 = &accs[4];
+    let keeper = &accs[5];
+
+    let wasm = Wasm::new(&app);
+
+    // Setup test
+    let (vault_addr, pool_id) = setup_test(&app, &accs);
+
+    let state = query_vault_state(&app, &vault_addr);
+    let vault_token_denom = state.vault_token.to_string();
+    assert_eq!(state.position.position_id, None);
+
+    // Depositing with a minimum liquidity that cannot be reached should fail
+    let deposit_funds = [
+        Coin::new(1_000_000_000, UATOM),
+        Coin::new(1_000_000_000, UOSMO),
+    ];
+    let res = wasm
+        .execute(
+            &vault_addr,
+            &ExecuteMsg::Deposit {
+                amount: Uint128::MAX,
+                recipient: None,
+            },
+            &deposit_funds,
+            user1,
+        )
+        .unwrap_err();
+    assert!(res.to_string().contains("less than the minimum"));
+
+    // Deposit. At a price of 1 the position mostly consists of uatom, so part
+    // of the uosmo should be refunded.
+    let uosmo_balance_before = query_token_balance(&app, &user1.address(), UOSMO);
+    wasm.execute(
+        &vault_addr,
+        &ExecuteMsg::Deposit {
+            amount: Uint128::zero(),
+            recipient: None,
+        },
+        &deposit_funds,
+        user1,
+    )
+    .unwrap();
+    let uosmo_spent = uosmo_balance_before - query_token_balance(&app, &user1.address(), UOSMO);
+    assert!(uosmo_spent < Uint128::new(1_000_000_000));
+
+    let state = query_vault_state(&app, &vault_addr);
+    let position_id = state.position.position_id.unwrap();
+    assert!(state.total_liquidity > Uint128::zero());
+    let vault_token_balance = query_token_balance(&app, &user1.address(), &vault_token_denom);
+    assert_eq!(vault_token_balance, state.vault_token_supply);
+    assert!(vault_token_balance > Uint128::zero());
+
+    // Swap back and forth to generate spread rewards
+    swap(
+        &app,
+        user2,
+        pool_id,
+        Coin::new(10_000_000_000, UOSMO),
+        UATOM,
+    );
+    swap(
+        &app,
+        user2,
+        pool_id,
+        Coin::new(10_000_000_000, UATOM),
+        UOSMO,
+    );
+
+    // Compound the spread rewards into the position
+    let treasury_uatom_before = query_token_balance(&app, &treasury.address(), UATOM);
+    wasm.execute(
+        &vault_addr,
+        &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Compound {}),
+        &[],
+        user2,
+    )
+    .unwrap();
+    let state_after_compound = query_vault_state(&app, &vault_addr);
+    assert!(state_after_compound.total_liquidity > state.total_liquidity);
+    assert_ne!(state_after_compound.position.position_id, Some(position_id));
+    assert_eq!(
+        state_after_compound.vault_token_supply,
+        state.vault_token_supply
+    );
+    assert!(query_token_balance(&app, &treasury.address(), UATOM) > treasury_uatom_before);
+
+    // Rebalancing should fail while the price is in range, and for anyone but
+    // the admin and the keeper
+    let rebalance_msg = |lower_tick: i64, upper_tick: i64| {
+        ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Rebalance {
+            lower_tick,
+            upper_tick,
+        })
+    };
+    let res = wasm
+        .execute(&vault_addr, &rebalance_msg(0, UPPER_TICK * 2), &[], keeper)
+        .unwrap_err();
+    assert!(res.to_string().contains("is in the range of the position"));
+
+    // Move the price above the range of the position
+    swap(
+        &app,
+        user2,
+        pool_id,
+        Coin::new(1_000_000_000_000, UOSMO),
+        UATOM,
+    );
+    let current_tick = query_pool(&app, pool_id).current_tick;
+    assert!(current_tick >= UPPER_TICK);
+    let tick = current_tick - current_tick % TICK_SPACING as i64;
+    let (new_lower_tick, new_upper_tick) = (tick - 1_000_000, tick + 1_000_000);
+
+    let res = wasm
+        .execute(
+            &vault_addr,
+            &rebalance_msg(new_lower_tick, new_upper_tick),
+            &[],
+            user2,
+        )
+        .unwrap_err();
+    assert!(res.to_string().contains("Unauthorized"));
+
+    // Rebalance around the current price
+    wasm.execute(
+        &vault_addr,
+        &rebalance_msg(new_lower_tick, new_upper_tick),
+        &[],
+        keeper,
+    )
+    .unwrap();
+    let state = query_vault_state(&app, &vault_addr);
+    assert_eq!(state.position.lower_tick, new_lower_tick);
+    assert_eq!(state.position.upper_tick, new_upper_tick);
+    assert!(state.position.position_id.is_some());
+    assert_ne!(
+        state.position.position_id,
+        state_after_compound.position.position_id
+    );
+    assert!(state.total_liquidity > Uint128::zero());
+    assert_eq!(
+        state.vault_token_supply,
+        state_after_compound.vault_token_supply
+    );
+
+    // Redeem half of the vault tokens. The position is in range, so both pool
+    // assets should be received.
+    let uatom_balance_before = query_token_balance(&app, &user1.address(), UATOM);
+    let uosmo_balance_before = query_token_balance(&app, &user1.address(), UOSMO);
+    let redeem_amount = vault_token_balance / Uint128::new(2);
+    wasm.execute(
+        &vault_addr,
+        &ExecuteMsg::Redeem {
+            amount: redeem_amount,
+            recipient: None,
+        },
+        &[Coin::new(redeem_amount.u128(), vault_token_denom.clone())],
+        user1,
+    )
+    .unwrap();
+    assert!(query_token_balance(&app, &user1.address(), UATOM) > uatom_balance_before);
+    assert!(query_token_balance(&app, &user1.address(), UOSMO) > uosmo_balance_before);
+    let state = query_vault_state(&app, &vault_addr);
+    assert_eq!(
+        state.vault_token_supply,
+        vault_token_balance - redeem_amount
+    );
+
+    // Redeem the rest, which withdraws the whole position
+    let redeem_amount = vault_token_balance - redeem_amount;
+    wasm.execute(
+        &vault_addr,
+        &ExecuteMsg::Redeem {
+            amount: redeem_amount,
+            recipient: None,
+        },
+        &[Coin::new(redeem_amount.u128(), vault_token_denom)],
+        user1,
+    )
+    .unwrap();
+    let state = query_vault_state(&app, &vault_addr);
+    assert_eq!(state.position.position_id, None);
+    assert_eq!(state.total_liquidity, Uint128::zero());
+    assert_eq!(state.vault_token_supply, Uint128::zero());
+}
+
+fn query_vault_state(app: &OsmosisTestApp, vault_addr: &str) -> StateResponse {
+    Wasm::new(app)
+        .query(
+            vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::State {}),
+        )
+        .unwrap()
+}
+
+fn query_pool(app: &OsmosisTestApp, pool_id: u64) -> Pool {
+    let pool = app
+        .query::<_, PoolResponse>(
+            "/osmosis.poolmanager.v1beta1.Query/Pool",
+            &PoolRequest { pool_id },
+        )
+        .unwrap()
+        .pool
+        .unwrap();
+    Pool::try_from(Binary::from(pool.value)).unwrap()
+}
+
+fn query_token_balance(app: &OsmosisTestApp, address: &str, denom: &str) -> Uint128 {
+    let balance = Bank::new(app)
+        .query_balance(&QueryBalanceRequest {
+            address: address.to_string(),
+            denom: denom.to_string(),
+        })
+        .unwrap()
+        .balance
+        .unwrap_or_default()
+        .amount;
+    Uint128::from_str(&balance).unwrap()
+}
+
+fn swap(
+    app: &OsmosisTestApp,
+    signer: &SigningAccount,
+    pool_id: u64,
+    token_in: Coin,
+    token_out_denom: &str,
+) {
+    app.execute::<_, MsgSwapExactAmountInResponse>(
+        MsgSwapExactAmountIn {
+            sender: signer.address(),
+            routes: vec![SwapAmountInRoute {
+                pool_id,
+                token_out_denom: token_out_denom.to_string(),
+            }],
+            token_in: Some(token_in.into()),
+            token_out_min_amount: "1".to_string(),
+        },
+        MsgSwapExactAmountIn::TYPE_URL,
+        signer,
+    )
+    .unwrap();
+}
@@ -0,0 +1,492 @@
+use std::str::FromStr;
+
+use apollo_vault::error::ContractError;
+use apollo_vault::migrations::migrate_contract;
+use apollo_vault::msg::{
+    ApolloExtensionExecuteMsg, ApolloExtensionQueryMsg, CallbackMsg, ExtensionQueryMsg,
+    CALLBACK_DATA_REPLY_ID,
+};
+use apollo_vault::unlocking_position_nft::UNLOCKING_POSITION_NFT_INSTANTIATE_REPLY_ID;
+use apollo_vault::AutocompoundingVault;
+
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError, StdResult,
+    SubMsgResponse, SubMsgResult, Uint128,
+};
+use cw2::set_contract_version;
+use cw_dex::osmosis::OsmosisPool;
+use cw_dex::traits::{LockedStaking, Pool};
+use cw_vault_standard::extensions::lockup::{LockupExecuteMsg, LockupQueryMsg};
+use cw_vault_standard::msg::{VaultInfoResponse, VaultStandardInfoResponse};
+use cw_vault_token::osmosis::OsmosisDenom;
+use osmosis_std::types::cosmos::base::v1beta1::Coin;
+use osmosis_std::types::osmosis::lockup::LockupQuerier;
+use osmosis_std::types::osmosis::superfluid::{
+    MsgLockAndSuperfluidDelegateResponse, MsgSuperfluidUndelegateAndUnbondLockResponse,
+};
+
+use crate::msg::{
+    ExecuteMsg, ExtensionExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg,
+    SuperfluidExtensionExecuteMsg,
+};
+use crate::staking::{
+    SuperfluidStaking, SUPERFLUID_LOCK_AND_DELEGATE_REPLY_ID,
+    SUPERFLUID_UNDELEGATE_AND_UNBOND_REPLY_ID,
+};
+use crate::validator;
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:osmosis-superfluid-vault";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Constants passed to VaultStandardInfo query
+const VAULT_STANDARD_VERSION: u16 = 1;
+const VAULT_STANDARD_EXTENSIONS: [&str; 1] = ["lockup"];
+
+pub type OsmosisSuperfluidVaultContract<'a> =
+    AutocompoundingVault<'a, SuperfluidStaking, OsmosisPool, OsmosisDenom>;
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    let contract = OsmosisSuperfluidVaultContract::default();
+
+    let admin_addr = deps.api.addr_validate(&msg.admin)?;
+    let config = msg.config.check(deps.as_ref())?;
+
+    // Validate that 10 osmo for vault token creation are sent
+    let osmo_amount = info
+        .funds
+        .iter()
+        .find(|coin| coin.denom == "uosmo")
+        .map(|coin| coin.amount)
+        .unwrap_or_default();
+    if osmo_amount < Uint128::new(10_000_000) {
+        return Err(ContractError::from(
+            "A minimum of 10_000_000 uosmo must be sent to create the vault token",
+        ));
+    }
+
+    // Create the pool object
+    let pool = OsmosisPool::new(msg.pool_id, deps.as_ref())?;
+
+    let staking = SuperfluidStaking::new(
+        msg.lockup_duration,
+        pool.lp_token().to_string(),
+        msg.validator_address,
+    )?;
+
+    let vault_token = OsmosisDenom::new(env.contract.address.to_string(), msg.vault_token_subdenom);
+
+    // Optionally instantiate a cw721 contract to represent unlocking positions
+    let instantiate_nft_msg = msg
+        .unlocking_position_nft
+        .map(|info| contract.instantiate_unlocking_position_nft(&env, info))
+        .transpose()?;
+
+    if let Some(epoch_duration) = msg.unlock_epoch_duration {
+        contract
+            .unlock_batches
+            .set_epoch_duration(deps.storage, epoch_duration)?;
+    }
+
+    Ok(contract
+        .init(deps, admin_addr, pool, staking, config, vault_token, None)?
+        .add_submessages(instantiate_nft_msg))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    let contract = OsmosisSuperfluidVaultContract::default();
+
+    match msg {
+        ExecuteMsg::Deposit { amount, recipient } => {
+            contract.execute_deposit(deps, env, &info, amount, recipient)
+        }
+        ExecuteMsg::Redeem { recipient, amount } => {
+            contract.execute_instant_redeem(deps, env, &info, amount, recipient)
+        }
+        ExecuteMsg::VaultExtension(msg) => match msg {
+            ExtensionExecuteMsg::Lockup(msg) => match msg {
+                LockupExecuteMsg::WithdrawUnlocked {
+                    recipient,
+                    lockup_id,
+                } => contract.execute_withdraw_unlocked(deps, env, &info, lockup_id, recipient),
+                LockupExecuteMsg::Unlock { amount } => {
+                    contract.execute_unlock(deps, env, &info, amount, None)
+                }
+            },
+            ExtensionExecuteMsg::Apollo(msg) => match msg {
+                ApolloExtensionExecuteMsg::UpdateConfig { updates } => {
+                    contract.execute_update_config(deps, env, info, updates)
+                }
+                ApolloExtensionExecuteMsg::UpdateAdmin {
+                    address,
+                    expiration,
+                } => contract.execute_update_admin(deps, env, info, address, expiration),
+                ApolloExtensionExecuteMsg::AcceptAdminTransfer {} => {
+                    contract.execute_accept_admin_transfer(deps, env, info)
+                }
+                ApolloExtensionExecuteMsg::RenounceAdmin { expiration } => {
+                    contract.execute_renounce_admin(deps, env, info, expiration)
+                }
+                ApolloExtensionExecuteMsg::ConfirmRenounceAdmin {} => {
+                    contract.execute_confirm_renounce_admin(deps, env, info)
+                }
+                ApolloExtensionExecuteMsg::DropAdminTransfer {} => {
+                    contract.execute_drop_admin_transfer(deps, env, info)
+                }
+                ApolloExtensionExecuteMsg::Unlock { amount, owner } => {
+                    contract.execute_unlock(deps, env, &info, amount, owner)
+                }
+                ApolloExtensionExecuteMsg::WithdrawAllUnlocked { recipient, limit } => {
+                    contract.execute_withdraw_all_unlocked(deps, env, &info, recipient, limit)
+                }
+                ApolloExtensionExecuteMsg::SetAutoWithdraw { enabled } => {
+                    contract.execute_set_auto_withdraw(deps, &info, enabled)
+                }
+                ApolloExtensionExecuteMsg::ProcessMaturedClaims { limit } => {
+                    contract.execute_process_matured_claims(deps, env, &info, limit)
+                }
+                ApolloExtensionExecuteMsg::TransferUnlockingPosition {
+                    lockup_id,
+                    new_owner,
+                } => contract
+                    .execute_transfer_unlocking_position(deps, env, &info, lockup_id, new_owner),
+                ApolloExtensionExecuteMsg::ApproveUnlockingPositionOperator {
+                    operator,
+                    expires,
+                } => contract.execute_approve_unlocking_position_operator(
+                    deps, env, &info, operator, expires,
+                ),
+                ApolloExtensionExecuteMsg::RevokeUnlockingPositionOperator { operator } => {
+                    contract.execute_revoke_unlocking_position_operator(deps, &info, operator)
+                }
+                ApolloExtensionExecuteMsg::ReindexClaims { limit } => {
+                    contract.execute_reindex_claims(deps, &info, limit)
+                }
+                ApolloExtensionExecuteMsg::ProcessUnlockBatch {} => {
+                    contract.execute_process_unlock_batch(deps, env, &info)
+                }
+                ApolloExtensionExecuteMsg::UpdateUnlockEpochDuration { epoch_duration } => {
+                    contract.execute_update_unlock_epoch_duration(deps, env, info, epoch_duration)
+                }
+                ApolloExtensionExecuteMsg::WithdrawUnlockBatch {
+                    batch_id,
+                    recipient,
+                } => contract.execute_withdraw_unlock_batch(deps, env, &info, batch_id, recipient),
+            },
+            ExtensionExecuteMsg::Superfluid(msg) => match msg {
+                SuperfluidExtensionExecuteMsg::UpdateValidator { validator_address } => {
+                    validator::execute_update_validator(
+                        deps,
+                        env,
+                        info,
+                        &contract,
+                        validator_address,
+                    )
+                }
+                SuperfluidExtensionExecuteMsg::Redelegate {} => {
+                    validator::execute_redelegate(deps, env, &contract)
+                }
+            },
+            ExtensionExecuteMsg::Callback(msg) => {
+                // Assert that only the contract itself can call this
+                if info.sender != env.contract.address {
+                    return Err(ContractError::Unauthorized {});
+                }
+
+                match msg {
+                    CallbackMsg::SellRewards {} => {
+                        contract.execute_callback_sell_rewards(deps, env, info)
+                    }
+                    CallbackMsg::ProvideLiquidity {} => {
+                        contract.execute_callback_provide_liquidity(deps, env, info)
+                    }
+                    CallbackMsg::Stake {
+                        base_token_balance_before,
+                    } => contract.execute_callback_stake(deps, env, base_token_balance_before),
+                    CallbackMsg::MintVaultToken { amount, recipient } => {
+                        contract.execute_callback_mint_vault_token(deps, env, amount, recipient)
+                    }
+                    CallbackMsg::Unlock {
+                        owner,
+                        vault_token_amount,
+                    } => {
+                        contract.execute_callback_unlock(deps, env, info, owner, vault_token_amount)
+                    }
+                    CallbackMsg::SaveClaim {} => contract.execute_callback_save_claim(deps),
+                    CallbackMsg::SaveUnlockBatchClaim { batch_id } => {
+                        contract.execute_callback_save_unlock_batch_claim(deps, batch_id)
+                    }
+                    CallbackMsg::RedeemFromBuffer { amount, recipient } => {
+                        contract.execute_callback_redeem_from_buffer(deps, env, amount, recipient)
+                    }
+                }
+            }
+        },
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    let contract = OsmosisSuperfluidVaultContract::default();
+    let base_vault = &contract.base_vault;
+
+    match msg {
+        QueryMsg::VaultStandardInfo {} => to_binary(&VaultStandardInfoResponse {
+            version: VAULT_STANDARD_VERSION,
+            extensions: VAULT_STANDARD_EXTENSIONS
+                .iter()
+                .map(|&s| s.into())
+                .collect(),
+        }),
+        QueryMsg::Info {} => {
+            let vault_token = base_vault.vault_token.load(deps.storage)?;
+            let base_token = base_vault.base_token.load(deps.storage)?;
+
+            to_binary(&VaultInfoResponse {
+                base_token: base_token.to_string(),
+                vault_token: vault_token.to_string(),
+            })
+        }
+        QueryMsg::PreviewDeposit { amount } => {
+            to_binary(&base_vault.query_simulate_deposit(deps, amount)?)
+        }
+        QueryMsg::PreviewRedeem { amount } => {
+            to_binary(&contract.query_preview_instant_redeem(deps, amount)?)
+        }
+        QueryMsg::TotalAssets {} => to_binary(&base_vault.query_total_assets(deps)?),
+        QueryMsg::TotalVaultTokenSupply {} => {
+            to_binary(&base_vault.query_total_vault_token_supply(deps)?)
+        }
+        QueryMsg::ConvertToShares { amount } => {
+            to_binary(&base_vault.query_simulate_deposit(deps, amount)?)
+        }
+        QueryMsg::ConvertToAssets { amount } => {
+            to_binary(&base_vault.query_simulate_withdraw(deps, amount)?)
+        }
+        QueryMsg::VaultExtension(msg) => match msg {
+            ExtensionQueryMsg::Lockup(msg) => match msg {
+                LockupQueryMsg::UnlockingPositions {
+                    owner,
+                    start_after,
+                    limit,
+                } => to_binary(&contract.query_unlocking_positions(
+                    deps,
+                    owner,
+                    start_after,
+                    limit,
+                )?),
+                LockupQueryMsg::UnlockingPosition { lockup_id } => {
+                    to_binary(&contract.claims.query_claim_by_id(deps, lockup_id)?)
+                }
+                LockupQueryMsg::LockupDuration {} => to_binary(
+                    &contract
+                        .staking
+                        .load(deps.storage)?
+                        .get_lockup_duration(deps)?,
+                ),
+            },
+            ExtensionQueryMsg::Apollo(msg) => match msg {
+                ApolloExtensionQueryMsg::State {} => to_binary(&contract.query_state(deps, env)?),
+                ApolloExtensionQueryMsg::Config {} => to_binary(&contract.query_config(deps)?),
+                ApolloExtensionQueryMsg::Admin {} => to_binary(&contract.query_admin(deps)?),
+                ApolloExtensionQueryMsg::PendingAdminTransfer {} => {
+                    to_binary(&contract.query_pending_admin_transfer(deps)?)
+                }
+                ApolloExtensionQueryMsg::ForceWithdrawWhitelist {} => {
+                    to_binary(&contract.query_force_withdraw_whitelist(deps)?)
+                }
+                ApolloExtensionQueryMsg::ContractVersion {} => {
+                    to_binary(&contract.query_contract_version(deps)?)
+                }
+                ApolloExtensionQueryMsg::RewardRoutes {} => {
+                    to_binary(&contract.query_reward_routes(deps)?)
+                }
+                ApolloExtensionQueryMsg::Solvency {} => {
+                    let (staked, unlocking) = query_lockup_module_balances(deps, &env, &contract)?;
+                    to_binary(&contract.query_solvency(deps, env, staked, unlocking)?)
+                }
+                ApolloExtensionQueryMsg::UnlockingPositionOperators {
+                    owner,
+                    start_after,
+                    limit,
+                } => to_binary(&contract.query_unlocking_position_operators(
+                    deps,
+                    owner,
+                    start_after,
+                    limit,
+                )?),
+                ApolloExtensionQueryMsg::AutoWithdraw { owner } => {
+                    to_binary(&contract.query_auto_withdraw(deps, owner)?)
+                }
+                ApolloExtensionQueryMsg::UnlockingPositionNft {} => {
+                    to_binary(&contract.query_unlocking_position_nft(deps)?)
+                }
+                ApolloExtensionQueryMsg::AllUnlockingPositions {
+                    start_after,
+                    limit,
+                    filter,
+                } => to_binary(&contract.query_all_unlocking_positions(
+                    deps,
+                    env,
+                    start_after,
+                    limit,
+                    filter,
+                )?),
+                ApolloExtensionQueryMsg::UnlockingTotals {
+                    bucket_duration,
+                    num_buckets,
+                } => to_binary(&contract.query_unlocking_totals(
+                    deps,
+                    env,
+                    bucket_duration,
+                    num_buckets,
+                )?),
+                ApolloExtensionQueryMsg::ClaimsReindexCursor {} => {
+                    to_binary(&contract.query_claims_reindex_cursor(deps)?)
+                }
+                ApolloExtensionQueryMsg::UnlockEpochDuration {} => {
+                    to_binary(&contract.query_unlock_epoch_duration(deps)?)
+                }
+                ApolloExtensionQueryMsg::UnlockBatch { batch_id } => {
+                    to_binary(&contract.query_unlock_batch(deps, batch_id)?)
+                }
+                ApolloExtensionQueryMsg::UnlockBatches { start_after, limit } => {
+                    to_binary(&contract.query_unlock_batches(deps, start_after, limit)?)
+                }
+                ApolloExtensionQueryMsg::UnlockBatchShares {
+                    owner,
+                    start_after,
+                    limit,
+                } => to_binary(&contract.query_unlock_batch_shares(
+                    deps,
+                    owner,
+                    start_after,
+                    limit,
+                )?),
+                ApolloExtensionQueryMsg::ChangeLog { start_after, limit } => {
+                    to_binary(&contract.query_change_log(deps, start_after, limit)?)
+                }
+            },
+        },
+    }
+}
+
+/// Returns the amounts of base tokens locked and unbonding in the Osmosis lockup
+/// module for the vault.
+fn query_lockup_module_balances(
+    deps: Deps,
+    env: &Env,
+    contract: &OsmosisSuperfluidVaultContract,
+) -> StdResult<(Uint128, Uint128)> {
+    let denom = contract
+        .base_vault
+        .base_token
+        .load(deps.storage)?
+        .to_string();
+    let staking = contract.staking.load(deps.storage)?;
+    let querier = LockupQuerier::new(&deps.querier);
+
+    let amount_of_denom = |coins: Vec<Coin>| -> StdResult<Uint128> {
+        coins
+            .into_iter()
+            .filter(|coin| coin.denom == denom)
+            .try_fold(Uint128::zero(), |acc, coin| {
+                Ok(acc.checked_add(Uint128::from_str(&coin.amount)?)?)
+            })
+    };
+
+    let locked = match staking.lock_id {
+        Some(lock_id) => amount_of_denom(
+            querier
+                .locked_by_id(lock_id)?
+                .lock
+                .map(|lock| lock.coins)
+                .unwrap_or_default(),
+        )?,
+        None => Uint128::zero(),
+    };
+    let unlocking = amount_of_denom(
+        querier
+            .account_unlocking_coins(env.contract.address.to_string())?
+            .coins,
+    )?;
+
+    Ok((locked, unlocking))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, ContractError> {
+    let contract = OsmosisSuperfluidVaultContract::default();
+
+    // The instantiate reply is parsed from the whole reply rather than only the
+    // data
+    if reply.id == UNLOCKING_POSITION_NFT_INSTANTIATE_REPLY_ID {
+        return contract.reply_instantiate_unlocking_position_nft(deps, reply);
+    }
+
+    if let SubMsgResult::Ok(SubMsgResponse {
+        data: Some(b),
+        events: _,
+    }) = reply.result
+    {
+        match reply.id {
+            SUPERFLUID_LOCK_AND_DELEGATE_REPLY_ID => {
+                // A new lock was created and superfluid delegated because the
+                // vault had no lock that is not unbonding. Save its ID so that
+                // the next stake adds to it.
+                let res: MsgLockAndSuperfluidDelegateResponse =
+                    b.try_into().map_err(ContractError::Std)?;
+
+                let mut staking = contract.staking.load(deps.storage)?;
+                staking.lock_id = Some(res.id);
+                contract.staking.save(deps.storage, &staking)?;
+                Ok(Response::default())
+            }
+            SUPERFLUID_UNDELEGATE_AND_UNBOND_REPLY_ID => {
+                // The ID of the unbonding lock becomes the ID of the unlocking
+                // position. If the whole lock is unbonding, the vault no longer
+                // has a lock to add tokens to.
+                let res: MsgSuperfluidUndelegateAndUnbondLockResponse =
+                    b.try_into().map_err(ContractError::Std)?;
+                if res.lock_id == 0 {
+                    return Err(ContractError::Std(StdError::generic_err(
+                        "MsgSuperfluidUndelegateAndUnbondLockResponse is missing the lock ID",
+                    )));
+                }
+
+                let mut staking = contract.staking.load(deps.storage)?;
+                if staking.lock_id == Some(res.lock_id) {
+                    staking.lock_id = None;
+                    contract.staking.save(deps.storage, &staking)?;
+                }
+                contract.set_pending_claim_id(deps, res.lock_id)
+            }
+            CALLBACK_DATA_REPLY_ID => contract.reply_callback_data(b),
+            id => Err(ContractError::UnknownReplyId(id)),
+        }
+    } else {
+        Ok(Response::default())
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let contract = OsmosisSuperfluidVaultContract::default();
+    let steps = contract.migration_steps();
+    migrate_contract(deps, &env, CONTRACT_NAME, CONTRACT_VERSION, steps)
+}
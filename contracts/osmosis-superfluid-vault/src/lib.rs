@@ -0,0 +1,4 @@
+pub mod contract;
+pub mod msg;
+pub mod staking;
+pub mod validator;
@@ -0,0 +1,79 @@
+use apollo_vault::msg::{
+    ApolloExtensionExecuteMsg, CallbackMsg, ExtensionQueryMsg, UnlockingPositionNftInstantiateInfo,
+};
+use apollo_vault::state::ConfigUnchecked;
+use cosmwasm_schema::cw_serde;
+use cw_vault_standard::extensions::lockup::LockupExecuteMsg;
+use cw_vault_standard::{VaultStandardExecuteMsg, VaultStandardQueryMsg};
+
+/// ExecuteMsg for an Autocompounding Vault.
+pub type ExecuteMsg = VaultStandardExecuteMsg<ExtensionExecuteMsg>;
+
+/// QueryMsg for an Autocompounding Vault.
+pub type QueryMsg = VaultStandardQueryMsg<ExtensionQueryMsg>;
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// Address that is allowed to update config.
+    pub admin: String,
+    /// The ID of the pool that this vault will autocompound. The LP token of
+    /// the pool must be a superfluid asset.
+    pub pool_id: u64,
+    /// The lockup duration in seconds that this vault will use when staking
+    /// LP tokens. Must equal the unbonding time of the chain.
+    pub lockup_duration: u64,
+    /// The operator address of the validator that the vault's LP tokens will
+    /// be superfluid delegated to.
+    pub validator_address: String,
+    /// Configurable parameters for the contract.
+    pub config: ConfigUnchecked,
+    /// The subdenom that will be used for the native vault token, e.g.
+    /// the denom of the vault token will be:
+    /// "factory/{vault_contract}/{vault_token_subdenom}".
+    pub vault_token_subdenom: String,
+    /// Optional info to instantiate a cw721 contract that represents unlocking
+    /// positions as NFTs. If `None`, unlocking positions are not NFTs.
+    pub unlocking_position_nft: Option<UnlockingPositionNftInstantiateInfo>,
+    /// Optional duration in seconds of an unlock epoch. If set, unlock requests
+    /// made during an epoch are unlocked together as one batch once the epoch
+    /// has ended. If `None`, each unlock request is unlocked separately.
+    pub unlock_epoch_duration: Option<u64>,
+}
+
+/// Extension execute messages for the superfluid vault. The `Callback`,
+/// `Apollo` and `Lockup` messages are the same as those of other apollo
+/// autocompounding vaults.
+#[cw_serde]
+#[allow(clippy::large_enum_variant)]
+pub enum ExtensionExecuteMsg {
+    /// Execute a callback message.
+    Callback(CallbackMsg),
+    /// Execute an Apollo vault specific message.
+    Apollo(ApolloExtensionExecuteMsg),
+    /// Execute a message from the lockup extension.
+    Lockup(LockupExecuteMsg),
+    /// Execute a superfluid vault specific message.
+    Superfluid(SuperfluidExtensionExecuteMsg),
+}
+
+/// Superfluid extension messages define functionality that is only part of
+/// the superfluid vault.
+#[cw_serde]
+pub enum SuperfluidExtensionExecuteMsg {
+    /// Change the validator that the vault's base tokens are superfluid
+    /// delegated to. The vault's lock is superfluid undelegated from the
+    /// current validator and can be delegated to the new validator with
+    /// `Redelegate` once the undelegation has finished. Unlocking is paused
+    /// until then. Only callable by the admin.
+    UpdateValidator {
+        /// The operator address of the new validator.
+        validator_address: String,
+    },
+    /// Superfluid delegate the vault's lock to the validator set with
+    /// `UpdateValidator` once the undelegation from the previous validator
+    /// has finished. Can be called by anyone, e.g. keepers.
+    Redelegate {},
+}
+
+#[cw_serde]
+pub struct MigrateMsg {}
@@ -0,0 +1,387 @@
+use apollo_cw_asset::AssetList;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    Addr, Deps, Env, Event, QuerierWrapper, Response, StdError, StdResult, SubMsg, Timestamp,
+    Uint128,
+};
+use cw_dex::traits::{LockedStaking, Rewards, Stake, Unlock};
+use cw_dex::CwDexError;
+use cw_utils::Duration;
+use osmosis_std::types::cosmos::base::v1beta1::Coin;
+use osmosis_std::types::osmosis::lockup::MsgLockTokens;
+use osmosis_std::types::osmosis::superfluid::{
+    MsgLockAndSuperfluidDelegate, MsgSuperfluidDelegate, MsgSuperfluidUndelegate,
+    MsgSuperfluidUndelegateAndUnbondLock,
+};
+
+/// Reply ID of `MsgLockAndSuperfluidDelegate`, whose response contains the ID
+/// of the vault's new superfluid lock.
+pub const SUPERFLUID_LOCK_AND_DELEGATE_REPLY_ID: u64 = 1001;
+
+/// Reply ID of `MsgSuperfluidUndelegateAndUnbondLock`, whose response contains
+/// the ID of the lock that is unbonding.
+pub const SUPERFLUID_UNDELEGATE_AND_UNBOND_REPLY_ID: u64 = 1002;
+
+/// Staking implementation that superfluid delegates the vault's LP tokens to a
+/// validator. The vault holds a single lock with a duration equal to the
+/// unbonding time of the chain. Osmosis adds newly locked tokens to an existing
+/// lock with the same denom and duration, which increases the superfluid
+/// delegation of the lock, so all of the vault's LP tokens are delegated to the
+/// same validator.
+#[cw_serde]
+pub struct SuperfluidStaking {
+    /// The ID of the vault's superfluid lock. `None` if the vault has no lock
+    /// that is not unbonding.
+    pub lock_id: Option<u64>,
+    /// The lockup duration in seconds. Must equal the unbonding time of the
+    /// chain for the lock to be superfluid delegated.
+    pub lockup_duration: u64,
+    /// The denom of the LP token.
+    pub lp_token_denom: String,
+    /// The operator address of the validator that the lock is superfluid
+    /// delegated to.
+    pub validator_address: String,
+    /// Set while the lock is being superfluid undelegated from the previous
+    /// validator after the validator was changed. The time after which the
+    /// lock can be delegated to `validator_address`. Unlocking is paused until
+    /// then, because only superfluid delegated locks can be partially unbonded.
+    #[serde(default)]
+    pub redelegate_at: Option<Timestamp>,
+}
+
+impl SuperfluidStaking {
+    /// Creates a new `SuperfluidStaking` without a lock.
+    pub fn new(
+        lockup_duration: u64,
+        lp_token_denom: String,
+        validator_address: String,
+    ) -> StdResult<Self> {
+        if lockup_duration == 0 {
+            return Err(StdError::generic_err("Lockup duration cannot be zero"));
+        }
+        if validator_address.is_empty() {
+            return Err(StdError::generic_err("Validator address cannot be empty"));
+        }
+
+        Ok(Self {
+            lock_id: None,
+            lockup_duration,
+            lp_token_denom,
+            validator_address,
+            redelegate_at: None,
+        })
+    }
+
+    /// Changes the validator that the vault's lock is superfluid delegated to.
+    /// If the vault has a lock, it is superfluid undelegated from the current
+    /// validator, which takes the unbonding time of the chain. After that it
+    /// can be delegated to the new validator with [`Self::redelegate`].
+    pub fn update_validator(
+        &mut self,
+        env: &Env,
+        validator_address: String,
+    ) -> StdResult<Response> {
+        if validator_address.is_empty() {
+            return Err(StdError::generic_err("Validator address cannot be empty"));
+        }
+        if validator_address == self.validator_address {
+            return Err(StdError::generic_err(format!(
+                "Vault is already delegated to {}",
+                validator_address
+            )));
+        }
+        if let Some(redelegate_at) = self.redelegate_at {
+            return Err(StdError::generic_err(format!(
+                "Vault is already redelegating until {}",
+                redelegate_at
+            )));
+        }
+
+        let event = Event::new("apollo/vaults/superfluid_staking").add_attributes(vec![
+            ("action", "update_validator"),
+            ("before", &self.validator_address),
+            ("after", &validator_address),
+        ]);
+        self.validator_address = validator_address;
+
+        // Newly locked tokens are added to the undelegating lock, so they are
+        // delegated together with the lock when it is redelegated
+        let mut res = Response::new();
+        if let Some(lock_id) = self.lock_id {
+            let redelegate_at = env.block.time.plus_seconds(self.lockup_duration);
+            self.redelegate_at = Some(redelegate_at);
+            res = res.add_message(MsgSuperfluidUndelegate {
+                sender: env.contract.address.to_string(),
+                lock_id,
+            });
+        }
+
+        Ok(res.add_event(event))
+    }
+
+    /// Superfluid delegates the vault's lock to `validator_address` once its
+    /// undelegation from the previous validator has finished.
+    pub fn redelegate(&mut self, env: &Env) -> StdResult<Response> {
+        let redelegate_at = self
+            .redelegate_at
+            .ok_or_else(|| StdError::generic_err("Vault is not redelegating"))?;
+        if env.block.time < redelegate_at {
+            return Err(StdError::generic_err(format!(
+                "Vault can be redelegated at {}",
+                redelegate_at
+            )));
+        }
+        self.redelegate_at = None;
+
+        let event = Event::new("apollo/vaults/superfluid_staking").add_attributes(vec![
+            ("action", "redelegate"),
+            ("validator_address", &self.validator_address),
+        ]);
+
+        let mut res = Response::new();
+        if let Some(lock_id) = self.lock_id {
+            res = res.add_message(MsgSuperfluidDelegate {
+                sender: env.contract.address.to_string(),
+                lock_id,
+                val_addr: self.validator_address.clone(),
+            });
+        }
+
+        Ok(res.add_event(event))
+    }
+
+    fn coin(&self, amount: Uint128) -> Coin {
+        Coin {
+            denom: self.lp_token_denom.clone(),
+            amount: amount.to_string(),
+        }
+    }
+}
+
+impl Rewards for SuperfluidStaking {
+    fn claim_rewards(&self, _deps: Deps, _env: &Env) -> Result<Response, CwDexError> {
+        // Both the incentives of the lock and the staking rewards of the
+        // superfluid delegation are distributed to the lock owner every epoch,
+        // so there is nothing to claim. The staking rewards are paid in OSMO,
+        // which is one of the pool assets and is compounded as such.
+        Ok(Response::new())
+    }
+
+    fn query_pending_rewards(
+        &self,
+        _querier: &QuerierWrapper,
+        _user: &Addr,
+    ) -> Result<AssetList, CwDexError> {
+        // Rewards are distributed every epoch and are never pending
+        Ok(AssetList::new())
+    }
+}
+
+impl Stake for SuperfluidStaking {
+    fn stake(&self, _deps: Deps, env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
+        let owner = env.contract.address.to_string();
+
+        let event = Event::new("apollo/vaults/superfluid_staking").add_attributes(vec![
+            ("action", "stake"),
+            ("amount", &amount.to_string()),
+            ("validator_address", &self.validator_address),
+        ]);
+
+        // Add the tokens to the existing lock, which increases its superfluid
+        // delegation. Otherwise create a new lock and delegate it. The ID of
+        // the new lock is saved when handling the reply.
+        let res = match self.lock_id {
+            Some(_) => Response::new().add_message(MsgLockTokens {
+                owner,
+                duration: Some(osmosis_std::shim::Duration {
+                    seconds: self.lockup_duration as i64,
+                    nanos: 0,
+                }),
+                coins: vec![self.coin(amount)],
+            }),
+            None => Response::new().add_submessage(SubMsg::reply_on_success(
+                MsgLockAndSuperfluidDelegate {
+                    sender: owner,
+                    coins: vec![self.coin(amount)],
+                    val_addr: self.validator_address.clone(),
+                },
+                SUPERFLUID_LOCK_AND_DELEGATE_REPLY_ID,
+            )),
+        };
+
+        Ok(res.add_event(event))
+    }
+}
+
+impl Unlock for SuperfluidStaking {
+    fn unlock(&self, _deps: Deps, env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
+        let lock_id = self
+            .lock_id
+            .ok_or_else(|| StdError::generic_err("Vault has no superfluid lock"))?;
+        if let Some(redelegate_at) = self.redelegate_at {
+            return Err(StdError::generic_err(format!(
+                "Unlocking is paused while the vault is redelegating until {}",
+                redelegate_at
+            ))
+            .into());
+        }
+
+        let event = Event::new("apollo/vaults/superfluid_staking").add_attributes(vec![
+            ("action", "unlock"),
+            ("amount", &amount.to_string()),
+            ("lock_id", &lock_id.to_string()),
+        ]);
+
+        // Undelegate and start unbonding `amount` of the lock. The unbonding
+        // tokens are split off into a new lock, unless the whole lock is
+        // unbonded, and the ID of the unbonding lock is read from the reply.
+        Ok(Response::new()
+            .add_submessage(SubMsg::reply_on_success(
+                MsgSuperfluidUndelegateAndUnbondLock {
+                    sender: env.contract.address.to_string(),
+                    lock_id,
+                    coin: Some(self.coin(amount)),
+                },
+                SUPERFLUID_UNDELEGATE_AND_UNBOND_REPLY_ID,
+            ))
+            .add_event(event))
+    }
+
+    fn withdraw_unlocked(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        _amount: Uint128,
+    ) -> Result<Response, CwDexError> {
+        // Unbonded tokens are returned to the vault automatically
+        Ok(Response::new())
+    }
+}
+
+impl LockedStaking for SuperfluidStaking {
+    fn get_lockup_duration(&self, _deps: Deps) -> Result<Duration, CwDexError> {
+        Ok(Duration::Time(self.lockup_duration))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::CosmosMsg;
+
+    use super::*;
+
+    const LOCKUP_DURATION: u64 = 1_209_600;
+    const VALIDATOR: &str = "osmovaloper1old";
+    const NEW_VALIDATOR: &str = "osmovaloper1new";
+
+    fn staking(lock_id: Option<u64>) -> SuperfluidStaking {
+        let mut staking = SuperfluidStaking::new(
+            LOCKUP_DURATION,
+            "gamm/pool/1".to_string(),
+            VALIDATOR.to_string(),
+        )
+        .unwrap();
+        staking.lock_id = lock_id;
+        staking
+    }
+
+    #[test]
+    fn test_update_validator_undelegates_lock() {
+        let env = mock_env();
+        let mut staking = staking(Some(7));
+
+        let res = staking
+            .update_validator(&env, NEW_VALIDATOR.to_string())
+            .unwrap();
+
+        let expected: CosmosMsg = MsgSuperfluidUndelegate {
+            sender: env.contract.address.to_string(),
+            lock_id: 7,
+        }
+        .into();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].msg, expected);
+        assert_eq!(staking.validator_address, NEW_VALIDATOR);
+        assert_eq!(
+            staking.redelegate_at,
+            Some(env.block.time.plus_seconds(LOCKUP_DURATION))
+        );
+
+        // Unlocking is paused and the validator cannot be changed again
+        let deps = mock_dependencies();
+        let err = staking
+            .unlock(deps.as_ref(), &env, Uint128::new(100))
+            .unwrap_err();
+        assert!(err.to_string().contains("Unlocking is paused"));
+        let err = staking
+            .update_validator(&env, VALIDATOR.to_string())
+            .unwrap_err();
+        assert!(err.to_string().contains("already redelegating"));
+    }
+
+    #[test]
+    fn test_update_validator_without_lock() {
+        let env = mock_env();
+        let mut staking = staking(None);
+
+        let res = staking
+            .update_validator(&env, NEW_VALIDATOR.to_string())
+            .unwrap();
+
+        assert!(res.messages.is_empty());
+        assert_eq!(staking.validator_address, NEW_VALIDATOR);
+        assert_eq!(staking.redelegate_at, None);
+    }
+
+    #[test]
+    fn test_update_validator_to_same_or_empty_fails() {
+        let env = mock_env();
+        let mut staking = staking(Some(7));
+
+        let err = staking
+            .update_validator(&env, VALIDATOR.to_string())
+            .unwrap_err();
+        assert!(err.to_string().contains("already delegated"));
+        let err = staking.update_validator(&env, String::new()).unwrap_err();
+        assert!(err.to_string().contains("cannot be empty"));
+        assert_eq!(staking, self::staking(Some(7)));
+    }
+
+    #[test]
+    fn test_redelegate() {
+        let mut env = mock_env();
+        let mut staking = staking(Some(7));
+
+        let err = staking.redelegate(&env).unwrap_err();
+        assert!(err.to_string().contains("not redelegating"));
+
+        staking
+            .update_validator(&env, NEW_VALIDATOR.to_string())
+            .unwrap();
+
+        // Cannot redelegate before the undelegation has finished
+        env.block.time = env.block.time.plus_seconds(LOCKUP_DURATION - 1);
+        let err = staking.redelegate(&env).unwrap_err();
+        assert!(err.to_string().contains("can be redelegated at"));
+
+        env.block.time = env.block.time.plus_seconds(1);
+        let res = staking.redelegate(&env).unwrap();
+
+        let expected: CosmosMsg = MsgSuperfluidDelegate {
+            sender: env.contract.address.to_string(),
+            lock_id: 7,
+            val_addr: NEW_VALIDATOR.to_string(),
+        }
+        .into();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].msg, expected);
+        assert_eq!(staking.redelegate_at, None);
+
+        // Unlocking works again
+        let deps = mock_dependencies();
+        staking
+            .unlock(deps.as_ref(), &env, Uint128::new(100))
+            .unwrap();
+    }
+}
@@ -0,0 +1,55 @@
+use apollo_vault::error::ContractError;
+use apollo_vault::state::ChangeLogAction;
+use cosmwasm_std::{DepsMut, Env, MessageInfo, Response};
+
+use crate::contract::OsmosisSuperfluidVaultContract;
+
+/// Change the validator that the vault's lock is superfluid delegated to. Only
+/// callable by the admin.
+///
+/// Osmosis cannot move a superfluid delegation between validators, so the lock
+/// is superfluid undelegated from the current validator, which takes the
+/// unbonding time of the chain. Unlocking is paused until the lock is
+/// delegated to the new validator with `Redelegate`.
+pub fn execute_update_validator(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: &OsmosisSuperfluidVaultContract,
+    validator_address: String,
+) -> Result<Response, ContractError> {
+    contract.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let mut staking = contract.staking.load(deps.storage)?;
+    let before = staking.validator_address.clone();
+    let res = staking.update_validator(&env, validator_address.clone())?;
+    contract.staking.save(deps.storage, &staking)?;
+
+    contract.change_log.record(
+        deps.storage,
+        &env.block,
+        &info.sender,
+        ChangeLogAction::Custom {
+            action: "update_validator".to_string(),
+            before,
+            after: validator_address,
+        },
+    )?;
+
+    Ok(res)
+}
+
+/// Superfluid delegate the vault's lock to the new validator once its
+/// undelegation from the previous validator has finished. Can be called by
+/// anyone.
+pub fn execute_redelegate(
+    deps: DepsMut,
+    env: Env,
+    contract: &OsmosisSuperfluidVaultContract,
+) -> Result<Response, ContractError> {
+    let mut staking = contract.staking.load(deps.storage)?;
+    let res = staking.redelegate(&env)?;
+    contract.staking.save(deps.storage, &staking)?;
+
+    Ok(res)
+}
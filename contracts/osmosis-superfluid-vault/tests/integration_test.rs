@@ -0,0 +1,467 @@
+use std::str::FromStr;
+
+use apollo_cw_asset::AssetInfoUnchecked;
+use apollo_vault::msg::{
+    ApolloExtensionQueryMsg, DepositResponseData, ExtensionQueryMsg, SolvencyResponse,
+    StateResponse, WithdrawUnlockedResponseData,
+};
+use apollo_vault::state::ConfigUnchecked;
+use cosmwasm_std::{from_slice, Coin, Decimal, Empty, Uint128};
+use cw_dex::osmosis::OsmosisPool;
+use cw_dex::traits::Pool as PoolTrait;
+use cw_dex_router::helpers::CwDexRouterUnchecked;
+use cw_it::config::TestConfig;
+use cw_it::helpers::{instantiate_contract, instantiate_contract_with_funds, upload_wasm_files};
+use cw_it::mock_api::OsmosisMockApi;
+use cw_vault_standard::extensions::lockup::{LockupExecuteMsg, LockupQueryMsg, UnlockingPosition};
+use cw_vault_token::osmosis::OsmosisDenom;
+use liquidity_helper::LiquidityHelperUnchecked;
+use osmosis_std::types::osmosis::superfluid::{
+    SuperfluidDelegationsByDelegatorRequest, SuperfluidDelegationsByDelegatorResponse,
+};
+use osmosis_superfluid_vault::msg::{
+    ExecuteMsg, ExtensionExecuteMsg, InstantiateMsg, QueryMsg, SuperfluidExtensionExecuteMsg,
+};
+use osmosis_superfluid_vault::staking::SuperfluidStaking;
+use osmosis_testing::cosmrs::proto::cosmos::bank::v1beta1::{MsgSend, QueryBalanceRequest};
+use osmosis_testing::cosmrs::proto::cosmos::base::v1beta1::Coin as ProtoCoin;
+use osmosis_testing::cosmrs::proto::cosmos::staking::v1beta1::{
+    QueryParamsRequest, QueryParamsResponse,
+};
+use osmosis_testing::{
+    Account, Bank, Gamm, Module, OsmosisTestApp as BindingsRunner, Runner, SigningAccount, Wasm,
+};
+
+const TEST_CONFIG_PATH: &str = "tests/configs/osmosis.yaml";
+const UATOM: &str = "uatom";
+const UOSMO: &str = "uosmo";
+const PERFORMANCE_FEE: Decimal = Decimal::raw(5 * 10u128.pow(16)); // 5%
+
+/// Set up the contracts and the superfluid pool needed to run the tests
+/// Returns (String,String,u64) of (vault_addr, base_token, lockup_duration)
+pub fn setup_test(
+    runner: &BindingsRunner,
+    accs: &[SigningAccount],
+    test_config: &TestConfig,
+) -> (String, String, u64) {
+    let gamm = Gamm::new(runner);
+    let api = OsmosisMockApi::new();
+
+    let admin = &accs[0];
+    let force_withdraw_admin = &accs[1];
+    let treasury = &accs[2];
+    let user1 = &accs[3];
+
+    // Create base pool and register its LP token as a superfluid asset
+    let pool_id = gamm
+        .create_basic_pool(
+            &[
+                Coin::new(1_000_000_000_000, UATOM),
+                Coin::new(1_000_000_000_000, UOSMO),
+            ],
+            user1,
+        )
+        .unwrap()
+        .data
+        .pool_id;
+    println!("Pool ID: {}", pool_id);
+    let base_pool = OsmosisPool::unchecked(pool_id);
+    let base_token = base_pool.lp_token().to_string();
+    runner.add_superfluid_lp_share(&base_token);
+
+    // The lockup duration of a superfluid lock must equal the unbonding time
+    let staking_params = runner
+        .query::<QueryParamsRequest, QueryParamsResponse>(
+            "/cosmos.staking.v1beta1.Query/Params",
+            &QueryParamsRequest {},
+        )
+        .unwrap()
+        .params
+        .unwrap();
+    let lockup_duration = staking_params.unbonding_time.unwrap().seconds as u64;
+    let validator_address = runner.get_first_validator_address().unwrap();
+
+    // Upload wasm files
+    let code_ids = upload_wasm_files(runner, admin, test_config.clone()).unwrap();
+
+    // Instantiate Osmosis Liquidity Helper
+    let osmosis_liquidity_helper = instantiate_contract::<_, _, LiquidityHelperUnchecked>(
+        runner,
+        admin,
+        code_ids["osmosis_liquidity_helper"],
+        &Empty {},
+    )
+    .unwrap();
+
+    // Instantiate CwDexRouter. The staking rewards are paid in OSMO, which is
+    // also the reward liquidation target, so no paths are needed.
+    let cw_dex_router = instantiate_contract::<_, _, CwDexRouterUnchecked>(
+        runner,
+        admin,
+        code_ids["cw_dex_router"],
+        &Empty {},
+    )
+    .unwrap()
+    .check(&api)
+    .unwrap();
+
+    let config = ConfigUnchecked {
+        force_withdraw_whitelist: vec![force_withdraw_admin.address().clone()],
+        performance_fee: PERFORMANCE_FEE,
+        early_exit_penalty: Decimal::zero(),
+        early_exit_penalty_decay: false,
+        early_exit_penalty_to_treasury: false,
+        auto_withdraw_keeper_fee: Decimal::zero(),
+        min_unlock_amount: Uint128::zero(),
+        max_unlocking_positions_per_owner: 0,
+        redemption_buffer_ratio: Decimal::zero(),
+        instant_redemption_fee: Decimal::zero(),
+        reward_assets: vec![AssetInfoUnchecked::Native(UOSMO.to_string())],
+        reward_liquidation_target: AssetInfoUnchecked::Native(UOSMO.to_string()),
+        treasury: treasury.address().clone(),
+        liquidity_helper: osmosis_liquidity_helper.clone(),
+        router: cw_dex_router.clone().into(),
+    };
+
+    // Instantiate osmosis superfluid vault contract
+    let vault_addr: String = instantiate_contract_with_funds(
+        runner,
+        admin,
+        code_ids["osmosis_superfluid_vault"],
+        &InstantiateMsg {
+            admin: admin.address().clone(),
+            lockup_duration,
+            pool_id: base_pool.pool_id(),
+            validator_address: validator_address.clone(),
+            vault_token_subdenom: "osmosis-superfluid-vault".to_string(),
+            unlocking_position_nft: None,
+            unlock_epoch_duration: None,
+            config,
+        },
+        &[Coin {
+            // 10 OSMO needed to create vault token
+            denom: UOSMO.to_string(),
+            amount: Uint128::from(10_000_000u128),
+        }],
+    )
+    .unwrap();
+
+    println!(" ------ Addresses -------");
+    println!("admin: {}", admin.address());
+    println!("treasury: {}", treasury.address());
+    println!("user1: {}", user1.address());
+    println!("validator: {}", validator_address);
+
+    println!(" ------ Contracts -------");
+    println!("Vault: {}", vault_addr);
+    println!("Liquidity helper: {:?}", osmosis_liquidity_helper);
+    println!("CwDexRouter: {}", cw_dex_router.clone().addr().to_string());
+    println!("-----------------------------------");
+
+    (vault_addr, base_token, lockup_duration)
+}
+
+#[test]
+pub fn test_osmosis_superfluid_vault_functionality() {
+    let test_config = TestConfig::from_yaml(TEST_CONFIG_PATH);
+
+    let runner = BindingsRunner::default();
+    let accs = runner
+        .init_accounts(
+            &[
+                Coin::new(1_000_000_000_000_000_000_000_000, UATOM),
+                Coin::new(1_000_000_000_000_000_000_000_000, UOSMO),
+            ],
+            5,
+        )
+        .unwrap();
+
+    let admin = &accs[0];
+    let user1 = &accs[3];
+
+    let wasm = Wasm::new(&runner);
+
+    // Setup test
+    let (vault_addr, base_token, lockup_duration) = setup_test(&runner, &accs, &test_config);
+
+    let state = query_vault_state(&runner, &vault_addr);
+    let vault_token_denom = state.vault_token.to_string();
+    assert_eq!(state.staking.lock_id, None);
+
+    // Deposit half of user1's base tokens
+    let deposit_amount = query_token_balance(&runner, &user1.address(), &base_token) / 2u128;
+    let deposit_msg = ExecuteMsg::Deposit {
+        amount: deposit_amount,
+        recipient: None,
+    };
+    let res = wasm
+        .execute(
+            &vault_addr,
+            &deposit_msg,
+            &[Coin::new(deposit_amount.u128(), base_token.clone())],
+            user1,
+        )
+        .unwrap();
+    let data: DepositResponseData = from_slice(&res.data.data).unwrap();
+    assert_eq!(
+        data.vault_token_amount,
+        query_token_balance(&runner, &user1.address(), &vault_token_denom)
+    );
+    assert_eq!(data.recipient.to_string(), user1.address());
+
+    // The deposit should have created a superfluid lock delegated to the validator
+    let state = query_vault_state(&runner, &vault_addr);
+    let lock_id = state.staking.lock_id.unwrap();
+    assert_eq!(state.total_staked_base_tokens, deposit_amount);
+    let delegation = query_superfluid_delegation(&runner, &vault_addr);
+    assert_eq!(
+        delegation.superfluid_delegation_records[0].validator_address,
+        state.staking.validator_address
+    );
+    assert_eq!(delegation_amount(&delegation), deposit_amount);
+
+    // Simulate OSMO staking rewards. These should be compounded on the next
+    // deposit and added to the same lock.
+    send_native_coins(&runner, admin, &vault_addr, UOSMO, "100000000");
+    wasm.execute(
+        &vault_addr,
+        &deposit_msg,
+        &[Coin::new(deposit_amount.u128(), base_token.clone())],
+        user1,
+    )
+    .unwrap();
+    let state = query_vault_state(&runner, &vault_addr);
+    assert_eq!(state.staking.lock_id, Some(lock_id));
+    assert!(state.total_staked_base_tokens > deposit_amount * Uint128::new(2));
+    let delegation = query_superfluid_delegation(&runner, &vault_addr);
+    assert_eq!(
+        delegation_amount(&delegation),
+        state.total_staked_base_tokens
+    );
+
+    // Unlock half of user1's vault tokens
+    let vault_token_balance = query_token_balance(&runner, &user1.address(), &vault_token_denom);
+    let unlock_amount = vault_token_balance / 2u128;
+    let res = wasm
+        .execute(
+            &vault_addr,
+            &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(LockupExecuteMsg::Unlock {
+                amount: unlock_amount,
+            })),
+            &[Coin::new(unlock_amount.u128(), vault_token_denom.clone())],
+            user1,
+        )
+        .unwrap();
+
+    // The unlocking tokens should be split off into a new lock and undelegated
+    let positions = query_unlocking_positions(&runner, &vault_addr, &user1.address());
+    assert_eq!(positions.len(), 1);
+    let position = positions[0].clone();
+    assert_ne!(position.id, lock_id);
+    let data: UnlockingPosition = from_slice(&res.data.data).unwrap();
+    assert_eq!(data, position);
+    let state = query_vault_state(&runner, &vault_addr);
+    assert_eq!(state.staking.lock_id, Some(lock_id));
+    let delegation = query_superfluid_delegation(&runner, &vault_addr);
+    assert_eq!(
+        delegation_amount(&delegation),
+        state.total_staked_base_tokens
+    );
+    let solvency = query_solvency(&runner, &vault_addr);
+    assert_eq!(
+        solvency.total_unlocking_base_tokens,
+        position.base_token_amount
+    );
+    assert_eq!(solvency.shortfall, Uint128::zero());
+
+    // Withdrawing before the unbonding time has passed should fail
+    let withdraw_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(
+        LockupExecuteMsg::WithdrawUnlocked {
+            lockup_id: position.id,
+            recipient: None,
+        },
+    ));
+    wasm.execute(&vault_addr, &withdraw_msg, &[], user1)
+        .unwrap_err();
+
+    runner.increase_time(lockup_duration);
+
+    let base_token_balance_before = query_token_balance(&runner, &user1.address(), &base_token);
+    let res = wasm
+        .execute(&vault_addr, &withdraw_msg, &[], user1)
+        .unwrap();
+    let base_token_balance_after = query_token_balance(&runner, &user1.address(), &base_token);
+    assert_eq!(
+        base_token_balance_after - base_token_balance_before,
+        position.base_token_amount
+    );
+    let data: WithdrawUnlockedResponseData = from_slice(&res.data.data).unwrap();
+    assert_eq!(data.lockup_id, position.id);
+    assert_eq!(data.base_token_amount, position.base_token_amount);
+    assert_eq!(data.recipient.to_string(), user1.address());
+
+    // Unlocking all remaining vault tokens unbonds the whole lock, so the next
+    // deposit should create a new superfluid lock
+    let vault_token_balance = query_token_balance(&runner, &user1.address(), &vault_token_denom);
+    wasm.execute(
+        &vault_addr,
+        &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(LockupExecuteMsg::Unlock {
+            amount: vault_token_balance,
+        })),
+        &[Coin::new(vault_token_balance.u128(), vault_token_denom)],
+        user1,
+    )
+    .unwrap();
+    let state = query_vault_state(&runner, &vault_addr);
+    assert_eq!(state.staking.lock_id, None);
+    assert_eq!(state.total_staked_base_tokens, Uint128::zero());
+
+    wasm.execute(
+        &vault_addr,
+        &deposit_msg,
+        &[Coin::new(deposit_amount.u128(), base_token)],
+        user1,
+    )
+    .unwrap();
+    let state = query_vault_state(&runner, &vault_addr);
+    let new_lock_id = state.staking.lock_id.unwrap();
+    assert_ne!(new_lock_id, lock_id);
+    let delegation = query_superfluid_delegation(&runner, &vault_addr);
+    assert_eq!(
+        delegation_amount(&delegation),
+        state.total_staked_base_tokens
+    );
+    let solvency = query_solvency(&runner, &vault_addr);
+    assert_eq!(solvency.shortfall, Uint128::zero());
+
+    // Only the admin can change the validator, and only to another validator
+    let update_validator_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Superfluid(
+        SuperfluidExtensionExecuteMsg::UpdateValidator {
+            validator_address: state.staking.validator_address.clone(),
+        },
+    ));
+    let err = wasm
+        .execute(&vault_addr, &update_validator_msg, &[], user1)
+        .unwrap_err();
+    assert!(err.to_string().contains("Caller is not admin"));
+    let err = wasm
+        .execute(&vault_addr, &update_validator_msg, &[], admin)
+        .unwrap_err();
+    assert!(err.to_string().contains("already delegated"));
+
+    // Redelegating fails if the validator was not changed
+    let err = wasm
+        .execute(
+            &vault_addr,
+            &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Superfluid(
+                SuperfluidExtensionExecuteMsg::Redelegate {},
+            )),
+            &[],
+            user1,
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("not redelegating"));
+}
+
+fn query_vault_state(
+    runner: &BindingsRunner,
+    vault_addr: &str,
+) -> StateResponse<SuperfluidStaking, OsmosisPool, OsmosisDenom> {
+    let wasm = Wasm::new(runner);
+    wasm.query(
+        vault_addr,
+        &QueryMsg::VaultExtension(ExtensionQueryMsg::Apollo(ApolloExtensionQueryMsg::State {})),
+    )
+    .unwrap()
+}
+
+fn query_solvency(runner: &BindingsRunner, vault_addr: &str) -> SolvencyResponse {
+    let wasm = Wasm::new(runner);
+    wasm.query(
+        vault_addr,
+        &QueryMsg::VaultExtension(ExtensionQueryMsg::Apollo(
+            ApolloExtensionQueryMsg::Solvency {},
+        )),
+    )
+    .unwrap()
+}
+
+fn query_unlocking_positions(
+    runner: &BindingsRunner,
+    vault_addr: &str,
+    owner: &str,
+) -> Vec<UnlockingPosition> {
+    let wasm = Wasm::new(runner);
+    wasm.query(
+        vault_addr,
+        &QueryMsg::VaultExtension(ExtensionQueryMsg::Lockup(
+            LockupQueryMsg::UnlockingPositions {
+                owner: owner.to_string(),
+                limit: None,
+                start_after: None,
+            },
+        )),
+    )
+    .unwrap()
+}
+
+fn query_superfluid_delegation(
+    runner: &BindingsRunner,
+    vault_addr: &str,
+) -> SuperfluidDelegationsByDelegatorResponse {
+    runner
+        .query(
+            "/osmosis.superfluid.Query/SuperfluidDelegationsByDelegator",
+            &SuperfluidDelegationsByDelegatorRequest {
+                delegator_address: vault_addr.to_string(),
+            },
+        )
+        .unwrap()
+}
+
+/// Returns the amount of LP tokens in the vault's only superfluid delegation
+fn delegation_amount(delegation: &SuperfluidDelegationsByDelegatorResponse) -> Uint128 {
+    assert_eq!(delegation.superfluid_delegation_records.len(), 1);
+    let amount = delegation.superfluid_delegation_records[0]
+        .delegation_amount
+        .clone()
+        .unwrap()
+        .amount;
+    Uint128::from_str(&amount).unwrap()
+}
+
+fn query_token_balance(runner: &BindingsRunner, address: &str, denom: &str) -> Uint128 {
+    let bank = Bank::new(runner);
+    let balance = bank
+        .query_balance(&QueryBalanceRequest {
+            address: address.to_string(),
+            denom: denom.to_string(),
+        })
+        .unwrap()
+        .balance
+        .unwrap_or_default()
+        .amount;
+    Uint128::from_str(&balance).unwrap()
+}
+
+fn send_native_coins(
+    runner: &BindingsRunner,
+    from: &SigningAccount,
+    to: &str,
+    denom: &str,
+    amount: impl Into<String>,
+) {
+    let bank = Bank::new(runner);
+    bank.send(
+        MsgSend {
+            amount: vec![ProtoCoin {
+                denom: denom.to_string(),
+                amount: amount.into(),
+            }],
+            from_address: from.address(),
+            to_address: to.to_string(),
+        },
+        from,
+    )
+    .unwrap();
+}
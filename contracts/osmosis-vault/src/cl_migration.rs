@@ -0,0 +1,876 @@
+use apollo_cw_asset::{Asset, AssetInfo};
+use apollo_vault::error::ContractError;
+use apollo_vault::msg::{ApolloExtensionExecuteMsg, CallbackMsg};
+use apollo_vault::state::{ChangeLogAction, Claim, UnlockBatchStatus, MAX_LIMIT};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_binary, Addr, Coin, Deps, DepsMut, Empty, Env, Event, MessageInfo, Order, Response,
+    StdResult, Storage, Uint128, WasmMsg,
+};
+use cw_dex::osmosis::{OsmosisPool, OsmosisStaking};
+use cw_dex::traits::Pool;
+use cw_storage_plus::Item;
+use cw_vault_standard::extensions::force_unlock::ForceUnlockExecuteMsg;
+use cw_vault_standard::extensions::lockup::LockupExecuteMsg;
+use cw_vault_standard::msg::{VaultInfoResponse, VaultStandardExecuteMsg, VaultStandardQueryMsg};
+use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use osmosis_std::types::osmosis::lockup::MsgBeginUnlocking;
+use serde::Deserialize;
+
+use crate::contract::OsmosisVaultContract;
+use crate::lockup::{self, LEGACY_LOCK_MIGRATIONS};
+use crate::msg::{ExecuteMsg, ExtensionExecuteMsg, OsmosisExtensionExecuteMsg};
+
+/// The migration of the vault from its GAMM pool to a concentrated liquidity
+/// vault. Kept after the migration is completed, so that the unlocking
+/// positions created before it are withdrawn in GAMM shares.
+pub const CL_MIGRATION: Item<ClMigration> = Item::new("cl_migration");
+
+/// A migration of the vault from its GAMM pool to a concentrated liquidity
+/// vault, whose vault tokens become the base tokens of the vault.
+#[cw_serde]
+pub struct ClMigration {
+    /// The concentrated liquidity vault.
+    pub cl_vault: Addr,
+    /// The vault token of the concentrated liquidity vault.
+    pub cl_vault_token: String,
+    /// The ID of the concentrated liquidity pool of `cl_vault`.
+    pub cl_pool_id: u64,
+    /// The ID of the GAMM pool that the vault migrated from.
+    pub legacy_pool_id: u64,
+    /// The GAMM shares of the pool, the base token before the migration.
+    pub legacy_base_token: AssetInfo,
+    /// The lockup duration in seconds, which is kept by the migration.
+    pub lockup_duration: u64,
+    /// Unlocking positions with an ID up to this one were created before the
+    /// migration and are withdrawn in GAMM shares. `None` if there were no
+    /// unlocking positions.
+    pub last_legacy_claim_id: Option<u64>,
+    /// The amount of GAMM shares that are exchanged for the pool assets.
+    pub base_token_amount: Uint128,
+    /// The time in seconds at which the GAMM shares have unlocked.
+    pub unlocked_at: u64,
+    /// Whether the migration has been completed.
+    pub completed: bool,
+}
+
+impl ClMigration {
+    /// Returns true if the unlocking position with ID `lockup_id` was created
+    /// before the migration.
+    pub fn is_legacy_claim(&self, lockup_id: u64) -> bool {
+        matches!(
+            self.last_legacy_claim_id,
+            Some(last_legacy_claim_id) if lockup_id <= last_legacy_claim_id
+        )
+    }
+}
+
+/// The extension queries of the concentrated liquidity vault that are used by
+/// the migration.
+#[cw_serde]
+enum ClVaultExtensionQueryMsg {
+    State {},
+}
+
+/// The part of the `State` response of the concentrated liquidity vault that
+/// is used by the migration.
+#[derive(Deserialize)]
+struct ClVaultStateResponse {
+    position: ClVaultPosition,
+}
+
+#[derive(Deserialize)]
+struct ClVaultPosition {
+    pool_id: u64,
+}
+
+/// Begin migrating the vault from its GAMM pool to the concentrated liquidity
+/// vault `cl_vault`, whose pool assets must be those of the GAMM pool. Only
+/// callable by the admin.
+///
+/// The vault's lock of GAMM shares starts unlocking. Until the migration is
+/// completed with `CompleteConcentratedLiquidityMigration`, the vault has no
+/// locked base tokens, so deposits, unlocks and redemptions are paused.
+/// Unlocking positions that exist at this point keep their GAMM shares.
+pub fn execute_migrate_to_concentrated_liquidity(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: &OsmosisVaultContract,
+    cl_vault: String,
+) -> Result<Response, ContractError> {
+    contract.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+    if CL_MIGRATION.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::from(
+            "The vault has already been migrated to concentrated liquidity",
+        ));
+    }
+    if !lockup::query_legacy_lock_amount(deps.as_ref(), contract)?.is_zero()
+        || LEGACY_LOCK_MIGRATIONS
+            .keys(deps.storage, None, None, Order::Ascending)
+            .next()
+            .is_some()
+    {
+        return Err(ContractError::from(
+            "Cannot migrate to concentrated liquidity while base tokens are in or migrating from the legacy lock",
+        ));
+    }
+    // The base tokens of the open batch are unlocked after the migration, so
+    // its amount would be in the wrong base token
+    if let Some(batch) = contract.unlock_batches.open_batch(deps.storage)? {
+        if !batch.base_token_amount.is_zero() {
+            return Err(ContractError::from(
+                "Cannot migrate to concentrated liquidity while the open unlock batch has base tokens. Process it with ProcessUnlockBatch first",
+            ));
+        }
+    }
+
+    let cl_vault = deps.api.addr_validate(&cl_vault)?;
+    let legacy_pool = contract.pool.load(deps.storage)?;
+    let (cl_pool_id, cl_vault_token) = query_cl_vault(deps.as_ref(), &cl_vault, &legacy_pool)?;
+
+    lockup::reconcile_lock_id(deps.branch(), &env, contract)?;
+    let mut staking = contract.staking.load(deps.storage)?;
+    let lockup_duration = lockup::lockup_duration_seconds(deps.as_ref(), &staking)?;
+    let legacy_base_token = contract.base_vault.base_token.load(deps.storage)?;
+    let denom = legacy_base_token.to_string();
+
+    // The redemption buffer is exchanged together with the locked base tokens
+    let locked = match staking.lock_id {
+        Some(lock_id) => lockup::query_locked_amount(deps.as_ref(), lock_id, &denom)?,
+        None => Uint128::zero(),
+    };
+    let buffer = contract
+        .redemption_buffer
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    let base_token_amount = locked.checked_add(buffer)?;
+
+    let begin_unlocking_msg = staking
+        .lock_id
+        .filter(|_| !locked.is_zero())
+        .map(|lock_id| MsgBeginUnlocking {
+            owner: env.contract.address.to_string(),
+            id: lock_id,
+            coins: vec![ProtoCoin {
+                denom: denom.clone(),
+                amount: locked.to_string(),
+            }],
+        });
+    // The whole lock is unlocking
+    staking.lock_id = None;
+    contract.staking.save(deps.storage, &staking)?;
+
+    let migration = ClMigration {
+        cl_vault,
+        cl_vault_token,
+        cl_pool_id,
+        legacy_pool_id: legacy_pool.pool_id(),
+        legacy_base_token,
+        lockup_duration,
+        last_legacy_claim_id: contract.claims.last_claim_id(deps.storage)?,
+        base_token_amount,
+        unlocked_at: env.block.time.plus_seconds(lockup_duration).seconds(),
+        completed: false,
+    };
+    CL_MIGRATION.save(deps.storage, &migration)?;
+
+    contract.change_log.record(
+        deps.storage,
+        &env.block,
+        &info.sender,
+        ChangeLogAction::Custom {
+            action: "migrate_to_concentrated_liquidity".to_string(),
+            before: denom,
+            after: migration.cl_vault_token.clone(),
+        },
+    )?;
+
+    let event = Event::new("apollo/vaults/osmosis_vault").add_attributes(vec![
+        (
+            "action",
+            "execute_migrate_to_concentrated_liquidity".to_string(),
+        ),
+        ("cl_vault", migration.cl_vault.to_string()),
+        ("cl_pool_id", cl_pool_id.to_string()),
+        ("base_token_amount", base_token_amount.to_string()),
+        ("unlocked_at", migration.unlocked_at.to_string()),
+    ]);
+
+    Ok(Response::new()
+        .add_messages(begin_unlocking_msg)
+        .add_event(event))
+}
+
+/// Complete the migration to concentrated liquidity once the vault's GAMM
+/// shares have unlocked. Only callable by the admin.
+///
+/// The GAMM shares are exchanged for the pool assets, the vault token of the
+/// concentrated liquidity vault becomes the base token and the pool assets are
+/// deposited into the concentrated liquidity vault by the `ProvideLiquidity`
+/// callback. The vault tokens received are staked by the `Stake` callback and
+/// become the total staked base tokens, so that each vault token keeps its
+/// share of the vault.
+pub fn execute_complete_concentrated_liquidity_migration(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: &OsmosisVaultContract,
+) -> Result<Response, ContractError> {
+    contract.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let mut migration = CL_MIGRATION
+        .may_load(deps.storage)?
+        .filter(|migration| !migration.completed)
+        .ok_or_else(|| {
+            ContractError::from("There is no migration to concentrated liquidity to complete")
+        })?;
+    // Osmosis returns unlocked tokens at the end of the block
+    if env.block.time.seconds() <= migration.unlocked_at {
+        return Err(ContractError::from(format!(
+            "The GAMM shares of the vault unlock at {}",
+            migration.unlocked_at
+        )));
+    }
+
+    let exit_res = if migration.base_token_amount.is_zero() {
+        Response::new()
+    } else {
+        OsmosisPool::unchecked(migration.legacy_pool_id).withdraw_liquidity(
+            deps.as_ref(),
+            &env,
+            Asset::new(
+                migration.legacy_base_token.clone(),
+                migration.base_token_amount,
+            ),
+        )?
+    };
+
+    let base_token = AssetInfo::Native(migration.cl_vault_token.clone());
+    let base_token_balance_before =
+        base_token.query_balance(&deps.querier, &env.contract.address)?;
+    contract
+        .base_vault
+        .base_token
+        .save(deps.storage, &base_token)?;
+    contract.staking.save(
+        deps.storage,
+        &OsmosisStaking::new(
+            migration.lockup_duration,
+            None,
+            migration.cl_vault_token.clone(),
+        )?,
+    )?;
+    contract
+        .pool
+        .save(deps.storage, &OsmosisPool::unchecked(migration.cl_pool_id))?;
+    // Set to the vault tokens received by the `Stake` callback
+    contract
+        .base_vault
+        .total_staked_base_tokens
+        .save(deps.storage, &Uint128::zero())?;
+    contract
+        .redemption_buffer
+        .save(deps.storage, &Uint128::zero())?;
+
+    migration.completed = true;
+    CL_MIGRATION.save(deps.storage, &migration)?;
+
+    let provide_liquidity_msg = CallbackMsg::ProvideLiquidity {}.into_cosmos_msg(&env)?;
+    let stake_msg = CallbackMsg::Stake {
+        base_token_balance_before,
+    }
+    .into_cosmos_msg(&env)?;
+
+    let event = Event::new("apollo/vaults/osmosis_vault").add_attributes(vec![
+        (
+            "action",
+            "execute_complete_concentrated_liquidity_migration".to_string(),
+        ),
+        ("cl_vault", migration.cl_vault.to_string()),
+        ("base_token", migration.cl_vault_token),
+        (
+            "legacy_base_token_amount",
+            migration.base_token_amount.to_string(),
+        ),
+    ]);
+
+    Ok(exit_res
+        .add_message(provide_liquidity_msg)
+        .add_message(stake_msg)
+        .add_event(event))
+}
+
+/// Callback function to sell the reward assets for the pool assets. After the
+/// migration the pool of the vault is a concentrated liquidity pool, so the
+/// pool assets are taken from the GAMM pool, which has the same assets.
+pub fn execute_callback_sell_rewards(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: &OsmosisVaultContract,
+) -> Result<Response, ContractError> {
+    let migration = match completed_migration(deps.storage)? {
+        Some(migration) => migration,
+        None => return contract.execute_callback_sell_rewards(deps, env, info),
+    };
+
+    let pool = contract.pool.load(deps.storage)?;
+    contract.pool.save(
+        deps.storage,
+        &OsmosisPool::unchecked(migration.legacy_pool_id),
+    )?;
+    let res = contract.execute_callback_sell_rewards(deps.branch(), env, info)?;
+    contract.pool.save(deps.storage, &pool)?;
+
+    Ok(res)
+}
+
+/// Callback function to turn the pool assets in the contract into base
+/// tokens. After the migration they are deposited into the concentrated
+/// liquidity vault, which refunds the part that does not fit its position to
+/// be deposited by the next compound.
+pub fn execute_callback_provide_liquidity(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: &OsmosisVaultContract,
+) -> Result<Response, ContractError> {
+    let migration = match completed_migration(deps.storage)? {
+        Some(migration) => migration,
+        None => return contract.execute_callback_provide_liquidity(deps, env, info),
+    };
+
+    let mut funds = OsmosisPool::unchecked(migration.legacy_pool_id)
+        .pool_assets(deps.as_ref())?
+        .into_iter()
+        .map(|asset| {
+            let denom = asset.to_string();
+            deps.querier
+                .query_balance(env.contract.address.to_string(), denom)
+        })
+        .collect::<StdResult<Vec<Coin>>>()?
+        .into_iter()
+        .filter(|coin| !coin.amount.is_zero())
+        .collect::<Vec<_>>();
+    if funds.is_empty() {
+        return Ok(Response::default());
+    }
+    funds.sort_by(|a, b| a.denom.cmp(&b.denom));
+
+    let event = Event::new("apollo/vaults/osmosis_vault").add_attributes(vec![
+        ("action", "execute_callback_provide_liquidity".to_string()),
+        ("cl_vault", migration.cl_vault.to_string()),
+        ("funds", format!("{:?}", funds)),
+    ]);
+
+    let deposit_msg = WasmMsg::Execute {
+        contract_addr: migration.cl_vault.to_string(),
+        msg: to_binary(&VaultStandardExecuteMsg::<Empty>::Deposit {
+            amount: Uint128::zero(),
+            recipient: None,
+        })?,
+        funds,
+    };
+
+    Ok(Response::new().add_message(deposit_msg).add_event(event))
+}
+
+/// Runs `f`, which withdraws from the unlocking position with ID `lockup_id`,
+/// with the GAMM shares as the base token of the vault if the position was
+/// created before the completed migration.
+pub fn with_claim_base_token<F>(
+    deps: DepsMut,
+    contract: &OsmosisVaultContract,
+    lockup_id: u64,
+    f: F,
+) -> Result<Response, ContractError>
+where
+    F: FnOnce(DepsMut) -> Result<Response, ContractError>,
+{
+    match completed_migration(deps.storage)? {
+        Some(migration) if migration.is_legacy_claim(lockup_id) => {
+            with_legacy_base_token(deps, contract, &migration, f)
+        }
+        _ => f(deps),
+    }
+}
+
+/// Runs `f`, which withdraws from the unlock batch with ID `batch_id`, with the
+/// GAMM shares as the base token of the vault if the claim of the batch was
+/// created before the completed migration.
+pub fn with_batch_base_token<F>(
+    deps: DepsMut,
+    contract: &OsmosisVaultContract,
+    batch_id: u64,
+    f: F,
+) -> Result<Response, ContractError>
+where
+    F: FnOnce(DepsMut) -> Result<Response, ContractError>,
+{
+    let status = contract
+        .unlock_batches
+        .query_batch(deps.as_ref(), batch_id)
+        .map(|batch| batch.status);
+    match status {
+        Ok(UnlockBatchStatus::Unlocking { lockup_id, .. }) => {
+            with_claim_base_token(deps, contract, lockup_id, f)
+        }
+        _ => f(deps),
+    }
+}
+
+/// Returns an error if the unlocking position with ID `lockup_id` was created
+/// before the completed migration. Its GAMM shares cannot be staked in the
+/// vault, so it cannot be cancelled or exited early.
+pub fn assert_not_legacy_claim(storage: &dyn Storage, lockup_id: u64) -> Result<(), ContractError> {
+    match completed_migration(storage)? {
+        Some(migration) if migration.is_legacy_claim(lockup_id) => Err(ContractError::from(
+            "Unlocking positions created before the migration to concentrated liquidity can only be withdrawn",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Withdraw the sender's unlocking positions that have finished unlocking.
+/// The positions created before the completed migration are withdrawn in GAMM
+/// shares, so they are withdrawn first and the other positions are withdrawn
+/// by the next call.
+pub fn execute_withdraw_all_unlocked(
+    deps: DepsMut,
+    env: Env,
+    info: &MessageInfo,
+    contract: &OsmosisVaultContract,
+    recipient: Option<String>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let migration = match completed_migration(deps.storage)? {
+        Some(migration) => migration,
+        None => return contract.execute_withdraw_all_unlocked(deps, env, info, recipient, limit),
+    };
+
+    // Positions are withdrawn in the order of their IDs, so the matured
+    // positions created before the migration come first
+    let mut num_legacy = 0u32;
+    let mut start_after = None;
+    loop {
+        let claims = contract.claims.query_claims_for_owner(
+            deps.as_ref(),
+            &info.sender,
+            start_after,
+            Some(MAX_LIMIT),
+        )?;
+        let legacy_claims: Vec<_> = claims
+            .iter()
+            .filter(|(id, _)| migration.is_legacy_claim(*id))
+            .collect();
+        num_legacy += legacy_claims
+            .iter()
+            .filter(|(_, claim)| claim.release_at.is_expired(&env.block))
+            .count() as u32;
+        if legacy_claims.len() < MAX_LIMIT as usize || num_legacy >= MAX_LIMIT {
+            break;
+        }
+        start_after = legacy_claims.last().map(|(id, _)| *id);
+    }
+
+    if num_legacy == 0 {
+        return contract.execute_withdraw_all_unlocked(deps, env, info, recipient, limit);
+    }
+    let limit = Some(limit.map_or(num_legacy, |limit| limit.min(num_legacy)));
+    with_legacy_base_token(deps, contract, &migration, |deps| {
+        contract.execute_withdraw_all_unlocked(deps, env, info, recipient, limit)
+    })
+}
+
+/// Send the base tokens of matured unlocking positions to their owners, for
+/// owners that opted in to auto-withdrawal. The positions created before the
+/// completed migration are withdrawn in GAMM shares, so only the leading
+/// positions of the same kind are processed in one call.
+pub fn execute_process_matured_claims(
+    deps: DepsMut,
+    env: Env,
+    info: &MessageInfo,
+    contract: &OsmosisVaultContract,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let migration = match completed_migration(deps.storage)? {
+        Some(migration) => migration,
+        None => return contract.execute_process_matured_claims(deps, env, info, limit),
+    };
+
+    let claims: Vec<Claim> =
+        contract
+            .claims
+            .matured_auto_withdraw_claims(deps.storage, &env.block, limit)?;
+    let legacy = match claims.first() {
+        Some(claim) => migration.is_legacy_claim(claim.id),
+        None => return contract.execute_process_matured_claims(deps, env, info, limit),
+    };
+    let num_leading = claims
+        .iter()
+        .take_while(|claim| migration.is_legacy_claim(claim.id) == legacy)
+        .count() as u32;
+
+    if legacy {
+        with_legacy_base_token(deps, contract, &migration, |deps| {
+            contract.execute_process_matured_claims(deps, env, info, Some(num_leading))
+        })
+    } else {
+        contract.execute_process_matured_claims(deps, env, info, Some(num_leading))
+    }
+}
+
+/// Returns an error if `msg` would stake or unlock base tokens while the
+/// migration to concentrated liquidity is in progress, since the vault's GAMM
+/// shares are unlocking until the migration is completed.
+pub fn assert_not_migrating(storage: &dyn Storage, msg: &ExecuteMsg) -> Result<(), ContractError> {
+    let paused = match msg {
+        ExecuteMsg::Deposit { .. } | ExecuteMsg::Redeem { .. } => true,
+        ExecuteMsg::VaultExtension(msg) => matches!(
+            msg,
+            ExtensionExecuteMsg::Lockup(LockupExecuteMsg::Unlock { .. })
+                | ExtensionExecuteMsg::ForceUnlock(ForceUnlockExecuteMsg::ForceRedeem { .. })
+                | ExtensionExecuteMsg::Apollo(
+                    ApolloExtensionExecuteMsg::Unlock { .. }
+                        | ApolloExtensionExecuteMsg::CancelUnlock { .. }
+                        | ApolloExtensionExecuteMsg::EarlyExitUnlocking { .. }
+                        | ApolloExtensionExecuteMsg::ProcessUnlockBatch {}
+                )
+                | ExtensionExecuteMsg::Osmosis(
+                    OsmosisExtensionExecuteMsg::UpdateLockupDuration { .. }
+                        | OsmosisExtensionExecuteMsg::MigrateLegacyLock { .. }
+                )
+        ),
+    };
+
+    if paused
+        && matches!(
+            CL_MIGRATION.may_load(storage)?,
+            Some(migration) if !migration.completed
+        )
+    {
+        return Err(ContractError::from(
+            "Paused until the migration to concentrated liquidity is completed",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns the migration to concentrated liquidity, or `None` if the vault has
+/// not been migrated.
+pub fn query_cl_migration(deps: Deps) -> StdResult<Option<ClMigration>> {
+    CL_MIGRATION.may_load(deps.storage)
+}
+
+/// Returns the amount of GAMM shares held by the vault for the unlocking
+/// positions created before the completed migration, or zero if the vault has
+/// not been migrated.
+pub fn query_legacy_base_token_balance(deps: Deps, env: &Env) -> StdResult<Uint128> {
+    match completed_migration(deps.storage)? {
+        Some(migration) => migration
+            .legacy_base_token
+            .query_balance(&deps.querier, &env.contract.address),
+        None => Ok(Uint128::zero()),
+    }
+}
+
+/// Queries the concentrated liquidity vault and checks that its pool assets
+/// are those of `legacy_pool`.
+///
+/// ## Returns
+/// Returns the ID of the concentrated liquidity pool and the vault token of
+/// the concentrated liquidity vault.
+fn query_cl_vault(
+    deps: Deps,
+    cl_vault: &Addr,
+    legacy_pool: &OsmosisPool,
+) -> Result<(u64, String), ContractError> {
+    let info: VaultInfoResponse = deps.querier.query_wasm_smart(
+        cl_vault.to_string(),
+        &VaultStandardQueryMsg::<ClVaultExtensionQueryMsg>::Info {},
+    )?;
+    let state: ClVaultStateResponse = deps.querier.query_wasm_smart(
+        cl_vault.to_string(),
+        &VaultStandardQueryMsg::VaultExtension(ClVaultExtensionQueryMsg::State {}),
+    )?;
+
+    // The base token of the concentrated liquidity vault is reported as its
+    // pool assets separated by a slash
+    let mut cl_pool_assets: Vec<String> = info.base_token.split('/').map(String::from).collect();
+    let mut pool_assets: Vec<String> = legacy_pool
+        .pool_assets(deps)?
+        .into_iter()
+        .map(|asset| asset.to_string())
+        .collect();
+    cl_pool_assets.sort();
+    pool_assets.sort();
+    if cl_pool_assets != pool_assets {
+        return Err(ContractError::from(format!(
+            "The pool assets {:?} of the concentrated liquidity vault are not the pool assets {:?} of the vault",
+            cl_pool_assets, pool_assets
+        )));
+    }
+
+    Ok((state.position.pool_id, info.vault_token))
+}
+
+/// Runs `f` with the GAMM shares as the base token of the vault and a staking
+/// of GAMM shares, so that the base tokens of unlocking positions created
+/// before the migration are withdrawn in GAMM shares.
+fn with_legacy_base_token<F>(
+    mut deps: DepsMut,
+    contract: &OsmosisVaultContract,
+    migration: &ClMigration,
+    f: F,
+) -> Result<Response, ContractError>
+where
+    F: FnOnce(DepsMut) -> Result<Response, ContractError>,
+{
+    let base_token = contract.base_vault.base_token.load(deps.storage)?;
+    let staking = contract.staking.load(deps.storage)?;
+
+    contract
+        .base_vault
+        .base_token
+        .save(deps.storage, &migration.legacy_base_token)?;
+    contract.staking.save(
+        deps.storage,
+        &OsmosisStaking::new(
+            migration.lockup_duration,
+            None,
+            migration.legacy_base_token.to_string(),
+        )?,
+    )?;
+    let res = f(deps.branch())?;
+    contract
+        .base_vault
+        .base_token
+        .save(deps.storage, &base_token)?;
+    contract.staking.save(deps.storage, &staking)?;
+
+    Ok(res)
+}
+
+fn completed_migration(storage: &dyn Storage) -> StdResult<Option<ClMigration>> {
+    Ok(CL_MIGRATION
+        .may_load(storage)?
+        .filter(|migration| migration.completed))
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::Deps;
+
+    use super::*;
+
+    const ADMIN: &str = "admin";
+    const GAMM_SHARES: &str = "gamm/pool/1";
+    const CL_VAULT_TOKEN: &str = "factory/cl_vault/vault";
+    const LOCKUP_DURATION: u64 = 1_209_600;
+
+    fn migration(completed: bool) -> ClMigration {
+        ClMigration {
+            cl_vault: Addr::unchecked("cl_vault"),
+            cl_vault_token: CL_VAULT_TOKEN.to_string(),
+            cl_pool_id: 2,
+            legacy_pool_id: 1,
+            legacy_base_token: AssetInfo::Native(GAMM_SHARES.to_string()),
+            lockup_duration: LOCKUP_DURATION,
+            last_legacy_claim_id: Some(5),
+            base_token_amount: Uint128::new(1000),
+            unlocked_at: mock_env().block.time.seconds(),
+            completed,
+        }
+    }
+
+    /// Saves the migration and the state of the vault after it, with the vault
+    /// token of the concentrated liquidity vault as base token.
+    fn setup(deps: DepsMut, migration: &ClMigration) {
+        let contract = OsmosisVaultContract::default();
+        CL_MIGRATION.save(deps.storage, migration).unwrap();
+        contract
+            .base_vault
+            .base_token
+            .save(deps.storage, &AssetInfo::Native(CL_VAULT_TOKEN.to_string()))
+            .unwrap();
+        contract
+            .staking
+            .save(
+                deps.storage,
+                &OsmosisStaking::new(LOCKUP_DURATION, Some(9), CL_VAULT_TOKEN.to_string()).unwrap(),
+            )
+            .unwrap();
+    }
+
+    fn base_token(deps: Deps) -> String {
+        OsmosisVaultContract::default()
+            .base_vault
+            .base_token
+            .load(deps.storage)
+            .unwrap()
+            .to_string()
+    }
+
+    /// Runs [`with_claim_base_token`] for the unlocking position with ID
+    /// `lockup_id` and returns the base token seen inside.
+    fn claim_base_token(deps: DepsMut, lockup_id: u64) -> String {
+        let contract = OsmosisVaultContract::default();
+        let res = with_claim_base_token(deps, &contract, lockup_id, |deps| {
+            Ok(Response::new().add_attribute("base_token", base_token(deps.as_ref())))
+        })
+        .unwrap();
+        res.attributes[0].value.clone()
+    }
+
+    #[test]
+    fn test_is_legacy_claim() {
+        let mut migration = migration(true);
+        assert!(migration.is_legacy_claim(1));
+        assert!(migration.is_legacy_claim(5));
+        assert!(!migration.is_legacy_claim(6));
+
+        migration.last_legacy_claim_id = None;
+        assert!(!migration.is_legacy_claim(1));
+    }
+
+    #[test]
+    fn test_with_claim_base_token() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut(), &migration(true));
+
+        assert_eq!(claim_base_token(deps.as_mut(), 5), GAMM_SHARES);
+        assert_eq!(claim_base_token(deps.as_mut(), 6), CL_VAULT_TOKEN);
+
+        // The base token and staking are restored
+        assert_eq!(base_token(deps.as_ref()), CL_VAULT_TOKEN);
+        let staking = OsmosisVaultContract::default()
+            .staking
+            .load(&deps.storage)
+            .unwrap();
+        assert_eq!(staking.lock_id, Some(9));
+    }
+
+    #[test]
+    fn test_with_claim_base_token_before_completion() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut(), &migration(false));
+
+        // The base token is only switched when the migration is completed
+        assert_eq!(claim_base_token(deps.as_mut(), 5), CL_VAULT_TOKEN);
+    }
+
+    #[test]
+    fn test_assert_not_legacy_claim() {
+        let mut deps = mock_dependencies();
+        assert_not_legacy_claim(&deps.storage, 5).unwrap();
+
+        setup(deps.as_mut(), &migration(true));
+        assert_not_legacy_claim(&deps.storage, 5).unwrap_err();
+        assert_not_legacy_claim(&deps.storage, 6).unwrap();
+    }
+
+    #[test]
+    fn test_assert_not_migrating() {
+        let mut deps = mock_dependencies();
+        let deposit = ExecuteMsg::Deposit {
+            amount: Uint128::new(100),
+            recipient: None,
+        };
+        let unlock =
+            ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(LockupExecuteMsg::Unlock {
+                amount: Uint128::new(100),
+            }));
+        let withdraw = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(
+            LockupExecuteMsg::WithdrawUnlocked {
+                recipient: None,
+                lockup_id: 5,
+            },
+        ));
+
+        assert_not_migrating(&deps.storage, &deposit).unwrap();
+
+        CL_MIGRATION
+            .save(&mut deps.storage, &migration(false))
+            .unwrap();
+        assert_not_migrating(&deps.storage, &deposit).unwrap_err();
+        assert_not_migrating(&deps.storage, &unlock).unwrap_err();
+        assert_not_migrating(&deps.storage, &withdraw).unwrap();
+
+        CL_MIGRATION
+            .save(&mut deps.storage, &migration(true))
+            .unwrap();
+        assert_not_migrating(&deps.storage, &deposit).unwrap();
+        assert_not_migrating(&deps.storage, &unlock).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_only_admin() {
+        let mut deps = mock_dependencies();
+        let contract = OsmosisVaultContract::default();
+        contract
+            .admin
+            .set(deps.as_mut(), Some(Addr::unchecked(ADMIN)))
+            .unwrap();
+
+        let err = execute_migrate_to_concentrated_liquidity(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("other", &[]),
+            &contract,
+            "cl_vault".to_string(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::AdminError(_)));
+
+        // Cannot migrate twice
+        CL_MIGRATION
+            .save(&mut deps.storage, &migration(false))
+            .unwrap();
+        execute_migrate_to_concentrated_liquidity(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            &contract,
+            "cl_vault".to_string(),
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_complete_before_unlocked_fails() {
+        let mut deps = mock_dependencies();
+        let contract = OsmosisVaultContract::default();
+        contract
+            .admin
+            .set(deps.as_mut(), Some(Addr::unchecked(ADMIN)))
+            .unwrap();
+        let complete = |deps: DepsMut, env: Env| {
+            execute_complete_concentrated_liquidity_migration(
+                deps,
+                env,
+                mock_info(ADMIN, &[]),
+                &contract,
+            )
+        };
+
+        // There is no migration to complete
+        complete(deps.as_mut(), mock_env()).unwrap_err();
+
+        // The GAMM shares are returned at the end of the block in which they
+        // unlock
+        setup(deps.as_mut(), &migration(false));
+        complete(deps.as_mut(), mock_env()).unwrap_err();
+
+        // A completed migration cannot be completed again
+        CL_MIGRATION
+            .save(&mut deps.storage, &migration(true))
+            .unwrap();
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(1);
+        complete(deps.as_mut(), env).unwrap_err();
+    }
+}
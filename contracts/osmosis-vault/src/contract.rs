@@ -1,30 +1,36 @@
 use apollo_vault::error::ContractError;
-use apollo_vault::execute_unlock::OSMOSIS_TEMP_WORKAROUND_LOCK_TOKENS_REPLY_ID;
+use apollo_vault::migrations::{migrate_contract, MigrationStep};
 use apollo_vault::msg::{
-    ApolloExtensionExecuteMsg, ApolloExtensionQueryMsg, CallbackMsg, ExtensionExecuteMsg,
-    ExtensionQueryMsg,
+    ApolloExtensionExecuteMsg, ApolloExtensionQueryMsg, CallbackMsg, CALLBACK_DATA_REPLY_ID,
 };
+use apollo_vault::unlocking_position_nft::UNLOCKING_POSITION_NFT_INSTANTIATE_REPLY_ID;
 use apollo_vault::AutocompoundingVault;
 
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, Binary, Deps, DepsMut, Env, Event, MessageInfo, Reply, Response, StdResult,
-    SubMsgResponse, SubMsgResult, Uint128,
+    to_binary, Binary, Deps, DepsMut, Env, Event, MessageInfo, Reply, Response, StdError,
+    StdResult, SubMsgResponse, SubMsgResult, Uint128,
 };
-use cw2::{get_contract_version, set_contract_version};
+use cw2::set_contract_version;
 use cw_dex::osmosis::{
     OsmosisPool, OsmosisStaking, OSMOSIS_LOCK_TOKENS_REPLY_ID, OSMOSIS_UNLOCK_TOKENS_REPLY_ID,
 };
-use cw_dex::traits::{LockedStaking, Pool};
+use cw_dex::traits::Pool;
 use cw_vault_standard::extensions::force_unlock::ForceUnlockExecuteMsg;
 use cw_vault_standard::extensions::lockup::{LockupExecuteMsg, LockupQueryMsg};
 use cw_vault_standard::msg::{VaultInfoResponse, VaultStandardInfoResponse};
 use cw_vault_token::osmosis::OsmosisDenom;
-use osmosis_std::types::osmosis::lockup::MsgLockTokensResponse;
-use semver::Version;
+use osmosis_std::shim::Duration;
+use osmosis_std::types::osmosis::lockup::{
+    LockupQuerier, MsgBeginUnlocking, MsgBeginUnlockingResponse, MsgLockTokensResponse,
+};
 
-use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use crate::msg::{
+    ExecuteMsg, ExtensionExecuteMsg, ExtensionQueryMsg, InstantiateMsg, MigrateMsg,
+    OsmosisExtensionExecuteMsg, OsmosisExtensionQueryMsg, QueryMsg,
+};
+use crate::{cl_migration, lockup};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:osmosis-vault";
@@ -70,7 +76,21 @@ pub fn instantiate(
 
     let vault_token = OsmosisDenom::new(env.contract.address.to_string(), msg.vault_token_subdenom);
 
-    contract.init(deps, admin_addr, pool, staking, config, vault_token, None)
+    // Optionally instantiate a cw721 contract to represent unlocking positions
+    let instantiate_nft_msg = msg
+        .unlocking_position_nft
+        .map(|info| contract.instantiate_unlocking_position_nft(&env, info))
+        .transpose()?;
+
+    if let Some(epoch_duration) = msg.unlock_epoch_duration {
+        contract
+            .unlock_batches
+            .set_epoch_duration(deps.storage, epoch_duration)?;
+    }
+
+    Ok(contract
+        .init(deps, admin_addr, pool, staking, config, vault_token, None)?
+        .add_submessages(instantiate_nft_msg))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -81,43 +101,46 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     let contract = OsmosisVaultContract::default();
+    cl_migration::assert_not_migrating(deps.storage, &msg)?;
 
     match msg {
         ExecuteMsg::Deposit { amount, recipient } => {
             contract.execute_deposit(deps, env, &info, amount, recipient)
         }
-        ExecuteMsg::Redeem {
-            recipient: _,
-            amount: _,
-        } => Err(ContractError::from(
-            "Redeem is not supported for locked vaults. Use Unlock and WithdrawUnlocked.",
-        )),
+        ExecuteMsg::Redeem { recipient, amount } => {
+            contract.execute_instant_redeem(deps, env, &info, amount, recipient)
+        }
         ExecuteMsg::VaultExtension(msg) => match msg {
             ExtensionExecuteMsg::Lockup(msg) => match msg {
                 LockupExecuteMsg::WithdrawUnlocked {
                     recipient,
                     lockup_id,
-                } => contract.execute_withdraw_unlocked(deps, env, &info, lockup_id, recipient),
+                } => cl_migration::with_claim_base_token(deps, &contract, lockup_id, |deps| {
+                    contract.execute_withdraw_unlocked(deps, env, &info, lockup_id, recipient)
+                }),
                 LockupExecuteMsg::Unlock { amount } => {
-                    contract.execute_unlock(deps, env, &info, amount)
+                    contract.execute_unlock(deps, env, &info, amount, None)
                 }
             },
             ExtensionExecuteMsg::ForceUnlock(msg) => match msg {
                 ForceUnlockExecuteMsg::ForceRedeem { recipient, amount } => {
-                    contract.execute_force_redeem(deps, env, info, amount, recipient)
+                    lockup::execute_force_redeem(deps, env, info, &contract, amount, recipient)
                 }
                 ForceUnlockExecuteMsg::ForceWithdrawUnlocking {
                     lockup_id,
                     amount,
                     recipient,
-                } => contract.execute_force_withdraw_unlocking(
-                    deps, env, info, lockup_id, amount, recipient,
-                ),
+                } => cl_migration::with_claim_base_token(deps, &contract, lockup_id, |deps| {
+                    contract.execute_force_withdraw_unlocking(
+                        deps, env, info, lockup_id, amount, recipient,
+                    )
+                }),
                 ForceUnlockExecuteMsg::UpdateForceWithdrawWhitelist {
                     add_addresses,
                     remove_addresses,
                 } => contract.execute_update_force_withdraw_whitelist(
                     deps,
+                    env,
                     info,
                     add_addresses,
                     remove_addresses,
@@ -125,16 +148,104 @@ pub fn execute(
             },
             ExtensionExecuteMsg::Apollo(msg) => match msg {
                 ApolloExtensionExecuteMsg::UpdateConfig { updates } => {
-                    contract.execute_update_config(deps, info, updates)
-                }
-                ApolloExtensionExecuteMsg::UpdateAdmin { address } => {
-                    contract.execute_update_admin(deps, info, address)
+                    contract.execute_update_config(deps, env, info, updates)
                 }
+                ApolloExtensionExecuteMsg::UpdateAdmin {
+                    address,
+                    expiration,
+                } => contract.execute_update_admin(deps, env, info, address, expiration),
                 ApolloExtensionExecuteMsg::AcceptAdminTransfer {} => {
-                    contract.execute_accept_admin_transfer(deps, info)
+                    contract.execute_accept_admin_transfer(deps, env, info)
+                }
+                ApolloExtensionExecuteMsg::RenounceAdmin { expiration } => {
+                    contract.execute_renounce_admin(deps, env, info, expiration)
+                }
+                ApolloExtensionExecuteMsg::ConfirmRenounceAdmin {} => {
+                    contract.execute_confirm_renounce_admin(deps, env, info)
                 }
                 ApolloExtensionExecuteMsg::DropAdminTransfer {} => {
-                    contract.execute_drop_admin_transfer(deps, info)
+                    contract.execute_drop_admin_transfer(deps, env, info)
+                }
+                ApolloExtensionExecuteMsg::Unlock { amount, owner } => {
+                    contract.execute_unlock(deps, env, &info, amount, owner)
+                }
+                ApolloExtensionExecuteMsg::CancelUnlock { lockup_id } => {
+                    cl_migration::assert_not_legacy_claim(deps.storage, lockup_id)?;
+                    contract.execute_cancel_unlock(deps, env, &info, lockup_id)
+                }
+                ApolloExtensionExecuteMsg::EarlyExitUnlocking {
+                    lockup_id,
+                    recipient,
+                } => {
+                    cl_migration::assert_not_legacy_claim(deps.storage, lockup_id)?;
+                    contract.execute_early_exit_unlocking(deps, env, &info, lockup_id, recipient)
+                }
+                ApolloExtensionExecuteMsg::WithdrawAllUnlocked { recipient, limit } => {
+                    cl_migration::execute_withdraw_all_unlocked(
+                        deps, env, &info, &contract, recipient, limit,
+                    )
+                }
+                ApolloExtensionExecuteMsg::SetAutoWithdraw { enabled } => {
+                    contract.execute_set_auto_withdraw(deps, &info, enabled)
+                }
+                ApolloExtensionExecuteMsg::ProcessMaturedClaims { limit } => {
+                    cl_migration::execute_process_matured_claims(deps, env, &info, &contract, limit)
+                }
+                ApolloExtensionExecuteMsg::TransferUnlockingPosition {
+                    lockup_id,
+                    new_owner,
+                } => contract
+                    .execute_transfer_unlocking_position(deps, env, &info, lockup_id, new_owner),
+                ApolloExtensionExecuteMsg::ApproveUnlockingPositionOperator {
+                    operator,
+                    expires,
+                } => contract.execute_approve_unlocking_position_operator(
+                    deps, env, &info, operator, expires,
+                ),
+                ApolloExtensionExecuteMsg::RevokeUnlockingPositionOperator { operator } => {
+                    contract.execute_revoke_unlocking_position_operator(deps, &info, operator)
+                }
+                ApolloExtensionExecuteMsg::ReindexClaims { limit } => {
+                    contract.execute_reindex_claims(deps, &info, limit)
+                }
+                ApolloExtensionExecuteMsg::ProcessUnlockBatch {} => {
+                    lockup::execute_process_unlock_batch(deps, env, &info, &contract)
+                }
+                ApolloExtensionExecuteMsg::UpdateUnlockEpochDuration { epoch_duration } => {
+                    contract.execute_update_unlock_epoch_duration(deps, env, info, epoch_duration)
+                }
+                ApolloExtensionExecuteMsg::WithdrawUnlockBatch {
+                    batch_id,
+                    recipient,
+                } => cl_migration::with_batch_base_token(deps, &contract, batch_id, |deps| {
+                    contract.execute_withdraw_unlock_batch(deps, env, &info, batch_id, recipient)
+                }),
+            },
+            ExtensionExecuteMsg::Osmosis(msg) => match msg {
+                OsmosisExtensionExecuteMsg::UpdateLockupDuration { lockup_duration } => {
+                    lockup::execute_update_lockup_duration(
+                        deps,
+                        env,
+                        info,
+                        &contract,
+                        lockup_duration,
+                    )
+                }
+                OsmosisExtensionExecuteMsg::MigrateLegacyLock { amount } => {
+                    lockup::execute_migrate_legacy_lock(deps, env, info, &contract, amount)
+                }
+                OsmosisExtensionExecuteMsg::ReconcileLockId {} => {
+                    lockup::execute_reconcile_lock_id(deps, env, info, &contract)
+                }
+                OsmosisExtensionExecuteMsg::MigrateToConcentratedLiquidity { cl_vault } => {
+                    cl_migration::execute_migrate_to_concentrated_liquidity(
+                        deps, env, info, &contract, cl_vault,
+                    )
+                }
+                OsmosisExtensionExecuteMsg::CompleteConcentratedLiquidityMigration {} => {
+                    cl_migration::execute_complete_concentrated_liquidity_migration(
+                        deps, env, info, &contract,
+                    )
                 }
             },
             ExtensionExecuteMsg::Callback(msg) => {
@@ -145,24 +256,42 @@ pub fn execute(
 
                 match msg {
                     CallbackMsg::SellRewards {} => {
-                        contract.execute_callback_sell_rewards(deps, env, info)
+                        cl_migration::execute_callback_sell_rewards(deps, env, info, &contract)
                     }
                     CallbackMsg::ProvideLiquidity {} => {
-                        contract.execute_callback_provide_liquidity(deps, env, info)
+                        cl_migration::execute_callback_provide_liquidity(deps, env, info, &contract)
                     }
                     CallbackMsg::Stake {
                         base_token_balance_before,
-                    } => contract.execute_callback_stake(deps, env, base_token_balance_before),
+                    } => lockup::execute_callback_stake(
+                        deps,
+                        env,
+                        &contract,
+                        base_token_balance_before,
+                    ),
                     CallbackMsg::MintVaultToken { amount, recipient } => {
                         contract.execute_callback_mint_vault_token(deps, env, amount, recipient)
                     }
                     CallbackMsg::Unlock {
                         owner,
                         vault_token_amount,
-                    } => {
-                        contract.execute_callback_unlock(deps, env, info, owner, vault_token_amount)
+                    } => lockup::execute_callback_unlock(
+                        deps,
+                        env,
+                        info,
+                        &contract,
+                        owner,
+                        vault_token_amount,
+                    ),
+                    CallbackMsg::SaveClaim {} => {
+                        lockup::execute_callback_save_claim(deps, &contract)
+                    }
+                    CallbackMsg::SaveUnlockBatchClaim { batch_id } => {
+                        contract.execute_callback_save_unlock_batch_claim(deps, batch_id)
+                    }
+                    CallbackMsg::RedeemFromBuffer { amount, recipient } => {
+                        contract.execute_callback_redeem_from_buffer(deps, env, amount, recipient)
                     }
-                    CallbackMsg::SaveClaim {} => contract.execute_callback_save_claim(deps),
                 }
             }
         },
@@ -195,7 +324,7 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             to_binary(&base_vault.query_simulate_deposit(deps, amount)?)
         }
         QueryMsg::PreviewRedeem { amount } => {
-            to_binary(&base_vault.query_simulate_withdraw(deps, amount)?)
+            to_binary(&contract.query_preview_instant_redeem(deps, amount)?)
         }
         QueryMsg::TotalAssets {} => to_binary(&base_vault.query_total_assets(deps)?),
         QueryMsg::TotalVaultTokenSupply {} => {
@@ -222,24 +351,144 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
                 LockupQueryMsg::UnlockingPosition { lockup_id } => {
                     to_binary(&contract.claims.query_claim_by_id(deps, lockup_id)?)
                 }
-                LockupQueryMsg::LockupDuration {} => to_binary(
-                    &contract
-                        .staking
-                        .load(deps.storage)?
-                        .get_lockup_duration(deps)?,
-                ),
+                LockupQueryMsg::LockupDuration {} => {
+                    to_binary(&lockup::query_lockup_duration(deps, &contract)?)
+                }
             },
             ExtensionQueryMsg::Apollo(msg) => match msg {
                 ApolloExtensionQueryMsg::State {} => to_binary(&contract.query_state(deps, env)?),
+                ApolloExtensionQueryMsg::Config {} => to_binary(&contract.query_config(deps)?),
+                ApolloExtensionQueryMsg::Admin {} => to_binary(&contract.query_admin(deps)?),
+                ApolloExtensionQueryMsg::PendingAdminTransfer {} => {
+                    to_binary(&contract.query_pending_admin_transfer(deps)?)
+                }
+                ApolloExtensionQueryMsg::ForceWithdrawWhitelist {} => {
+                    to_binary(&contract.query_force_withdraw_whitelist(deps)?)
+                }
+                ApolloExtensionQueryMsg::ContractVersion {} => {
+                    to_binary(&contract.query_contract_version(deps)?)
+                }
+                ApolloExtensionQueryMsg::RewardRoutes {} => {
+                    to_binary(&contract.query_reward_routes(deps)?)
+                }
+                ApolloExtensionQueryMsg::Solvency {} => {
+                    let (staked, unlocking) = query_lockup_module_balances(deps, &env, &contract)?;
+                    to_binary(&contract.query_solvency(deps, env, staked, unlocking)?)
+                }
+                ApolloExtensionQueryMsg::UnlockingPositionOperators {
+                    owner,
+                    start_after,
+                    limit,
+                } => to_binary(&contract.query_unlocking_position_operators(
+                    deps,
+                    owner,
+                    start_after,
+                    limit,
+                )?),
+                ApolloExtensionQueryMsg::AutoWithdraw { owner } => {
+                    to_binary(&contract.query_auto_withdraw(deps, owner)?)
+                }
+                ApolloExtensionQueryMsg::UnlockingPositionNft {} => {
+                    to_binary(&contract.query_unlocking_position_nft(deps)?)
+                }
+                ApolloExtensionQueryMsg::AllUnlockingPositions {
+                    start_after,
+                    limit,
+                    filter,
+                } => to_binary(&contract.query_all_unlocking_positions(
+                    deps,
+                    env,
+                    start_after,
+                    limit,
+                    filter,
+                )?),
+                ApolloExtensionQueryMsg::UnlockingTotals {
+                    bucket_duration,
+                    num_buckets,
+                } => to_binary(&contract.query_unlocking_totals(
+                    deps,
+                    env,
+                    bucket_duration,
+                    num_buckets,
+                )?),
+                ApolloExtensionQueryMsg::ClaimsReindexCursor {} => {
+                    to_binary(&contract.query_claims_reindex_cursor(deps)?)
+                }
+                ApolloExtensionQueryMsg::UnlockEpochDuration {} => {
+                    to_binary(&contract.query_unlock_epoch_duration(deps)?)
+                }
+                ApolloExtensionQueryMsg::UnlockBatch { batch_id } => {
+                    to_binary(&contract.query_unlock_batch(deps, batch_id)?)
+                }
+                ApolloExtensionQueryMsg::UnlockBatches { start_after, limit } => {
+                    to_binary(&contract.query_unlock_batches(deps, start_after, limit)?)
+                }
+                ApolloExtensionQueryMsg::UnlockBatchShares {
+                    owner,
+                    start_after,
+                    limit,
+                } => to_binary(&contract.query_unlock_batch_shares(
+                    deps,
+                    owner,
+                    start_after,
+                    limit,
+                )?),
+                ApolloExtensionQueryMsg::ChangeLog { start_after, limit } => {
+                    to_binary(&contract.query_change_log(deps, start_after, limit)?)
+                }
+            },
+            ExtensionQueryMsg::Osmosis(msg) => match msg {
+                OsmosisExtensionQueryMsg::ConcentratedLiquidityMigration {} => {
+                    to_binary(&cl_migration::query_cl_migration(deps)?)
+                }
             },
         },
     }
 }
 
+/// Returns the amounts of base tokens locked and unlocking in the Osmosis lockup
+/// module for the vault, including the base tokens locked in the legacy lock.
+/// After the migration to concentrated liquidity, the GAMM shares held for the
+/// unlocking positions created before it are counted as unlocking, since the
+/// total unlocking base tokens include these positions.
+fn query_lockup_module_balances(
+    deps: Deps,
+    env: &Env,
+    contract: &OsmosisVaultContract,
+) -> StdResult<(Uint128, Uint128)> {
+    let denom = contract
+        .base_vault
+        .base_token
+        .load(deps.storage)?
+        .to_string();
+    let staking = contract.staking.load(deps.storage)?;
+
+    let locked = match staking.lock_id {
+        Some(lock_id) => lockup::query_locked_amount(deps, lock_id, &denom)?,
+        None => Uint128::zero(),
+    }
+    .checked_add(lockup::query_legacy_lock_amount(deps, contract)?)?;
+    let unlocking = lockup::amount_of_denom(
+        LockupQuerier::new(&deps.querier)
+            .account_unlocking_coins(env.contract.address.to_string())?
+            .coins,
+        &denom,
+    )?
+    .checked_add(cl_migration::query_legacy_base_token_balance(deps, env)?)?;
+
+    Ok((locked, unlocking))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, ContractError> {
     let contract = OsmosisVaultContract::default();
 
+    // The instantiate reply is parsed from the whole reply rather than only the
+    // data
+    if reply.id == UNLOCKING_POSITION_NFT_INSTANTIATE_REPLY_ID {
+        return contract.reply_instantiate_unlocking_position_nft(deps, reply);
+    }
+
     if let SubMsgResult::Ok(SubMsgResponse {
         data: Some(b),
         events: _,
@@ -259,21 +508,18 @@ pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, Contrac
                 contract.staking.save(deps.storage, &staking)?;
                 Ok(Response::default())
             }
-            OSMOSIS_UNLOCK_TOKENS_REPLY_ID => Ok(Response::default()),
-            OSMOSIS_TEMP_WORKAROUND_LOCK_TOKENS_REPLY_ID => {
-                let res: MsgLockTokensResponse = b.try_into().map_err(ContractError::Std)?;
-
-                let mut pending_claim = contract.claims.get_pending_claim(deps.storage)?;
-                pending_claim.id = res.id - 1;
-                contract
-                    .claims
-                    .set_pending_claim(deps.storage, &pending_claim)?;
-
-                let event = Event::new("apollo/vault/unlock/reply")
-                    .add_attribute("vault_type", "osmosis")
-                    .add_attribute("lock_id", res.id.to_string());
-                Ok(Response::default().add_event(event))
+            OSMOSIS_UNLOCK_TOKENS_REPLY_ID => {
+                // The unlocking tokens are split off into a new lock, whose ID
+                // becomes the ID of the unlocking position
+                let res: MsgBeginUnlockingResponse = b.try_into().map_err(ContractError::Std)?;
+                if res.unlocking_lock_id == 0 {
+                    return Err(ContractError::Std(StdError::generic_err(
+                        "MsgBeginUnlockingResponse is missing the unlocking lock ID",
+                    )));
+                }
+                contract.set_pending_claim_id(deps, res.unlocking_lock_id)
             }
+            CALLBACK_DATA_REPLY_ID => contract.reply_callback_data(b),
             id => Err(ContractError::UnknownReplyId(id)),
         }
     } else {
@@ -282,16 +528,53 @@ pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, Contrac
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
-    let version: Version = CONTRACT_VERSION.parse()?;
-    let storage_version: Version = get_contract_version(deps.storage)?.version.parse()?;
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let contract = OsmosisVaultContract::default();
+    let mut steps = contract.migration_steps();
+    steps.push(MigrationStep::new(
+        "<0.2.0",
+        "unlock_lock_id_workaround_locks",
+        |deps, env| unlock_lock_id_workaround_locks(deps, env, &contract),
+    ));
+    migrate_contract(deps, &env, CONTRACT_NAME, CONTRACT_VERSION, steps)
+}
 
-    // migrate only if newer
-    if storage_version < version {
-        set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+/// Begins unlocking the 1 unit locks with durations of a few nanoseconds that
+/// versions before 0.2.0 created to learn the ID of new unlocking positions.
+/// The base tokens are returned to the vault when the locks have unlocked.
+fn unlock_lock_id_workaround_locks(
+    deps: DepsMut,
+    env: &Env,
+    contract: &OsmosisVaultContract,
+) -> Result<Response, ContractError> {
+    let staking = contract.staking.load(deps.storage)?;
+    let locks = LockupQuerier::new(&deps.querier)
+        .account_locked_longer_duration_not_unlocking_only(
+            env.contract.address.to_string(),
+            Some(Duration {
+                seconds: 0,
+                nanos: 0,
+            }),
+        )?
+        .locks;
 
-        // If state structure changed in any contract version in the way
-        // migration is needed, it should occur here
-    }
-    Ok(Response::default())
+    let msgs: Vec<MsgBeginUnlocking> = locks
+        .into_iter()
+        .filter(|lock| {
+            Some(lock.id) != staking.lock_id
+                && matches!(&lock.duration, Some(d) if d.seconds == 0 && d.nanos > 0)
+        })
+        .map(|lock| MsgBeginUnlocking {
+            owner: env.contract.address.to_string(),
+            id: lock.id,
+            // Empty coins unlocks the whole lock
+            coins: vec![],
+        })
+        .collect();
+
+    let event = Event::new("apollo/vaults/migrate")
+        .add_attribute("action", "unlock_lock_id_workaround_locks")
+        .add_attribute("num_locks", msgs.len().to_string());
+
+    Ok(Response::new().add_messages(msgs).add_event(event))
 }
@@ -1,2 +1,4 @@
+pub mod cl_migration;
 pub mod contract;
+pub mod lockup;
 pub mod msg;
@@ -0,0 +1,827 @@
+use std::str::FromStr;
+
+use apollo_utils::responses::merge_responses;
+use apollo_vault::error::ContractError;
+use apollo_vault::msg::CallbackMsg;
+use apollo_vault::state::{ChangeLogAction, Claim};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_binary, Addr, Deps, DepsMut, Env, Event, MessageInfo, Order, Response, StdResult, Storage,
+    Uint128,
+};
+use cw_dex::osmosis::OsmosisStaking;
+use cw_dex::traits::{ForceUnlock, LockedStaking, Stake, Unlock};
+use cw_storage_plus::{Bound, Item, Map};
+use cw_utils::Duration;
+use osmosis_std::types::cosmos::base::v1beta1::Coin;
+use osmosis_std::types::osmosis::lockup::{LockupQuerier, MsgBeginUnlocking, MsgExtendLockup};
+
+use crate::contract::OsmosisVaultContract;
+use crate::msg::{LockupDurationResponse, SplitUnlockResponseData};
+
+/// The lock that the vault staked its base tokens in before the lockup
+/// duration was shortened. Osmosis locks cannot be shortened, so the legacy
+/// lock keeps its duration and is drained by unlocks before the current lock.
+pub const LEGACY_LOCK: Item<LegacyLock> = Item::new("legacy_lock");
+
+/// The base tokens migrated from the legacy lock that are unlocking, by the
+/// time in seconds at which they have unlocked. Once they have unlocked they
+/// are staked again in the current lock.
+pub const LEGACY_LOCK_MIGRATIONS: Map<u64, Uint128> = Map::new("legacy_lock_migrations");
+
+/// Set when an unlock is split between the legacy lock and the current lock,
+/// so that the `Unlock` callback of the second part takes its base tokens from
+/// the current lock.
+const UNLOCK_FROM_CURRENT_LOCK: Item<bool> = Item::new("unlock_from_current_lock");
+
+/// The unlocking position of the first part of a split unlock, kept until the
+/// unlocking position of the second part is saved so that both are returned
+/// as the data of the response to the unlock.
+const LEGACY_UNLOCK_CLAIM: Item<Claim> = Item::new("legacy_unlock_claim");
+
+/// A lock of the vault with a lockup duration other than the current one.
+#[cw_serde]
+pub struct LegacyLock {
+    /// The ID of the lock.
+    pub lock_id: u64,
+    /// The lockup duration of the lock in seconds.
+    pub lockup_duration: u64,
+}
+
+/// Change the lockup duration that base tokens are staked with. Only callable
+/// by the admin.
+///
+/// If the new duration is longer, the existing lock is extended to the new
+/// duration. Otherwise the existing lock becomes the legacy lock, which
+/// unlocks are taken from first, and base tokens are staked in a new lock.
+/// The admin migrates the legacy lock to the new lock with
+/// `MigrateLegacyLock`. The duration cannot be changed again until the legacy
+/// lock is empty.
+pub fn execute_update_lockup_duration(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: &OsmosisVaultContract,
+    lockup_duration: u64,
+) -> Result<Response, ContractError> {
+    contract.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+    if let Some((legacy_lock, _)) = load_legacy_lock(deps.branch(), contract)? {
+        return Err(ContractError::from(format!(
+            "Cannot change the lockup duration while legacy lock {} has base tokens locked. Migrate them with MigrateLegacyLock first",
+            legacy_lock.lock_id
+        )));
+    }
+
+    let staking = contract.staking.load(deps.storage)?;
+    let current_duration = lockup_duration_seconds(deps.as_ref(), &staking)?;
+    if lockup_duration == current_duration {
+        return Err(ContractError::from(format!(
+            "Lockup duration is already {} seconds",
+            lockup_duration
+        )));
+    }
+
+    let denom = base_token_denom(deps.as_ref(), contract)?;
+    let mut new_staking = OsmosisStaking::new(lockup_duration, None, denom)?;
+
+    let mut res = Response::new();
+    if let Some(lock_id) = staking.lock_id {
+        if lockup_duration > current_duration {
+            // Extend the existing lock, which keeps its ID
+            res = res.add_message(MsgExtendLockup {
+                owner: env.contract.address.to_string(),
+                id: lock_id,
+                duration: Some(osmosis_std::shim::Duration {
+                    seconds: lockup_duration as i64,
+                    nanos: 0,
+                }),
+            });
+            new_staking.lock_id = Some(lock_id);
+        } else {
+            // The new lock is created and its ID saved on the next stake
+            LEGACY_LOCK.save(
+                deps.storage,
+                &LegacyLock {
+                    lock_id,
+                    lockup_duration: current_duration,
+                },
+            )?;
+        }
+    }
+    contract.staking.save(deps.storage, &new_staking)?;
+
+    contract.change_log.record(
+        deps.storage,
+        &env.block,
+        &info.sender,
+        ChangeLogAction::Custom {
+            action: "update_lockup_duration".to_string(),
+            before: current_duration.to_string(),
+            after: lockup_duration.to_string(),
+        },
+    )?;
+
+    let event = Event::new("apollo/vaults/osmosis_vault").add_attributes(vec![
+        ("action", "execute_update_lockup_duration"),
+        ("before", &current_duration.to_string()),
+        ("after", &lockup_duration.to_string()),
+    ]);
+
+    Ok(res.add_event(event))
+}
+
+/// Begin migrating `amount` base tokens, or all base tokens if `None`, from
+/// the legacy lock to the current lock. Only callable by the admin.
+///
+/// The base tokens begin unlocking from the legacy lock and are staked again
+/// in the current lock once they have unlocked, the next time base tokens are
+/// staked or the lock ID is reconciled. Until then they cannot be unlocked by
+/// users, so the admin should migrate the legacy lock in parts if the current
+/// lock holds too few base tokens to cover unlocks in the meantime.
+pub fn execute_migrate_legacy_lock(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: &OsmosisVaultContract,
+    amount: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    contract.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let (legacy_lock, locked) = load_legacy_lock(deps.branch(), contract)?
+        .ok_or_else(|| ContractError::from("There is no legacy lock to migrate"))?;
+    let amount = amount.unwrap_or(locked);
+    if amount.is_zero() || amount > locked {
+        return Err(ContractError::from(format!(
+            "Invalid amount {} to migrate. The legacy lock has {} base tokens locked",
+            amount, locked
+        )));
+    }
+    if amount == locked {
+        LEGACY_LOCK.remove(deps.storage);
+    }
+
+    let unlocked_at = env
+        .block
+        .time
+        .plus_seconds(legacy_lock.lockup_duration)
+        .seconds();
+    LEGACY_LOCK_MIGRATIONS.update(deps.storage, unlocked_at, |migrating| -> StdResult<_> {
+        Ok(migrating.unwrap_or_default().checked_add(amount)?)
+    })?;
+
+    let begin_unlocking_msg = MsgBeginUnlocking {
+        owner: env.contract.address.to_string(),
+        id: legacy_lock.lock_id,
+        coins: vec![Coin {
+            denom: base_token_denom(deps.as_ref(), contract)?,
+            amount: amount.to_string(),
+        }],
+    };
+
+    let event = Event::new("apollo/vaults/osmosis_vault").add_attributes(vec![
+        ("action", "execute_migrate_legacy_lock".to_string()),
+        ("lock_id", legacy_lock.lock_id.to_string()),
+        ("amount", amount.to_string()),
+        ("unlocked_at", unlocked_at.to_string()),
+    ]);
+
+    Ok(Response::new()
+        .add_message(begin_unlocking_msg)
+        .add_event(event))
+}
+
+/// Repair the ID of the vault's lock, remove the legacy lock if it is no
+/// longer locked and stake the base tokens migrated from the legacy lock that
+/// have unlocked. Can be called by anyone.
+pub fn execute_reconcile_lock_id(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: &OsmosisVaultContract,
+) -> Result<Response, ContractError> {
+    let (before, after) = reconcile_lock_id(deps.branch(), &env, contract)?;
+
+    let migrated = take_unlocked_migrations(deps.storage, &env)?;
+    let stake_res = if migrated.is_zero() {
+        Response::new()
+    } else {
+        contract
+            .staking
+            .load(deps.storage)?
+            .stake(deps.as_ref(), &env, migrated)?
+    };
+
+    let event = Event::new("apollo/vaults/osmosis_vault").add_attributes(vec![
+        ("action", "execute_reconcile_lock_id".to_string()),
+        ("sender", info.sender.to_string()),
+        ("lock_id_before", format!("{:?}", before)),
+        ("lock_id_after", format!("{:?}", after)),
+        ("migrated_base_tokens_staked", migrated.to_string()),
+    ]);
+
+    Ok(stake_res.add_event(event))
+}
+
+/// Queries the lockup module for the vault's locks that are not unlocking and
+/// sets the ID of the vault's lock to the lock of the base token with the
+/// current lockup duration, or to `None` if there is no such lock. Osmosis
+/// adds newly locked tokens to the lock with the lowest ID if there are
+/// several. Also removes the legacy lock if it is no longer locked.
+///
+/// ## Returns
+/// Returns the lock ID before and after reconciling.
+pub fn reconcile_lock_id(
+    deps: DepsMut,
+    env: &Env,
+    contract: &OsmosisVaultContract,
+) -> Result<(Option<u64>, Option<u64>), ContractError> {
+    let mut staking = contract.staking.load(deps.storage)?;
+    let lockup_duration = lockup_duration_seconds(deps.as_ref(), &staking)?;
+    let denom = base_token_denom(deps.as_ref(), contract)?;
+
+    let locks: Vec<_> = LockupQuerier::new(&deps.querier)
+        .account_locked_longer_duration_not_unlocking_only(
+            env.contract.address.to_string(),
+            Some(osmosis_std::shim::Duration {
+                seconds: 0,
+                nanos: 0,
+            }),
+        )?
+        .locks
+        .into_iter()
+        .filter(|lock| lock.coins.iter().any(|coin| coin.denom == denom))
+        .collect();
+
+    if let Some(legacy_lock) = LEGACY_LOCK.may_load(deps.storage)? {
+        if !locks.iter().any(|lock| lock.id == legacy_lock.lock_id) {
+            LEGACY_LOCK.remove(deps.storage);
+        }
+    }
+
+    let before = staking.lock_id;
+    staking.lock_id = locks
+        .iter()
+        .filter(|lock| {
+            matches!(
+                &lock.duration,
+                Some(duration) if duration.seconds == lockup_duration as i64 && duration.nanos == 0
+            )
+        })
+        .map(|lock| lock.id)
+        .min();
+    if staking.lock_id != before {
+        contract.staking.save(deps.storage, &staking)?;
+    }
+
+    Ok((before, staking.lock_id))
+}
+
+/// Callback function to stake the base tokens received since
+/// `base_token_balance_before`, after repairing the lock ID so that they are
+/// added to the vault's current lock. The base tokens migrated from the legacy
+/// lock that have unlocked are staked as well.
+pub fn execute_callback_stake(
+    mut deps: DepsMut,
+    env: Env,
+    contract: &OsmosisVaultContract,
+    base_token_balance_before: Uint128,
+) -> Result<Response, ContractError> {
+    let (before, after) = reconcile_lock_id(deps.branch(), &env, contract)?;
+    let migrated = take_unlocked_migrations(deps.storage, &env)?;
+    let base_token_balance_before = base_token_balance_before.checked_sub(migrated)?;
+    let event = (before != after).then(|| {
+        Event::new("apollo/vaults/osmosis_vault").add_attributes(vec![
+            ("action", "reconcile_lock_id".to_string()),
+            ("lock_id_before", format!("{:?}", before)),
+            ("lock_id_after", format!("{:?}", after)),
+        ])
+    });
+
+    Ok(contract
+        .execute_callback_stake(deps, env, base_token_balance_before)?
+        .add_events(event))
+}
+
+/// Callback function to burn vault tokens and start unlocking the base tokens
+/// they represent, taking them from the legacy lock first.
+///
+/// If the legacy lock has fewer base tokens locked than are unlocked, the
+/// unlock is split into two unlocking positions: one for the vault tokens
+/// worth the base tokens left in the legacy lock, which matures after the
+/// legacy lockup duration, and one for the remaining vault tokens, which is
+/// unlocked from the current lock by a second `Unlock` callback. Both count
+/// against the maximum number of unlocking positions of the owner, and both
+/// are returned as [`SplitUnlockResponseData`].
+pub fn execute_callback_unlock(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: &OsmosisVaultContract,
+    owner: Addr,
+    vault_token_amount: Uint128,
+) -> Result<Response, ContractError> {
+    // In epoch mode the base tokens are unlocked by `ProcessUnlockBatch`
+    if contract
+        .unlock_batches
+        .epoch_duration(deps.storage)?
+        .is_some()
+    {
+        return contract.execute_callback_unlock(deps, env, info, owner, vault_token_amount);
+    }
+
+    // The second part of a split unlock
+    if UNLOCK_FROM_CURRENT_LOCK.may_load(deps.storage)?.is_some() {
+        UNLOCK_FROM_CURRENT_LOCK.remove(deps.storage);
+        return contract.execute_callback_unlock(deps, env, info, owner, vault_token_amount);
+    }
+
+    let (legacy_lock, locked) = match load_legacy_lock(deps.branch(), contract)? {
+        Some(legacy_lock) => legacy_lock,
+        None => {
+            return contract.execute_callback_unlock(deps, env, info, owner, vault_token_amount)
+        }
+    };
+
+    let amount = contract
+        .base_vault
+        .query_simulate_withdraw(deps.as_ref(), vault_token_amount)?;
+    if amount <= locked {
+        if amount == locked {
+            LEGACY_LOCK.remove(deps.storage);
+        }
+        return with_legacy_staking(deps, contract, &legacy_lock, |deps| {
+            contract.execute_callback_unlock(deps, env, info, owner, vault_token_amount)
+        });
+    }
+
+    // Rounded down, so that the first part is not worth more than the base
+    // tokens left in the legacy lock
+    let legacy_vault_token_amount = contract
+        .base_vault
+        .query_simulate_deposit(deps.as_ref(), locked)?;
+    if legacy_vault_token_amount.is_zero() {
+        return contract.execute_callback_unlock(deps, env, info, owner, vault_token_amount);
+    }
+    assert_split_unlock_limit(deps.as_ref(), contract, &owner)?;
+    if contract
+        .base_vault
+        .query_simulate_withdraw(deps.as_ref(), legacy_vault_token_amount)?
+        == locked
+    {
+        LEGACY_LOCK.remove(deps.storage);
+    }
+
+    let legacy_res = with_legacy_staking(deps.branch(), contract, &legacy_lock, |deps| {
+        contract.execute_callback_unlock(
+            deps,
+            env.clone(),
+            info,
+            owner.clone(),
+            legacy_vault_token_amount,
+        )
+    })?;
+
+    // Save the claim of the first part before the second part creates its
+    // pending claim. The claim of the second part is saved by the `SaveClaim`
+    // callback of `Unlock`.
+    UNLOCK_FROM_CURRENT_LOCK.save(deps.storage, &true)?;
+    let save_claim_msg = CallbackMsg::SaveClaim {}.into_cosmos_msg(&env)?;
+    let unlock_msg = CallbackMsg::Unlock {
+        owner,
+        vault_token_amount: vault_token_amount - legacy_vault_token_amount,
+    }
+    .into_cosmos_msg(&env)?;
+
+    Ok(legacy_res
+        .add_message(save_claim_msg)
+        .add_message(unlock_msg))
+}
+
+/// Callback function to save the pending claim. The claim of the first part of
+/// a split unlock is kept, so that the response to saving the claim of the
+/// second part has both claims as [`SplitUnlockResponseData`].
+pub fn execute_callback_save_claim(
+    mut deps: DepsMut,
+    contract: &OsmosisVaultContract,
+) -> Result<Response, ContractError> {
+    let claim = contract.claims.get_pending_claim(deps.storage)?;
+    let res = contract.execute_callback_save_claim(deps.branch())?;
+
+    // The first part of a split unlock, before the second part is unlocked
+    if UNLOCK_FROM_CURRENT_LOCK.may_load(deps.storage)?.is_some() {
+        LEGACY_UNLOCK_CLAIM.save(deps.storage, &claim)?;
+        return Ok(res);
+    }
+
+    match LEGACY_UNLOCK_CLAIM.may_load(deps.storage)? {
+        Some(legacy_claim) => {
+            LEGACY_UNLOCK_CLAIM.remove(deps.storage);
+            Ok(res.set_data(to_binary(&SplitUnlockResponseData {
+                legacy_unlocking_position: legacy_claim,
+                unlocking_position: claim,
+            })?))
+        }
+        None => Ok(res),
+    }
+}
+
+/// Returns an error if a split unlock would give `owner` more than the
+/// maximum number of unlocking positions. `Unlock` only checks that the owner
+/// can open one more position, but a split unlock opens two.
+fn assert_split_unlock_limit(
+    deps: Deps,
+    contract: &OsmosisVaultContract,
+    owner: &Addr,
+) -> Result<(), ContractError> {
+    let max = contract
+        .config
+        .load(deps.storage)?
+        .max_unlocking_positions_per_owner;
+    if max > 0
+        && contract
+            .claims
+            .count_claims_for_owner(deps.storage, owner, max as usize)
+            + 2
+            > max as usize
+    {
+        return Err(ContractError::TooManyUnlockingPositions {
+            owner: owner.to_string(),
+            max,
+        });
+    }
+
+    Ok(())
+}
+
+/// Start unlocking the base tokens of the open unlock batch, taking them from
+/// the legacy lock first and the rest from the current lock. The batch matures
+/// after the legacy lockup duration if any of its base tokens are taken from
+/// the legacy lock.
+pub fn execute_process_unlock_batch(
+    mut deps: DepsMut,
+    env: Env,
+    info: &MessageInfo,
+    contract: &OsmosisVaultContract,
+) -> Result<Response, ContractError> {
+    let amount = contract
+        .unlock_batches
+        .open_batch(deps.storage)?
+        .map(|batch| batch.base_token_amount)
+        .unwrap_or_default();
+    let legacy = split_from_legacy_lock(deps.branch(), contract, amount)?;
+
+    contract.execute_process_unlock_batch_with(deps, env, info, |deps, env, amount| {
+        let staking = contract.staking.load(deps.storage)?;
+        let (from_legacy, legacy_res, lockup_duration) = match &legacy {
+            Some((legacy_staking, from_legacy)) => (
+                *from_legacy,
+                legacy_staking.unlock(deps, env, *from_legacy)?,
+                legacy_staking.get_lockup_duration(deps)?,
+            ),
+            None => (
+                Uint128::zero(),
+                Response::new(),
+                staking.get_lockup_duration(deps)?,
+            ),
+        };
+        let current_res = if amount > from_legacy {
+            staking.unlock(deps, env, amount - from_legacy)?
+        } else {
+            Response::new()
+        };
+
+        Ok((
+            merge_responses(vec![legacy_res, current_res]),
+            lockup_duration,
+        ))
+    })
+}
+
+/// Force withdrawal of a locked position, taking the base tokens from the
+/// legacy lock first and the rest from the current lock.
+pub fn execute_force_redeem(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: &OsmosisVaultContract,
+    vault_token_amount: Uint128,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let amount = contract
+        .base_vault
+        .query_simulate_withdraw(deps.as_ref(), vault_token_amount)?;
+    let legacy = split_from_legacy_lock(deps.branch(), contract, amount)?;
+
+    contract.execute_force_redeem_with(
+        deps,
+        env,
+        info,
+        vault_token_amount,
+        recipient,
+        |deps, env, amount| {
+            let (from_legacy, legacy_res) = match &legacy {
+                Some((legacy_staking, from_legacy)) => {
+                    let from_legacy = amount.min(*from_legacy);
+                    (
+                        from_legacy,
+                        legacy_staking.force_unlock(deps, env, None, from_legacy)?,
+                    )
+                }
+                None => (Uint128::zero(), Response::new()),
+            };
+            let current_res = if amount > from_legacy {
+                contract.staking.load(deps.storage)?.force_unlock(
+                    deps,
+                    env,
+                    None,
+                    amount - from_legacy,
+                )?
+            } else {
+                Response::new()
+            };
+
+            Ok(merge_responses(vec![legacy_res, current_res]))
+        },
+    )
+}
+
+/// Returns the current lockup duration together with the lockup duration of
+/// the legacy lock, if there is one.
+pub fn query_lockup_duration(
+    deps: Deps,
+    contract: &OsmosisVaultContract,
+) -> StdResult<LockupDurationResponse> {
+    let lockup_duration = contract
+        .staking
+        .load(deps.storage)?
+        .get_lockup_duration(deps)?;
+    let legacy_lockup_duration = LEGACY_LOCK
+        .may_load(deps.storage)?
+        .map(|legacy_lock| Duration::Time(legacy_lock.lockup_duration));
+
+    Ok(LockupDurationResponse {
+        lockup_duration,
+        legacy_lockup_duration,
+    })
+}
+
+/// Returns the amount of base tokens locked in the legacy lock, or zero if
+/// there is no legacy lock.
+pub fn query_legacy_lock_amount(deps: Deps, contract: &OsmosisVaultContract) -> StdResult<Uint128> {
+    match LEGACY_LOCK.may_load(deps.storage)? {
+        Some(legacy_lock) => query_locked_amount(
+            deps,
+            legacy_lock.lock_id,
+            &base_token_denom(deps, contract)?,
+        ),
+        None => Ok(Uint128::zero()),
+    }
+}
+
+/// Runs `f` with the legacy lock as the staking of the vault, so that base
+/// tokens are unlocked from the legacy lock and the unlocking position is
+/// created with its lockup duration.
+fn with_legacy_staking<F>(
+    mut deps: DepsMut,
+    contract: &OsmosisVaultContract,
+    legacy_lock: &LegacyLock,
+    f: F,
+) -> Result<Response, ContractError>
+where
+    F: FnOnce(DepsMut) -> Result<Response, ContractError>,
+{
+    let staking = contract.staking.load(deps.storage)?;
+    let legacy_staking = legacy_staking(deps.as_ref(), contract, legacy_lock)?;
+
+    contract.staking.save(deps.storage, &legacy_staking)?;
+    let res = f(deps.branch())?;
+    contract.staking.save(deps.storage, &staking)?;
+
+    Ok(res)
+}
+
+/// Returns the staking of the legacy lock together with the part of `amount`
+/// base tokens to take from it, or `None` if there is no legacy lock or
+/// `amount` is zero. The legacy lock is removed if all of its base tokens are
+/// taken.
+fn split_from_legacy_lock(
+    mut deps: DepsMut,
+    contract: &OsmosisVaultContract,
+    amount: Uint128,
+) -> Result<Option<(OsmosisStaking, Uint128)>, ContractError> {
+    if amount.is_zero() {
+        return Ok(None);
+    }
+    let (legacy_lock, locked) = match load_legacy_lock(deps.branch(), contract)? {
+        Some(legacy_lock) => legacy_lock,
+        None => return Ok(None),
+    };
+    if amount >= locked {
+        LEGACY_LOCK.remove(deps.storage);
+    }
+
+    Ok(Some((
+        legacy_staking(deps.as_ref(), contract, &legacy_lock)?,
+        amount.min(locked),
+    )))
+}
+
+fn legacy_staking(
+    deps: Deps,
+    contract: &OsmosisVaultContract,
+    legacy_lock: &LegacyLock,
+) -> Result<OsmosisStaking, ContractError> {
+    Ok(OsmosisStaking::new(
+        legacy_lock.lockup_duration,
+        Some(legacy_lock.lock_id),
+        base_token_denom(deps, contract)?,
+    )?)
+}
+
+/// Removes the base tokens migrated from the legacy lock that have unlocked
+/// and returns their amount. Osmosis returns unlocked base tokens at the end
+/// of the block, so only migrations that unlocked before the current block
+/// time are taken.
+fn take_unlocked_migrations(storage: &mut dyn Storage, env: &Env) -> StdResult<Uint128> {
+    let unlocked = LEGACY_LOCK_MIGRATIONS
+        .range(
+            storage,
+            None,
+            Some(Bound::exclusive(env.block.time.seconds())),
+            Order::Ascending,
+        )
+        .collect::<StdResult<Vec<_>>>()?;
+
+    unlocked
+        .into_iter()
+        .try_fold(Uint128::zero(), |total, (unlocked_at, amount)| {
+            LEGACY_LOCK_MIGRATIONS.remove(storage, unlocked_at);
+            Ok(total.checked_add(amount)?)
+        })
+}
+
+/// Loads the legacy lock together with the amount of base tokens locked in
+/// it. Removes the legacy lock and returns `None` if it is empty.
+fn load_legacy_lock(
+    deps: DepsMut,
+    contract: &OsmosisVaultContract,
+) -> StdResult<Option<(LegacyLock, Uint128)>> {
+    let legacy_lock = match LEGACY_LOCK.may_load(deps.storage)? {
+        Some(legacy_lock) => legacy_lock,
+        None => return Ok(None),
+    };
+
+    let locked = query_locked_amount(
+        deps.as_ref(),
+        legacy_lock.lock_id,
+        &base_token_denom(deps.as_ref(), contract)?,
+    )?;
+    if locked.is_zero() {
+        LEGACY_LOCK.remove(deps.storage);
+        return Ok(None);
+    }
+
+    Ok(Some((legacy_lock, locked)))
+}
+
+/// Returns the amount of `denom` locked in the lock with ID `lock_id`.
+pub fn query_locked_amount(deps: Deps, lock_id: u64, denom: &str) -> StdResult<Uint128> {
+    amount_of_denom(
+        LockupQuerier::new(&deps.querier)
+            .locked_by_id(lock_id)?
+            .lock
+            .map(|lock| lock.coins)
+            .unwrap_or_default(),
+        denom,
+    )
+}
+
+/// Returns the total amount of `denom` in `coins`.
+pub fn amount_of_denom(coins: Vec<Coin>, denom: &str) -> StdResult<Uint128> {
+    coins
+        .into_iter()
+        .filter(|coin| coin.denom == denom)
+        .try_fold(Uint128::zero(), |acc, coin| {
+            Ok(acc.checked_add(Uint128::from_str(&coin.amount)?)?)
+        })
+}
+
+fn base_token_denom(deps: Deps, contract: &OsmosisVaultContract) -> StdResult<String> {
+    Ok(contract
+        .base_vault
+        .base_token
+        .load(deps.storage)?
+        .to_string())
+}
+
+/// Returns the lockup duration of `staking` in seconds.
+pub fn lockup_duration_seconds(deps: Deps, staking: &OsmosisStaking) -> Result<u64, ContractError> {
+    match staking.get_lockup_duration(deps)? {
+        Duration::Time(seconds) => Ok(seconds),
+        Duration::Height(_) => Err(ContractError::from(
+            "Osmosis lockup durations must be in seconds",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_vault::state::Config;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{from_binary, from_slice};
+
+    use super::*;
+
+    const OWNER: &str = "owner";
+
+    fn create_pending_claim(storage: &mut dyn Storage, amount: u128, lockup_duration: u64) {
+        OsmosisVaultContract::default()
+            .claims
+            .create_pending_claim(
+                storage,
+                &Addr::unchecked(OWNER),
+                Uint128::new(amount),
+                Duration::Time(lockup_duration).after(&mock_env().block),
+                None,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_save_claims_of_split_unlock() {
+        let mut deps = mock_dependencies();
+        let contract = OsmosisVaultContract::default();
+
+        // The first part of the split unlock is saved while the second part
+        // has not been unlocked yet
+        create_pending_claim(&mut deps.storage, 100, 1_209_600);
+        UNLOCK_FROM_CURRENT_LOCK
+            .save(&mut deps.storage, &true)
+            .unwrap();
+        execute_callback_save_claim(deps.as_mut(), &contract).unwrap();
+        UNLOCK_FROM_CURRENT_LOCK.remove(&mut deps.storage);
+
+        // The response to saving the second part has both claims as data
+        create_pending_claim(&mut deps.storage, 50, 86_400);
+        let res = execute_callback_save_claim(deps.as_mut(), &contract).unwrap();
+        let data: SplitUnlockResponseData = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(data.legacy_unlocking_position.id, 0);
+        assert_eq!(
+            data.legacy_unlocking_position.base_token_amount,
+            Uint128::new(100)
+        );
+        assert_eq!(data.unlocking_position.id, 1);
+        assert_eq!(data.unlocking_position.base_token_amount, Uint128::new(50));
+        assert!(LEGACY_UNLOCK_CLAIM
+            .may_load(&deps.storage)
+            .unwrap()
+            .is_none());
+
+        // Claims of unlocks that are not split are returned on their own
+        create_pending_claim(&mut deps.storage, 20, 86_400);
+        let res = execute_callback_save_claim(deps.as_mut(), &contract).unwrap();
+        let claim: Claim = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(claim.id, 2);
+    }
+
+    #[test]
+    fn test_split_unlock_counts_two_positions() {
+        let mut deps = mock_dependencies();
+        let contract = OsmosisVaultContract::default();
+        let config: Config = from_slice(
+            br#"{
+                "performance_fee": "0",
+                "treasury": "treasury",
+                "router": "router",
+                "reward_assets": [],
+                "reward_liquidation_target": { "native": "uosmo" },
+                "force_withdraw_whitelist": [],
+                "liquidity_helper": "liquidity_helper",
+                "max_unlocking_positions_per_owner": 3
+            }"#,
+        )
+        .unwrap();
+        contract.config.save(&mut deps.storage, &config).unwrap();
+        let owner = Addr::unchecked(OWNER);
+
+        create_pending_claim(&mut deps.storage, 100, 86_400);
+        contract
+            .claims
+            .commit_pending_claim(&mut deps.storage)
+            .unwrap();
+        assert_split_unlock_limit(deps.as_ref(), &contract, &owner).unwrap();
+
+        // With two open positions, a split unlock would open a fourth
+        create_pending_claim(&mut deps.storage, 100, 86_400);
+        contract
+            .claims
+            .commit_pending_claim(&mut deps.storage)
+            .unwrap();
+        let err = assert_split_unlock_limit(deps.as_ref(), &contract, &owner).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::TooManyUnlockingPositions { max: 3, .. }
+        ));
+    }
+}
@@ -1,6 +1,13 @@
-use apollo_vault::msg::{ExtensionExecuteMsg, ExtensionQueryMsg};
-use apollo_vault::state::ConfigUnchecked;
+use apollo_vault::msg::{
+    ApolloExtensionExecuteMsg, ApolloExtensionQueryMsg, CallbackMsg,
+    UnlockingPositionNftInstantiateInfo,
+};
+use apollo_vault::state::{Claim, ConfigUnchecked};
 use cosmwasm_schema::cw_serde;
+use cosmwasm_std::Uint128;
+use cw_utils::Duration;
+use cw_vault_standard::extensions::force_unlock::ForceUnlockExecuteMsg;
+use cw_vault_standard::extensions::lockup::{LockupExecuteMsg, LockupQueryMsg};
 use cw_vault_standard::{VaultStandardExecuteMsg, VaultStandardQueryMsg};
 
 /// ExecuteMsg for an Autocompounding Vault.
@@ -24,6 +31,127 @@ pub struct InstantiateMsg {
     /// the denom of the vault token will be:
     /// "factory/{vault_contract}/{vault_token_subdenom}".
     pub vault_token_subdenom: String,
+    /// Optional info to instantiate a cw721 contract that represents unlocking
+    /// positions as NFTs. If `None`, unlocking positions are not NFTs.
+    pub unlocking_position_nft: Option<UnlockingPositionNftInstantiateInfo>,
+    /// Optional duration in seconds of an unlock epoch. If set, unlock requests
+    /// made during an epoch are unlocked together as one batch once the epoch
+    /// has ended. If `None`, each unlock request is unlocked separately.
+    pub unlock_epoch_duration: Option<u64>,
+}
+
+/// Extension execute messages for the Osmosis vault. The `Callback`, `Apollo`,
+/// `Lockup` and `ForceUnlock` messages are the same as those of other apollo
+/// autocompounding vaults.
+#[cw_serde]
+#[allow(clippy::large_enum_variant)]
+pub enum ExtensionExecuteMsg {
+    /// Execute a callback message.
+    Callback(CallbackMsg),
+    /// Execute an Apollo vault specific message.
+    Apollo(ApolloExtensionExecuteMsg),
+    /// Execute a message from the lockup extension.
+    Lockup(LockupExecuteMsg),
+    /// Execute a message from the force unlock extension.
+    ForceUnlock(ForceUnlockExecuteMsg),
+    /// Execute an Osmosis vault specific message.
+    Osmosis(OsmosisExtensionExecuteMsg),
+}
+
+/// Osmosis extension messages define functionality that is only part of the
+/// Osmosis vault.
+#[cw_serde]
+pub enum OsmosisExtensionExecuteMsg {
+    /// Change the lockup duration in seconds that base tokens are staked with.
+    /// Existing unlocking positions keep their release time. Only callable by
+    /// the admin.
+    UpdateLockupDuration {
+        /// The new lockup duration in seconds.
+        lockup_duration: u64,
+    },
+    /// Begin migrating base tokens from the legacy lock, the lock that the
+    /// vault staked its base tokens in before the lockup duration was
+    /// shortened, to a lock with the current lockup duration. The base tokens
+    /// start unlocking and are staked again once they have unlocked. Only
+    /// callable by the admin.
+    MigrateLegacyLock {
+        /// Optional amount of base tokens to migrate. Defaults to all of the
+        /// base tokens in the legacy lock. Base tokens that are being migrated
+        /// cannot be unlocked by users until they are staked again.
+        amount: Option<Uint128>,
+    },
+    /// Repair the stored ID of the vault's lock from the locks of the vault in
+    /// the Osmosis lockup module. Can be called by anyone, e.g. keepers.
+    ReconcileLockId {},
+    /// Begin migrating the vault from its GAMM pool to `cl_vault`, a
+    /// concentrated liquidity vault of the pool that the GAMM pool is linked
+    /// to. The vault's GAMM shares start unlocking, and deposits, unlocks and
+    /// redemptions are paused until the migration is completed with
+    /// `CompleteConcentratedLiquidityMigration`. Unlocking positions created
+    /// before the migration are still withdrawn in GAMM shares. Only callable
+    /// by the admin.
+    MigrateToConcentratedLiquidity {
+        /// The address of the concentrated liquidity vault.
+        cl_vault: String,
+    },
+    /// Complete the migration started with `MigrateToConcentratedLiquidity`
+    /// once the vault's GAMM shares have unlocked. The GAMM shares are
+    /// exchanged for the pool assets, which are deposited into the
+    /// concentrated liquidity vault, and the vault tokens received become the
+    /// base tokens of the vault. Only callable by the admin.
+    CompleteConcentratedLiquidityMigration {},
+}
+
+/// Extension query messages for the Osmosis vault. The `Lockup` and `Apollo`
+/// queries are the same as those of other apollo autocompounding vaults.
+#[cw_serde]
+pub enum ExtensionQueryMsg {
+    /// Queries related to the lockup extension.
+    Lockup(LockupQueryMsg),
+    /// Apollo extension queries.
+    Apollo(ApolloExtensionQueryMsg),
+    /// Osmosis vault specific queries.
+    Osmosis(OsmosisExtensionQueryMsg),
+}
+
+/// Osmosis extension queries define functionality that is only part of the
+/// Osmosis vault.
+#[cw_serde]
+pub enum OsmosisExtensionQueryMsg {
+    /// Query the migration of the vault from its GAMM pool to a concentrated
+    /// liquidity vault, including the GAMM shares that unlocking positions
+    /// created before the migration are withdrawn in. Returns an
+    /// `Option<ClMigration>`, which is `None` if the vault has not been
+    /// migrated.
+    ConcentratedLiquidityMigration {},
+}
+
+/// Response to the `LockupDuration` query of the lockup extension. Unlike in
+/// other vaults, whose response is only the lockup duration, it also has the
+/// lockup duration of the legacy lock.
+#[cw_serde]
+pub struct LockupDurationResponse {
+    /// The lockup duration that base tokens are staked with. Unlocks taken
+    /// from the current lock mature after this duration.
+    pub lockup_duration: Duration,
+    /// The lockup duration of the legacy lock, the lock that the vault staked
+    /// its base tokens in before the lockup duration was shortened, or `None`
+    /// if there is no legacy lock. Unlocks are taken from the legacy lock first
+    /// and mature after this duration.
+    pub legacy_lockup_duration: Option<Duration>,
+}
+
+/// Data set on the response to `Unlock` if the unlock is split between the
+/// legacy lock and the current lock. Other unlocks have the created
+/// [`Claim`] as data, like in other apollo vaults.
+#[cw_serde]
+pub struct SplitUnlockResponseData {
+    /// The unlocking position of the base tokens taken from the legacy lock,
+    /// which matures after the legacy lockup duration.
+    pub legacy_unlocking_position: Claim,
+    /// The unlocking position of the base tokens taken from the current lock,
+    /// which matures after the current lockup duration.
+    pub unlocking_position: Claim,
 }
 
 #[cw_serde]
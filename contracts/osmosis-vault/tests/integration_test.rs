@@ -3,27 +3,35 @@ use std::str::FromStr;
 
 use apollo_cw_asset::{AssetInfo, AssetInfoUnchecked};
 use apollo_vault::msg::{
-    ApolloExtensionQueryMsg, ExtensionExecuteMsg, ExtensionQueryMsg, StateResponse,
+    ApolloExtensionExecuteMsg, ApolloExtensionQueryMsg, DepositResponseData, RedeemResponseData,
+    SolvencyResponse, StateResponse, UnlockBatchRequestResponseData, UnlockingPositionMetadata,
+    UnlockingPositionNftInstantiateInfo, WithdrawUnlockBatchResponseData,
+    WithdrawUnlockedResponseData,
 };
-use apollo_vault::state::ConfigUnchecked;
+use apollo_vault::state::{ConfigUnchecked, ConfigUpdates, UnlockBatch, UnlockBatchStatus};
+use apollo_vault::unlocking_position_nft::UnlockingPositionNftExecuteMsg;
 use base_vault::DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN;
-use cosmwasm_std::{Coin, Decimal, Empty, Uint128};
+use cosmwasm_std::{from_slice, Addr, Coin, Decimal, Empty, Uint128};
+use cw721::{Cw721QueryMsg, NftInfoResponse, NumTokensResponse, OwnerOfResponse};
 use cw_dex::osmosis::{OsmosisPool, OsmosisStaking};
 use cw_dex::traits::Pool as PoolTrait;
 use cw_dex::Pool;
 use cw_dex_router::helpers::CwDexRouterUnchecked;
 use cw_dex_router::operations::{SwapOperation, SwapOperationsList};
 use cw_it::config::TestConfig;
-use cw_it::helpers::{
-    bank_send, instantiate_contract, instantiate_contract_with_funds, upload_wasm_files,
-};
+use cw_it::helpers::{instantiate_contract, instantiate_contract_with_funds, upload_wasm_files};
 use cw_it::mock_api::OsmosisMockApi;
+use cw_utils::Duration;
 use cw_vault_standard::extensions::force_unlock::ForceUnlockExecuteMsg;
 use cw_vault_standard::extensions::lockup::{LockupExecuteMsg, LockupQueryMsg, UnlockingPosition};
 use osmosis_std::types::osmosis::lockup::{
-    MsgBeginUnlocking, MsgBeginUnlockingResponse, MsgLockTokens, MsgLockTokensResponse,
+    LockedRequest, LockedResponse, MsgBeginUnlocking, MsgBeginUnlockingResponse, MsgLockTokens,
+    MsgLockTokensResponse,
+};
+use osmosis_vault::msg::{
+    ExecuteMsg, ExtensionExecuteMsg, ExtensionQueryMsg, LockupDurationResponse,
+    OsmosisExtensionExecuteMsg, SplitUnlockResponseData,
 };
-use osmosis_vault::msg::ExecuteMsg;
 
 use cw_vault_token::osmosis::OsmosisDenom;
 use liquidity_helper::LiquidityHelperUnchecked;
@@ -55,6 +63,7 @@ pub fn setup_test<'a, R>(
     reward_liquidation_target: String,
     accs: &[SigningAccount],
     test_config: &TestConfig,
+    unlocking_position_nft: bool,
 ) -> (String, String)
 where
     R: Runner<'a>,
@@ -194,6 +203,14 @@ where
     let config = ConfigUnchecked {
         force_withdraw_whitelist: vec![force_withdraw_admin.address().clone()],
         performance_fee: PERFORMANCE_FEE,
+        early_exit_penalty: Decimal::zero(),
+        early_exit_penalty_decay: false,
+        early_exit_penalty_to_treasury: false,
+        auto_withdraw_keeper_fee: Decimal::zero(),
+        min_unlock_amount: Uint128::zero(),
+        max_unlocking_positions_per_owner: 0,
+        redemption_buffer_ratio: Decimal::zero(),
+        instant_redemption_fee: Decimal::zero(),
         reward_assets,
         reward_liquidation_target: AssetInfoUnchecked::Native(reward_liquidation_target),
         treasury: treasury.address().clone(),
@@ -211,6 +228,16 @@ where
             lockup_duration: 86400u64,
             pool_id: base_pool.pool_id(),
             vault_token_subdenom: "osmosis-vault".to_string(),
+            unlocking_position_nft: unlocking_position_nft.then(|| {
+                UnlockingPositionNftInstantiateInfo {
+                    code_id: code_ids["unlocking_position_nft"],
+                    name: "Osmosis vault unlocking positions".to_string(),
+                    symbol: "UNLOCK".to_string(),
+                    label: "unlocking_position_nft".to_string(),
+                    admin: None,
+                }
+            }),
+            unlock_epoch_duration: None,
             config,
         },
         &[Coin {
@@ -221,19 +248,6 @@ where
     )
     .unwrap();
 
-    // WARNING!!! This is a hack
-    // Send 1B base token to allow contract to create new locks on ExecuteMsg::Unlock
-    bank_send(
-        runner,
-        user1,
-        &vault_addr,
-        vec![Coin {
-            denom: format!("gamm/pool/{}", base_pool.pool_id()),
-            amount: Uint128::from(1_000_000_000u128),
-        }],
-    )
-    .unwrap();
-
     println!(" ------ Addresses -------");
     println!("admin: {}", admin.address());
     println!("force_withdraw_admin: {}", force_withdraw_admin.address());
@@ -336,6 +350,7 @@ pub fn test_osmosis_vault_functionality(
         reward_liquidation_target,
         &accs,
         &test_config,
+        false,
     );
 
     // Query vault state
@@ -363,7 +378,7 @@ pub fn test_osmosis_vault_functionality(
         amount: deposit_amount,
         recipient: None,
     };
-    let _res = wasm
+    let res = wasm
         .execute(
             &vault_addr,
             &deposit_msg,
@@ -380,6 +395,11 @@ pub fn test_osmosis_vault_functionality(
     println!("User1 vault token balance: {}", vault_token_balance);
     assert_ne!(vault_token_balance, Uint128::zero());
 
+    // The response data should contain the minted vault tokens
+    let data: DepositResponseData = from_slice(&res.data.data).unwrap();
+    assert_eq!(data.vault_token_amount, vault_token_balance);
+    assert_eq!(data.recipient.to_string(), user1.address());
+
     // Assert total staked amount and vault token supply is correct
     let state = query_vault_state(&runner, &vault_addr);
     let total_staked_amount = state.total_staked_base_tokens;
@@ -462,7 +482,7 @@ pub fn test_osmosis_vault_functionality(
         amount: user2_deposit_amount,
         recipient: None,
     };
-    let _res = wasm
+    let res = wasm
         .execute(
             &vault_addr,
             &deposit_msg,
@@ -476,6 +496,9 @@ pub fn test_osmosis_vault_functionality(
     let user2_vault_token_balance =
         query_token_balance(&runner, &user2.address(), &vault_token_denom);
     assert_ne!(user2_vault_token_balance, Uint128::zero());
+    let data: DepositResponseData = from_slice(&res.data.data).unwrap();
+    assert_eq!(data.vault_token_amount, user2_vault_token_balance);
+    assert_eq!(data.recipient.to_string(), user2.address());
     let user2_base_token_balance = query_token_balance(&runner, &user2.address(), &base_token);
     assert!(user2_base_token_balance.is_zero());
 
@@ -594,7 +617,7 @@ pub fn test_osmosis_vault_functionality(
         ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(LockupExecuteMsg::Unlock {
             amount: user1_withdraw_amount,
         }));
-    let _res = wasm
+    let res = wasm
         .execute(
             &vault_addr,
             &withdraw_msg,
@@ -623,6 +646,27 @@ pub fn test_osmosis_vault_functionality(
     assert!(unlocking_positions.len() == 1);
     let position = unlocking_positions[0].clone();
 
+    // The response data should be the created unlocking position
+    let data: UnlockingPosition = from_slice(&res.data.data).unwrap();
+    assert_eq!(data, position);
+
+    // Assert that the unlocking position is accounted for and the vault is solvent
+    let solvency = query_solvency(&runner, &vault_addr);
+    println!("Solvency: {:?}", solvency);
+    assert_eq!(
+        solvency.total_unlocking_base_tokens,
+        position.base_token_amount
+    );
+    assert_eq!(
+        solvency.module_staked_base_tokens,
+        solvency.total_staked_base_tokens
+    );
+    assert_eq!(
+        solvency.module_unlocking_base_tokens,
+        position.base_token_amount
+    );
+    assert_eq!(solvency.shortfall, Uint128::zero());
+
     // Withdraw unlocked
     println!("Withdrawing unlocked, should fail");
     let withdraw_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(
@@ -648,7 +692,7 @@ pub fn test_osmosis_vault_functionality(
 
     // Withdraw unlocked
     println!("Withdrawing unlocked");
-    let _res = wasm
+    let res = wasm
         .execute(&vault_addr, &withdraw_msg, &[], user1)
         .unwrap();
 
@@ -665,6 +709,10 @@ pub fn test_osmosis_vault_functionality(
         base_token_balance_increase,
         user1_base_token_balance_in_vault
     );
+    let data: WithdrawUnlockedResponseData = from_slice(&res.data.data).unwrap();
+    assert_eq!(data.lockup_id, position.id);
+    assert_eq!(data.base_token_amount, base_token_balance_increase);
+    assert_eq!(data.recipient.to_string(), user1.address());
 
     // Query vault token supply
     let vault_token_supply: Uint128 = wasm
@@ -1203,25 +1251,1202 @@ pub fn test_osmosis_vault_functionality(
         .unwrap();
     println!("Unlocking positions: {:?}", unlocking_positions);
     assert!(unlocking_positions.is_empty());
-}
 
-const TWO_WEEKS_IN_SECS: u64 = 60 * 60 * 24 * 14;
+    println!("=========== Test unlock with another owner of the unlocking position ===========");
+    // Unlock from force_withdraw_admin with user2 as owner of the position
+    let unlock_amount = Uint128::from(1000000u128);
+    let msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+        ApolloExtensionExecuteMsg::Unlock {
+            amount: unlock_amount,
+            owner: Some(user2.address()),
+        },
+    ));
 
-fn query_vault_state<'a, R>(
-    runner: &'a R,
-    vault_addr: &str,
-) -> StateResponse<OsmosisStaking, OsmosisPool, OsmosisDenom>
-where
-    R: Runner<'a>,
-{
-    let wasm = Wasm::new(runner);
-    let state: StateResponse<OsmosisStaking, OsmosisPool, OsmosisDenom> = wasm
-        .query(
-            vault_addr,
-            &QueryMsg::VaultExtension(ExtensionQueryMsg::Apollo(ApolloExtensionQueryMsg::State {})),
+    // The sender must be an approved operator of the owner
+    let err = wasm
+        .execute(
+            &vault_addr,
+            &msg,
+            &[Coin::new(unlock_amount.u128(), &vault_token_denom)],
+            force_withdraw_admin,
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("is not an approved operator"));
+    let approve_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+        ApolloExtensionExecuteMsg::ApproveUnlockingPositionOperator {
+            operator: force_withdraw_admin.address(),
+            expires: None,
+        },
+    ));
+    wasm.execute(&vault_addr, &approve_msg, &[], user2).unwrap();
+
+    wasm.execute(
+        &vault_addr,
+        &msg,
+        &[Coin::new(unlock_amount.u128(), &vault_token_denom)],
+        force_withdraw_admin,
+    )
+    .unwrap();
+
+    // Revoke the approval again
+    let revoke_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+        ApolloExtensionExecuteMsg::RevokeUnlockingPositionOperator {
+            operator: force_withdraw_admin.address(),
+        },
+    ));
+    wasm.execute(&vault_addr, &revoke_msg, &[], user2).unwrap();
+
+    // The position is owned by user2 and not by the sender
+    let query_positions = |owner: String| -> Vec<UnlockingPosition> {
+        wasm.query(
+            &vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Lockup(
+                LockupQueryMsg::UnlockingPositions {
+                    owner,
+                    limit: None,
+                    start_after: None,
+                },
+            )),
         )
+        .unwrap()
+    };
+    assert!(query_positions(force_withdraw_admin.address()).is_empty());
+    let unlocking_positions = query_positions(user2.address());
+    println!("Unlocking positions: {:?}", unlocking_positions);
+    assert!(unlocking_positions.len() == 1);
+    let position = unlocking_positions[0].clone();
+
+    // Increment chain time
+    runner.increase_time(TWO_WEEKS_IN_SECS);
+
+    // The sender can't withdraw the position
+    let withdraw_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(
+        LockupExecuteMsg::WithdrawUnlocked {
+            lockup_id: position.id,
+            recipient: None,
+        },
+    ));
+    wasm.execute(&vault_addr, &withdraw_msg, &[], force_withdraw_admin)
+        .unwrap_err();
+
+    // The owner withdraws the position
+    let user2_bt_balance_before = query_token_balance(&runner, &user2.address(), &base_token);
+    wasm.execute(&vault_addr, &withdraw_msg, &[], user2)
         .unwrap();
-    state
+    let user2_bt_balance_after = query_token_balance(&runner, &user2.address(), &base_token);
+    assert_eq!(
+        user2_bt_balance_after - user2_bt_balance_before,
+        position.base_token_amount
+    );
+    assert!(query_positions(user2.address()).is_empty());
+
+    println!("=========== Test cancel unlock ===========");
+    // Unlock from force_withdraw_admin
+    let unlock_amount = Uint128::from(1000000u128);
+    let msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(LockupExecuteMsg::Unlock {
+        amount: unlock_amount,
+    }));
+    wasm.execute(
+        &vault_addr,
+        &msg,
+        &[Coin::new(unlock_amount.u128(), &vault_token_denom)],
+        force_withdraw_admin,
+    )
+    .unwrap();
+    let unlocking_positions = query_positions(force_withdraw_admin.address());
+    assert!(unlocking_positions.len() == 1);
+    let position = unlocking_positions[0].clone();
+
+    let fwa_vt_balance_before =
+        query_token_balance(&runner, &force_withdraw_admin.address(), &vault_token_denom);
+    let state_before = query_vault_state(&runner, &vault_addr);
+
+    // Only the owner can cancel the position
+    let cancel_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+        ApolloExtensionExecuteMsg::CancelUnlock {
+            lockup_id: position.id,
+        },
+    ));
+    wasm.execute(&vault_addr, &cancel_msg, &[], user2)
+        .unwrap_err();
+
+    // Cancel the position, which should stake the base tokens again and mint
+    // vault tokens to the owner
+    wasm.execute(&vault_addr, &cancel_msg, &[], force_withdraw_admin)
+        .unwrap();
+    assert!(query_positions(force_withdraw_admin.address()).is_empty());
+    let fwa_vt_balance_after =
+        query_token_balance(&runner, &force_withdraw_admin.address(), &vault_token_denom);
+    assert!(fwa_vt_balance_after > fwa_vt_balance_before);
+    let state_after = query_vault_state(&runner, &vault_addr);
+    assert!(
+        state_after.total_staked_base_tokens
+            >= state_before.total_staked_base_tokens + position.base_token_amount
+    );
+    let solvency = query_solvency(&runner, &vault_addr);
+    assert_eq!(solvency.total_unlocking_base_tokens, Uint128::zero());
+    assert_eq!(solvency.shortfall, Uint128::zero());
+
+    println!("=========== Test early exit from unlocking position ===========");
+    // Unlock from force_withdraw_admin
+    let unlock_amount = Uint128::from(1000000u128);
+    let msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(LockupExecuteMsg::Unlock {
+        amount: unlock_amount,
+    }));
+    wasm.execute(
+        &vault_addr,
+        &msg,
+        &[Coin::new(unlock_amount.u128(), &vault_token_denom)],
+        force_withdraw_admin,
+    )
+    .unwrap();
+    let position = query_positions(force_withdraw_admin.address())[0].clone();
+
+    // Early exit is disabled by default
+    let early_exit_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+        ApolloExtensionExecuteMsg::EarlyExitUnlocking {
+            lockup_id: position.id,
+            recipient: None,
+        },
+    ));
+    wasm.execute(&vault_addr, &early_exit_msg, &[], force_withdraw_admin)
+        .unwrap_err();
+
+    // Enable early exit with a 10% penalty sent to the treasury
+    let update_config_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+        ApolloExtensionExecuteMsg::UpdateConfig {
+            updates: ConfigUpdates {
+                early_exit_penalty: Some(Decimal::percent(10)),
+                early_exit_penalty_to_treasury: Some(true),
+                ..Default::default()
+            },
+        },
+    ));
+    wasm.execute(&vault_addr, &update_config_msg, &[], admin)
+        .unwrap();
+
+    let fwa_bt_balance_before =
+        query_token_balance(&runner, &force_withdraw_admin.address(), &base_token);
+    let treasury_bt_balance_before = query_token_balance(&runner, &treasury.address(), &base_token);
+    wasm.execute(&vault_addr, &early_exit_msg, &[], force_withdraw_admin)
+        .unwrap();
+
+    // The penalty is sent to the treasury and the rest to the owner
+    let penalty_amount = position.base_token_amount * Decimal::percent(10);
+    let fwa_bt_balance_after =
+        query_token_balance(&runner, &force_withdraw_admin.address(), &base_token);
+    let treasury_bt_balance_after = query_token_balance(&runner, &treasury.address(), &base_token);
+    assert_eq!(
+        fwa_bt_balance_after - fwa_bt_balance_before,
+        position.base_token_amount - penalty_amount
+    );
+    assert_eq!(
+        treasury_bt_balance_after - treasury_bt_balance_before,
+        penalty_amount
+    );
+    assert!(query_positions(force_withdraw_admin.address()).is_empty());
+
+    // Keep the penalty in the vault, which stakes it
+    let update_config_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+        ApolloExtensionExecuteMsg::UpdateConfig {
+            updates: ConfigUpdates {
+                early_exit_penalty_to_treasury: Some(false),
+                ..Default::default()
+            },
+        },
+    ));
+    wasm.execute(&vault_addr, &update_config_msg, &[], admin)
+        .unwrap();
+    wasm.execute(
+        &vault_addr,
+        &msg,
+        &[Coin::new(unlock_amount.u128(), &vault_token_denom)],
+        force_withdraw_admin,
+    )
+    .unwrap();
+    let position = query_positions(force_withdraw_admin.address())[0].clone();
+    let early_exit_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+        ApolloExtensionExecuteMsg::EarlyExitUnlocking {
+            lockup_id: position.id,
+            recipient: None,
+        },
+    ));
+
+    let fwa_bt_balance_before =
+        query_token_balance(&runner, &force_withdraw_admin.address(), &base_token);
+    let treasury_bt_balance_before = query_token_balance(&runner, &treasury.address(), &base_token);
+    let state_before = query_vault_state(&runner, &vault_addr);
+    wasm.execute(&vault_addr, &early_exit_msg, &[], force_withdraw_admin)
+        .unwrap();
+
+    // The treasury receives nothing and the total staked base tokens grow by
+    // the penalty
+    let penalty_amount = position.base_token_amount * Decimal::percent(10);
+    let fwa_bt_balance_after =
+        query_token_balance(&runner, &force_withdraw_admin.address(), &base_token);
+    assert_eq!(
+        fwa_bt_balance_after - fwa_bt_balance_before,
+        position.base_token_amount - penalty_amount
+    );
+    assert_eq!(
+        query_token_balance(&runner, &treasury.address(), &base_token),
+        treasury_bt_balance_before
+    );
+    let state_after = query_vault_state(&runner, &vault_addr);
+    assert_eq!(
+        state_after.total_staked_base_tokens,
+        state_before.total_staked_base_tokens + penalty_amount
+    );
+    let solvency = query_solvency(&runner, &vault_addr);
+    assert_eq!(solvency.shortfall, Uint128::zero());
+
+    println!("=========== Test keeper auto-withdrawal of matured positions ===========");
+    // Opt in to auto-withdrawal and unlock from force_withdraw_admin
+    let opt_in_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+        ApolloExtensionExecuteMsg::SetAutoWithdraw { enabled: true },
+    ));
+    wasm.execute(&vault_addr, &opt_in_msg, &[], force_withdraw_admin)
+        .unwrap();
+    let unlock_amount = Uint128::from(1000000u128);
+    let msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(LockupExecuteMsg::Unlock {
+        amount: unlock_amount,
+    }));
+    wasm.execute(
+        &vault_addr,
+        &msg,
+        &[Coin::new(unlock_amount.u128(), &vault_token_denom)],
+        force_withdraw_admin,
+    )
+    .unwrap();
+    let position = query_positions(force_withdraw_admin.address())[0].clone();
+
+    // Set a 1% keeper fee
+    let update_config_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+        ApolloExtensionExecuteMsg::UpdateConfig {
+            updates: ConfigUpdates {
+                auto_withdraw_keeper_fee: Some(Decimal::percent(1)),
+                ..Default::default()
+            },
+        },
+    ));
+    wasm.execute(&vault_addr, &update_config_msg, &[], admin)
+        .unwrap();
+
+    // Nothing to process before the position has matured
+    let process_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+        ApolloExtensionExecuteMsg::ProcessMaturedClaims { limit: None },
+    ));
+    wasm.execute(&vault_addr, &process_msg, &[], user1)
+        .unwrap_err();
+
+    runner.increase_time(TWO_WEEKS_IN_SECS);
+
+    // A keeper sends the matured position to its owner and receives the fee
+    let fwa_bt_balance_before =
+        query_token_balance(&runner, &force_withdraw_admin.address(), &base_token);
+    let keeper_bt_balance_before = query_token_balance(&runner, &user1.address(), &base_token);
+    wasm.execute(&vault_addr, &process_msg, &[], user1).unwrap();
+    let keeper_fee = position.base_token_amount * Decimal::percent(1);
+    let fwa_bt_balance_after =
+        query_token_balance(&runner, &force_withdraw_admin.address(), &base_token);
+    let keeper_bt_balance_after = query_token_balance(&runner, &user1.address(), &base_token);
+    assert_eq!(
+        fwa_bt_balance_after - fwa_bt_balance_before,
+        position.base_token_amount - keeper_fee
+    );
+    assert_eq!(
+        keeper_bt_balance_after - keeper_bt_balance_before,
+        keeper_fee
+    );
+    assert!(query_positions(force_withdraw_admin.address()).is_empty());
+
+    println!("=========== Test unlocking position limits ===========");
+    // Deposit from user1, who unlocked all of their vault tokens earlier
+    let deposit_amount =
+        query_token_balance(&runner, &user1.address(), &base_token) / Uint128::from(10u128);
+    let deposit_msg = ExecuteMsg::Deposit {
+        amount: deposit_amount,
+        recipient: None,
+    };
+    wasm.execute(
+        &vault_addr,
+        &deposit_msg,
+        &[Coin::new(deposit_amount.u128(), &base_token)],
+        user1,
+    )
+    .unwrap();
+
+    // Allow user1 a single additional unlocking position of at least 1 base token
+    let num_positions = query_positions(user1.address()).len() as u32;
+    let update_config_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+        ApolloExtensionExecuteMsg::UpdateConfig {
+            updates: ConfigUpdates {
+                min_unlock_amount: Some(Uint128::one()),
+                max_unlocking_positions_per_owner: Some(num_positions + 1),
+                ..Default::default()
+            },
+        },
+    ));
+    wasm.execute(&vault_addr, &update_config_msg, &[], admin)
+        .unwrap();
+    let unlock = |amount: u128| {
+        let msg =
+            ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(LockupExecuteMsg::Unlock {
+                amount: Uint128::from(amount),
+            }));
+        wasm.execute(
+            &vault_addr,
+            &msg,
+            &[Coin::new(amount, &vault_token_denom)],
+            user1,
+        )
+    };
+
+    // Unlocking less than the minimum amount fails
+    let err = unlock(1000).unwrap_err();
+    assert!(err.to_string().contains("Unlock amount too small"));
+
+    // The first unlock succeeds, but the second exceeds the maximum number of
+    // positions
+    unlock(1000000).unwrap();
+    assert_eq!(
+        query_positions(user1.address()).len() as u32,
+        num_positions + 1
+    );
+    let err = unlock(1000000).unwrap_err();
+    assert!(err.to_string().contains("Too many unlocking positions"));
+
+    println!("=========== Test instant redemption from the redemption buffer ===========");
+    let redeem_amount = Uint128::from(1000000u128);
+    let redeem_msg = ExecuteMsg::Redeem {
+        recipient: None,
+        amount: redeem_amount,
+    };
+    let redeem_funds = [Coin::new(redeem_amount.u128(), &vault_token_denom)];
+
+    // The redemption buffer is empty while it is disabled
+    assert_eq!(
+        query_vault_state(&runner, &vault_addr).redemption_buffer,
+        Uint128::zero()
+    );
+    let err = wasm
+        .execute(&vault_addr, &redeem_msg, &redeem_funds, user1)
+        .unwrap_err();
+    assert!(err.to_string().contains("exceeds the redemption buffer"));
+
+    // Keep 10% of the base tokens in the buffer and charge a 1% fee
+    let update_config_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+        ApolloExtensionExecuteMsg::UpdateConfig {
+            updates: ConfigUpdates {
+                redemption_buffer_ratio: Some(Decimal::percent(10)),
+                instant_redemption_fee: Some(Decimal::percent(1)),
+                ..Default::default()
+            },
+        },
+    ));
+    wasm.execute(&vault_addr, &update_config_msg, &[], admin)
+        .unwrap();
+
+    // The buffer is refilled on deposits
+    wasm.execute(
+        &vault_addr,
+        &deposit_msg,
+        &[Coin::new(deposit_amount.u128(), &base_token)],
+        user1,
+    )
+    .unwrap();
+    let state = query_vault_state(&runner, &vault_addr);
+    assert!(!state.redemption_buffer.is_zero());
+    assert!(state.redemption_buffer <= state.total_staked_base_tokens * Decimal::percent(10));
+
+    // The preview of the redemption includes the fee
+    let convert_msg = QueryMsg::ConvertToAssets {
+        amount: redeem_amount,
+    };
+    let assets: Uint128 = wasm.query(&vault_addr, &convert_msg).unwrap();
+    let preview_msg = QueryMsg::PreviewRedeem {
+        amount: redeem_amount,
+    };
+    let preview: Uint128 = wasm.query(&vault_addr, &preview_msg).unwrap();
+    assert_eq!(preview, assets - assets * Decimal::percent(1));
+
+    // Redeem instantly
+    let bt_balance_before = query_token_balance(&runner, &user1.address(), &base_token);
+    let res = wasm
+        .execute(&vault_addr, &redeem_msg, &redeem_funds, user1)
+        .unwrap();
+    let bt_balance_after = query_token_balance(&runner, &user1.address(), &base_token);
+    // Compounding before the redemption can only increase the amount
+    assert!(bt_balance_after - bt_balance_before >= preview);
+    let data: RedeemResponseData = from_slice(&res.data.data).unwrap();
+    assert_eq!(data.vault_token_amount, redeem_amount);
+    assert_eq!(data.base_token_amount, bt_balance_after - bt_balance_before);
+    assert_eq!(data.recipient.to_string(), user1.address());
+    let state_after = query_vault_state(&runner, &vault_addr);
+    assert!(state_after.redemption_buffer < state.redemption_buffer);
+    let solvency = query_solvency(&runner, &vault_addr);
+    assert_eq!(solvency.shortfall, Uint128::zero());
+
+    println!("=========== Test changing the lockup duration ===========");
+    let update_lockup_duration_msg = |lockup_duration: u64| {
+        ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Osmosis(
+            OsmosisExtensionExecuteMsg::UpdateLockupDuration { lockup_duration },
+        ))
+    };
+    let query_lockup_durations = || -> LockupDurationResponse {
+        wasm.query(
+            &vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Lockup(LockupQueryMsg::LockupDuration {})),
+        )
+        .unwrap()
+    };
+    let query_lockup_duration = || query_lockup_durations().lockup_duration;
+    let query_legacy_lockup_duration = || query_lockup_durations().legacy_lockup_duration;
+
+    // Only the admin can change the lockup duration
+    wasm.execute(
+        &vault_addr,
+        &update_lockup_duration_msg(ONE_WEEK_IN_SECS),
+        &[],
+        user1,
+    )
+    .unwrap_err();
+
+    // A longer lockup duration extends the existing lock
+    let lock_id = query_vault_state(&runner, &vault_addr).staking.lock_id;
+    assert!(lock_id.is_some());
+    wasm.execute(
+        &vault_addr,
+        &update_lockup_duration_msg(ONE_WEEK_IN_SECS),
+        &[],
+        admin,
+    )
+    .unwrap();
+    assert_eq!(query_lockup_duration(), Duration::Time(ONE_WEEK_IN_SECS));
+    assert_eq!(query_legacy_lockup_duration(), None);
+    assert_eq!(
+        query_vault_state(&runner, &vault_addr).staking.lock_id,
+        lock_id
+    );
+
+    // A shorter lockup duration keeps the existing lock as the legacy lock
+    wasm.execute(
+        &vault_addr,
+        &update_lockup_duration_msg(ONE_DAY_IN_SECS),
+        &[],
+        admin,
+    )
+    .unwrap();
+    assert_eq!(query_lockup_duration(), Duration::Time(ONE_DAY_IN_SECS));
+    assert_eq!(
+        query_legacy_lockup_duration(),
+        Some(Duration::Time(ONE_WEEK_IN_SECS))
+    );
+    let err = wasm
+        .execute(
+            &vault_addr,
+            &update_lockup_duration_msg(TWO_WEEKS_IN_SECS),
+            &[],
+            admin,
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("legacy lock"));
+
+    // New deposits are staked in a new lock
+    wasm.execute(
+        &vault_addr,
+        &deposit_msg,
+        &[Coin::new(deposit_amount.u128(), &base_token)],
+        user1,
+    )
+    .unwrap();
+    let new_lock_id = query_vault_state(&runner, &vault_addr).staking.lock_id;
+    assert!(new_lock_id.is_some());
+    assert_ne!(new_lock_id, lock_id);
+    let solvency = query_solvency(&runner, &vault_addr);
+    assert_eq!(solvency.shortfall, Uint128::zero());
+
+    // Unlocks are taken from the legacy lock and mature after its lockup
+    // duration
+    let update_config_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+        ApolloExtensionExecuteMsg::UpdateConfig {
+            updates: ConfigUpdates {
+                max_unlocking_positions_per_owner: Some(0),
+                ..Default::default()
+            },
+        },
+    ));
+    wasm.execute(&vault_addr, &update_config_msg, &[], admin)
+        .unwrap();
+    unlock(1000000).unwrap();
+    let position = query_positions(user1.address()).last().unwrap().clone();
+    let withdraw_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(
+        LockupExecuteMsg::WithdrawUnlocked {
+            lockup_id: position.id,
+            recipient: None,
+        },
+    ));
+    runner.increase_time(ONE_DAY_IN_SECS + 1);
+    wasm.execute(&vault_addr, &withdraw_msg, &[], user1)
+        .unwrap_err();
+    runner.increase_time(ONE_WEEK_IN_SECS);
+    wasm.execute(&vault_addr, &withdraw_msg, &[], user1)
+        .unwrap();
+
+    println!("=========== Test migrating the legacy lock ===========");
+    let lock_id = lock_id.unwrap();
+    let new_lock_id = new_lock_id.unwrap();
+    let legacy_locked = query_lock_amount(&runner, lock_id, &base_token);
+    let current_locked = query_lock_amount(&runner, new_lock_id, &base_token);
+    let migrate_msg = |amount: Option<Uint128>| {
+        ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Osmosis(
+            OsmosisExtensionExecuteMsg::MigrateLegacyLock { amount },
+        ))
+    };
+
+    // Only the admin can migrate the legacy lock, and not more than it holds
+    wasm.execute(&vault_addr, &migrate_msg(None), &[], user1)
+        .unwrap_err();
+    wasm.execute(
+        &vault_addr,
+        &migrate_msg(Some(legacy_locked + Uint128::one())),
+        &[],
+        admin,
+    )
+    .unwrap_err();
+
+    // Migrate all but half of the base tokens in the current lock
+    let legacy_remaining = current_locked / Uint128::from(2u128);
+    let migrated = legacy_locked - legacy_remaining;
+    wasm.execute(&vault_addr, &migrate_msg(Some(migrated)), &[], admin)
+        .unwrap();
+    assert_eq!(
+        query_lock_amount(&runner, lock_id, &base_token),
+        legacy_remaining
+    );
+    let solvency = query_solvency(&runner, &vault_addr);
+    assert_eq!(solvency.shortfall, Uint128::zero());
+
+    // An unlock larger than the legacy lock is split into a position from the
+    // legacy lock and a position from the current lock, which both count
+    // against the maximum number of unlocking positions
+    let num_positions = query_positions(user1.address()).len();
+    let vault_token_amount: Uint128 = wasm
+        .query(
+            &vault_addr,
+            &QueryMsg::ConvertToShares {
+                amount: current_locked,
+            },
+        )
+        .unwrap();
+    let max_positions_msg = |max: usize| {
+        ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+            ApolloExtensionExecuteMsg::UpdateConfig {
+                updates: ConfigUpdates {
+                    max_unlocking_positions_per_owner: Some(max as u32),
+                    ..Default::default()
+                },
+            },
+        ))
+    };
+    wasm.execute(
+        &vault_addr,
+        &max_positions_msg(num_positions + 1),
+        &[],
+        admin,
+    )
+    .unwrap();
+    let err = unlock(vault_token_amount.u128()).unwrap_err();
+    assert!(err.to_string().contains("Too many unlocking positions"));
+    wasm.execute(
+        &vault_addr,
+        &max_positions_msg(num_positions + 2),
+        &[],
+        admin,
+    )
+    .unwrap();
+    let res = unlock(vault_token_amount.u128()).unwrap();
+    let data: SplitUnlockResponseData = from_slice(&res.data.data).unwrap();
+    let positions = query_positions(user1.address());
+    assert_eq!(positions.len(), num_positions + 2);
+    let legacy_position = &positions[positions.len() - 2];
+    let current_position = &positions[positions.len() - 1];
+    assert_eq!(data.legacy_unlocking_position.id, legacy_position.id);
+    assert_eq!(data.unlocking_position.id, current_position.id);
+    wasm.execute(&vault_addr, &max_positions_msg(0), &[], admin)
+        .unwrap();
+    assert!(legacy_position.base_token_amount <= legacy_remaining);
+    assert!(legacy_position.release_at > current_position.release_at);
+    assert!(
+        legacy_position.base_token_amount + current_position.base_token_amount
+            >= current_locked - Uint128::from(2u128)
+    );
+    let solvency = query_solvency(&runner, &vault_addr);
+    assert_eq!(solvency.shortfall, Uint128::zero());
+
+    // The migrated base tokens are staked in the current lock once they have
+    // unlocked. Anyone can reconcile the lock ID to stake them.
+    let reconcile_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Osmosis(
+        OsmosisExtensionExecuteMsg::ReconcileLockId {},
+    ));
+    let current_locked = query_lock_amount(&runner, new_lock_id, &base_token);
+    runner.increase_time(ONE_WEEK_IN_SECS + 1);
+    wasm.execute(&vault_addr, &reconcile_msg, &[], user2)
+        .unwrap();
+    assert_eq!(
+        query_vault_state(&runner, &vault_addr).staking.lock_id,
+        Some(new_lock_id)
+    );
+    assert_eq!(
+        query_lock_amount(&runner, new_lock_id, &base_token),
+        current_locked + migrated
+    );
+    let solvency = query_solvency(&runner, &vault_addr);
+    assert_eq!(solvency.shortfall, Uint128::zero());
+
+    // Migrate the rest of the legacy lock, after which the lockup duration can
+    // be changed again
+    if query_legacy_lockup_duration().is_some() {
+        wasm.execute(&vault_addr, &migrate_msg(None), &[], admin)
+            .unwrap();
+    }
+    assert_eq!(query_legacy_lockup_duration(), None);
+    wasm.execute(
+        &vault_addr,
+        &update_lockup_duration_msg(ONE_WEEK_IN_SECS),
+        &[],
+        admin,
+    )
+    .unwrap();
+    assert_eq!(query_lockup_duration(), Duration::Time(ONE_WEEK_IN_SECS));
+}
+
+#[test]
+pub fn test_unlock_epoch_mode() {
+    let test_config = TestConfig::from_yaml(TEST_CONFIG_PATH);
+    let runner = BindingsRunner::default();
+    let accs = runner
+        .init_accounts(
+            &[
+                Coin::new(1_000_000_000_000_000_000_000_000, UATOM),
+                Coin::new(1_000_000_000_000_000_000_000_000, UOSMO),
+                Coin::new(1_000_000_000_000_000_000_000_000, UION),
+            ],
+            10,
+        )
+        .unwrap();
+
+    let admin = &accs[0];
+    let user1 = &accs[3];
+    let user2 = &accs[4];
+
+    let wasm = Wasm::new(&runner);
+
+    let (vault_addr, base_token) = setup_test(
+        &runner,
+        vec![
+            Coin::new(1_000_000_000_000, UATOM),
+            Coin::new(1_000_000_000_000, UOSMO),
+        ],
+        &vec![String::from(UION)],
+        vec![
+            Coin::new(1_000_000_000_000, UION),
+            Coin::new(1_000_000_000_000, UOSMO),
+        ],
+        None,
+        UOSMO.to_string(),
+        &accs,
+        &test_config,
+        false,
+    );
+    let vault_token_denom = query_vault_state(&runner, &vault_addr)
+        .vault_token
+        .to_string();
+
+    println!("=========== Test updating the unlock epoch duration ===========");
+    let query_epoch_duration = || -> Option<u64> {
+        wasm.query(
+            &vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Apollo(
+                ApolloExtensionQueryMsg::UnlockEpochDuration {},
+            )),
+        )
+        .unwrap()
+    };
+    let update_epoch_duration_msg = |epoch_duration: Option<u64>| {
+        ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+            ApolloExtensionExecuteMsg::UpdateUnlockEpochDuration { epoch_duration },
+        ))
+    };
+    assert_eq!(query_epoch_duration(), None);
+
+    // Only the admin can update the epoch duration, which must be positive
+    wasm.execute(
+        &vault_addr,
+        &update_epoch_duration_msg(Some(ONE_DAY_IN_SECS)),
+        &[],
+        user1,
+    )
+    .unwrap_err();
+    wasm.execute(&vault_addr, &update_epoch_duration_msg(Some(0)), &[], admin)
+        .unwrap_err();
+    wasm.execute(
+        &vault_addr,
+        &update_epoch_duration_msg(Some(ONE_DAY_IN_SECS)),
+        &[],
+        admin,
+    )
+    .unwrap();
+    assert_eq!(query_epoch_duration(), Some(ONE_DAY_IN_SECS));
+
+    println!("=========== Test unlocking in epoch mode ===========");
+    // Deposit from both users
+    let deposit_amount = Uint128::from(100_000_000u128);
+    send_native_coins(
+        &runner,
+        user1,
+        &user2.address(),
+        &base_token,
+        deposit_amount,
+    );
+    for user in [user1, user2] {
+        wasm.execute(
+            &vault_addr,
+            &ExecuteMsg::Deposit {
+                amount: deposit_amount,
+                recipient: None,
+            },
+            &[Coin::new(deposit_amount.u128(), &base_token)],
+            user,
+        )
+        .unwrap();
+    }
+
+    // Unlock requests of both users are added to the same batch
+    let unlock = |user: &SigningAccount| -> UnlockBatchRequestResponseData {
+        let vault_token_balance = query_token_balance(&runner, &user.address(), &vault_token_denom);
+        let res = wasm
+            .execute(
+                &vault_addr,
+                &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(
+                    LockupExecuteMsg::Unlock {
+                        amount: vault_token_balance,
+                    },
+                )),
+                &[Coin::new(vault_token_balance.u128(), &vault_token_denom)],
+                user,
+            )
+            .unwrap();
+        assert_eq!(
+            query_token_balance(&runner, &user.address(), &vault_token_denom),
+            Uint128::zero()
+        );
+        from_slice(&res.data.data).unwrap()
+    };
+    let request1 = unlock(user1);
+    let request2 = unlock(user2);
+    assert_eq!(request1.batch_id, request2.batch_id);
+    assert_eq!(request1.epoch_end, request2.epoch_end);
+    let batch_id = request1.batch_id;
+
+    let query_batch = || -> UnlockBatch {
+        wasm.query(
+            &vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Apollo(
+                ApolloExtensionQueryMsg::UnlockBatch { batch_id },
+            )),
+        )
+        .unwrap()
+    };
+    let batch = query_batch();
+    assert_eq!(batch.status, UnlockBatchStatus::Open);
+    assert_eq!(batch.total_shares, request1.shares + request2.shares);
+    assert_eq!(batch.base_token_amount, batch.total_shares);
+
+    // Changing the epoch duration does not change the epoch of the open batch
+    wasm.execute(
+        &vault_addr,
+        &update_epoch_duration_msg(Some(ONE_WEEK_IN_SECS)),
+        &[],
+        admin,
+    )
+    .unwrap();
+    assert_eq!(query_batch().epoch_end, request1.epoch_end);
+
+    // The batch can only be processed once its epoch has ended
+    let process_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+        ApolloExtensionExecuteMsg::ProcessUnlockBatch {},
+    ));
+    wasm.execute(&vault_addr, &process_msg, &[], user2)
+        .unwrap_err();
+    runner.increase_time(ONE_DAY_IN_SECS + 1);
+    wasm.execute(&vault_addr, &process_msg, &[], user2).unwrap();
+    let batch = query_batch();
+    assert!(matches!(batch.status, UnlockBatchStatus::Unlocking { .. }));
+    let solvency = query_solvency(&runner, &vault_addr);
+    assert_eq!(solvency.shortfall, Uint128::zero());
+
+    // The batch can only be withdrawn from once it has finished unlocking, and
+    // only by owners of shares
+    let withdraw_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Apollo(
+        ApolloExtensionExecuteMsg::WithdrawUnlockBatch {
+            batch_id,
+            recipient: None,
+        },
+    ));
+    wasm.execute(&vault_addr, &withdraw_msg, &[], user1)
+        .unwrap_err();
+    runner.increase_time(ONE_DAY_IN_SECS + 1);
+    wasm.execute(&vault_addr, &withdraw_msg, &[], admin)
+        .unwrap_err();
+
+    for (user, request) in [(user1, &request1), (user2, &request2)] {
+        let balance_before = query_token_balance(&runner, &user.address(), &base_token);
+        let res = wasm.execute(&vault_addr, &withdraw_msg, &[], user).unwrap();
+        let data: WithdrawUnlockBatchResponseData = from_slice(&res.data.data).unwrap();
+        assert_eq!(data.batch_id, batch_id);
+        assert_eq!(data.recipient.to_string(), user.address());
+        assert_eq!(
+            data.base_token_amount,
+            batch
+                .base_token_amount
+                .multiply_ratio(request.shares, batch.total_shares)
+        );
+        assert_eq!(
+            query_token_balance(&runner, &user.address(), &base_token),
+            balance_before + data.base_token_amount
+        );
+
+        // Shares can only be withdrawn once
+        wasm.execute(&vault_addr, &withdraw_msg, &[], user)
+            .unwrap_err();
+    }
+
+    // The batch is removed once fully withdrawn
+    let batches: Vec<UnlockBatch> = wasm
+        .query(
+            &vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Apollo(
+                ApolloExtensionQueryMsg::UnlockBatches {
+                    start_after: None,
+                    limit: None,
+                },
+            )),
+        )
+        .unwrap();
+    assert!(batches.is_empty());
+
+    println!("=========== Test disabling epoch mode ===========");
+    wasm.execute(&vault_addr, &update_epoch_duration_msg(None), &[], admin)
+        .unwrap();
+    assert_eq!(query_epoch_duration(), None);
+    wasm.execute(
+        &vault_addr,
+        &ExecuteMsg::Deposit {
+            amount: deposit_amount,
+            recipient: None,
+        },
+        &[Coin::new(deposit_amount.u128(), &base_token)],
+        user1,
+    )
+    .unwrap();
+    let vault_token_balance = query_token_balance(&runner, &user1.address(), &vault_token_denom);
+    let res = wasm
+        .execute(
+            &vault_addr,
+            &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(LockupExecuteMsg::Unlock {
+                amount: vault_token_balance,
+            })),
+            &[Coin::new(vault_token_balance.u128(), &vault_token_denom)],
+            user1,
+        )
+        .unwrap();
+    let position: UnlockingPosition = from_slice(&res.data.data).unwrap();
+    assert_eq!(position.owner.to_string(), user1.address());
+}
+
+#[test]
+pub fn test_unlocking_position_nft() {
+    let test_config = TestConfig::from_yaml(TEST_CONFIG_PATH);
+    let runner = BindingsRunner::default();
+    let accs = runner
+        .init_accounts(
+            &[
+                Coin::new(1_000_000_000_000_000_000_000_000, UATOM),
+                Coin::new(1_000_000_000_000_000_000_000_000, UOSMO),
+                Coin::new(1_000_000_000_000_000_000_000_000, UION),
+            ],
+            10,
+        )
+        .unwrap();
+
+    let force_withdraw_admin = &accs[1];
+    let user1 = &accs[3];
+    let user2 = &accs[4];
+
+    let wasm = Wasm::new(&runner);
+
+    let (vault_addr, base_token) = setup_test(
+        &runner,
+        vec![
+            Coin::new(1_000_000_000_000, UATOM),
+            Coin::new(1_000_000_000_000, UOSMO),
+        ],
+        &vec![String::from(UION)],
+        vec![
+            Coin::new(1_000_000_000_000, UION),
+            Coin::new(1_000_000_000_000, UOSMO),
+        ],
+        None,
+        UOSMO.to_string(),
+        &accs,
+        &test_config,
+        true,
+    );
+    let vault_token_denom = query_vault_state(&runner, &vault_addr)
+        .vault_token
+        .to_string();
+    let nft_addr: Option<Addr> = wasm
+        .query(
+            &vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Apollo(
+                ApolloExtensionQueryMsg::UnlockingPositionNft {},
+            )),
+        )
+        .unwrap();
+    let nft_addr = nft_addr.unwrap().to_string();
+
+    let query_nft_owner = |lockup_id: u64| -> String {
+        let res: OwnerOfResponse = wasm
+            .query(
+                &nft_addr,
+                &Cw721QueryMsg::OwnerOf {
+                    token_id: lockup_id.to_string(),
+                    include_expired: None,
+                },
+            )
+            .unwrap();
+        res.owner
+    };
+    let query_nft_metadata = |lockup_id: u64| -> Option<UnlockingPositionMetadata> {
+        wasm.query::<_, NftInfoResponse<UnlockingPositionMetadata>>(
+            &nft_addr,
+            &Cw721QueryMsg::NftInfo {
+                token_id: lockup_id.to_string(),
+            },
+        )
+        .ok()
+        .map(|res| res.extension)
+    };
+    let query_num_nfts = || -> u64 {
+        let res: NumTokensResponse = wasm.query(&nft_addr, &Cw721QueryMsg::NumTokens {}).unwrap();
+        res.count
+    };
+    let deposit = |user: &SigningAccount, amount: Uint128| {
+        wasm.execute(
+            &vault_addr,
+            &ExecuteMsg::Deposit {
+                amount,
+                recipient: None,
+            },
+            &[Coin::new(amount.u128(), &base_token)],
+            user,
+        )
+        .unwrap();
+    };
+    let unlock = |user: &SigningAccount, amount: Uint128| -> UnlockingPosition {
+        let res = wasm
+            .execute(
+                &vault_addr,
+                &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(
+                    LockupExecuteMsg::Unlock { amount },
+                )),
+                &[Coin::new(amount.u128(), &vault_token_denom)],
+                user,
+            )
+            .unwrap();
+        from_slice(&res.data.data).unwrap()
+    };
+    let transfer_nft = |from: &SigningAccount, to: &SigningAccount, lockup_id: u64| {
+        wasm.execute(
+            &nft_addr,
+            &UnlockingPositionNftExecuteMsg::TransferNft {
+                recipient: to.address(),
+                token_id: lockup_id.to_string(),
+            },
+            &[],
+            from,
+        )
+        .unwrap();
+    };
+    let withdraw_msg = |lockup_id: u64| {
+        ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(
+            LockupExecuteMsg::WithdrawUnlocked {
+                lockup_id,
+                recipient: None,
+            },
+        ))
+    };
+    let force_withdraw_msg = |lockup_id: u64, amount: Option<Uint128>| {
+        ExecuteMsg::VaultExtension(ExtensionExecuteMsg::ForceUnlock(
+            ForceUnlockExecuteMsg::ForceWithdrawUnlocking {
+                lockup_id,
+                amount,
+                recipient: None,
+            },
+        ))
+    };
+
+    println!("=========== Test minting unlocking position NFTs ===========");
+    deposit(user1, Uint128::from(100_000_000u128));
+    let vault_token_balance = query_token_balance(&runner, &user1.address(), &vault_token_denom);
+    let position1 = unlock(user1, vault_token_balance / Uint128::from(2u128));
+    let position2 = unlock(
+        user1,
+        query_token_balance(&runner, &user1.address(), &vault_token_denom),
+    );
+
+    // An NFT with the metadata of the position is minted to the owner
+    for position in [&position1, &position2] {
+        assert_eq!(query_nft_owner(position.id), user1.address());
+        assert_eq!(
+            query_nft_metadata(position.id),
+            Some(UnlockingPositionMetadata {
+                base_token_amount: position.base_token_amount,
+                release_at: position.release_at,
+            })
+        );
+    }
+    assert_eq!(query_num_nfts(), 2);
+
+    println!("=========== Test withdrawing after transferring the NFT ===========");
+    transfer_nft(user1, user2, position1.id);
+    assert_eq!(query_nft_owner(position1.id), user2.address());
+    runner.increase_time(ONE_DAY_IN_SECS);
+
+    // Only the holder of the NFT can withdraw
+    wasm.execute(&vault_addr, &withdraw_msg(position1.id), &[], user1)
+        .unwrap_err();
+    let balance_before = query_token_balance(&runner, &user2.address(), &base_token);
+    let res = wasm
+        .execute(&vault_addr, &withdraw_msg(position1.id), &[], user2)
+        .unwrap();
+    let balance_after = query_token_balance(&runner, &user2.address(), &base_token);
+    assert_eq!(balance_after - balance_before, position1.base_token_amount);
+    let data: WithdrawUnlockedResponseData = from_slice(&res.data.data).unwrap();
+    assert_eq!(data.recipient.to_string(), user2.address());
+
+    // The vault burns the NFT without an approval of the holder
+    assert_eq!(query_nft_metadata(position1.id), None);
+    assert_eq!(query_num_nfts(), 1);
+
+    // The original owner can still withdraw the position they kept
+    wasm.execute(&vault_addr, &withdraw_msg(position2.id), &[], user1)
+        .unwrap();
+    assert_eq!(query_nft_metadata(position2.id), None);
+    assert_eq!(query_num_nfts(), 0);
+
+    println!("=========== Test force withdrawing an NFT ===========");
+    runner.whitelist_address_for_force_unlock(&vault_addr);
+    deposit(user1, Uint128::from(100_000_000u128));
+    let position3 = unlock(
+        user1,
+        query_token_balance(&runner, &user1.address(), &vault_token_denom),
+    );
+    transfer_nft(user1, force_withdraw_admin, position3.id);
+
+    // A partial force withdrawal updates the metadata of the NFT
+    let first_amount = position3.base_token_amount / Uint128::from(2u128);
+    let remaining_amount = position3.base_token_amount - first_amount;
+    let balance_before = query_token_balance(&runner, &force_withdraw_admin.address(), &base_token);
+    wasm.execute(
+        &vault_addr,
+        &force_withdraw_msg(position3.id, Some(first_amount)),
+        &[],
+        force_withdraw_admin,
+    )
+    .unwrap();
+    let balance_after = query_token_balance(&runner, &force_withdraw_admin.address(), &base_token);
+    assert_eq!(balance_after - balance_before, first_amount);
+    assert_eq!(
+        query_nft_metadata(position3.id),
+        Some(UnlockingPositionMetadata {
+            base_token_amount: remaining_amount,
+            release_at: position3.release_at,
+        })
+    );
+    assert_eq!(
+        query_nft_owner(position3.id),
+        force_withdraw_admin.address()
+    );
+
+    // Force withdrawing the rest burns the NFT
+    wasm.execute(
+        &vault_addr,
+        &force_withdraw_msg(position3.id, None),
+        &[],
+        force_withdraw_admin,
+    )
+    .unwrap();
+    let balance_after_all =
+        query_token_balance(&runner, &force_withdraw_admin.address(), &base_token);
+    assert_eq!(balance_after_all - balance_after, remaining_amount);
+    assert_eq!(query_nft_metadata(position3.id), None);
+    assert_eq!(query_num_nfts(), 0);
+}
+
+const ONE_DAY_IN_SECS: u64 = 60 * 60 * 24;
+const ONE_WEEK_IN_SECS: u64 = 60 * 60 * 24 * 7;
+const TWO_WEEKS_IN_SECS: u64 = 60 * 60 * 24 * 14;
+
+fn query_vault_state<'a, R>(
+    runner: &'a R,
+    vault_addr: &str,
+) -> StateResponse<OsmosisStaking, OsmosisPool, OsmosisDenom>
+where
+    R: Runner<'a>,
+{
+    let wasm = Wasm::new(runner);
+    let state: StateResponse<OsmosisStaking, OsmosisPool, OsmosisDenom> = wasm
+        .query(
+            vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Apollo(ApolloExtensionQueryMsg::State {})),
+        )
+        .unwrap();
+    state
+}
+
+fn query_solvency<'a, R>(runner: &'a R, vault_addr: &str) -> SolvencyResponse
+where
+    R: Runner<'a>,
+{
+    let wasm = Wasm::new(runner);
+    wasm.query(
+        vault_addr,
+        &QueryMsg::VaultExtension(ExtensionQueryMsg::Apollo(
+            ApolloExtensionQueryMsg::Solvency {},
+        )),
+    )
+    .unwrap()
+}
+
+fn query_lock_amount<'a, R>(runner: &'a R, lock_id: u64, denom: &str) -> Uint128
+where
+    R: Runner<'a>,
+{
+    let res: LockedResponse = runner
+        .query(
+            "/osmosis.lockup.Query/LockedByID",
+            &LockedRequest { lock_id },
+        )
+        .unwrap();
+    res.lock
+        .map(|lock| lock.coins)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|coin| coin.denom == denom)
+        .map(|coin| Uint128::from_str(&coin.amount).unwrap())
+        .sum()
 }
 
 fn query_token_balance<'a, R>(runner: &'a R, address: &str, denom: &str) -> Uint128
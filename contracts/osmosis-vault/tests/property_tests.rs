@@ -1,7 +1,5 @@
 use apollo_cw_asset::{AssetInfo, AssetInfoUnchecked};
-use apollo_vault::msg::{
-    ApolloExtensionQueryMsg, ExtensionExecuteMsg, ExtensionQueryMsg, StateResponse,
-};
+use apollo_vault::msg::{ApolloExtensionQueryMsg, StateResponse};
 use apollo_vault::state::ConfigUnchecked;
 use cosmwasm_std::testing::{MockApi, MockStorage};
 use cosmwasm_std::{Coin, Decimal, Deps, Empty, Querier, QuerierWrapper, StdResult, Uint128};
@@ -17,7 +15,7 @@ use cw_it::helpers::{
 };
 use cw_it::mock_api::OsmosisMockApi;
 use cw_vault_standard::extensions::lockup::{LockupExecuteMsg, LockupQueryMsg, UnlockingPosition};
-use osmosis_vault::msg::ExecuteMsg;
+use osmosis_vault::msg::{ExecuteMsg, ExtensionExecuteMsg, ExtensionQueryMsg};
 use std::time::Duration;
 
 use cw_vault_token::osmosis::OsmosisDenom;
@@ -181,6 +179,14 @@ impl<'a, R: Runner<'a> + Querier> OsmosisVaultRobot<'a, R> {
         let config = ConfigUnchecked {
             force_withdraw_whitelist: vec![force_withdraw_admin.address().clone()],
             performance_fee,
+            early_exit_penalty: Decimal::zero(),
+            early_exit_penalty_decay: false,
+            early_exit_penalty_to_treasury: false,
+            auto_withdraw_keeper_fee: Decimal::zero(),
+            min_unlock_amount: Uint128::zero(),
+            max_unlocking_positions_per_owner: 0,
+            redemption_buffer_ratio: Decimal::zero(),
+            instant_redemption_fee: Decimal::zero(),
             reward_assets,
             reward_liquidation_target: AssetInfoUnchecked::Native(reward_liquidation_target),
             treasury: treasury.address().clone(),
@@ -198,6 +204,8 @@ impl<'a, R: Runner<'a> + Querier> OsmosisVaultRobot<'a, R> {
                 lockup_duration: 86400u64,
                 pool_id: base_pool.pool_id(),
                 vault_token_subdenom: "osmosis-vault".to_string(),
+                unlocking_position_nft: None,
+                unlock_epoch_duration: None,
                 config,
             },
             &[Coin {
@@ -207,19 +215,6 @@ impl<'a, R: Runner<'a> + Querier> OsmosisVaultRobot<'a, R> {
         )
         .unwrap();
 
-        // WARNING!!! This is a hack
-        // Send 1B base token to allow contract to create new locks on ExecuteMsg::Unlock
-        bank_send(
-            app,
-            admin,
-            &vault_addr,
-            vec![Coin {
-                denom: format!("gamm/pool/{}", base_pool.pool_id()),
-                amount: Uint128::from(1_000_000_000u128),
-            }],
-        )
-        .unwrap();
-
         println!(" ------ Addresses -------");
         println!("admin: {}", admin.address());
         println!("force_withdraw_admin: {}", force_withdraw_admin.address());
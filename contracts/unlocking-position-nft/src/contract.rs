@@ -0,0 +1,279 @@
+use apollo_vault::msg::{UnlockingPositionMetadata, UnlockingPositionNftExtensionMsg};
+use apollo_vault::unlocking_position_nft::UnlockingPositionNftExecuteMsg;
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdError, StdResult};
+use cw2::set_contract_version;
+use cw721_base::{ContractError, Cw721Contract, InstantiateMsg, QueryMsg};
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:unlocking-position-nft";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// cw721-base with [`UnlockingPositionMetadata`] as the token extension and
+/// [`UnlockingPositionNftExtensionMsg`] as the extension execute message.
+pub type UnlockingPositionNftContract<'a> =
+    Cw721Contract<'a, UnlockingPositionMetadata, Empty, UnlockingPositionNftExtensionMsg, Empty>;
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    let res = UnlockingPositionNftContract::default().instantiate(deps.branch(), env, info, msg)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(res)
+}
+
+/// Handles the messages of cw721-base, except that the minter, i.e. the
+/// vault, can burn any NFT when its unlocking position is withdrawn and update
+/// the metadata of an NFT when base tokens are withdrawn from its position.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: UnlockingPositionNftExecuteMsg,
+) -> Result<Response, ContractError> {
+    let contract = UnlockingPositionNftContract::default();
+    let minter = contract.minter.load(deps.storage)?;
+
+    match msg {
+        UnlockingPositionNftExecuteMsg::Burn { token_id } if info.sender == minter => {
+            execute_burn_by_minter(deps, &contract, token_id)
+        }
+        UnlockingPositionNftExecuteMsg::Extension { msg } => {
+            if info.sender != minter {
+                return Err(ContractError::Unauthorized {});
+            }
+            match msg {
+                UnlockingPositionNftExtensionMsg::UpdateMetadata { token_id, metadata } => {
+                    execute_update_metadata(deps, &contract, token_id, metadata)
+                }
+            }
+        }
+        msg => contract.execute(deps, env, info, msg),
+    }
+}
+
+/// Burns the NFT with ID `token_id` regardless of its holder and approvals.
+fn execute_burn_by_minter(
+    deps: DepsMut,
+    contract: &UnlockingPositionNftContract,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    // Fails if the token does not exist
+    contract.tokens.load(deps.storage, &token_id)?;
+    contract.tokens.remove(deps.storage, &token_id)?;
+    contract.decrement_tokens(deps.storage)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "burn_by_minter")
+        .add_attribute("token_id", token_id))
+}
+
+/// Replaces the metadata of the NFT with ID `token_id`.
+fn execute_update_metadata(
+    deps: DepsMut,
+    contract: &UnlockingPositionNftContract,
+    token_id: String,
+    metadata: UnlockingPositionMetadata,
+) -> Result<Response, ContractError> {
+    contract
+        .tokens
+        .update(deps.storage, &token_id, |token| -> StdResult<_> {
+            let mut token = token.ok_or_else(|| {
+                StdError::not_found(format!("unlocking position NFT {}", token_id))
+            })?;
+            token.extension = metadata.clone();
+            Ok(token)
+        })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_metadata")
+        .add_attribute("token_id", token_id)
+        .add_attribute("base_token_amount", metadata.base_token_amount)
+        .add_attribute("release_at", metadata.release_at.to_string()))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg<Empty>) -> StdResult<Binary> {
+    UnlockingPositionNftContract::default().query(deps, env, msg)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: Empty) -> StdResult<Response> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::{from_binary, OwnedDeps, Uint128};
+    use cw721::{NftInfoResponse, NumTokensResponse};
+    use cw721_base::MintMsg;
+    use cw_utils::Expiration;
+
+    use super::*;
+
+    const VAULT: &str = "vault";
+    const HOLDER: &str = "holder";
+    const TOKEN_ID: &str = "1";
+
+    fn metadata(base_token_amount: u128) -> UnlockingPositionMetadata {
+        UnlockingPositionMetadata {
+            base_token_amount: Uint128::new(base_token_amount),
+            release_at: Expiration::AtHeight(100),
+        }
+    }
+
+    /// Instantiates the contract with the vault as minter and mints an NFT to
+    /// the holder.
+    fn setup() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            InstantiateMsg {
+                name: "Unlocking positions".to_string(),
+                symbol: "UNLOCK".to_string(),
+                minter: VAULT.to_string(),
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(VAULT, &[]),
+            UnlockingPositionNftExecuteMsg::Mint(MintMsg {
+                token_id: TOKEN_ID.to_string(),
+                owner: HOLDER.to_string(),
+                token_uri: None,
+                extension: metadata(1000),
+            }),
+        )
+        .unwrap();
+        deps
+    }
+
+    fn num_tokens(deps: Deps) -> u64 {
+        let res: NumTokensResponse =
+            from_binary(&query(deps, mock_env(), QueryMsg::NumTokens {}).unwrap()).unwrap();
+        res.count
+    }
+
+    fn nft_metadata(deps: Deps) -> StdResult<UnlockingPositionMetadata> {
+        let res: NftInfoResponse<UnlockingPositionMetadata> = from_binary(&query(
+            deps,
+            mock_env(),
+            QueryMsg::NftInfo {
+                token_id: TOKEN_ID.to_string(),
+            },
+        )?)?;
+        Ok(res.extension)
+    }
+
+    fn burn_msg() -> UnlockingPositionNftExecuteMsg {
+        UnlockingPositionNftExecuteMsg::Burn {
+            token_id: TOKEN_ID.to_string(),
+        }
+    }
+
+    fn update_metadata_msg(base_token_amount: u128) -> UnlockingPositionNftExecuteMsg {
+        UnlockingPositionNftExecuteMsg::Extension {
+            msg: UnlockingPositionNftExtensionMsg::UpdateMetadata {
+                token_id: TOKEN_ID.to_string(),
+                metadata: metadata(base_token_amount),
+            },
+        }
+    }
+
+    #[test]
+    fn test_minter_can_burn_without_approval() {
+        let mut deps = setup();
+
+        execute(deps.as_mut(), mock_env(), mock_info(VAULT, &[]), burn_msg()).unwrap();
+
+        assert_eq!(num_tokens(deps.as_ref()), 0);
+        nft_metadata(deps.as_ref()).unwrap_err();
+
+        // Burning a token that does not exist fails
+        execute(deps.as_mut(), mock_env(), mock_info(VAULT, &[]), burn_msg()).unwrap_err();
+    }
+
+    #[test]
+    fn test_burn_by_others_follows_cw721_base() {
+        let mut deps = setup();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("other", &[]),
+            burn_msg(),
+        )
+        .unwrap_err();
+        assert_eq!(num_tokens(deps.as_ref()), 1);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(HOLDER, &[]),
+            burn_msg(),
+        )
+        .unwrap();
+        assert_eq!(num_tokens(deps.as_ref()), 0);
+    }
+
+    #[test]
+    fn test_update_metadata() {
+        let mut deps = setup();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(VAULT, &[]),
+            update_metadata_msg(400),
+        )
+        .unwrap();
+
+        assert_eq!(nft_metadata(deps.as_ref()).unwrap(), metadata(400));
+    }
+
+    #[test]
+    fn test_only_minter_can_update_metadata() {
+        let mut deps = setup();
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(HOLDER, &[]),
+            update_metadata_msg(400),
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        assert_eq!(nft_metadata(deps.as_ref()).unwrap(), metadata(1000));
+    }
+
+    #[test]
+    fn test_update_metadata_of_missing_token_fails() {
+        let mut deps = setup();
+        execute(deps.as_mut(), mock_env(), mock_info(VAULT, &[]), burn_msg()).unwrap();
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(VAULT, &[]),
+            update_metadata_msg(400),
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, ContractError::Std(StdError::NotFound { .. })));
+    }
+}
@@ -0,0 +1,4 @@
+docker run --rm -v "$(pwd)":/code \
+  --mount type=volume,source="astroport-tokenfactory-vault_cache",target=/code/contracts/astroport-tokenfactory-vault/target \
+  --mount type=volume,source=registry_cache,target=/usr/local/cargo/registry \
+  cosmwasm/rust-optimizer:0.12.10 ./contracts/astroport-tokenfactory-vault
@@ -0,0 +1,5 @@
+docker run --rm -v "$(pwd)":/code \
+  --mount type=volume,source="osmosis-superfluid-vault_cache",target=/code/contracts/osmosis-superfluid-vault/target \
+  --mount type=volume,source=registry_cache,target=/usr/local/cargo/registry \
+  cosmwasm/rust-optimizer:0.12.10 ./contracts/osmosis-superfluid-vault
+
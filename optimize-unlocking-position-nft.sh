@@ -0,0 +1,4 @@
+docker run --rm -v "$(pwd)":/code \
+  --mount type=volume,source="unlocking-position-nft_cache",target=/code/contracts/unlocking-position-nft/target \
+  --mount type=volume,source=registry_cache,target=/usr/local/cargo/registry \
+  cosmwasm/rust-optimizer:0.12.10 ./contracts/unlocking-position-nft
@@ -1,14 +1,18 @@
 use base_vault::BaseVault;
-use cosmwasm_std::{Addr, Binary, DepsMut, Event, MessageInfo, Response};
+use cosmwasm_std::{Addr, Binary, Deps, DepsMut, Env, Event, MessageInfo, Response, Uint128};
 use cw_controllers::Admin;
 use cw_dex::traits::Pool;
 use cw_storage_plus::Item;
+use cw_utils::{parse_execute_response_data, Expiration};
 use cw_vault_token::VaultToken;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use crate::error::ContractError;
-use crate::state::{Claims, Config, ConfigUpdates};
+use crate::state::{
+    AdminTransferProposal, ChangeLog, ChangeLogAction, Claims, Config, ConfigUpdates,
+    UnlockBatches, DEFAULT_ADMIN_TRANSFER_EXPIRATION, MAX_ADMIN_TRANSFER_EXPIRATION,
+};
 
 /// AutocompoundingVault is a wrapper around BaseVault that implements
 /// autocompounding functionality.
@@ -28,13 +32,29 @@ pub struct AutocompoundingVault<'a, S, P, V> {
     /// The admin address that is allowed to update the config.
     pub admin: Admin<'a>,
 
-    /// Temporary storage of an address that will become the new admin once
-    /// they accept the transfer request.
-    pub admin_transfer: Item<'a, Addr>,
+    /// Temporary storage of a pending admin transfer or admin renunciation,
+    /// which expires if it is not accepted or confirmed in time.
+    pub admin_transfer: Item<'a, AdminTransferProposal>,
 
     /// Stores claims of base_tokens for users who have burned their vault
     /// tokens via ExecuteMsg::Unlock.
     pub claims: Claims<'a>,
+
+    /// Append-only history of config updates, admin transfers and whitelist
+    /// changes.
+    pub change_log: ChangeLog<'a>,
+
+    /// Optional cw721 contract that mints an NFT for every unlocking position.
+    /// If set, the holder of the NFT is the owner of the unlocking position.
+    pub unlocking_position_nft: Item<'a, Addr>,
+
+    /// Unlock requests that are batched per epoch and unlocked together, if
+    /// epoch mode is enabled.
+    pub unlock_batches: UnlockBatches<'a>,
+
+    /// The base tokens kept unstaked in the vault for instant redemptions.
+    /// These are included in `total_staked_base_tokens`.
+    pub redemption_buffer: Item<'a, Uint128>,
 }
 
 impl<'a, S, P, V> Default for AutocompoundingVault<'a, S, P, V> {
@@ -44,9 +64,34 @@ impl<'a, S, P, V> Default for AutocompoundingVault<'a, S, P, V> {
             pool: Item::new("pool"),
             staking: Item::new("staking"),
             config: Item::new("config"),
-            claims: Claims::new("claims", "claims_index", "pending_claim", "num_claims"),
+            claims: Claims::new(
+                "claims",
+                "claims_index",
+                "pending_claim",
+                "num_claims",
+                "claims_release_at_index",
+                "claim_operators",
+                "total_unlocking_base_tokens",
+                "claim_auto_withdraw",
+                "claim_auto_withdraw_index",
+                "claims_reindex_cursor",
+                "claim_lockup_durations",
+                "pending_claim_lockup_duration",
+                "claims_owner_release_at_index",
+                "claims_release_totals",
+            ),
             admin: Admin::new("admin"),
-            admin_transfer: Item::new("admin_transfer"),
+            admin_transfer: Item::new("admin_transfer_proposal"),
+            change_log: ChangeLog::new("change_log", "change_log_next_id"),
+            unlocking_position_nft: Item::new("unlocking_position_nft"),
+            unlock_batches: UnlockBatches::new(
+                "unlock_epoch_duration",
+                "unlock_batches",
+                "open_unlock_batch",
+                "next_unlock_batch_id",
+                "unlock_batch_shares",
+            ),
+            redemption_buffer: Item::new("redemption_buffer"),
         }
     }
 }
@@ -88,86 +133,266 @@ where
             .base_vault
             .init(deps, pool.lp_token(), vault_token, init_info)?)
     }
+}
 
-    /// Update the admin address.
+/// Admin transfer functions. These only use the storage of the vault, so they
+/// have no trait bounds on the generics.
+impl<S, P, V> AutocompoundingVault<'_, S, P, V> {
+    /// Propose a transfer of admin rights to `address`. The transfer must be
+    /// accepted by `address` before `expiration`, which defaults to
+    /// [`DEFAULT_ADMIN_TRANSFER_EXPIRATION`] seconds from now. Replaces any
+    /// pending admin transfer.
     pub fn execute_update_admin(
         &self,
         deps: DepsMut,
+        env: Env,
         info: MessageInfo,
         address: String,
+        expiration: Option<Expiration>,
     ) -> Result<Response, ContractError> {
-        self.admin.assert_admin(deps.as_ref(), &info.sender)?;
         let admin_addr = deps.api.addr_validate(&address)?;
-        self.admin_transfer.save(deps.storage, &admin_addr)?;
+        let expires =
+            self.propose_admin_transfer(deps, &env, &info, Some(admin_addr), expiration)?;
+
         let event = Event::new("apollo/vaults/autocompounding_vault").add_attributes(vec![
             ("action", "execute_update_admin"),
-            (
-                "previous_admin",
-                self
-                    .admin
-                    .get(deps.as_ref())?
-                    .unwrap_or_else(|| Addr::unchecked("")).as_ref(),
-            ),
+            ("previous_admin", info.sender.as_str()),
             ("new_admin", &address),
+            ("expires", &expires.to_string()),
         ]);
         Ok(Response::new().add_event(event))
     }
 
+    /// Propose to renounce admin rights, leaving the vault without an admin.
+    /// The renunciation must be confirmed by the admin via
+    /// `ConfirmRenounceAdmin` before `expiration`, which defaults to
+    /// [`DEFAULT_ADMIN_TRANSFER_EXPIRATION`] seconds from now. Replaces any
+    /// pending admin transfer.
+    pub fn execute_renounce_admin(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        expiration: Option<Expiration>,
+    ) -> Result<Response, ContractError> {
+        let expires = self.propose_admin_transfer(deps, &env, &info, None, expiration)?;
+
+        let event = Event::new("apollo/vaults/autocompounding_vault").add_attributes(vec![
+            ("action", "execute_renounce_admin"),
+            ("expires", &expires.to_string()),
+        ]);
+        Ok(Response::new().add_event(event))
+    }
+
+    /// Saves a new admin transfer proposal after checking that the sender is
+    /// the admin and that the expiration is a time in the future, at most
+    /// [`MAX_ADMIN_TRANSFER_EXPIRATION`] seconds from now. Returns the
+    /// expiration of the proposal.
+    fn propose_admin_transfer(
+        &self,
+        deps: DepsMut,
+        env: &Env,
+        info: &MessageInfo,
+        new_admin: Option<Addr>,
+        expiration: Option<Expiration>,
+    ) -> Result<Expiration, ContractError> {
+        self.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+        let expires = expiration.unwrap_or_else(|| {
+            Expiration::AtTime(
+                env.block
+                    .time
+                    .plus_seconds(DEFAULT_ADMIN_TRANSFER_EXPIRATION),
+            )
+        });
+        // Proposals that never expire, or expire at a block height that can't
+        // be compared to the maximum duration, would allow open-ended transfers
+        let max_expires = env.block.time.plus_seconds(MAX_ADMIN_TRANSFER_EXPIRATION);
+        let is_valid = match expires {
+            Expiration::AtTime(time) => !expires.is_expired(&env.block) && time <= max_expires,
+            _ => false,
+        };
+        if !is_valid {
+            return Err(ContractError::InvalidAdminTransferExpiration {
+                expires,
+                max: MAX_ADMIN_TRANSFER_EXPIRATION,
+            });
+        }
+
+        let admin = self.admin.get(deps.as_ref())?;
+        let previous_proposal = self.admin_transfer.may_load(deps.storage)?;
+        let proposal = AdminTransferProposal { new_admin, expires };
+        self.admin_transfer.save(deps.storage, &proposal)?;
+        self.change_log.record(
+            deps.storage,
+            &env.block,
+            &info.sender,
+            ChangeLogAction::ProposeAdminTransfer {
+                admin,
+                previous_proposal,
+                proposal,
+            },
+        )?;
+
+        Ok(expires)
+    }
+
+    /// Loads the pending admin transfer proposal, returning an error if there
+    /// is none or if it has expired.
+    fn load_active_admin_transfer(
+        &self,
+        deps: Deps,
+        env: &Env,
+    ) -> Result<AdminTransferProposal, ContractError> {
+        let proposal = self
+            .admin_transfer
+            .may_load(deps.storage)?
+            .ok_or(ContractError::NoPendingAdminTransfer {})?;
+        if proposal.expires.is_expired(&env.block) {
+            return Err(ContractError::AdminTransferExpired {
+                expires: proposal.expires,
+            });
+        }
+        Ok(proposal)
+    }
+
     /// Accept the admin transfer request. This must be called by the new admin
-    /// address for the transfer to complete.
+    /// address before the transfer expires for the transfer to complete.
     pub fn execute_accept_admin_transfer(
         &self,
         mut deps: DepsMut,
+        env: Env,
         info: MessageInfo,
     ) -> Result<Response, ContractError> {
-        let new_admin = self.admin_transfer.load(deps.storage)?;
-        if info.sender != new_admin {
-            return Err(ContractError::Unauthorized {});
-        }
+        let proposal = self.load_active_admin_transfer(deps.as_ref(), &env)?;
+        // A pending renunciation can not be accepted by anyone, it must be
+        // confirmed by the admin via `ConfirmRenounceAdmin`.
+        let new_admin = match proposal.new_admin {
+            Some(new_admin) if new_admin == info.sender => new_admin,
+            _ => return Err(ContractError::Unauthorized {}),
+        };
         self.admin_transfer.remove(deps.storage);
+        let previous_admin = self.admin.get(deps.as_ref())?;
         let event = Event::new("apollo/vaults/autocompounding_vault").add_attributes(vec![
             ("action", "execute_accept_admin_transfer"),
             (
                 "previous_admin",
-                self
-                    .admin
-                    .get(deps.as_ref())?
-                    .unwrap_or_else(|| Addr::unchecked("")).as_ref(),
+                previous_admin
+                    .clone()
+                    .unwrap_or_else(|| Addr::unchecked(""))
+                    .as_ref(),
             ),
             ("new_admin", new_admin.as_ref()),
         ]);
-        self.admin.set(deps.branch(), Some(new_admin))?;
+        self.admin.set(deps.branch(), Some(new_admin.clone()))?;
+        self.change_log.record(
+            deps.storage,
+            &env.block,
+            &info.sender,
+            ChangeLogAction::AcceptAdminTransfer {
+                previous_admin,
+                new_admin,
+            },
+        )?;
         Ok(Response::new().add_event(event))
     }
 
-    /// Removes the initiated admin transfer. This can only be called by the
-    /// admin who initiated the admin transfer.
+    /// Confirm a pending admin renunciation proposed via `RenounceAdmin`. This
+    /// must be called by the admin before the proposal expires. After this the
+    /// vault has no admin, and the config can never be updated again.
+    pub fn execute_confirm_renounce_admin(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+    ) -> Result<Response, ContractError> {
+        self.admin.assert_admin(deps.as_ref(), &info.sender)?;
+        let proposal = self.load_active_admin_transfer(deps.as_ref(), &env)?;
+        if proposal.new_admin.is_some() {
+            return Err(ContractError::NoPendingAdminTransfer {});
+        }
+        self.admin_transfer.remove(deps.storage);
+        let previous_admin = self.admin.get(deps.as_ref())?;
+        self.admin.set(deps.branch(), None)?;
+        self.change_log.record(
+            deps.storage,
+            &env.block,
+            &info.sender,
+            ChangeLogAction::RenounceAdmin {
+                previous_admin: previous_admin.clone(),
+            },
+        )?;
+        let event = Event::new("apollo/vaults/autocompounding_vault").add_attributes(vec![
+            ("action", "execute_confirm_renounce_admin"),
+            (
+                "previous_admin",
+                previous_admin
+                    .unwrap_or_else(|| Addr::unchecked(""))
+                    .as_ref(),
+            ),
+        ]);
+        Ok(Response::new().add_event(event))
+    }
+
+    /// Removes the initiated admin transfer or admin renunciation. This can
+    /// only be called by the admin who initiated it.
     pub fn execute_drop_admin_transfer(
         &self,
         deps: DepsMut,
+        env: Env,
         info: MessageInfo,
     ) -> Result<Response, ContractError> {
         self.admin.assert_admin(deps.as_ref(), &info.sender)?;
+        let proposal = self.admin_transfer.may_load(deps.storage)?;
         self.admin_transfer.remove(deps.storage);
+        self.change_log.record(
+            deps.storage,
+            &env.block,
+            &info.sender,
+            ChangeLogAction::DropAdminTransfer { proposal },
+        )?;
         let event = Event::new("apollo/vaults/autocompounding_vault")
             .add_attributes(vec![("action", "execute_drop_admin_transfer")]);
         Ok(Response::new().add_event(event))
     }
 
+    /// Handles the reply to a callback dispatched with
+    /// [`crate::msg::CallbackMsg::into_sub_msg_with_data`], setting the data of
+    /// the callback response as the data of this response. Since the data of a
+    /// reply overrides the data of the original response, the callback data is
+    /// returned to the caller of the original message.
+    pub fn reply_callback_data(&self, data: Binary) -> Result<Response, ContractError> {
+        let res = parse_execute_response_data(&data)?;
+
+        let mut response = Response::new();
+        if let Some(data) = res.data {
+            response = response.set_data(data);
+        }
+        Ok(response)
+    }
+
     /// Update the config.
     pub fn execute_update_config(
         &self,
         deps: DepsMut,
+        env: Env,
         info: MessageInfo,
         updates: ConfigUpdates,
     ) -> Result<Response, ContractError> {
         self.admin.assert_admin(deps.as_ref(), &info.sender)?;
 
-        let new_config = self
-            .config
-            .load(deps.storage)?
-            .update(deps.as_ref(), updates.clone())?;
+        let old_config = self.config.load(deps.storage)?;
+        let new_config = old_config.clone().update(deps.as_ref(), updates.clone())?;
         self.config.save(deps.storage, &new_config)?;
+        self.change_log.record(
+            deps.storage,
+            &env.block,
+            &info.sender,
+            ChangeLogAction::UpdateConfig {
+                before: Box::new(old_config),
+                after: Box::new(new_config),
+            },
+        )?;
 
         let event = Event::new("apollo/vaults/autocompounding_vault").add_attributes(vec![
             ("action", "execute_update_config"),
@@ -177,3 +402,237 @@ where
         Ok(Response::default().add_event(event))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier};
+    use cosmwasm_std::{attr, MemoryStorage, OwnedDeps};
+    use cw_controllers::AdminError;
+    use test_case::test_case;
+
+    use super::*;
+
+    type TestVault<'a> = AutocompoundingVault<'a, (), (), ()>;
+
+    const ADMIN: &str = "admin";
+    const NEW_ADMIN: &str = "new_admin";
+
+    fn setup() -> OwnedDeps<MemoryStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        TestVault::default()
+            .admin
+            .set(deps.as_mut(), Some(Addr::unchecked(ADMIN)))
+            .unwrap();
+        deps
+    }
+
+    fn after_seconds(env: &Env, seconds: u64) -> Expiration {
+        Expiration::AtTime(env.block.time.plus_seconds(seconds))
+    }
+
+    #[test_case(None => Ok(()) ; "default expiration")]
+    #[test_case(Some(DEFAULT_ADMIN_TRANSFER_EXPIRATION) => Ok(()) ; "seven days")]
+    #[test_case(Some(MAX_ADMIN_TRANSFER_EXPIRATION) => Ok(()) ; "maximum expiration")]
+    #[test_case(Some(MAX_ADMIN_TRANSFER_EXPIRATION + 1) => Err(()) ; "above maximum expiration")]
+    #[test_case(Some(0) => Err(()) ; "already expired")]
+    fn test_update_admin_expiration(seconds: Option<u64>) -> Result<(), ()> {
+        let mut deps = setup();
+        let env = mock_env();
+        let vault = TestVault::default();
+
+        vault
+            .execute_update_admin(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(ADMIN, &[]),
+                NEW_ADMIN.to_string(),
+                seconds.map(|s| after_seconds(&env, s)),
+            )
+            .map(|_| ())
+            .map_err(|e| {
+                assert!(matches!(
+                    e,
+                    ContractError::InvalidAdminTransferExpiration { .. }
+                ))
+            })
+    }
+
+    #[test_case(Expiration::Never {} ; "never")]
+    #[test_case(Expiration::AtHeight(mock_env().block.height + 1) ; "at height")]
+    fn test_update_admin_rejects_open_ended_expiration(expiration: Expiration) {
+        let mut deps = setup();
+        let vault = TestVault::default();
+
+        let err = vault
+            .execute_update_admin(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(ADMIN, &[]),
+                NEW_ADMIN.to_string(),
+                Some(expiration),
+            )
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::InvalidAdminTransferExpiration { expires, max }
+                if expires == expiration && max == MAX_ADMIN_TRANSFER_EXPIRATION
+        ));
+        assert_eq!(vault.admin_transfer.may_load(&deps.storage).unwrap(), None);
+    }
+
+    #[test]
+    fn test_accept_admin_transfer() {
+        let mut deps = setup();
+        let env = mock_env();
+        let vault = TestVault::default();
+
+        let res = vault
+            .execute_update_admin(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(ADMIN, &[]),
+                NEW_ADMIN.to_string(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            res.events[0].attributes,
+            vec![
+                attr("action", "execute_update_admin"),
+                attr("previous_admin", ADMIN),
+                attr("new_admin", NEW_ADMIN),
+                attr(
+                    "expires",
+                    after_seconds(&env, DEFAULT_ADMIN_TRANSFER_EXPIRATION).to_string()
+                ),
+            ]
+        );
+
+        // Only the proposed admin can accept
+        let err = vault
+            .execute_accept_admin_transfer(deps.as_mut(), env.clone(), mock_info(ADMIN, &[]))
+            .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        vault
+            .execute_accept_admin_transfer(deps.as_mut(), env, mock_info(NEW_ADMIN, &[]))
+            .unwrap();
+        assert_eq!(
+            vault.admin.get(deps.as_ref()).unwrap(),
+            Some(Addr::unchecked(NEW_ADMIN))
+        );
+        assert_eq!(vault.admin_transfer.may_load(&deps.storage).unwrap(), None);
+    }
+
+    #[test]
+    fn test_accept_admin_transfer_after_expiry() {
+        let mut deps = setup();
+        let mut env = mock_env();
+        let vault = TestVault::default();
+
+        vault
+            .execute_update_admin(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(ADMIN, &[]),
+                NEW_ADMIN.to_string(),
+                None,
+            )
+            .unwrap();
+        let expires = after_seconds(&env, DEFAULT_ADMIN_TRANSFER_EXPIRATION);
+
+        env.block.time = env
+            .block
+            .time
+            .plus_seconds(DEFAULT_ADMIN_TRANSFER_EXPIRATION);
+        let err = vault
+            .execute_accept_admin_transfer(deps.as_mut(), env, mock_info(NEW_ADMIN, &[]))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::AdminTransferExpired { expires: e } if e == expires
+        ));
+        assert_eq!(
+            vault.admin.get(deps.as_ref()).unwrap(),
+            Some(Addr::unchecked(ADMIN))
+        );
+    }
+
+    #[test]
+    fn test_renounce_admin() {
+        let mut deps = setup();
+        let env = mock_env();
+        let vault = TestVault::default();
+
+        vault
+            .execute_renounce_admin(deps.as_mut(), env.clone(), mock_info(ADMIN, &[]), None)
+            .unwrap();
+
+        // A renunciation can't be accepted like a transfer
+        let err = vault
+            .execute_accept_admin_transfer(deps.as_mut(), env.clone(), mock_info(ADMIN, &[]))
+            .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        // Only the admin can confirm
+        let err = vault
+            .execute_confirm_renounce_admin(deps.as_mut(), env.clone(), mock_info(NEW_ADMIN, &[]))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::AdminError(AdminError::NotAdmin {})
+        ));
+
+        vault
+            .execute_confirm_renounce_admin(deps.as_mut(), env, mock_info(ADMIN, &[]))
+            .unwrap();
+        assert_eq!(vault.admin.get(deps.as_ref()).unwrap(), None);
+        assert_eq!(vault.admin_transfer.may_load(&deps.storage).unwrap(), None);
+    }
+
+    #[test]
+    fn test_confirm_renounce_admin_after_expiry() {
+        let mut deps = setup();
+        let mut env = mock_env();
+        let vault = TestVault::default();
+
+        vault
+            .execute_renounce_admin(deps.as_mut(), env.clone(), mock_info(ADMIN, &[]), None)
+            .unwrap();
+
+        env.block.time = env
+            .block
+            .time
+            .plus_seconds(DEFAULT_ADMIN_TRANSFER_EXPIRATION);
+        let err = vault
+            .execute_confirm_renounce_admin(deps.as_mut(), env, mock_info(ADMIN, &[]))
+            .unwrap_err();
+        assert!(matches!(err, ContractError::AdminTransferExpired { .. }));
+        assert_eq!(
+            vault.admin.get(deps.as_ref()).unwrap(),
+            Some(Addr::unchecked(ADMIN))
+        );
+    }
+
+    #[test]
+    fn test_confirm_renounce_admin_with_pending_transfer() {
+        let mut deps = setup();
+        let env = mock_env();
+        let vault = TestVault::default();
+
+        vault
+            .execute_update_admin(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(ADMIN, &[]),
+                NEW_ADMIN.to_string(),
+                None,
+            )
+            .unwrap();
+
+        let err = vault
+            .execute_confirm_renounce_admin(deps.as_mut(), env, mock_info(ADMIN, &[]))
+            .unwrap_err();
+        assert!(matches!(err, ContractError::NoPendingAdminTransfer {}));
+    }
+}
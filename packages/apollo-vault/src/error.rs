@@ -1,8 +1,9 @@
 use apollo_cw_asset::AssetInfo;
-use cosmwasm_std::{Coin, DivideByZeroError, OverflowError, StdError};
+use cosmwasm_std::{Coin, DivideByZeroError, OverflowError, StdError, Uint128};
 use cw_controllers::AdminError;
 use cw_dex::CwDexError;
 use cw_dex_router::ContractError as CwDexRouterError;
+use cw_utils::{Expiration, ParseReplyError};
 use cw_vault_token::CwTokenError;
 use thiserror::Error;
 
@@ -37,6 +38,9 @@ pub enum ContractError {
     #[error("{0}")]
     SemVer(#[from] semver::Error),
 
+    #[error("{0}")]
+    ParseReply(#[from] ParseReplyError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
@@ -73,6 +77,35 @@ pub enum ContractError {
         actual: Vec<Coin>,
     },
 
+    #[error("No pending admin transfer")]
+    NoPendingAdminTransfer {},
+
+    #[error("Admin transfer expired at {expires}")]
+    AdminTransferExpired { expires: Expiration },
+
+    #[error(
+        "Invalid admin transfer expiration {expires}. Must be a time in the future, at most {max} seconds from now"
+    )]
+    InvalidAdminTransferExpiration { expires: Expiration, max: u64 },
+
+    #[error("Contract name mismatch. Expected: {expected}, Actual: {actual}")]
+    ContractNameMismatch { expected: String, actual: String },
+
+    #[error("Cannot migrate from version {from} to lower version {to}")]
+    DowngradeNotSupported { from: String, to: String },
+
+    #[error("Unlocking positions are NFTs. Transfer the NFT or use WithdrawUnlocked instead.")]
+    UnlockingPositionIsNft {},
+
+    #[error("Unlock amount too small. Minimum unlock amount is {min} base tokens, got {actual}")]
+    UnlockAmountTooSmall { min: Uint128, actual: Uint128 },
+
+    #[error("Too many unlocking positions. {owner} already has the maximum of {max} open unlocking positions")]
+    TooManyUnlockingPositions { owner: String, max: u32 },
+
+    #[error("Redemption of {amount} base tokens exceeds the redemption buffer of {buffer} base tokens. Use Unlock and WithdrawUnlocked instead.")]
+    RedemptionBufferExceeded { amount: Uint128, buffer: Uint128 },
+
     #[error("{0}")]
     Generic(String),
 }
@@ -1,7 +1,7 @@
 use apollo_cw_asset::{Asset, AssetList};
 use cosmwasm_std::{
     attr, to_binary, Decimal, DepsMut, Env, Event, MessageInfo, Response, StdError, StdResult,
-    Uint128,
+    Storage, Uint128,
 };
 use cw_dex::traits::{Pool, Stake};
 use cw_vault_token::VaultToken;
@@ -216,24 +216,35 @@ where
         }
 
         // Update total_staked_base_tokens with amount from compound
-        self.base_vault
-            .total_staked_base_tokens
-            .update(deps.storage, |old_value| {
-                old_value
-                    .checked_add(amount_to_stake)
-                    .map_err(StdError::overflow)
-            })?;
-
-        // We stake the entire base_token_balance, which means we don't have to
-        // issue this call again in execute_callback_deposit.
-        let res = self
-            .staking
-            .load(deps.storage)?
-            .stake(deps.as_ref(), &env, amount_to_stake)?;
+        let total_staked_base_tokens =
+            self.base_vault
+                .total_staked_base_tokens
+                .update(deps.storage, |old_value| {
+                    old_value
+                        .checked_add(amount_to_stake)
+                        .map_err(StdError::overflow)
+                })?;
+
+        // Keep part of the base tokens unstaked to refill the redemption buffer,
+        // or stake the part of the buffer that is above its target.
+        let (amount_to_stake, redemption_buffer) =
+            self.refill_redemption_buffer(deps.storage, amount_to_stake, total_staked_base_tokens)?;
+
+        // We stake the entire base_token_balance, except for the redemption
+        // buffer, which means we don't have to issue this call again in
+        // execute_callback_deposit.
+        let res = if amount_to_stake.is_zero() {
+            Response::default()
+        } else {
+            self.staking
+                .load(deps.storage)?
+                .stake(deps.as_ref(), &env, amount_to_stake)?
+        };
 
         let event = Event::new("apollo/vaults/execute_compound").add_attributes(vec![
             attr("action", "execute_callback_stake"),
             attr("amount_to_stake", amount_to_stake.to_string()),
+            attr("redemption_buffer", redemption_buffer.to_string()),
             attr("base_token_balance", base_token_balance.to_string()),
             attr(
                 "base_token_balance_before",
@@ -244,3 +255,270 @@ where
         Ok(res.add_event(event))
     }
 }
+
+/// Functions for the redemption buffer. These only use the storage of the
+/// vault, so they have no trait bounds on the generics.
+impl<S, P, V> AutocompoundingVault<'_, S, P, V> {
+    /// Moves up to `amount` base tokens into the redemption buffer until it
+    /// holds `redemption_buffer_ratio` of `total_staked_base_tokens`, or
+    /// releases the part of the buffer that is above this target. Returns the
+    /// amount of base tokens to stake and the new size of the buffer.
+    fn refill_redemption_buffer(
+        &self,
+        storage: &mut dyn Storage,
+        amount: Uint128,
+        total_staked_base_tokens: Uint128,
+    ) -> StdResult<(Uint128, Uint128)> {
+        let ratio = self.config.load(storage)?.redemption_buffer_ratio;
+        let buffer = self
+            .redemption_buffer
+            .may_load(storage)?
+            .unwrap_or_default();
+        let target = total_staked_base_tokens * ratio;
+
+        let new_buffer = if buffer < target {
+            buffer + amount.min(target - buffer)
+        } else {
+            target
+        };
+        if new_buffer != buffer {
+            self.redemption_buffer.save(storage, &new_buffer)?;
+        }
+
+        Ok((amount + buffer - new_buffer, new_buffer))
+    }
+
+    /// Takes the part of an unlock of `amount` base tokens that exceeds the
+    /// base tokens locked in the staking module from the redemption buffer.
+    /// The buffer is included in `total_staked_base_tokens` but is not staked,
+    /// so without this unlocks larger than the locked base tokens would fail.
+    /// Must be called after `amount` has been deducted from
+    /// `total_staked_base_tokens`.
+    ///
+    /// Returns the amount of base tokens taken from the buffer, which must be
+    /// staked again before unlocking `amount`, or paid out of the contract's
+    /// balance.
+    #[cfg(any(feature = "lockup", feature = "force-unlock"))]
+    pub(crate) fn take_from_redemption_buffer(
+        &self,
+        storage: &mut dyn Storage,
+        amount: Uint128,
+    ) -> StdResult<Uint128> {
+        let buffer = self
+            .redemption_buffer
+            .may_load(storage)?
+            .unwrap_or_default();
+        if buffer.is_zero() {
+            return Ok(Uint128::zero());
+        }
+
+        // The base tokens of the open unlock batch have been deducted from
+        // `total_staked_base_tokens` but are still locked
+        let open_batch_amount = self
+            .unlock_batches
+            .open_batch(storage)?
+            .map(|batch| batch.base_token_amount)
+            .unwrap_or_default();
+        let locked = self
+            .base_vault
+            .total_staked_base_tokens
+            .load(storage)?
+            .checked_add(open_batch_amount)?
+            .checked_add(amount)?
+            .saturating_sub(buffer);
+
+        let from_buffer = amount.saturating_sub(locked).min(buffer);
+        if !from_buffer.is_zero() {
+            self.redemption_buffer
+                .save(storage, &(buffer - from_buffer))?;
+        }
+        Ok(from_buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::AssetInfo;
+    #[cfg(feature = "lockup")]
+    use cosmwasm_std::testing::mock_env;
+    use cosmwasm_std::testing::{mock_dependencies, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{Addr, OwnedDeps};
+    use cw_dex_router::helpers::CwDexRouterBase;
+    use liquidity_helper::LiquidityHelperBase;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::state::Config;
+
+    type TestVault<'a> = AutocompoundingVault<'a, (), (), ()>;
+
+    fn setup(
+        ratio: Decimal,
+        total_staked: u128,
+        buffer: u128,
+    ) -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        let vault = TestVault::default();
+        vault
+            .config
+            .save(
+                &mut deps.storage,
+                &Config {
+                    performance_fee: Decimal::zero(),
+                    treasury: Addr::unchecked("treasury"),
+                    router: CwDexRouterBase(Addr::unchecked("router")),
+                    reward_assets: vec![],
+                    reward_liquidation_target: AssetInfo::Native("uosmo".to_string()),
+                    force_withdraw_whitelist: vec![],
+                    liquidity_helper: LiquidityHelperBase(Addr::unchecked("liquidity_helper")),
+                    early_exit_penalty: Decimal::zero(),
+                    early_exit_penalty_decay: false,
+                    early_exit_penalty_to_treasury: false,
+                    auto_withdraw_keeper_fee: Decimal::zero(),
+                    min_unlock_amount: Uint128::zero(),
+                    max_unlocking_positions_per_owner: 0,
+                    redemption_buffer_ratio: ratio,
+                    instant_redemption_fee: Decimal::zero(),
+                },
+            )
+            .unwrap();
+        vault
+            .base_vault
+            .total_staked_base_tokens
+            .save(&mut deps.storage, &Uint128::new(total_staked))
+            .unwrap();
+        vault
+            .redemption_buffer
+            .save(&mut deps.storage, &Uint128::new(buffer))
+            .unwrap();
+        deps
+    }
+
+    #[test_case(Decimal::zero(), 0, 100 => (100, 0); "buffer disabled")]
+    #[test_case(Decimal::percent(10), 0, 100 => (90, 10); "fills buffer to target")]
+    #[test_case(Decimal::percent(10), 0, 5 => (0, 5); "amount below target")]
+    #[test_case(Decimal::percent(10), 10, 100 => (100, 10); "buffer at target")]
+    #[test_case(Decimal::percent(10), 50, 100 => (140, 10); "releases buffer above target")]
+    #[test_case(Decimal::percent(20), 15, 100 => (95, 20); "tops up buffer")]
+    fn test_refill_redemption_buffer(ratio: Decimal, buffer: u128, amount: u128) -> (u128, u128) {
+        // `total_staked_base_tokens` already includes `amount`
+        let mut deps = setup(ratio, 100, buffer);
+        let vault = TestVault::default();
+
+        let (to_stake, new_buffer) = vault
+            .refill_redemption_buffer(&mut deps.storage, Uint128::new(amount), Uint128::new(100))
+            .unwrap();
+        assert_eq!(
+            vault.redemption_buffer.load(&deps.storage).unwrap(),
+            new_buffer
+        );
+        // All base tokens are either staked or in the buffer
+        assert_eq!(to_stake + new_buffer, Uint128::new(amount + buffer));
+
+        (to_stake.u128(), new_buffer.u128())
+    }
+
+    #[cfg(feature = "lockup")]
+    #[test_case(100, 0, 50 => 0; "buffer empty")]
+    #[test_case(100, 10, 50 => 0; "unlock below locked")]
+    #[test_case(10, 10, 50 => 0; "unlock equal to locked")]
+    #[test_case(5, 10, 15 => 5; "unlock above locked")]
+    #[test_case(0, 10, 15 => 10; "unlock of more than buffer above locked")]
+    #[test_case(0, 25, 25 => 25; "unlock all base tokens")]
+    fn test_take_from_redemption_buffer(total_staked: u128, buffer: u128, amount: u128) -> u128 {
+        // `total_staked_base_tokens` is after the unlocked base tokens have been
+        // deducted
+        let mut deps = setup(Decimal::percent(20), total_staked, buffer);
+        let vault = TestVault::default();
+
+        let from_buffer = vault
+            .take_from_redemption_buffer(&mut deps.storage, Uint128::new(amount))
+            .unwrap();
+        assert_eq!(
+            vault.redemption_buffer.load(&deps.storage).unwrap(),
+            Uint128::new(buffer) - from_buffer
+        );
+
+        from_buffer.u128()
+    }
+
+    #[cfg(feature = "lockup")]
+    #[test_case(30, 15 => 0; "open batch covers unlock")]
+    #[test_case(5, 15 => 5; "buffer covers rest of unlock")]
+    #[test_case(0, 20 => 10; "buffer exhausted")]
+    fn test_take_from_redemption_buffer_with_open_unlock_batch(
+        open_batch_amount: u128,
+        amount: u128,
+    ) -> u128 {
+        let mut deps = setup(Decimal::percent(20), 0, 10);
+        let vault = TestVault::default();
+
+        // The base tokens of the open batch have been deducted from
+        // `total_staked_base_tokens` but are still locked, so the unlock can be
+        // taken from them before the buffer
+        vault
+            .unlock_batches
+            .set_epoch_duration(&mut deps.storage, 100)
+            .unwrap();
+        if open_batch_amount > 0 {
+            vault
+                .unlock_batches
+                .add_request(
+                    &mut deps.storage,
+                    &mock_env().block,
+                    &Addr::unchecked("owner"),
+                    Uint128::new(open_batch_amount),
+                )
+                .unwrap();
+        }
+
+        let from_buffer = vault
+            .take_from_redemption_buffer(&mut deps.storage, Uint128::new(amount))
+            .unwrap();
+        assert_eq!(
+            vault.redemption_buffer.load(&deps.storage).unwrap(),
+            Uint128::new(10) - from_buffer
+        );
+
+        from_buffer.u128()
+    }
+
+    #[cfg(feature = "lockup")]
+    #[test]
+    fn test_take_from_redemption_buffer_in_steps() {
+        // 20 base tokens are locked and 10 are in the buffer
+        let mut deps = setup(Decimal::percent(20), 30, 10);
+        let vault = TestVault::default();
+        let unlock = |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, amount: u128| {
+            vault
+                .base_vault
+                .total_staked_base_tokens
+                .update(&mut deps.storage, |total| -> StdResult<_> {
+                    Ok(total.checked_sub(Uint128::new(amount))?)
+                })
+                .unwrap();
+            let from_buffer = vault
+                .take_from_redemption_buffer(&mut deps.storage, Uint128::new(amount))
+                .unwrap();
+            (
+                from_buffer.u128(),
+                vault.redemption_buffer.load(&deps.storage).unwrap().u128(),
+            )
+        };
+
+        // Unlocks up to the locked base tokens leave the buffer untouched
+        assert_eq!(unlock(&mut deps, 15), (0, 10));
+        // The part above the locked base tokens is taken from the buffer
+        assert_eq!(unlock(&mut deps, 10), (5, 5));
+        // Unlocking the rest empties the buffer
+        assert_eq!(unlock(&mut deps, 5), (5, 0));
+        assert_eq!(
+            vault
+                .base_vault
+                .total_staked_base_tokens
+                .load(&deps.storage)
+                .unwrap(),
+            Uint128::zero()
+        );
+    }
+}
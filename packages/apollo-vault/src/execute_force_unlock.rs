@@ -1,18 +1,22 @@
 use std::collections::HashSet;
 
 use apollo_utils::responses::merge_responses;
-use cosmwasm_std::{attr, Addr, DepsMut, Env, Event, MessageInfo, Response, Uint128};
-use cw_dex::traits::{ForceUnlock, Pool};
+use cosmwasm_std::{
+    attr, to_binary, Addr, Deps, DepsMut, Env, Event, MessageInfo, Response, StdResult, Uint128,
+};
+use cw_dex::traits::{ForceUnlock, LockedStaking, Pool};
 use cw_vault_token::VaultToken;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use crate::error::ContractError;
+use crate::msg::{CallbackMsg, EarlyExitResponseData};
+use crate::state::{ChangeLogAction, Claim};
 use crate::AutocompoundingVault;
 
 impl<S, P, V> AutocompoundingVault<'_, S, P, V>
 where
-    S: ForceUnlock + Serialize + DeserializeOwned,
+    S: ForceUnlock + LockedStaking + Serialize + DeserializeOwned,
     P: Pool + Serialize + DeserializeOwned,
     V: VaultToken + Serialize + DeserializeOwned,
 {
@@ -20,12 +24,42 @@ where
     /// addresses in the event of liquidation.
     pub fn execute_force_redeem(
         &self,
-        mut deps: DepsMut,
+        deps: DepsMut,
         env: Env,
         info: MessageInfo,
         vault_token_amount: Uint128,
         recipient: Option<String>,
     ) -> Result<Response, ContractError> {
+        self.execute_force_redeem_with(
+            deps,
+            env,
+            info,
+            vault_token_amount,
+            recipient,
+            |deps, env, amount| {
+                Ok(self
+                    .staking
+                    .load(deps.storage)?
+                    .force_unlock(deps, env, None, amount)?)
+            },
+        )
+    }
+
+    /// Like `execute_force_redeem`, but the locked base tokens are force
+    /// unlocked by `force_unlock`. This allows vaults whose base tokens are
+    /// staked in several locks to split the force unlock between them.
+    pub fn execute_force_redeem_with<F>(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        vault_token_amount: Uint128,
+        recipient: Option<String>,
+        force_unlock: F,
+    ) -> Result<Response, ContractError>
+    where
+        F: FnOnce(Deps, &Env, Uint128) -> Result<Response, ContractError>,
+    {
         let cfg = self.config.load(deps.storage)?;
         let vault_token = self.base_vault.vault_token.load(deps.storage)?;
 
@@ -50,10 +84,14 @@ where
             vault_token_amount,
         )?;
 
-        // Call force withdraw on staked LP
-        let staking = self.staking.load(deps.storage)?;
-        let force_withdraw_res =
-            staking.force_unlock(deps.as_ref(), &env, None, lp_tokens_to_unlock)?;
+        // Call force withdraw on staked LP. The part of the base tokens that is
+        // not locked is paid out of the redemption buffer.
+        let from_buffer = self.take_from_redemption_buffer(deps.storage, lp_tokens_to_unlock)?;
+        let force_withdraw_res = if from_buffer == lp_tokens_to_unlock {
+            Response::default()
+        } else {
+            force_unlock(deps.as_ref(), &env, lp_tokens_to_unlock - from_buffer)?
+        };
 
         // Send the unstaked tokens to the recipient
         let send_res = self
@@ -65,6 +103,7 @@ where
             attr("recipient", recipient),
             attr("vault_token_amount", vault_token_amount),
             attr("redeem_amount", lp_tokens_to_unlock),
+            attr("from_redemption_buffer", from_buffer),
         ]);
 
         Ok(merge_responses(vec![burn_res, force_withdraw_res, send_res]).add_event(event))
@@ -74,7 +113,7 @@ where
     /// whitelisted addresses.
     pub fn execute_force_withdraw_unlocking(
         &self,
-        deps: DepsMut,
+        mut deps: DepsMut,
         env: Env,
         info: MessageInfo,
         lockup_id: u64,
@@ -93,13 +132,13 @@ where
             return Err(ContractError::Unauthorized {});
         }
 
+        // If the unlocking position is an NFT, the holder of the NFT owns it
+        self.sync_claim_owner_with_nft(deps.branch(), lockup_id)?;
+
         // Check if the lockup is expired. We must do this before calling
         // force_claim, as it may delete the claim if all of the tokens are claimed.
-        let is_expired = self
-            .claims
-            .query_claim_by_id(deps.as_ref(), lockup_id)?
-            .release_at
-            .is_expired(&env.block);
+        let claim = self.claims.query_claim_by_id(deps.as_ref(), lockup_id)?;
+        let is_expired = claim.release_at.is_expired(&env.block);
 
         // Get the claimed amount and update the claim in storage, deleting it if
         // all of the tokens are claimed, or updating it with the remaining amount.
@@ -107,6 +146,18 @@ where
             .claims
             .force_claim(deps.storage, &info, lockup_id, amount)?;
 
+        // Burn the NFT representing the claim if the claim was deleted,
+        // otherwise update its metadata with the remaining amount
+        let nft_msg = if claimed_amount == claim.base_token_amount {
+            self.burn_unlocking_position_nft_msg(deps.as_ref(), lockup_id)?
+        } else {
+            let remaining = Claim {
+                base_token_amount: claim.base_token_amount - claimed_amount,
+                ..claim
+            };
+            self.update_unlocking_position_nft_msg(deps.as_ref(), &remaining)?
+        };
+
         // If the lockup is not expired, call force withdraw to retrieve the
         // locked tokens.
         // If the lockup is already expired the tokens are already unlocked and
@@ -130,7 +181,192 @@ where
             attr("claimed_amount", claimed_amount),
         ]);
 
-        Ok(merge_responses(vec![force_withdraw_res, send_res]).add_event(event))
+        Ok(merge_responses(vec![force_withdraw_res, send_res])
+            .add_messages(nft_msg)
+            .add_event(event))
+    }
+
+    /// Cancel the unlocking position with ID `lockup_id` and re-enter the
+    /// vault with its base tokens. The claim is removed, the base tokens are
+    /// staked again and vault tokens are minted to the owner of the position at
+    /// the current share price. Must be called by the owner of the position.
+    ///
+    /// Since lockups can't be cancelled in the staking module, the base tokens
+    /// are retrieved by force unlocking the lockup, which requires the vault
+    /// to be whitelisted for force unlocking in the staking module. If the
+    /// lockup has already matured the base tokens are already in the contract.
+    pub fn execute_cancel_unlock(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: &MessageInfo,
+        lockup_id: u64,
+    ) -> Result<Response, ContractError> {
+        // If the unlocking position is an NFT, the holder of the NFT owns it
+        self.sync_claim_owner_with_nft(deps.branch(), lockup_id)?;
+
+        // Check if the lockup is expired before removing the claim
+        let claim = self.claims.query_claim_by_id(deps.as_ref(), lockup_id)?;
+        let is_expired = claim.release_at.is_expired(&env.block);
+
+        // Remove the claim. Returns an error if the sender doesn't own it.
+        let amount = self
+            .claims
+            .force_claim(deps.storage, info, lockup_id, None)?;
+
+        let burn_nft_msg = self.burn_unlocking_position_nft_msg(deps.as_ref(), lockup_id)?;
+
+        // If the lockup is not expired, force unlock it to retrieve the base
+        // tokens. The tokens are received after the balance is queried in
+        // `compound`, so they must not be counted as a deposit.
+        let (force_unlock_res, user_deposit_amount) = if !is_expired {
+            let staking = self.staking.load(deps.storage)?;
+            (
+                staking.force_unlock(deps.as_ref(), &env, Some(lockup_id), amount)?,
+                Uint128::zero(),
+            )
+        } else {
+            (Response::default(), amount)
+        };
+
+        // Compound. Also stakes the base tokens of the position
+        let compound_res = self.compound(deps, &env, user_deposit_amount)?;
+
+        // Mint vault tokens to the owner. The data of the callback response is
+        // forwarded in the reply, so that it becomes the data of this response.
+        let mint_res = Response::new().add_submessage(
+            CallbackMsg::MintVaultToken {
+                amount,
+                recipient: claim.owner.clone(),
+            }
+            .into_sub_msg_with_data(&env)?,
+        );
+
+        let event = Event::new("apollo/vaults/execute_force_unlock").add_attributes(vec![
+            attr("action", "execute_cancel_unlock"),
+            attr("owner", claim.owner),
+            attr("lockup_id", lockup_id.to_string()),
+            attr("base_token_amount", amount),
+        ]);
+
+        Ok(
+            merge_responses(vec![force_unlock_res, compound_res, mint_res])
+                .add_messages(burn_nft_msg)
+                .add_event(event),
+        )
+    }
+
+    /// Withdraw the unlocking position with ID `lockup_id` before it has
+    /// finished unlocking, paying the early exit penalty of the config. Can be
+    /// called by the owner of any unlocking position, but requires the vault
+    /// to be whitelisted for force unlocking in the staking module.
+    ///
+    /// The penalty is sent to the treasury or staked in the vault, depending
+    /// on the config.
+    ///
+    /// ## Arguments
+    /// - lockup_id: ID of the unlocking position to withdraw.
+    /// - recipient: Optional address to receive the base tokens. If `None` is
+    ///   provided `info.sender` will be used instead.
+    pub fn execute_early_exit_unlocking(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: &MessageInfo,
+        lockup_id: u64,
+        recipient: Option<String>,
+    ) -> Result<Response, ContractError> {
+        let cfg = self.config.load(deps.storage)?;
+        if cfg.early_exit_penalty.is_zero() {
+            return Err(ContractError::from("Early exit is not enabled"));
+        }
+
+        // Unwrap recipient or use caller's address
+        let recipient =
+            recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+
+        // If the unlocking position is an NFT, the holder of the NFT owns it
+        self.sync_claim_owner_with_nft(deps.branch(), lockup_id)?;
+
+        let claim = self.claims.query_claim_by_id(deps.as_ref(), lockup_id)?;
+        if claim.release_at.is_expired(&env.block) {
+            return Err(ContractError::from(
+                "Unlocking position has matured. Use WithdrawUnlocked instead.",
+            ));
+        }
+
+        // The penalty decays over the lockup duration that the position was
+        // unlocked with. Positions created before it was recorded use the
+        // current lockup duration.
+        let staking = self.staking.load(deps.storage)?;
+        let lockup_duration = match self.claims.lockup_duration(deps.storage, lockup_id)? {
+            Some(lockup_duration) => lockup_duration,
+            None => staking.get_lockup_duration(deps.as_ref())?,
+        };
+
+        // Remove the claim. Returns an error if the sender doesn't own it.
+        let amount = self
+            .claims
+            .force_claim(deps.storage, info, lockup_id, None)?;
+
+        let burn_nft_msg = self.burn_unlocking_position_nft_msg(deps.as_ref(), lockup_id)?;
+
+        let penalty_rate =
+            cfg.early_exit_penalty_rate(&claim.release_at, &lockup_duration, &env.block);
+        let penalty_amount = amount * penalty_rate;
+        let withdraw_amount = amount.checked_sub(penalty_amount)?;
+
+        // Retrieve the locked tokens
+        let force_unlock_res =
+            staking.force_unlock(deps.as_ref(), &env, Some(lockup_id), amount)?;
+
+        // Send the penalty to the treasury, or stake it in the vault, which
+        // increases the value of the vault tokens. The force unlocked tokens are
+        // received before the penalty is staked.
+        let penalty_res = if penalty_amount.is_zero() {
+            Response::default()
+        } else if cfg.early_exit_penalty_to_treasury {
+            self.base_vault
+                .send_base_tokens(deps.branch(), &cfg.treasury, penalty_amount)?
+        } else {
+            self.base_vault
+                .total_staked_base_tokens
+                .update(deps.storage, |total| -> StdResult<_> {
+                    Ok(total.checked_add(penalty_amount)?)
+                })?;
+            staking.stake(deps.as_ref(), &env, penalty_amount)?
+        };
+
+        // Send the rest of the tokens to the recipient
+        let send_res = if withdraw_amount.is_zero() {
+            Response::default()
+        } else {
+            self.base_vault
+                .send_base_tokens(deps, &recipient, withdraw_amount)?
+        };
+
+        let event = Event::new("apollo/vaults/execute_force_unlock").add_attributes(vec![
+            attr("action", "execute_early_exit_unlocking"),
+            attr("recipient", recipient.clone()),
+            attr("lockup_id", lockup_id.to_string()),
+            attr("base_token_amount", withdraw_amount),
+            attr("penalty_amount", penalty_amount),
+            attr("penalty_rate", penalty_rate.to_string()),
+        ]);
+
+        let data = EarlyExitResponseData {
+            lockup_id,
+            base_token_amount: withdraw_amount,
+            penalty_amount,
+            recipient,
+        };
+
+        Ok(
+            merge_responses(vec![force_unlock_res, send_res, penalty_res])
+                .add_messages(burn_nft_msg)
+                .add_event(event)
+                .set_data(to_binary(&data)?),
+        )
     }
 
     /// Update the whitelist of addresses that can force withdraw from the
@@ -138,6 +374,7 @@ where
     pub fn execute_update_force_withdraw_whitelist(
         &self,
         deps: DepsMut,
+        env: Env,
         info: MessageInfo,
         add_addresses: Vec<String>,
         remove_addresses: Vec<String>,
@@ -159,16 +396,26 @@ where
 
         //Update whitelist and remove duplicates
         let new_whitelist: Vec<Addr> = whitelist
-            .into_iter()
+            .iter()
             .filter(|x| !remove_addresses.contains(x))
-            .chain(add_addresses.into_iter())
+            .cloned()
+            .chain(add_addresses)
             .collect::<HashSet<_>>()
             .into_iter()
             .collect::<Vec<_>>();
 
         //Save new whitelist
-        cfg.force_withdraw_whitelist = new_whitelist;
+        cfg.force_withdraw_whitelist = new_whitelist.clone();
         self.config.save(deps.storage, &cfg)?;
+        self.change_log.record(
+            deps.storage,
+            &env.block,
+            &info.sender,
+            ChangeLogAction::UpdateForceWithdrawWhitelist {
+                before: whitelist,
+                after: new_whitelist,
+            },
+        )?;
 
         let event = Event::new("apollo/vaults/execute_force_unlock").add_attributes(vec![attr(
             "action",
@@ -1,5 +1,5 @@
 use apollo_utils::responses::merge_responses;
-use cosmwasm_std::{attr, Addr, DepsMut, Env, Event, MessageInfo, Response, Uint128};
+use cosmwasm_std::{attr, to_binary, Addr, DepsMut, Env, Event, MessageInfo, Response, Uint128};
 
 use cw_dex::traits::{Pool, Stake, Unstake};
 
@@ -7,7 +7,7 @@ use cw_vault_token::VaultToken;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::msg::CallbackMsg;
+use crate::msg::{CallbackMsg, RedeemResponseData};
 use crate::AutocompoundingVault;
 
 use crate::error::ContractError;
@@ -53,15 +53,16 @@ where
             attr("amount", vault_token_amount),
         ]);
 
-        // Compound then redeem
+        // Compound then redeem. The data of the callback response is forwarded
+        // in the reply, so that it becomes the data of this response.
         Ok(self
             .compound(deps, &env, Uint128::zero())?
-            .add_message(
+            .add_submessage(
                 CallbackMsg::Redeem {
                     amount: vault_token_amount,
                     recipient,
                 }
-                .into_cosmos_msg(&env)?,
+                .into_sub_msg_with_data(&env)?,
             )
             .add_event(event))
     }
@@ -95,11 +96,19 @@ where
 
         let event = Event::new("apollo/vaults/execute_redeem").add_attributes(vec![
             attr("action", "execute_callback_redeem"),
-            attr("recipient", recipient),
+            attr("recipient", recipient.clone()),
             attr("vault_token_amount", vault_token_amount),
             attr("lp_tokens_to_unstake", lp_tokens_to_unstake),
         ]);
 
-        Ok(merge_responses(vec![burn_res, unstake_res, send_res]).add_event(event))
+        let data = RedeemResponseData {
+            vault_token_amount,
+            base_token_amount: lp_tokens_to_unstake,
+            recipient,
+        };
+
+        Ok(merge_responses(vec![burn_res, unstake_res, send_res])
+            .add_event(event)
+            .set_data(to_binary(&data)?))
     }
 }
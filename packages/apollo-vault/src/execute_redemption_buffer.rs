@@ -0,0 +1,152 @@
+use apollo_utils::responses::merge_responses;
+use cosmwasm_std::{
+    attr, to_binary, Addr, Deps, DepsMut, Env, Event, MessageInfo, Response, StdError, StdResult,
+    Uint128,
+};
+use cw_dex::traits::{LockedStaking, Pool};
+use cw_vault_token::VaultToken;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::ContractError;
+use crate::msg::{CallbackMsg, RedeemResponseData};
+use crate::AutocompoundingVault;
+
+/// ExecuteMsg handlers for instant redemptions from lockup vaults. Base tokens
+/// are paid out of the redemption buffer, which is kept unstaked and refilled
+/// in `execute_callback_stake`.
+impl<S, P, V> AutocompoundingVault<'_, S, P, V>
+where
+    S: LockedStaking + Serialize + DeserializeOwned,
+    P: Pool + Serialize + DeserializeOwned,
+    V: VaultToken + Serialize + DeserializeOwned,
+{
+    /// Redeem vault tokens for base tokens from the redemption buffer, minus
+    /// the instant redemption fee. This will first compound the pending
+    /// rewards. If the vault token is a native token, the tokens must be sent
+    /// in the `info.funds` field.
+    ///
+    /// ## Arguments
+    /// - `vault_token_amount`: Amount of vault tokens to redeem.
+    /// - `recipient`: Optional address to receive the base tokens. If None, the
+    ///   `info.sender` will be used instead.
+    pub fn execute_instant_redeem(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: &MessageInfo,
+        vault_token_amount: Uint128,
+        recipient: Option<String>,
+    ) -> Result<Response, ContractError> {
+        let vault_token = self.base_vault.vault_token.load(deps.storage)?;
+
+        // Receive the vault token to the contract's balance, or validate that it was
+        // already received
+        vault_token.receive(deps.branch(), &env, info, vault_token_amount)?;
+
+        // Unwrap recipient or use caller's address
+        let recipient =
+            recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+
+        let event = Event::new("apollo/vaults/execute_redemption_buffer").add_attributes(vec![
+            attr("action", "execute_instant_redeem"),
+            attr("recipient", recipient.clone()),
+            attr("amount", vault_token_amount),
+        ]);
+
+        // Compound then redeem. The data of the callback response is forwarded
+        // in the reply, so that it becomes the data of this response.
+        Ok(self
+            .compound(deps, &env, Uint128::zero())?
+            .add_submessage(
+                CallbackMsg::RedeemFromBuffer {
+                    amount: vault_token_amount,
+                    recipient,
+                }
+                .into_sub_msg_with_data(&env)?,
+            )
+            .add_event(event))
+    }
+
+    /// Callback function to burn `vault_token_amount` vault tokens and send the
+    /// base tokens they represent, minus the instant redemption fee, from the
+    /// redemption buffer to `recipient`. Called from the
+    /// `execute_instant_redeem` function.
+    pub fn execute_callback_redeem_from_buffer(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        vault_token_amount: Uint128,
+        recipient: Addr,
+    ) -> Result<Response, ContractError> {
+        let fee_rate = self.config.load(deps.storage)?.instant_redemption_fee;
+
+        // Burn vault tokens and get the amount of base tokens to withdraw
+        let (base_token_amount, burn_res) = self.base_vault.burn_vault_tokens_for_base_tokens(
+            deps.branch(),
+            &env,
+            vault_token_amount,
+        )?;
+        let fee = base_token_amount * fee_rate;
+        let redeem_amount = base_token_amount - fee;
+
+        let buffer = self
+            .redemption_buffer
+            .may_load(deps.storage)?
+            .unwrap_or_default();
+        if redeem_amount > buffer {
+            return Err(ContractError::RedemptionBufferExceeded {
+                amount: redeem_amount,
+                buffer,
+            });
+        }
+        self.redemption_buffer
+            .save(deps.storage, &(buffer - redeem_amount))?;
+
+        // The fee stays in the redemption buffer and is added back to the staked
+        // base tokens, increasing the value of the remaining vault tokens
+        self.base_vault
+            .total_staked_base_tokens
+            .update(deps.storage, |old_value| {
+                old_value.checked_add(fee).map_err(StdError::overflow)
+            })?;
+
+        let send_res = self
+            .base_vault
+            .send_base_tokens(deps, &recipient, redeem_amount)?;
+
+        let event = Event::new("apollo/vaults/execute_redemption_buffer").add_attributes(vec![
+            attr("action", "execute_callback_redeem_from_buffer"),
+            attr("recipient", recipient.clone()),
+            attr("vault_token_amount", vault_token_amount),
+            attr("redeem_amount", redeem_amount),
+            attr("fee", fee),
+        ]);
+
+        let data = RedeemResponseData {
+            vault_token_amount,
+            base_token_amount: redeem_amount,
+            recipient,
+        };
+
+        Ok(merge_responses(vec![burn_res, send_res])
+            .add_event(event)
+            .set_data(to_binary(&data)?))
+    }
+
+    /// Returns the amount of base tokens that would be received when instantly
+    /// redeeming `vault_token_amount` vault tokens, after the instant
+    /// redemption fee. Does not check the size of the redemption buffer.
+    pub fn query_preview_instant_redeem(
+        &self,
+        deps: Deps,
+        vault_token_amount: Uint128,
+    ) -> StdResult<Uint128> {
+        let fee_rate = self.config.load(deps.storage)?.instant_redemption_fee;
+        let base_token_amount = self
+            .base_vault
+            .query_simulate_withdraw(deps, vault_token_amount)?;
+
+        Ok(base_token_amount - base_token_amount * fee_rate)
+    }
+}
@@ -1,6 +1,8 @@
 use apollo_utils::assets::receive_asset;
 use apollo_utils::responses::merge_responses;
-use cosmwasm_std::{attr, Addr, Coin, DepsMut, Env, Event, MessageInfo, Response, Uint128};
+use cosmwasm_std::{
+    attr, to_binary, Addr, Coin, DepsMut, Env, Event, MessageInfo, Response, Uint128,
+};
 
 use cw_dex::traits::{Pool, Stake};
 
@@ -9,7 +11,7 @@ use cw_vault_token::VaultToken;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::msg::CallbackMsg;
+use crate::msg::{CallbackMsg, DepositResponseData};
 use crate::AutocompoundingVault;
 
 use crate::error::ContractError;
@@ -68,16 +70,62 @@ where
             AssetInfo::Native(_) => amount,
         };
 
+        let deposit_res = self.deposit(deps, &env, amount, user_deposit_amount, recipient)?;
+
+        Ok(merge_responses(vec![receive_res, deposit_res]))
+    }
+
+    /// Deposit cw20 base tokens that were sent to the vault with the `Send`
+    /// message of the base token, so that no allowance is needed. This is
+    /// called from the `Receive` hook of the vault with the `sender` and
+    /// `amount` of the `Cw20ReceiveMsg`.
+    ///
+    /// The `info.sender` must be the cw20 base token. Vault tokens are minted
+    /// to `recipient`, or to `sender` if `None`.
+    pub fn execute_receive_deposit(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: &MessageInfo,
+        sender: String,
+        amount: Uint128,
+        recipient: Option<String>,
+    ) -> Result<Response, ContractError> {
+        // Check that the received tokens are the base token
+        match self.base_vault.base_token.load(deps.storage)? {
+            AssetInfo::Cw20(addr) if addr == info.sender => {}
+            _ => return Err(ContractError::InvalidDepositAsset {}),
+        }
+
+        // Unwrap recipient or use the sender of the base tokens
+        let recipient = deps.api.addr_validate(&recipient.unwrap_or(sender))?;
+
+        // The base tokens were transferred to the contract before this message,
+        // so they are already part of the contract balance.
+        self.deposit(deps, &env, amount, amount, recipient)
+    }
+
+    /// Compounds, stakes `user_deposit_amount` of the deposited base tokens
+    /// and mints vault tokens for `amount` base tokens to `recipient`.
+    fn deposit(
+        &self,
+        deps: DepsMut,
+        env: &Env,
+        amount: Uint128,
+        user_deposit_amount: Uint128,
+        recipient: Addr,
+    ) -> Result<Response, ContractError> {
         // Compound. Also stakes the users deposit
-        let compound_res = self.compound(deps, &env, user_deposit_amount)?;
+        let compound_res = self.compound(deps, env, user_deposit_amount)?;
 
-        // Mint vault tokens to recipient
-        let mint_res = Response::new().add_message(
+        // Mint vault tokens to recipient. The data of the callback response is
+        // forwarded in the reply, so that it becomes the data of this response.
+        let mint_res = Response::new().add_submessage(
             CallbackMsg::MintVaultToken {
                 amount,
                 recipient: recipient.clone(),
             }
-            .into_cosmos_msg(&env)?,
+            .into_sub_msg_with_data(env)?,
         );
 
         let event = Event::new("apollo/vaults/execute_staking").add_attributes(vec![
@@ -87,7 +135,7 @@ where
         ]);
 
         // Merge responses and add message to mint vault token
-        Ok(merge_responses(vec![receive_res, compound_res, mint_res]).add_event(event))
+        Ok(merge_responses(vec![compound_res, mint_res]).add_event(event))
     }
 
     /// Callback function to mint `amount` of vault tokens to
@@ -122,9 +170,15 @@ where
             attr("mint_amount", vault_tokens),
         ]);
 
+        let data = DepositResponseData {
+            vault_token_amount: vault_tokens,
+            recipient: vault_token_recipient.clone(),
+        };
+
         // Return Response with message to mint vault tokens
         Ok(vault_token
             .mint(deps, &env, &vault_token_recipient, vault_tokens)?
-            .add_event(event))
+            .add_event(event)
+            .set_data(to_binary(&data)?))
     }
 }
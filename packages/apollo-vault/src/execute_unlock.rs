@@ -1,25 +1,25 @@
 use crate::error::ContractError;
-use crate::msg::CallbackMsg;
+use crate::msg::{
+    CallbackMsg, ProcessMaturedClaimsResponseData, UnlockingPositionOperatorResponse,
+    WithdrawAllUnlockedResponseData, WithdrawUnlockedResponseData,
+};
+use crate::state::{
+    UnlockingPositionCursor, UnlockingPositionFilter, UnlockingTotals, DEFAULT_REINDEX_LIMIT,
+};
 use crate::AutocompoundingVault;
 use apollo_utils::responses::merge_responses;
 use cosmwasm_std::{
-    attr, Addr, Coin, Deps, DepsMut, Env, Event, MessageInfo, Response, StdResult, SubMsg, Uint128,
+    attr, to_binary, Addr, Deps, DepsMut, Env, Event, MessageInfo, Response, StdResult, Uint128,
 };
 use cw_dex::traits::{LockedStaking, Pool};
-use cw_storage_plus::Item;
+use cw_utils::Expiration;
 use cw_vault_standard::extensions::lockup::{
     UnlockingPosition, UNLOCKING_POSITION_ATTR_KEY, UNLOCKING_POSITION_CREATED_EVENT_TYPE,
 };
 use cw_vault_token::VaultToken;
-use osmosis_std::shim::Duration;
-use osmosis_std::types::osmosis::lockup::MsgLockTokens;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-/// Temporary reply id for the workaround to get the lock id of unlocking positions.
-pub const OSMOSIS_TEMP_WORKAROUND_LOCK_TOKENS_REPLY_ID: u64 = 932;
-const DURATION_COUNTER: Item<i32> = Item::new("duration_counter");
-
 /// ExecuteMsg handlers related to vaults that have a lockup. Here we have the
 /// trait bound Unlock on the S generic.
 impl<S, P, V> AutocompoundingVault<'_, S, P, V>
@@ -29,7 +29,8 @@ where
     V: VaultToken + Serialize + DeserializeOwned,
 {
     /// Withdraw the base tokens from a locked position that has finished
-    /// unlocking.
+    /// unlocking. If unlocking positions are NFTs, the sender must hold the
+    /// NFT, which is burned by the vault.
     ///
     /// ## Arguments
     /// - lockup_id: ID of the lockup position to withdraw from.
@@ -37,7 +38,7 @@ where
     ///   `None` is provided `info.sender` will be used instead.
     pub fn execute_withdraw_unlocked(
         &self,
-        deps: DepsMut,
+        mut deps: DepsMut,
         env: Env,
         info: &MessageInfo,
         lockup_id: u64,
@@ -47,10 +48,15 @@ where
         let recipient =
             recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
 
+        // If the unlocking position is an NFT, the holder of the NFT owns it
+        self.sync_claim_owner_with_nft(deps.branch(), lockup_id)?;
+
         let sum_to_claim = self
             .claims
             .claim_tokens(deps.storage, &env.block, info, lockup_id)?;
 
+        let burn_nft_msg = self.burn_unlocking_position_nft_msg(deps.as_ref(), lockup_id)?;
+
         let res = self.staking.load(deps.storage)?.withdraw_unlocked(
             deps.as_ref(),
             &env,
@@ -64,80 +70,426 @@ where
             attr("amount", sum_to_claim),
         ]);
 
+        let data = WithdrawUnlockedResponseData {
+            lockup_id,
+            base_token_amount: sum_to_claim,
+            recipient: recipient.clone(),
+        };
+
+        Ok(merge_responses(vec![
+            res,
+            self.base_vault
+                .send_base_tokens(deps, &recipient, sum_to_claim)?,
+        ])
+        .add_messages(burn_nft_msg)
+        .add_event(event)
+        .set_data(to_binary(&data)?))
+    }
+
+    /// Withdraw the base tokens from all of the sender's locked positions that
+    /// have finished unlocking, in a single withdrawal and transfer.
+    ///
+    /// ## Arguments
+    /// - recipient: Optional address to receive the withdrawn base tokens. If
+    ///   `None` is provided `info.sender` will be used instead.
+    /// - limit: Optional maximum number of positions to withdraw from.
+    pub fn execute_withdraw_all_unlocked(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: &MessageInfo,
+        recipient: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Response, ContractError> {
+        // Unwrap recipient or use caller's address
+        let recipient =
+            recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+
+        // The owner index is not updated when unlocking position NFTs are
+        // transferred, so this can only be used if the positions are not NFTs
+        self.assert_no_unlocking_position_nft(deps.as_ref())?;
+
+        let (lockup_ids, sum_to_claim) =
+            self.claims
+                .claim_all_matured(deps.storage, &env.block, &info.sender, limit)?;
+
+        let res = self.staking.load(deps.storage)?.withdraw_unlocked(
+            deps.as_ref(),
+            &env,
+            sum_to_claim,
+        )?;
+
+        let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
+            attr("action", "execute_withdraw_all_unlocked"),
+            attr("recipient", recipient.clone()),
+            attr(
+                "lockup_ids",
+                lockup_ids
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            attr("amount", sum_to_claim),
+        ]);
+
+        let data = WithdrawAllUnlockedResponseData {
+            lockup_ids,
+            base_token_amount: sum_to_claim,
+            recipient: recipient.clone(),
+        };
+
         Ok(merge_responses(vec![
             res,
             self.base_vault
                 .send_base_tokens(deps, &recipient, sum_to_claim)?,
         ])
-        .add_event(event))
+        .add_event(event)
+        .set_data(to_binary(&data)?))
+    }
+
+    /// Opt the sender in to or out of auto-withdrawal, in which keepers send
+    /// the base tokens of the sender's matured unlocking positions to the
+    /// sender with `ProcessMaturedClaims`.
+    pub fn execute_set_auto_withdraw(
+        &self,
+        deps: DepsMut,
+        info: &MessageInfo,
+        enabled: bool,
+    ) -> Result<Response, ContractError> {
+        // The owner stored in the claims is not updated when unlocking position
+        // NFTs are transferred, so the base tokens could be sent to a previous
+        // holder
+        self.assert_no_unlocking_position_nft(deps.as_ref())?;
+
+        self.claims
+            .set_auto_withdraw(deps.storage, &info.sender, enabled)?;
+
+        let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
+            attr("action", "execute_set_auto_withdraw"),
+            attr("owner", info.sender.to_string()),
+            attr("enabled", enabled.to_string()),
+        ]);
+
+        Ok(Response::default().add_event(event))
+    }
+
+    /// Send the base tokens of matured unlocking positions to their owners,
+    /// for owners that opted in to auto-withdrawal. Can be called by anyone.
+    /// The keeper fee of the config is deducted from each position and sent
+    /// to the sender.
+    ///
+    /// ## Arguments
+    /// - limit: Optional maximum number of unlocking positions to withdraw.
+    pub fn execute_process_matured_claims(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: &MessageInfo,
+        limit: Option<u32>,
+    ) -> Result<Response, ContractError> {
+        self.assert_no_unlocking_position_nft(deps.as_ref())?;
+        let keeper_fee = self.config.load(deps.storage)?.auto_withdraw_keeper_fee;
+
+        let claims = self
+            .claims
+            .matured_auto_withdraw_claims(deps.storage, &env.block, limit)?;
+        if claims.is_empty() {
+            return Err(ContractError::from("No matured claims to process"));
+        }
+
+        let mut lockup_ids = Vec::with_capacity(claims.len());
+        let mut total_amount = Uint128::zero();
+        let mut total_fee = Uint128::zero();
+        let mut send_responses = vec![];
+        for claim in claims {
+            self.claims
+                .decrease_claim(deps.storage, claim.id, claim.base_token_amount)?;
+
+            let fee = claim.base_token_amount * keeper_fee;
+            send_responses.push(self.base_vault.send_base_tokens(
+                deps.branch(),
+                &claim.owner,
+                claim.base_token_amount.checked_sub(fee)?,
+            )?);
+
+            lockup_ids.push(claim.id);
+            total_amount = total_amount.checked_add(claim.base_token_amount)?;
+            total_fee = total_fee.checked_add(fee)?;
+        }
+
+        let withdraw_res = self.staking.load(deps.storage)?.withdraw_unlocked(
+            deps.as_ref(),
+            &env,
+            total_amount,
+        )?;
+
+        // Send the keeper fees to the sender
+        if !total_fee.is_zero() {
+            send_responses.push(self.base_vault.send_base_tokens(
+                deps.branch(),
+                &info.sender,
+                total_fee,
+            )?);
+        }
+
+        let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
+            attr("action", "execute_process_matured_claims"),
+            attr("keeper", info.sender.to_string()),
+            attr(
+                "lockup_ids",
+                lockup_ids
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            attr("amount", total_amount),
+            attr("keeper_fee", total_fee),
+        ]);
+
+        let data = ProcessMaturedClaimsResponseData {
+            lockup_ids,
+            base_token_amount: total_amount,
+            keeper_fee: total_fee,
+        };
+
+        Ok(
+            merge_responses([vec![withdraw_res], send_responses].concat())
+                .add_event(event)
+                .set_data(to_binary(&data)?),
+        )
+    }
+
+    /// Transfer ownership of the unlocking position with ID `lockup_id` to
+    /// `new_owner`. Must be called by the owner of the position or by an
+    /// operator approved by the owner.
+    pub fn execute_transfer_unlocking_position(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: &MessageInfo,
+        lockup_id: u64,
+        new_owner: String,
+    ) -> Result<Response, ContractError> {
+        // Unlocking position NFTs must be transferred on the NFT contract
+        self.assert_no_unlocking_position_nft(deps.as_ref())?;
+
+        let new_owner = deps.api.addr_validate(&new_owner)?;
+        let previous_owner = self
+            .claims
+            .query_claim_by_id(deps.as_ref(), lockup_id)?
+            .owner;
+
+        let claim = self.claims.transfer_claim(
+            deps.storage,
+            &env.block,
+            &info.sender,
+            lockup_id,
+            &new_owner,
+        )?;
+
+        let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
+            attr("action", "execute_transfer_unlocking_position"),
+            attr("sender", info.sender.to_string()),
+            attr("previous_owner", previous_owner),
+            attr("new_owner", new_owner),
+            attr("lockup_id", lockup_id.to_string()),
+        ]);
+
+        Ok(Response::default()
+            .add_event(event)
+            .set_data(to_binary(&claim)?))
+    }
+
+    /// Approve `operator` to transfer all of the sender's unlocking positions
+    /// until `expires`. If `expires` is `None` the approval never expires.
+    pub fn execute_approve_unlocking_position_operator(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: &MessageInfo,
+        operator: String,
+        expires: Option<Expiration>,
+    ) -> Result<Response, ContractError> {
+        let operator = deps.api.addr_validate(&operator)?;
+        let expires = expires.unwrap_or(Expiration::Never {});
+
+        self.claims
+            .approve_operator(deps.storage, &env.block, &info.sender, &operator, expires)?;
+
+        let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
+            attr("action", "execute_approve_unlocking_position_operator"),
+            attr("owner", info.sender.to_string()),
+            attr("operator", operator),
+            attr("expires", expires.to_string()),
+        ]);
+
+        Ok(Response::default().add_event(event))
+    }
+
+    /// Revoke the approval of `operator` to transfer the sender's unlocking
+    /// positions.
+    pub fn execute_revoke_unlocking_position_operator(
+        &self,
+        deps: DepsMut,
+        info: &MessageInfo,
+        operator: String,
+    ) -> Result<Response, ContractError> {
+        let operator = deps.api.addr_validate(&operator)?;
+
+        self.claims
+            .revoke_operator(deps.storage, &info.sender, &operator)?;
+
+        let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
+            attr("action", "execute_revoke_unlocking_position_operator"),
+            attr("owner", info.sender.to_string()),
+            attr("operator", operator),
+        ]);
+
+        Ok(Response::default().add_event(event))
+    }
+
+    /// Assert that an unlock of `vault_token_amount` vault tokens to `owner`
+    /// respects the minimum unlock amount and the maximum number of open
+    /// unlocking positions per owner. The base token amount is calculated
+    /// before compounding, so it can only be lower than the amount that is
+    /// unlocked. Requests in epoch mode do not create unlocking positions for
+    /// their owners, so the maximum number of positions is not checked.
+    fn assert_unlock_limits(
+        &self,
+        deps: Deps,
+        owner: &Addr,
+        vault_token_amount: Uint128,
+        epoch_mode: bool,
+    ) -> Result<(), ContractError> {
+        let cfg = self.config.load(deps.storage)?;
+
+        if !cfg.min_unlock_amount.is_zero() {
+            let base_token_amount = self.base_vault.calculate_base_tokens(
+                vault_token_amount,
+                self.base_vault
+                    .total_staked_base_tokens
+                    .load(deps.storage)?,
+                self.base_vault
+                    .vault_token
+                    .load(deps.storage)?
+                    .query_total_supply(deps)?,
+            )?;
+            if base_token_amount < cfg.min_unlock_amount {
+                return Err(ContractError::UnlockAmountTooSmall {
+                    min: cfg.min_unlock_amount,
+                    actual: base_token_amount,
+                });
+            }
+        }
+
+        let max = cfg.max_unlocking_positions_per_owner;
+        if max > 0
+            && !epoch_mode
+            && self
+                .claims
+                .count_claims_for_owner(deps.storage, owner, max as usize)
+                >= max as usize
+        {
+            return Err(ContractError::TooManyUnlockingPositions {
+                owner: owner.to_string(),
+                max,
+            });
+        }
+
+        Ok(())
     }
 
     /// Burn `vault_token_amount` vault tokens and start the unlocking process.
     /// If the vault token is a native token it must be sent in the `info.funds`
     /// field.
+    ///
+    /// ## Arguments
+    /// - vault_token_amount: The amount of vault tokens to unlock.
+    /// - owner: Optional address of the owner of the unlocking position. If
+    ///   `None` is provided `info.sender` will be used instead. If it is
+    ///   another address, `info.sender` must be an approved operator of the
+    ///   owner. See [`Self::assert_can_unlock_to`].
     pub fn execute_unlock(
         &self,
         mut deps: DepsMut,
         env: Env,
         info: &MessageInfo,
         vault_token_amount: Uint128,
+        owner: Option<String>,
     ) -> Result<Response, ContractError> {
-        let vault_token = self.base_vault.vault_token.load(deps.storage)?;
+        // Unwrap owner or use caller's address
+        let owner = owner.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+        self.assert_can_unlock_to(deps.as_ref(), &env, &owner, &info.sender)?;
 
-        // WARNING!!! THIS IS A HACK TO GET THE UNLOCKING POSITION ID
-        // Create a new unlocking position with a new duration. This will be called as a submessage
-        // and is handled in the reply handler to get the id of the new unlocking position, which
-        // will be the id of the new lock minus one. To ensure that a new lock is created, we need
-        // to create a lock tokens with a new duration that has not been used before by the vault.
-        //
-        // A proper solution will be possible after the next chain upgrade, around 2023-03-13.
-
-        let base_token_denom = self.base_vault.base_token.load(deps.storage)?.to_string();
-        let duration_count = DURATION_COUNTER.load(deps.storage).unwrap_or_default() + 1;
-        DURATION_COUNTER.save(deps.storage, &duration_count)?;
-
-        let create_new_lock_msg = MsgLockTokens {
-            owner: env.contract.address.to_string(),
-            duration: Some(Duration {
-                seconds: 0,
-                nanos: duration_count,
-            }),
-            coins: vec![Coin::new(1u128, base_token_denom).into()],
-        };
+        let vault_token = self.base_vault.vault_token.load(deps.storage)?;
 
         // Receive the vault token to the contract's balance, or validate that it was
         // already received
         vault_token.receive(deps.branch(), &env, info, vault_token_amount)?;
 
+        let epoch_mode = self.unlock_batches.epoch_duration(deps.storage)?.is_some();
+
+        self.assert_unlock_limits(deps.as_ref(), &owner, vault_token_amount, epoch_mode)?;
+
         // First compound the vault
         let compound_res = self.compound(deps, &env, Uint128::zero())?;
 
         // Continue with the unlock after compounding
         let unlock_msg = CallbackMsg::Unlock {
-            owner: info.sender.clone(),
+            owner: owner.clone(),
             vault_token_amount,
-        }
-        .into_cosmos_msg(&env)?;
-
-        // Store the claim for base_tokens
-        let store_claim_msg = CallbackMsg::SaveClaim {}.into_cosmos_msg(&env)?;
+        };
 
         let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
             attr("action", "execute_unlock"),
-            attr("owner", info.sender.to_string()),
+            attr("sender", info.sender.to_string()),
+            attr("owner", owner.to_string()),
             attr("amount", vault_token_amount),
         ]);
 
+        // In epoch mode the request is added to the open unlock batch by the
+        // callback, which sets the batch request as data.
+        if epoch_mode {
+            return Ok(compound_res
+                .add_submessage(unlock_msg.into_sub_msg_with_data(&env)?)
+                .add_event(event));
+        }
+        let unlock_msg = unlock_msg.into_cosmos_msg(&env)?;
+
+        // Store the claim for base_tokens. The data of the callback response is
+        // forwarded in the reply, so that it becomes the data of this response.
+        let store_claim_msg = CallbackMsg::SaveClaim {}.into_sub_msg_with_data(&env)?;
+
         Ok(compound_res
             .add_message(unlock_msg)
-            .add_submessage(SubMsg::reply_on_success(
-                create_new_lock_msg,
-                OSMOSIS_TEMP_WORKAROUND_LOCK_TOKENS_REPLY_ID,
-            ))
-            .add_message(store_claim_msg)
+            .add_submessage(store_claim_msg)
             .add_event(event))
     }
 
+    /// Sets the ID of the pending claim to `lockup_id`, the ID of the lockup
+    /// created by the staking module when unlocking. Should be called when
+    /// handling the reply to the unlock message, before the `SaveClaim`
+    /// callback commits the claim.
+    pub fn set_pending_claim_id(
+        &self,
+        deps: DepsMut,
+        lockup_id: u64,
+    ) -> Result<Response, ContractError> {
+        let mut pending_claim = self.claims.get_pending_claim(deps.storage)?;
+        pending_claim.id = lockup_id;
+        self.claims
+            .set_pending_claim(deps.storage, &pending_claim)?;
+
+        let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
+            attr("action", "set_pending_claim_id"),
+            attr("lockup_id", lockup_id.to_string()),
+        ]);
+
+        Ok(Response::default().add_event(event))
+    }
+
     /// Transfer vault tokens to the vault to start unlocking a locked position.
     pub fn execute_callback_unlock(
         &self,
@@ -156,20 +508,40 @@ where
             vault_token_amount,
         )?;
 
-        let expiration = self
-            .staking
-            .load(deps.storage)?
-            .get_lockup_duration(deps.as_ref())?
-            .after(&env.block);
+        // In epoch mode the base tokens are unlocked later together with the
+        // other requests in the open unlock batch
+        if self.unlock_batches.epoch_duration(deps.storage)?.is_some() {
+            let request_res =
+                self.add_unlock_batch_request(deps, &env, &owner, lp_tokens_to_unlock)?;
+            // Keep the data of the batch request in the merged response
+            let mut res = merge_responses(vec![burn_res, request_res.clone()]);
+            res.data = request_res.data;
+            return Ok(res);
+        }
+
+        let lockup_duration = staking.get_lockup_duration(deps.as_ref())?;
 
-        // Create a pending claim for using the default ID.
+        // Create a pending claim using the default ID. If the staking module
+        // issues its own lockup IDs, the ID is replaced with
+        // `set_pending_claim_id` when handling the reply to the unlock message.
         self.claims.create_pending_claim(
             deps.storage,
             &owner,
             lp_tokens_to_unlock,
-            expiration,
+            lockup_duration.after(&env.block),
             None,
         )?;
+        self.claims
+            .set_pending_lockup_duration(deps.storage, &lockup_duration)?;
+
+        // Stake the base tokens taken from the redemption buffer again, so that
+        // all of the base tokens can be unlocked from the staking module
+        let from_buffer = self.take_from_redemption_buffer(deps.storage, lp_tokens_to_unlock)?;
+        let stake_res = if from_buffer.is_zero() {
+            Response::default()
+        } else {
+            staking.stake(deps.as_ref(), &env, from_buffer)?
+        };
 
         // Unstake response
         let unlock_res = staking.unlock(deps.as_ref(), &env, lp_tokens_to_unlock)?;
@@ -181,12 +553,13 @@ where
             ("owner", owner.as_ref()),
             ("vault_token_amount", &vault_token_amount.to_string()),
             ("lp_tokens_to_unlock", &lp_tokens_to_unlock.to_string()),
+            ("from_redemption_buffer", &from_buffer.to_string()),
         ]);
 
-        // Create response.
-        // We also send the lockup_id back in the data field so that the caller
-        // can read it easily in a SubMsg reply.
-        Ok(merge_responses(vec![burn_res, unlock_res]).add_event(event))
+        // The lockup ID is not known yet at this point, so the unlocking
+        // position is returned in the data field of the response to the
+        // `SaveClaim` callback instead.
+        Ok(merge_responses(vec![burn_res, stake_res, unlock_res]).add_event(event))
     }
 
     /// Callback function to save a pending claim to the claims store.
@@ -196,18 +569,49 @@ where
         // Commit the pending claim
         self.claims.commit_pending_claim(deps.storage)?;
 
+        // Mint an NFT representing the claim, if unlocking positions are NFTs
+        let mint_nft_msg = self.mint_unlocking_position_nft_msg(deps.as_ref(), &claim)?;
+
         let event = Event::new(UNLOCKING_POSITION_CREATED_EVENT_TYPE)
             .add_attribute("action", "execute_callback_save_claim")
             .add_attribute("unlock_amount", claim.base_token_amount.to_string())
-            .add_attribute("owner", claim.owner)
+            .add_attribute("owner", claim.owner.to_string())
             .add_attribute("release_at", claim.release_at.to_string())
             .add_attribute(UNLOCKING_POSITION_ATTR_KEY, claim.id.to_string());
 
+        // Return the unlocking position in the data field so that the caller
+        // of `Unlock` can read the lockup ID in a SubMsg reply.
+        Ok(Response::default()
+            .add_messages(mint_nft_msg)
+            .add_event(event)
+            .set_data(to_binary(&claim)?))
+    }
+
+    /// Reindex the next `limit` unlocking positions of the reindex started in
+    /// a migration. Can be called by anyone.
+    pub fn execute_reindex_claims(
+        &self,
+        deps: DepsMut,
+        info: &MessageInfo,
+        limit: Option<u32>,
+    ) -> Result<Response, ContractError> {
+        let finished = self
+            .claims
+            .reindex(deps.storage, limit.unwrap_or(DEFAULT_REINDEX_LIMIT))?;
+
+        let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
+            attr("action", "execute_reindex_claims"),
+            attr("sender", info.sender.to_string()),
+            attr("finished", finished.to_string()),
+        ]);
+
         Ok(Response::default().add_event(event))
     }
 
     /// Query unlocking positions for `owner`. Optional arguments `start_after`
-    /// and `limit` can be used for pagination.
+    /// and `limit` can be used for pagination. If unlocking positions are NFTs,
+    /// the owner is the address that unlocked or last withdrew from the
+    /// position, which may differ from the current holder of the NFT.
     ///
     /// ## Arguments
     /// - owner: Address of the owner of the lockup positions.
@@ -226,4 +630,175 @@ where
             .query_claims_for_owner(deps, &owner, start_after, limit)?;
         Ok(claims.into_iter().map(|(_, lockup)| lockup).collect())
     }
+
+    /// Query the operators approved to transfer the unlocking positions of
+    /// `owner`. Optional arguments `start_after` and `limit` can be used for
+    /// pagination.
+    ///
+    /// ## Arguments
+    /// - owner: Address of the owner of the lockup positions.
+    /// - start_after: Optional operator address to start the query after.
+    /// - limit: Optional maximum number of operators to return.
+    pub fn query_unlocking_position_operators(
+        &self,
+        deps: Deps,
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<UnlockingPositionOperatorResponse>> {
+        let owner = deps.api.addr_validate(&owner)?;
+        let start_after = start_after
+            .map(|x| deps.api.addr_validate(&x))
+            .transpose()?;
+        let operators = self
+            .claims
+            .query_operators(deps, &owner, start_after, limit)?;
+        Ok(operators
+            .into_iter()
+            .map(|(operator, expires)| UnlockingPositionOperatorResponse { operator, expires })
+            .collect())
+    }
+
+    /// Query the ID of the next unlocking position to reindex, or `None` if no
+    /// reindex is in progress.
+    pub fn query_claims_reindex_cursor(&self, deps: Deps) -> StdResult<Option<u64>> {
+        self.claims.reindex_cursor(deps.storage)
+    }
+
+    /// Query whether `owner` opted in to auto-withdrawal of matured unlocking
+    /// positions.
+    pub fn query_auto_withdraw(&self, deps: Deps, owner: String) -> StdResult<bool> {
+        let owner = deps.api.addr_validate(&owner)?;
+        Ok(self.claims.is_auto_withdraw(deps.storage, &owner))
+    }
+
+    /// Query the unlocking positions of all owners, ordered by when they
+    /// mature.
+    ///
+    /// ## Arguments
+    /// - start_after: Optional cursor of the unlocking position to start the
+    ///   query after.
+    /// - limit: Optional maximum number of unlocking positions to return.
+    /// - filter: Optional filter to only return matured or pending positions.
+    pub fn query_all_unlocking_positions(
+        &self,
+        deps: Deps,
+        env: Env,
+        start_after: Option<UnlockingPositionCursor>,
+        limit: Option<u32>,
+        filter: Option<UnlockingPositionFilter>,
+    ) -> StdResult<Vec<UnlockingPosition>> {
+        self.claims
+            .query_all_claims(deps, &env.block, filter, start_after, limit)
+    }
+
+    /// Query the total base tokens in unlocking positions, and the base tokens
+    /// maturing in `num_buckets` time buckets of `bucket_duration` seconds.
+    pub fn query_unlocking_totals(
+        &self,
+        deps: Deps,
+        env: Env,
+        bucket_duration: u64,
+        num_buckets: u32,
+    ) -> StdResult<UnlockingTotals> {
+        self.claims
+            .query_totals(deps, &env.block, bucket_duration, num_buckets)
+    }
+}
+
+impl<S, P, V> AutocompoundingVault<'_, S, P, V> {
+    /// Assert that `sender` can unlock vault tokens to unlocking positions
+    /// owned by `owner`.
+    ///
+    /// Vault tokens can always be unlocked to the sender itself. Unlocking to
+    /// another owner requires the owner's approval of the sender as an
+    /// operator with `ApproveUnlockingPositionOperator`, even though the
+    /// sender pays with its own vault tokens. Without it anyone could fill up
+    /// the owner's `max_unlocking_positions_per_owner` with dust positions,
+    /// preventing the owner from unlocking. A contract that unlocks vault
+    /// tokens it holds on behalf of its users must therefore be approved by
+    /// each user it unlocks to.
+    pub fn assert_can_unlock_to(
+        &self,
+        deps: Deps,
+        env: &Env,
+        owner: &Addr,
+        sender: &Addr,
+    ) -> Result<(), ContractError> {
+        if owner != sender
+            && !self
+                .claims
+                .is_operator(deps.storage, &env.block, owner, sender)?
+        {
+            return Err(ContractError::from(format!(
+                "{} is not an approved operator of {}",
+                sender, owner
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+
+    use super::*;
+
+    type TestVault<'a> = AutocompoundingVault<'a, (), (), ()>;
+
+    const OWNER: &str = "owner";
+    const OPERATOR: &str = "operator";
+
+    #[test]
+    fn test_assert_can_unlock_to() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let vault = TestVault::default();
+        let owner = Addr::unchecked(OWNER);
+        let operator = Addr::unchecked(OPERATOR);
+
+        // Anyone can unlock to themselves
+        vault
+            .assert_can_unlock_to(deps.as_ref(), &env, &operator, &operator)
+            .unwrap();
+
+        // Unlocking to another owner requires the owner's approval
+        let err = vault
+            .assert_can_unlock_to(deps.as_ref(), &env, &owner, &operator)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "operator is not an approved operator of owner"
+        );
+
+        let expires = Expiration::AtTime(env.block.time.plus_seconds(100));
+        vault
+            .claims
+            .approve_operator(&mut deps.storage, &env.block, &owner, &operator, expires)
+            .unwrap();
+        vault
+            .assert_can_unlock_to(deps.as_ref(), &env, &owner, &operator)
+            .unwrap();
+
+        // The approval does not work in the other direction or after it expires
+        vault
+            .assert_can_unlock_to(deps.as_ref(), &env, &operator, &owner)
+            .unwrap_err();
+        env.block.time = env.block.time.plus_seconds(100);
+        vault
+            .assert_can_unlock_to(deps.as_ref(), &env, &owner, &operator)
+            .unwrap_err();
+
+        // Nor once it is revoked
+        env.block.time = env.block.time.minus_seconds(100);
+        vault
+            .claims
+            .revoke_operator(&mut deps.storage, &owner, &operator)
+            .unwrap();
+        vault
+            .assert_can_unlock_to(deps.as_ref(), &env, &owner, &operator)
+            .unwrap_err();
+    }
 }
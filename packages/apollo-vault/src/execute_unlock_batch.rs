@@ -0,0 +1,302 @@
+use apollo_utils::responses::merge_responses;
+use cosmwasm_std::{
+    attr, to_binary, Addr, Deps, DepsMut, Env, Event, MessageInfo, Response, StdResult, Uint128,
+};
+use cw_dex::traits::{LockedStaking, Pool};
+use cw_utils::Duration;
+use cw_vault_token::VaultToken;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::ContractError;
+use crate::msg::{
+    CallbackMsg, UnlockBatchRequestResponseData, UnlockBatchSharesResponse,
+    WithdrawUnlockBatchResponseData,
+};
+use crate::state::{ChangeLogAction, UnlockBatch};
+use crate::AutocompoundingVault;
+
+/// Functions for the optional epoch mode, in which unlock requests made during
+/// an epoch are added to a batch that is unlocked as a single unlocking
+/// position owned by the vault once the epoch has ended. The owners of the
+/// requests hold shares of the batch, which they can withdraw pro-rata once
+/// the batch has finished unlocking.
+impl<S, P, V> AutocompoundingVault<'_, S, P, V>
+where
+    S: LockedStaking + Serialize + DeserializeOwned,
+    P: Pool + Serialize + DeserializeOwned,
+    V: VaultToken + Serialize + DeserializeOwned,
+{
+    /// Add a request of `owner` to unlock `base_token_amount` base tokens to
+    /// the open unlock batch. Called from the `Unlock` callback in epoch mode,
+    /// after the vault tokens have been burned.
+    pub(crate) fn add_unlock_batch_request(
+        &self,
+        deps: DepsMut,
+        env: &Env,
+        owner: &Addr,
+        base_token_amount: Uint128,
+    ) -> Result<Response, ContractError> {
+        let (batch, shares) =
+            self.unlock_batches
+                .add_request(deps.storage, &env.block, owner, base_token_amount)?;
+
+        let event = Event::new("apollo/vaults/execute_unlock_batch").add_attributes(vec![
+            attr("action", "add_unlock_batch_request"),
+            attr("owner", owner.to_string()),
+            attr("batch_id", batch.id.to_string()),
+            attr("base_token_amount", base_token_amount),
+            attr("shares", shares),
+        ]);
+
+        let data = UnlockBatchRequestResponseData {
+            batch_id: batch.id,
+            shares,
+            epoch_end: batch.epoch_end,
+        };
+
+        Ok(Response::default()
+            .add_event(event)
+            .set_data(to_binary(&data)?))
+    }
+
+    /// Start unlocking the base tokens of the open unlock batch, whose epoch
+    /// must have ended. A pending claim owned by the vault is created for the
+    /// base tokens and saved as the claim of the batch in the
+    /// `SaveUnlockBatchClaim` callback.
+    pub fn execute_process_unlock_batch(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: &MessageInfo,
+    ) -> Result<Response, ContractError> {
+        self.execute_process_unlock_batch_with(deps, env, info, |deps, env, amount| {
+            let staking = self.staking.load(deps.storage)?;
+            Ok((
+                staking.unlock(deps, env, amount)?,
+                staking.get_lockup_duration(deps)?,
+            ))
+        })
+    }
+
+    /// Like `execute_process_unlock_batch`, but the base tokens are unlocked
+    /// by `unlock`, which returns the response unlocking the base tokens and
+    /// the lockup duration after which they are unlocked. This allows vaults
+    /// whose base tokens are staked in several locks to split the unlock
+    /// between them.
+    pub fn execute_process_unlock_batch_with<F>(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: &MessageInfo,
+        unlock: F,
+    ) -> Result<Response, ContractError>
+    where
+        F: FnOnce(Deps, &Env, Uint128) -> Result<(Response, Duration), ContractError>,
+    {
+        let batch = self
+            .unlock_batches
+            .close_open_batch(deps.storage, &env.block)?;
+
+        let staking = self.staking.load(deps.storage)?;
+
+        // Stake the base tokens taken from the redemption buffer again, so that
+        // all of the base tokens can be unlocked from the staking module
+        let from_buffer =
+            self.take_from_redemption_buffer(deps.storage, batch.base_token_amount)?;
+        let stake_res = if from_buffer.is_zero() {
+            Response::default()
+        } else {
+            staking.stake(deps.as_ref(), &env, from_buffer)?
+        };
+
+        let (unlock_res, lockup_duration) = unlock(deps.as_ref(), &env, batch.base_token_amount)?;
+
+        // Create a pending claim using the default ID. If the staking module
+        // issues its own lockup IDs, the ID is replaced when handling the reply
+        // to the unlock message.
+        self.claims.create_pending_claim(
+            deps.storage,
+            &env.contract.address,
+            batch.base_token_amount,
+            lockup_duration.after(&env.block),
+            None,
+        )?;
+        self.claims
+            .set_pending_lockup_duration(deps.storage, &lockup_duration)?;
+
+        let save_claim_msg =
+            CallbackMsg::SaveUnlockBatchClaim { batch_id: batch.id }.into_cosmos_msg(&env)?;
+
+        let event = Event::new("apollo/vaults/execute_unlock_batch").add_attributes(vec![
+            attr("action", "execute_process_unlock_batch"),
+            attr("sender", info.sender.to_string()),
+            attr("batch_id", batch.id.to_string()),
+            attr("base_token_amount", batch.base_token_amount),
+            attr("from_redemption_buffer", from_buffer),
+        ]);
+
+        Ok(merge_responses(vec![stake_res, unlock_res])
+            .add_message(save_claim_msg)
+            .add_event(event))
+    }
+
+    /// Save the pending claim as the claim of the unlock batch with ID
+    /// `batch_id`.
+    pub fn execute_callback_save_unlock_batch_claim(
+        &self,
+        deps: DepsMut,
+        batch_id: u64,
+    ) -> Result<Response, ContractError> {
+        let claim = self.claims.get_pending_claim(deps.storage)?;
+        self.claims.commit_pending_claim(deps.storage)?;
+        let batch = self
+            .unlock_batches
+            .set_unlocking(deps.storage, batch_id, &claim)?;
+
+        let event = Event::new("apollo/vaults/execute_unlock_batch").add_attributes(vec![
+            attr("action", "execute_callback_save_unlock_batch_claim"),
+            attr("batch_id", batch.id.to_string()),
+            attr("lockup_id", claim.id.to_string()),
+            attr("release_at", claim.release_at.to_string()),
+        ]);
+
+        Ok(Response::default().add_event(event))
+    }
+
+    /// Withdraw the sender's pro-rata share of the base tokens of an unlock
+    /// batch that has finished unlocking.
+    ///
+    /// ## Arguments
+    /// - batch_id: ID of the unlock batch to withdraw from.
+    /// - recipient: Optional address to receive the withdrawn base tokens. If
+    ///   `None` is provided `info.sender` will be used instead.
+    pub fn execute_withdraw_unlock_batch(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: &MessageInfo,
+        batch_id: u64,
+        recipient: Option<String>,
+    ) -> Result<Response, ContractError> {
+        // Unwrap recipient or use caller's address
+        let recipient =
+            recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+
+        let (amount, lockup_id) =
+            self.unlock_batches
+                .withdraw(deps.storage, &env.block, batch_id, &info.sender)?;
+        self.claims
+            .decrease_claim(deps.storage, lockup_id, amount)?;
+
+        let res =
+            self.staking
+                .load(deps.storage)?
+                .withdraw_unlocked(deps.as_ref(), &env, amount)?;
+
+        let event = Event::new("apollo/vaults/execute_unlock_batch").add_attributes(vec![
+            attr("action", "execute_withdraw_unlock_batch"),
+            attr("recipient", recipient.clone()),
+            attr("batch_id", batch_id.to_string()),
+            attr("amount", amount),
+        ]);
+
+        let data = WithdrawUnlockBatchResponseData {
+            batch_id,
+            base_token_amount: amount,
+            recipient: recipient.clone(),
+        };
+
+        Ok(merge_responses(vec![
+            res,
+            self.base_vault.send_base_tokens(deps, &recipient, amount)?,
+        ])
+        .add_event(event)
+        .set_data(to_binary(&data)?))
+    }
+
+    /// Update the duration of an unlock epoch in seconds, or disable epoch mode
+    /// if `epoch_duration` is `None`. Only callable by the admin.
+    pub fn execute_update_unlock_epoch_duration(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        epoch_duration: Option<u64>,
+    ) -> Result<Response, ContractError> {
+        self.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+        let before = self.unlock_batches.epoch_duration(deps.storage)?;
+        match epoch_duration {
+            Some(epoch_duration) => self
+                .unlock_batches
+                .set_epoch_duration(deps.storage, epoch_duration)?,
+            None => self.unlock_batches.remove_epoch_duration(deps.storage),
+        }
+        self.change_log.record(
+            deps.storage,
+            &env.block,
+            &info.sender,
+            ChangeLogAction::UpdateUnlockEpochDuration {
+                before,
+                after: epoch_duration,
+            },
+        )?;
+
+        let event = Event::new("apollo/vaults/execute_unlock_batch").add_attributes(vec![
+            attr("action", "execute_update_unlock_epoch_duration"),
+            attr("before", format!("{:?}", before)),
+            attr("after", format!("{:?}", epoch_duration)),
+        ]);
+
+        Ok(Response::default().add_event(event))
+    }
+
+    /// Query the duration of an unlock epoch in seconds, or `None` if epoch
+    /// mode is disabled.
+    pub fn query_unlock_epoch_duration(&self, deps: Deps) -> StdResult<Option<u64>> {
+        self.unlock_batches.epoch_duration(deps.storage)
+    }
+
+    /// Query an unlock batch by ID.
+    pub fn query_unlock_batch(&self, deps: Deps, batch_id: u64) -> StdResult<UnlockBatch> {
+        self.unlock_batches.query_batch(deps, batch_id)
+    }
+
+    /// Query all unlock batches that have not been fully withdrawn.
+    ///
+    /// ## Arguments
+    /// - start_after: Optional ID of the batch to start the query after.
+    /// - limit: Optional maximum number of batches to return.
+    pub fn query_unlock_batches(
+        &self,
+        deps: Deps,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<UnlockBatch>> {
+        self.unlock_batches.query_batches(deps, start_after, limit)
+    }
+
+    /// Query the shares of `owner` in unlock batches.
+    ///
+    /// ## Arguments
+    /// - owner: Address of the owner of the shares.
+    /// - start_after: Optional ID of the batch to start the query after.
+    /// - limit: Optional maximum number of batches to return.
+    pub fn query_unlock_batch_shares(
+        &self,
+        deps: Deps,
+        owner: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<UnlockBatchSharesResponse>> {
+        let owner = deps.api.addr_validate(&owner)?;
+        let shares =
+            self.unlock_batches
+                .query_shares_for_owner(deps, &owner, start_after, limit)?;
+        Ok(shares
+            .into_iter()
+            .map(|(batch_id, shares)| UnlockBatchSharesResponse { batch_id, shares })
+            .collect())
+    }
+}
@@ -34,16 +34,27 @@ pub mod execute_force_unlock;
 /// for non-lockup vaults.
 #[cfg(feature = "redeem")]
 pub mod execute_redeem;
+/// Logic related to instant redemptions from the redemption buffer of
+/// lockup vaults.
+#[cfg(feature = "lockup")]
+pub mod execute_redemption_buffer;
 /// Logic related to staking.
 pub mod execute_staking;
 /// Logic related to unlocking of locked positions.
 #[cfg(feature = "lockup")]
 pub mod execute_unlock;
+/// Logic related to unlocking in batches per epoch.
+#[cfg(feature = "lockup")]
+pub mod execute_unlock_batch;
+/// Versioned migrations of contract storage.
+pub mod migrations;
 /// Messages for the Autocompounding Vault.
 pub mod msg;
 /// Query functions for the Autocompounding Vault.
 pub mod query;
 /// Logic for state management.
 pub mod state;
+/// Logic for representing unlocking positions as cw721 NFTs.
+pub mod unlocking_position_nft;
 
 pub use crate::autocompounding_vault::AutocompoundingVault;
@@ -0,0 +1,459 @@
+use apollo_utils::responses::merge_responses;
+use cosmwasm_std::{Addr, DepsMut, Env, Event, Response};
+use cw2::{get_contract_version, set_contract_version};
+use cw_storage_plus::Item;
+use cw_utils::Expiration;
+use semver::{Version, VersionReq};
+
+use crate::error::ContractError;
+use crate::state::{
+    AdminTransferProposal, DEFAULT_ADMIN_TRANSFER_EXPIRATION, DEFAULT_REINDEX_LIMIT,
+};
+use crate::AutocompoundingVault;
+
+/// The function that performs a [`MigrationStep`].
+pub type MigrationFn<'a> = Box<dyn Fn(DepsMut, &Env) -> Result<Response, ContractError> + 'a>;
+
+/// A single migration of contract storage, which is run when migrating from a
+/// stored contract version that matches `version_req`.
+pub struct MigrationStep<'a> {
+    /// Semver requirement that the stored contract version must match for the
+    /// step to run, e.g. `"<0.2.0"`.
+    pub version_req: &'static str,
+    /// Name of the step, emitted in the migration event.
+    pub name: &'static str,
+    /// The function that migrates the storage.
+    pub run: MigrationFn<'a>,
+}
+
+impl<'a> MigrationStep<'a> {
+    /// Create a new MigrationStep
+    pub fn new(
+        version_req: &'static str,
+        name: &'static str,
+        run: impl Fn(DepsMut, &Env) -> Result<Response, ContractError> + 'a,
+    ) -> Self {
+        Self {
+            version_req,
+            name,
+            run: Box::new(run),
+        }
+    }
+}
+
+/// Migrates the contract storage from the stored cw2 version to
+/// `contract_version`, running all `steps` whose version requirement matches
+/// the stored version, in the order they are given.
+///
+/// Returns an error if the stored contract name does not equal
+/// `contract_name`, or if `contract_version` is lower than the stored version.
+/// Migrating to the stored version runs no steps.
+pub fn migrate_contract(
+    mut deps: DepsMut,
+    env: &Env,
+    contract_name: &str,
+    contract_version: &str,
+    steps: Vec<MigrationStep>,
+) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != contract_name {
+        return Err(ContractError::ContractNameMismatch {
+            expected: contract_name.to_string(),
+            actual: stored.contract,
+        });
+    }
+
+    let version: Version = contract_version.parse()?;
+    let stored_version: Version = stored.version.parse()?;
+    if version < stored_version {
+        return Err(ContractError::DowngradeNotSupported {
+            from: stored_version.to_string(),
+            to: version.to_string(),
+        });
+    }
+
+    let mut event = Event::new("apollo/vaults/migrate").add_attributes(vec![
+        ("from_version", stored_version.to_string()),
+        ("to_version", version.to_string()),
+    ]);
+    if version == stored_version {
+        return Ok(Response::new().add_event(event));
+    }
+
+    let mut responses = vec![];
+    for step in steps {
+        if VersionReq::parse(step.version_req)?.matches(&stored_version) {
+            responses.push((step.run)(deps.branch(), env)?);
+            event = event.add_attribute("step", step.name);
+        }
+    }
+
+    set_contract_version(deps.storage, contract_name, contract_version)?;
+
+    Ok(merge_responses(responses).add_event(event))
+}
+
+/// The key under which a pending admin transfer was stored as an `Addr` in
+/// versions before 0.2.0.
+const LEGACY_ADMIN_TRANSFER: Item<Addr> = Item::new("admin_transfer");
+
+/// The key of the counter that was used in versions before 0.2.0 to create
+/// Osmosis locks with unique durations in order to learn the ID of new
+/// unlocking positions.
+pub const LEGACY_DURATION_COUNTER: Item<i32> = Item::new("duration_counter");
+
+impl<'a, S, P, V> AutocompoundingVault<'a, S, P, V> {
+    /// Returns the migration steps for the storage owned by
+    /// AutocompoundingVault. Contracts should run these before their own
+    /// steps.
+    ///
+    /// The steps for versions before 0.2.0 migrate storage that only vaults
+    /// released before 0.2.0 have. New vault contracts therefore start at
+    /// version 0.2.0, so that these steps never run for them.
+    pub fn migration_steps(&self) -> Vec<MigrationStep<'_>> {
+        vec![
+            MigrationStep::new("<0.2.0", "admin_transfer_proposal", |deps, env| {
+                self.migrate_admin_transfer_proposal(deps, env)
+            }),
+            // Populates the release_at index and the total of base tokens in
+            // unlocking positions. Only the first batch of claims is reindexed
+            // during the migration, the rest with `ReindexClaims`.
+            MigrationStep::new("<0.2.0", "claims_reindex", |deps, _env| {
+                self.claims.start_reindex(deps.storage)?;
+                self.claims.reindex(deps.storage, DEFAULT_REINDEX_LIMIT)?;
+                Ok(Response::default())
+            }),
+            MigrationStep::new("<0.2.0", "remove_duration_counter", |deps, _env| {
+                LEGACY_DURATION_COUNTER.remove(deps.storage);
+                Ok(Response::default())
+            }),
+        ]
+    }
+
+    /// Converts a pending admin transfer stored as an `Addr` into an
+    /// [`AdminTransferProposal`] that expires after the default expiration.
+    fn migrate_admin_transfer_proposal(
+        &self,
+        deps: DepsMut,
+        env: &Env,
+    ) -> Result<Response, ContractError> {
+        if let Some(new_admin) = LEGACY_ADMIN_TRANSFER.may_load(deps.storage)? {
+            LEGACY_ADMIN_TRANSFER.remove(deps.storage);
+            self.admin_transfer.save(
+                deps.storage,
+                &AdminTransferProposal {
+                    new_admin: Some(new_admin),
+                    expires: Expiration::AtTime(
+                        env.block
+                            .time
+                            .plus_seconds(DEFAULT_ADMIN_TRANSFER_EXPIRATION),
+                    ),
+                },
+            )?;
+        }
+        Ok(Response::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::AssetInfo;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{Decimal, OwnedDeps, StdError, Storage, Uint128};
+    use cw_controllers::AdminResponse;
+    use cw_dex_router::helpers::CwDexRouterBase;
+    use liquidity_helper::LiquidityHelperBase;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::state::{Config, MAX_LIMIT};
+
+    type TestVault<'a> = AutocompoundingVault<'a, (), (), ()>;
+
+    const CONTRACT_NAME: &str = "crates.io:osmosis-vault";
+    const ADMIN: &str = "admin";
+    const NEW_ADMIN: &str = "new_admin";
+
+    /// Raw storage of a vault at version 0.1.4 with a pending admin transfer,
+    /// a duration counter from the Osmosis lock ID workaround and a config
+    /// without the fields added in 0.2.0.
+    const SNAPSHOT_0_1_4: &[(&str, &str)] = &[
+        (
+            "contract_info",
+            r#"{"contract":"crates.io:osmosis-vault","version":"0.1.4"}"#,
+        ),
+        ("admin", r#""admin""#),
+        ("admin_transfer", r#""new_admin""#),
+        ("duration_counter", "3"),
+        (
+            "config",
+            r#"{"performance_fee":"0.125","treasury":"treasury","router":"router","reward_assets":[{"native":"uosmo"}],"reward_liquidation_target":{"native":"uatom"},"force_withdraw_whitelist":["whitelisted"],"liquidity_helper":"liquidity_helper"}"#,
+        ),
+    ];
+
+    /// Returns the prefix of all storage keys in `namespace`, which is the
+    /// namespace prefixed with its length as used by cw-storage-plus.
+    fn namespace_prefix(namespace: &str) -> Vec<u8> {
+        let mut prefix = (namespace.len() as u16).to_be_bytes().to_vec();
+        prefix.extend_from_slice(namespace.as_bytes());
+        prefix
+    }
+
+    /// Writes claims to storage as a vault at version 0.1.4 stored them: the
+    /// claims map keyed by ID, the owner index and the claim counter, but none
+    /// of the indexes and totals added in 0.2.0.
+    fn seed_0_1_4_claims(storage: &mut dyn Storage, claims: &[(u64, &str, u128)]) {
+        for (id, owner, amount) in claims {
+            let mut key = namespace_prefix("claims");
+            key.extend_from_slice(&id.to_be_bytes());
+            let claim = format!(
+                r#"{{"id":{},"owner":"{}","release_at":{{"at_height":12445}},"base_token_amount":"{}"}}"#,
+                id, owner, amount
+            );
+            storage.set(&key, claim.as_bytes());
+
+            // The owner index stores the length of the primary key under the
+            // owner followed by the primary key
+            let mut key = namespace_prefix("claims_index");
+            key.extend_from_slice(&(owner.len() as u16).to_be_bytes());
+            key.extend_from_slice(owner.as_bytes());
+            key.extend_from_slice(&id.to_be_bytes());
+            storage.set(&key, b"8");
+        }
+        storage.set(b"num_claims", claims.len().to_string().as_bytes());
+    }
+
+    fn load_snapshot(snapshot: &[(&str, &str)]) -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        for (key, value) in snapshot {
+            deps.storage.set(key.as_bytes(), value.as_bytes());
+        }
+        deps
+    }
+
+    fn migrate(
+        deps: DepsMut,
+        env: &Env,
+        contract_name: &str,
+        contract_version: &str,
+    ) -> Result<Response, ContractError> {
+        let vault = TestVault::default();
+        migrate_contract(
+            deps,
+            env,
+            contract_name,
+            contract_version,
+            vault.migration_steps(),
+        )
+    }
+
+    #[test]
+    fn test_migrate_from_0_1_4() {
+        let mut deps = load_snapshot(SNAPSHOT_0_1_4);
+        let env = mock_env();
+
+        let res = migrate(deps.as_mut(), &env, CONTRACT_NAME, "0.2.0").unwrap();
+
+        let vault = TestVault::default();
+        assert_eq!(
+            vault.admin.query_admin(deps.as_ref()).unwrap(),
+            AdminResponse {
+                admin: Some(ADMIN.to_string())
+            }
+        );
+        assert_eq!(
+            vault.admin_transfer.load(&deps.storage).unwrap(),
+            AdminTransferProposal {
+                new_admin: Some(Addr::unchecked(NEW_ADMIN)),
+                expires: Expiration::AtTime(
+                    env.block
+                        .time
+                        .plus_seconds(DEFAULT_ADMIN_TRANSFER_EXPIRATION)
+                ),
+            }
+        );
+        assert_eq!(LEGACY_ADMIN_TRANSFER.may_load(&deps.storage).unwrap(), None);
+        assert_eq!(
+            LEGACY_DURATION_COUNTER.may_load(&deps.storage).unwrap(),
+            None
+        );
+
+        let version = get_contract_version(&deps.storage).unwrap();
+        assert_eq!(version.contract, CONTRACT_NAME);
+        assert_eq!(version.version, "0.2.0");
+
+        let event = &res.events[0];
+        assert!(event
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "step" && attr.value == "admin_transfer_proposal"));
+    }
+
+    #[test]
+    fn test_migrate_loads_0_1_4_config() {
+        let mut deps = load_snapshot(SNAPSHOT_0_1_4);
+        let env = mock_env();
+
+        migrate(deps.as_mut(), &env, CONTRACT_NAME, "0.2.0").unwrap();
+
+        // Fields added in 0.2.0 default to disabled
+        let vault = TestVault::default();
+        assert_eq!(
+            vault.config.load(&deps.storage).unwrap(),
+            Config {
+                performance_fee: Decimal::from_ratio(1u128, 8u128),
+                treasury: Addr::unchecked("treasury"),
+                router: CwDexRouterBase(Addr::unchecked("router")),
+                reward_assets: vec![AssetInfo::Native("uosmo".to_string())],
+                reward_liquidation_target: AssetInfo::Native("uatom".to_string()),
+                force_withdraw_whitelist: vec![Addr::unchecked("whitelisted")],
+                liquidity_helper: LiquidityHelperBase(Addr::unchecked("liquidity_helper")),
+                early_exit_penalty: Decimal::zero(),
+                early_exit_penalty_decay: false,
+                early_exit_penalty_to_treasury: false,
+                auto_withdraw_keeper_fee: Decimal::zero(),
+                min_unlock_amount: Uint128::zero(),
+                max_unlocking_positions_per_owner: 0,
+                redemption_buffer_ratio: Decimal::zero(),
+                instant_redemption_fee: Decimal::zero(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_migrate_reindexes_claims_in_batches() {
+        let mut deps = load_snapshot(SNAPSHOT_0_1_4);
+        let env = mock_env();
+        let vault = TestVault::default();
+        let num_claims = u64::from(DEFAULT_REINDEX_LIMIT) + 20;
+        let claims: Vec<(u64, &str, u128)> = (0..num_claims)
+            .map(|id| (id, if id % 2 == 0 { ADMIN } else { NEW_ADMIN }, 100))
+            .collect();
+        seed_0_1_4_claims(&mut deps.storage, &claims);
+
+        // The claims can be read through the owner index, but are missing
+        // from the indexes and totals added in 0.2.0
+        assert_eq!(
+            vault
+                .claims
+                .count_claims_for_owner(&deps.storage, &Addr::unchecked(ADMIN), usize::MAX),
+            claims.len() / 2
+        );
+        assert!(vault
+            .claims
+            .query_all_claims(deps.as_ref(), &env.block, None, None, None)
+            .unwrap()
+            .is_empty());
+
+        let res = migrate(deps.as_mut(), &env, CONTRACT_NAME, "0.2.0").unwrap();
+        assert!(res.events[0]
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "step" && attr.value == "claims_reindex"));
+
+        // Only the first batch is reindexed during the migration
+        assert_eq!(
+            vault.claims.reindex_cursor(&deps.storage).unwrap(),
+            Some(u64::from(DEFAULT_REINDEX_LIMIT))
+        );
+        assert_eq!(
+            vault.claims.total_unlocking(&deps.storage).unwrap(),
+            Uint128::new(100) * Uint128::from(DEFAULT_REINDEX_LIMIT)
+        );
+
+        assert!(vault
+            .claims
+            .reindex(&mut deps.storage, DEFAULT_REINDEX_LIMIT)
+            .unwrap());
+        assert_eq!(
+            vault.claims.total_unlocking(&deps.storage).unwrap(),
+            Uint128::new(100) * Uint128::from(num_claims)
+        );
+        // The release_at index and the release totals are populated
+        let totals = vault
+            .claims
+            .query_totals(deps.as_ref(), &env.block, 100, 1)
+            .unwrap();
+        assert_eq!(
+            totals.pending,
+            Uint128::new(100) * Uint128::from(num_claims)
+        );
+        assert_eq!(
+            vault
+                .claims
+                .query_all_claims(deps.as_ref(), &env.block, None, None, Some(MAX_LIMIT))
+                .unwrap()
+                .len(),
+            MAX_LIMIT as usize
+        );
+        assert_eq!(
+            vault
+                .claims
+                .count_claims_for_owner(&deps.storage, &Addr::unchecked(ADMIN), usize::MAX),
+            claims.len() / 2
+        );
+    }
+
+    #[test]
+    fn test_migrate_without_pending_admin_transfer() {
+        let mut deps = load_snapshot(&SNAPSHOT_0_1_4[..2]);
+        let env = mock_env();
+
+        migrate(deps.as_mut(), &env, CONTRACT_NAME, "0.2.0").unwrap();
+
+        let vault = TestVault::default();
+        assert_eq!(vault.admin_transfer.may_load(&deps.storage).unwrap(), None);
+    }
+
+    #[test]
+    fn test_migrate_skips_steps_for_newer_versions() {
+        let mut deps = load_snapshot(SNAPSHOT_0_1_4);
+        let env = mock_env();
+        set_contract_version(&mut deps.storage, CONTRACT_NAME, "0.2.0").unwrap();
+
+        migrate(deps.as_mut(), &env, CONTRACT_NAME, "0.2.1").unwrap();
+
+        // The legacy item is left untouched since the step did not run
+        assert_eq!(
+            LEGACY_ADMIN_TRANSFER.load(&deps.storage).unwrap(),
+            Addr::unchecked(NEW_ADMIN)
+        );
+        assert_eq!(
+            get_contract_version(&deps.storage).unwrap().version,
+            "0.2.1"
+        );
+    }
+
+    #[test_case(CONTRACT_NAME, "0.1.3" => matches Err(ContractError::DowngradeNotSupported { .. }); "downgrade")]
+    #[test_case("crates.io:astroport-vault", "0.2.0" => matches Err(ContractError::ContractNameMismatch { .. }); "contract name mismatch")]
+    #[test_case(CONTRACT_NAME, "not-a-version" => matches Err(ContractError::SemVer(_)); "invalid version")]
+    #[test_case(CONTRACT_NAME, "0.1.4" => matches Ok(_); "same version")]
+    fn test_migrate_errors(
+        contract_name: &str,
+        contract_version: &str,
+    ) -> Result<Response, ContractError> {
+        let mut deps = load_snapshot(SNAPSHOT_0_1_4);
+        let env = mock_env();
+
+        let res = migrate(deps.as_mut(), &env, contract_name, contract_version);
+
+        // Storage must be unchanged unless the migration succeeded
+        if res.is_err() {
+            assert_eq!(
+                get_contract_version(&deps.storage).unwrap().version,
+                "0.1.4"
+            );
+        }
+        res
+    }
+
+    #[test]
+    fn test_migrate_without_contract_version() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        let err = migrate(deps.as_mut(), &env, CONTRACT_NAME, "0.2.0").unwrap_err();
+
+        assert!(matches!(err, ContractError::Std(StdError::NotFound { .. })));
+    }
+}
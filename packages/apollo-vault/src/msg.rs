@@ -1,5 +1,10 @@
+use apollo_cw_asset::AssetInfo;
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{to_binary, Addr, CosmosMsg, Env, StdResult, Uint128, WasmMsg};
+use cosmwasm_std::{
+    to_binary, Addr, CosmosMsg, CustomMsg, Env, StdResult, SubMsg, Timestamp, Uint128, WasmMsg,
+};
+use cw_dex_router::operations::SwapOperationsList;
+use cw_utils::Expiration;
 #[cfg(feature = "force-unlock")]
 use cw_vault_standard::extensions::force_unlock::ForceUnlockExecuteMsg;
 #[cfg(feature = "lockup")]
@@ -7,6 +12,8 @@ use cw_vault_standard::extensions::lockup::{LockupExecuteMsg, LockupQueryMsg};
 use cw_vault_standard::msg::{VaultStandardExecuteMsg, VaultStandardQueryMsg};
 
 use crate::state::{Config, ConfigUpdates};
+#[cfg(feature = "lockup")]
+use crate::state::{UnlockingPositionCursor, UnlockingPositionFilter};
 
 /// ExecuteMsg for an Autocompounding Vault.
 pub type ExecuteMsg = VaultStandardExecuteMsg<ExtensionExecuteMsg>;
@@ -16,6 +23,7 @@ pub type QueryMsg = VaultStandardQueryMsg<ExtensionQueryMsg>;
 
 /// Extension execute messages for an apollo autocompounding vault
 #[cw_serde]
+#[allow(clippy::large_enum_variant)]
 pub enum ExtensionExecuteMsg {
     /// Execute a callback message.
     Callback(CallbackMsg),
@@ -77,6 +85,21 @@ pub enum CallbackMsg {
     /// Save the currently pending claim to the `claims` storage.
     #[cfg(feature = "lockup")]
     SaveClaim {},
+    /// Save the currently pending claim to the `claims` storage as the claim
+    /// of an unlock batch.
+    #[cfg(feature = "lockup")]
+    SaveUnlockBatchClaim {
+        /// The ID of the unlock batch.
+        batch_id: u64,
+    },
+    /// Redeem vault tokens for base tokens from the redemption buffer.
+    #[cfg(feature = "lockup")]
+    RedeemFromBuffer {
+        /// The address which should receive the base tokens.
+        recipient: Addr,
+        /// The amount of vault tokens sent to the contract.
+        amount: Uint128,
+    },
 }
 
 impl CallbackMsg {
@@ -93,28 +116,184 @@ impl CallbackMsg {
             funds: vec![],
         }))
     }
+
+    /// Convert the callback message to a [`SubMsg`] that replies on success
+    /// with [`CALLBACK_DATA_REPLY_ID`]. The reply must be handled with
+    /// [`crate::AutocompoundingVault::reply_callback_data`], so that the data
+    /// set by the callback becomes the data of the response to the original
+    /// message.
+    pub fn into_sub_msg_with_data(&self, env: &Env) -> StdResult<SubMsg> {
+        Ok(SubMsg::reply_on_success(
+            self.into_cosmos_msg(env)?,
+            CALLBACK_DATA_REPLY_ID,
+        ))
+    }
 }
 
+/// Reply ID for callbacks whose response data is forwarded as the data of the
+/// response to the original message.
+pub const CALLBACK_DATA_REPLY_ID: u64 = 933;
+
 /// Apollo extension messages define functionality that is part of all apollo
 /// vaults, but not part of the standard.
 #[cw_serde]
+#[allow(clippy::large_enum_variant)]
 pub enum ApolloExtensionExecuteMsg {
     /// Update the configuration of the vault.
     UpdateConfig {
         /// The config updates.
         updates: ConfigUpdates,
     },
-    /// Update the vault admin.
+    /// Propose a transfer of admin rights to a new address.
     UpdateAdmin {
         /// The new admin address.
         address: String,
+        /// Optional expiration of the transfer. Must be a time at most 30 days
+        /// from now. Defaults to 7 days from now.
+        expiration: Option<Expiration>,
     },
-    /// Accept the admin transfer. This must be called by the new admin to
-    /// finalize the transfer.
+    /// Accept the admin transfer. This must be called by the new admin before
+    /// the transfer expires to finalize the transfer.
     AcceptAdminTransfer {},
-    /// Removes the initiated admin transfer. This can only be called by the
-    /// admin who initiated the admin transfer.
+    /// Propose to renounce admin rights, leaving the vault without an admin.
+    /// Must be confirmed with `ConfirmRenounceAdmin`.
+    RenounceAdmin {
+        /// Optional expiration of the proposal. Must be a time at most 30 days
+        /// from now. Defaults to 7 days from now.
+        expiration: Option<Expiration>,
+    },
+    /// Confirm the admin renunciation. This must be called by the admin
+    /// before the proposal expires. This action is irreversible.
+    ConfirmRenounceAdmin {},
+    /// Removes the initiated admin transfer or admin renunciation. This can
+    /// only be called by the admin who initiated it.
     DropAdminTransfer {},
+    /// Burn vault tokens and start unlocking the base tokens they represent,
+    /// like the `Unlock` message of the lockup extension, but with an optional
+    /// owner of the unlocking position. This allows a contract holding vault
+    /// tokens on behalf of users to unlock them directly to a user.
+    #[cfg(feature = "lockup")]
+    Unlock {
+        /// The amount of vault tokens to unlock. If the vault token is a
+        /// native token it must be sent in the funds of the message.
+        amount: Uint128,
+        /// Optional address of the owner of the unlocking position. Defaults
+        /// to the sender. If it is another address, the owner must first have
+        /// approved the sender as an operator with
+        /// `ApproveUnlockingPositionOperator`, even though the vault tokens
+        /// are the sender's. This is because the position counts against the
+        /// owner's maximum number of unlocking positions. For example, a
+        /// contract holding vault tokens for its users must be approved by
+        /// each user before it can unlock to them.
+        owner: Option<String>,
+    },
+    /// Cancel an unlocking position of the sender and re-enter the vault with
+    /// its base tokens. Vault tokens are minted to the sender at the current
+    /// share price.
+    #[cfg(feature = "force-unlock")]
+    CancelUnlock {
+        /// The ID of the unlocking position to cancel.
+        lockup_id: u64,
+    },
+    /// Withdraw an unlocking position of the sender before it has finished
+    /// unlocking, paying the early exit penalty set in the config. Only
+    /// available if the early exit penalty is non-zero.
+    #[cfg(feature = "force-unlock")]
+    EarlyExitUnlocking {
+        /// The ID of the unlocking position to withdraw.
+        lockup_id: u64,
+        /// Optional address to receive the base tokens. Defaults to the
+        /// sender.
+        recipient: Option<String>,
+    },
+    /// Withdraw the base tokens from all of the sender's unlocking positions
+    /// that have finished unlocking.
+    #[cfg(feature = "lockup")]
+    WithdrawAllUnlocked {
+        /// Optional address to receive the base tokens. Defaults to the
+        /// sender.
+        recipient: Option<String>,
+        /// Optional maximum number of unlocking positions to withdraw from.
+        /// Defaults to 10.
+        limit: Option<u32>,
+    },
+    /// Opt in to or out of auto-withdrawal, in which keepers send the base
+    /// tokens of the sender's matured unlocking positions to the sender, minus
+    /// the keeper fee of the config. Not available if unlocking positions are
+    /// NFTs.
+    #[cfg(feature = "lockup")]
+    SetAutoWithdraw {
+        /// Whether to enable auto-withdrawal.
+        enabled: bool,
+    },
+    /// Send the base tokens of matured unlocking positions to their owners,
+    /// for owners that opted in to auto-withdrawal. Can be called by anyone.
+    /// The keeper fee of the config is deducted from each position and sent
+    /// to the sender.
+    #[cfg(feature = "lockup")]
+    ProcessMaturedClaims {
+        /// Optional maximum number of unlocking positions to process.
+        /// Defaults to 10.
+        limit: Option<u32>,
+    },
+    /// Transfer ownership of an unlocking position. Must be called by the
+    /// owner of the position, or by an operator approved by the owner.
+    #[cfg(feature = "lockup")]
+    TransferUnlockingPosition {
+        /// The ID of the unlocking position to transfer.
+        lockup_id: u64,
+        /// The address of the new owner.
+        new_owner: String,
+    },
+    /// Approve an operator to transfer all of the sender's unlocking
+    /// positions on the sender's behalf, and to unlock vault tokens to
+    /// unlocking positions owned by the sender.
+    #[cfg(feature = "lockup")]
+    ApproveUnlockingPositionOperator {
+        /// The address of the operator.
+        operator: String,
+        /// Optional expiration of the approval. Defaults to never expiring.
+        expires: Option<Expiration>,
+    },
+    /// Revoke the approval of an operator to transfer the sender's unlocking
+    /// positions.
+    #[cfg(feature = "lockup")]
+    RevokeUnlockingPositionOperator {
+        /// The address of the operator.
+        operator: String,
+    },
+    /// Continue the reindex of unlocking positions started when migrating
+    /// from a version without the release time index, reindexing the next
+    /// batch of positions. Can be called by anyone, e.g. keepers, until
+    /// `ClaimsReindexCursor` returns `None`.
+    #[cfg(feature = "lockup")]
+    ReindexClaims {
+        /// Optional maximum number of unlocking positions to reindex. Defaults
+        /// to [`crate::state::DEFAULT_REINDEX_LIMIT`].
+        limit: Option<u32>,
+    },
+    /// Start unlocking the base tokens of the open unlock batch once its
+    /// epoch has ended. Can be called by anyone. Only used in epoch mode.
+    #[cfg(feature = "lockup")]
+    ProcessUnlockBatch {},
+    /// Update the duration of an unlock epoch in seconds. Can only be called
+    /// by the admin. `None` disables epoch mode. The epoch of an already open
+    /// unlock batch is not changed.
+    #[cfg(feature = "lockup")]
+    UpdateUnlockEpochDuration {
+        /// The new epoch duration in seconds, or `None` to disable epoch mode.
+        epoch_duration: Option<u64>,
+    },
+    /// Withdraw the sender's share of the base tokens of an unlock batch that
+    /// has finished unlocking.
+    #[cfg(feature = "lockup")]
+    WithdrawUnlockBatch {
+        /// The ID of the unlock batch.
+        batch_id: u64,
+        /// Optional address to receive the base tokens. Defaults to the
+        /// sender.
+        recipient: Option<String>,
+    },
 }
 
 /// Apollo extension queries define functionality that is part of all apollo
@@ -123,6 +302,118 @@ pub enum ApolloExtensionExecuteMsg {
 pub enum ApolloExtensionQueryMsg {
     /// Query the current state of the vault.
     State {},
+    /// Query the config of the vault. Returns a `Config`.
+    Config {},
+    /// Query the admin of the vault. Returns an
+    /// `cw_controllers::AdminResponse`.
+    Admin {},
+    /// Query the pending admin transfer or admin renunciation, if any. Returns
+    /// an `Option<AdminTransferProposal>`.
+    PendingAdminTransfer {},
+    /// Query the addresses that are allowed to force withdraw from the vault.
+    /// Returns a `Vec<Addr>`.
+    ForceWithdrawWhitelist {},
+    /// Query the cw2 contract name and version. Returns a
+    /// `cw2::ContractVersion`.
+    ContractVersion {},
+    /// Query the router path used to swap each reward asset into the reward
+    /// liquidation target. Returns a `Vec<RewardRouteResponse>`.
+    RewardRoutes {},
+    /// Query whether the base tokens held by the staking module and the vault
+    /// cover the base tokens owed to depositors according to the vault's
+    /// internal accounting. Returns a `SolvencyResponse`.
+    Solvency {},
+    /// Query the operators approved to transfer the unlocking positions of an
+    /// owner. Returns a `Vec<UnlockingPositionOperatorResponse>`.
+    #[cfg(feature = "lockup")]
+    UnlockingPositionOperators {
+        /// The owner of the unlocking positions.
+        owner: String,
+        /// Optional operator address to start the query after.
+        start_after: Option<String>,
+        /// Optional maximum number of operators to return.
+        limit: Option<u32>,
+    },
+    /// Query whether an owner opted in to auto-withdrawal of matured unlocking
+    /// positions. Returns a `bool`.
+    #[cfg(feature = "lockup")]
+    AutoWithdraw {
+        /// The owner of the unlocking positions.
+        owner: String,
+    },
+    /// Query the address of the cw721 contract that represents unlocking
+    /// positions as NFTs. Returns an `Option<Addr>`, which is `None` if
+    /// unlocking positions are not represented as NFTs.
+    #[cfg(feature = "lockup")]
+    UnlockingPositionNft {},
+    /// Query the unlocking positions of all owners, ordered by when they
+    /// mature. Returns a `Vec<UnlockingPosition>`.
+    #[cfg(feature = "lockup")]
+    AllUnlockingPositions {
+        /// Optional cursor of the unlocking position to start the query
+        /// after, built from the last position of the previous page.
+        start_after: Option<UnlockingPositionCursor>,
+        /// Optional maximum number of unlocking positions to return. Capped
+        /// at [`crate::state::MAX_LIMIT`].
+        limit: Option<u32>,
+        /// Optional filter to only return matured or pending positions.
+        filter: Option<UnlockingPositionFilter>,
+    },
+    /// Query the total base tokens in unlocking positions, and the base
+    /// tokens maturing in consecutive time buckets starting at the current
+    /// block time. Returns an `UnlockingTotals`.
+    #[cfg(feature = "lockup")]
+    UnlockingTotals {
+        /// The duration of each bucket in seconds.
+        bucket_duration: u64,
+        /// The number of buckets. Capped at [`crate::state::MAX_LIMIT`].
+        num_buckets: u32,
+    },
+    /// Query the ID of the next unlocking position to reindex, if a reindex
+    /// started in a migration is in progress. Returns an `Option<u64>`. Until
+    /// the reindex has finished, `AllUnlockingPositions`, `UnlockingTotals`
+    /// and `Solvency` only include the positions that have been reindexed.
+    #[cfg(feature = "lockup")]
+    ClaimsReindexCursor {},
+    /// Query the duration of an unlock epoch in seconds. Returns an
+    /// `Option<u64>`, which is `None` if epoch mode is disabled.
+    #[cfg(feature = "lockup")]
+    UnlockEpochDuration {},
+    /// Query an unlock batch by ID. Returns an `UnlockBatch`.
+    #[cfg(feature = "lockup")]
+    UnlockBatch {
+        /// The ID of the unlock batch.
+        batch_id: u64,
+    },
+    /// Query all unlock batches that have not been fully withdrawn. Returns a
+    /// `Vec<UnlockBatch>`.
+    #[cfg(feature = "lockup")]
+    UnlockBatches {
+        /// Optional ID of the batch to start the query after.
+        start_after: Option<u64>,
+        /// Optional maximum number of batches to return.
+        limit: Option<u32>,
+    },
+    /// Query the shares of an owner in unlock batches. Returns a
+    /// `Vec<UnlockBatchSharesResponse>`.
+    #[cfg(feature = "lockup")]
+    UnlockBatchShares {
+        /// The owner of the shares.
+        owner: String,
+        /// Optional ID of the batch to start the query after.
+        start_after: Option<u64>,
+        /// Optional maximum number of batches to return.
+        limit: Option<u32>,
+    },
+    /// Query the history of config updates, admin transfers and whitelist
+    /// changes, in the order they were made. Returns a
+    /// `Vec<ChangeLogEntry>`.
+    ChangeLog {
+        /// Optional ID of the entry to start the query after.
+        start_after: Option<u64>,
+        /// Optional maximum number of entries to return.
+        limit: Option<u32>,
+    },
 }
 
 /// Extension query messages for an apollo autocompounding vault
@@ -135,6 +426,18 @@ pub enum ExtensionQueryMsg {
     Apollo(ApolloExtensionQueryMsg),
 }
 
+/// Hook messages that can be sent to the vault with the `Send` message of a
+/// cw20 base token.
+#[cw_serde]
+pub enum Cw20HookMsg {
+    /// Deposit the sent base tokens into the vault. Vault tokens are minted to
+    /// `recipient`, or to the sender of the base tokens if `None`.
+    Deposit {
+        /// The address that receives the minted vault tokens.
+        recipient: Option<String>,
+    },
+}
+
 /// Response struct containing information about the current state of the vault.
 /// Returned by the `AutocompoundingVault::query_state`.
 #[cw_serde]
@@ -154,4 +457,199 @@ pub struct StateResponse<S, P, V> {
     pub vault_token: V,
     /// The total supply of the vault token.
     pub vault_token_supply: Uint128,
+    /// The base tokens kept unstaked for instant redemptions. These are
+    /// included in `total_staked_base_tokens`.
+    pub redemption_buffer: Uint128,
+}
+
+/// Response struct comparing the base tokens owed to depositors according to
+/// the vault's internal accounting with the base tokens actually held. Returned
+/// by `AutocompoundingVault::query_solvency`.
+#[cw_serde]
+pub struct SolvencyResponse {
+    /// The base tokens staked by the vault according to its accounting.
+    pub total_staked_base_tokens: Uint128,
+    /// The base tokens in unlocking positions according to the vault's
+    /// accounting, including matured positions that have not been withdrawn.
+    pub total_unlocking_base_tokens: Uint128,
+    /// The base tokens staked in the staking module.
+    pub module_staked_base_tokens: Uint128,
+    /// The base tokens unlocking in the staking module.
+    pub module_unlocking_base_tokens: Uint128,
+    /// The base tokens held by the vault contract itself.
+    pub idle_base_tokens: Uint128,
+    /// The amount by which the base tokens owed exceed the base tokens held.
+    /// Zero if the vault is solvent.
+    pub shortfall: Uint128,
+}
+
+/// Response struct containing the router path for a reward asset. Returned by
+/// `AutocompoundingVault::query_reward_routes`.
+#[cw_serde]
+pub struct RewardRouteResponse {
+    /// The reward asset.
+    pub reward_asset: AssetInfo,
+    /// The path in the cw-dex-router used to swap the reward asset into the
+    /// reward liquidation target. `None` if the reward asset is the reward
+    /// liquidation target and thus does not need to be swapped.
+    pub path: Option<SwapOperationsList>,
+}
+
+/// Response struct containing an operator approved to transfer the unlocking
+/// positions of an owner.
+#[cw_serde]
+pub struct UnlockingPositionOperatorResponse {
+    /// The address of the operator.
+    pub operator: Addr,
+    /// The expiration of the approval. Expired approvals are returned until
+    /// they are revoked or replaced.
+    pub expires: Expiration,
+}
+
+/// Response struct containing the shares of an owner in an unlock batch.
+#[cw_serde]
+pub struct UnlockBatchSharesResponse {
+    /// The ID of the unlock batch.
+    pub batch_id: u64,
+    /// The shares of the owner in the batch.
+    pub shares: Uint128,
+}
+
+/// Info needed to instantiate a cw721 contract that represents unlocking
+/// positions as NFTs. The contract must be the `unlocking-position-nft`
+/// contract, which lets the vault as minter burn NFTs and update their
+/// metadata. The NFT is burned when the position is withdrawn.
+#[cw_serde]
+pub struct UnlockingPositionNftInstantiateInfo {
+    /// The code ID of the cw721 contract.
+    pub code_id: u64,
+    /// The name of the NFT collection.
+    pub name: String,
+    /// The symbol of the NFT collection.
+    pub symbol: String,
+    /// The label of the cw721 contract.
+    pub label: String,
+    /// Optional admin of the cw721 contract, that is allowed to migrate it.
+    pub admin: Option<String>,
+}
+
+/// Metadata of an unlocking position NFT. The token ID of the NFT is the ID of
+/// the unlocking position.
+#[cw_serde]
+pub struct UnlockingPositionMetadata {
+    /// The amount of base tokens that can be withdrawn from the unlocking
+    /// position.
+    pub base_token_amount: Uint128,
+    /// The point at which the base tokens can be withdrawn.
+    pub release_at: Expiration,
+}
+
+/// Execute messages of the unlocking position NFT contract in addition to
+/// those of cw721-base, sent as `Extension { msg }`. Only callable by the
+/// minter.
+#[cw_serde]
+pub enum UnlockingPositionNftExtensionMsg {
+    /// Update the metadata of an NFT after base tokens were withdrawn from its
+    /// unlocking position.
+    UpdateMetadata {
+        /// The ID of the NFT, which is the ID of the unlocking position.
+        token_id: String,
+        /// The new metadata of the NFT.
+        metadata: UnlockingPositionMetadata,
+    },
+}
+
+impl CustomMsg for UnlockingPositionNftExtensionMsg {}
+
+/// Data set on the response to `Deposit`.
+#[cw_serde]
+pub struct DepositResponseData {
+    /// The amount of vault tokens minted.
+    pub vault_token_amount: Uint128,
+    /// The address that received the minted vault tokens.
+    pub recipient: Addr,
+}
+
+/// Data set on the response to `Redeem`.
+#[cw_serde]
+pub struct RedeemResponseData {
+    /// The amount of vault tokens burned.
+    pub vault_token_amount: Uint128,
+    /// The amount of base tokens sent to the recipient.
+    pub base_token_amount: Uint128,
+    /// The address that received the base tokens.
+    pub recipient: Addr,
+}
+
+/// Data set on the response to `WithdrawAllUnlocked`.
+#[cw_serde]
+pub struct WithdrawAllUnlockedResponseData {
+    /// The IDs of the unlocking positions that were withdrawn from.
+    pub lockup_ids: Vec<u64>,
+    /// The total amount of base tokens sent to the recipient.
+    pub base_token_amount: Uint128,
+    /// The address that received the base tokens.
+    pub recipient: Addr,
+}
+
+/// Data set on the response to `ProcessMaturedClaims`.
+#[cw_serde]
+pub struct ProcessMaturedClaimsResponseData {
+    /// The IDs of the unlocking positions that were withdrawn.
+    pub lockup_ids: Vec<u64>,
+    /// The total amount of base tokens withdrawn, including the keeper fee.
+    pub base_token_amount: Uint128,
+    /// The total keeper fee sent to the sender.
+    pub keeper_fee: Uint128,
+}
+
+/// Data set on the response to `WithdrawUnlocked`. The response to `Unlock`
+/// instead has the created
+/// [`cw_vault_standard::extensions::lockup::UnlockingPosition`] as data.
+#[cw_serde]
+pub struct WithdrawUnlockedResponseData {
+    /// The ID of the unlocking position that was withdrawn from.
+    pub lockup_id: u64,
+    /// The amount of base tokens sent to the recipient.
+    pub base_token_amount: Uint128,
+    /// The address that received the base tokens.
+    pub recipient: Addr,
+}
+
+/// Data set on the response to `EarlyExitUnlocking`.
+#[cw_serde]
+pub struct EarlyExitResponseData {
+    /// The ID of the unlocking position that was withdrawn.
+    pub lockup_id: u64,
+    /// The amount of base tokens sent to the recipient.
+    pub base_token_amount: Uint128,
+    /// The amount of base tokens charged as early exit penalty.
+    pub penalty_amount: Uint128,
+    /// The address that received the base tokens.
+    pub recipient: Addr,
+}
+
+/// Data set on the response to `Unlock` in epoch mode, in which the unlock
+/// request is added to the open unlock batch instead of creating an unlocking
+/// position.
+#[cw_serde]
+pub struct UnlockBatchRequestResponseData {
+    /// The ID of the unlock batch the request was added to.
+    pub batch_id: u64,
+    /// The shares issued to the owner in the batch.
+    pub shares: Uint128,
+    /// The end of the epoch of the batch, after which the batch can be
+    /// unlocked.
+    pub epoch_end: Timestamp,
+}
+
+/// Data set on the response to `WithdrawUnlockBatch`.
+#[cw_serde]
+pub struct WithdrawUnlockBatchResponseData {
+    /// The ID of the unlock batch that was withdrawn from.
+    pub batch_id: u64,
+    /// The amount of base tokens sent to the recipient.
+    pub base_token_amount: Uint128,
+    /// The address that received the base tokens.
+    pub recipient: Addr,
 }
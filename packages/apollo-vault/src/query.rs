@@ -1,11 +1,14 @@
 use crate::AutocompoundingVault;
-use cosmwasm_std::Env;
+use cosmwasm_std::{Addr, Env, StdError};
+use cw2::{get_contract_version, ContractVersion};
+use cw_controllers::AdminResponse;
 use cw_vault_token::VaultToken;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::msg::StateResponse;
-use cosmwasm_std::{Deps, StdResult};
+use crate::msg::{RewardRouteResponse, SolvencyResponse, StateResponse};
+use crate::state::{AdminTransferProposal, ChangeLogEntry, Config};
+use cosmwasm_std::{Deps, StdResult, Uint128};
 
 impl<'a, S, P, V> AutocompoundingVault<'a, S, P, V>
 where
@@ -27,6 +30,10 @@ where
         let config = self.config.load(deps.storage)?;
         let staking = self.staking.load(deps.storage)?;
         let pool = self.pool.load(deps.storage)?;
+        let redemption_buffer = self
+            .redemption_buffer
+            .may_load(deps.storage)?
+            .unwrap_or_default();
 
         Ok(StateResponse {
             admin,
@@ -36,6 +43,131 @@ where
             config,
             staking,
             pool,
+            redemption_buffer,
         })
     }
+
+    /// Returns a comparison of the base tokens owed to depositors with the base
+    /// tokens held by the staking module and the vault. The amounts held by
+    /// the staking module must be queried by the contract, since they depend
+    /// on the staking implementation.
+    ///
+    /// ## Arguments
+    /// - module_staked_base_tokens: The base tokens staked in the staking
+    ///   module by the vault.
+    /// - module_unlocking_base_tokens: The base tokens unlocking in the staking
+    ///   module for the vault.
+    pub fn query_solvency(
+        &self,
+        deps: Deps,
+        env: Env,
+        module_staked_base_tokens: Uint128,
+        module_unlocking_base_tokens: Uint128,
+    ) -> StdResult<SolvencyResponse> {
+        let total_staked_base_tokens = self
+            .base_vault
+            .total_staked_base_tokens
+            .load(deps.storage)?;
+        let total_unlocking_base_tokens = self.claims.total_unlocking(deps.storage)?;
+        let idle_base_tokens = self
+            .base_vault
+            .base_token
+            .load(deps.storage)?
+            .query_balance(&deps.querier, &env.contract.address)?;
+
+        let owed = total_staked_base_tokens.checked_add(total_unlocking_base_tokens)?;
+        let held = module_staked_base_tokens
+            .checked_add(module_unlocking_base_tokens)?
+            .checked_add(idle_base_tokens)?;
+
+        Ok(SolvencyResponse {
+            total_staked_base_tokens,
+            total_unlocking_base_tokens,
+            module_staked_base_tokens,
+            module_unlocking_base_tokens,
+            idle_base_tokens,
+            shortfall: owed.saturating_sub(held),
+        })
+    }
+
+    /// Returns the config of the vault.
+    pub fn query_config(&self, deps: Deps) -> StdResult<Config> {
+        self.config.load(deps.storage)
+    }
+
+    /// Returns the admin of the vault.
+    pub fn query_admin(&self, deps: Deps) -> StdResult<AdminResponse> {
+        self.admin.query_admin(deps)
+    }
+
+    /// Returns the pending admin transfer or admin renunciation, or `None` if
+    /// there is none. Expired proposals are returned until they are replaced
+    /// or dropped.
+    pub fn query_pending_admin_transfer(
+        &self,
+        deps: Deps,
+    ) -> StdResult<Option<AdminTransferProposal>> {
+        self.admin_transfer.may_load(deps.storage)
+    }
+
+    /// Returns the addresses that are allowed to force withdraw from the vault.
+    pub fn query_force_withdraw_whitelist(&self, deps: Deps) -> StdResult<Vec<Addr>> {
+        Ok(self.config.load(deps.storage)?.force_withdraw_whitelist)
+    }
+
+    /// Returns the cw2 contract name and version.
+    pub fn query_contract_version(&self, deps: Deps) -> StdResult<ContractVersion> {
+        get_contract_version(deps.storage)
+    }
+
+    /// Returns the router path used to swap each reward asset into the reward
+    /// liquidation target.
+    pub fn query_reward_routes(&self, deps: Deps) -> StdResult<Vec<RewardRouteResponse>> {
+        let cfg = self.config.load(deps.storage)?;
+
+        cfg.reward_assets
+            .into_iter()
+            .map(|reward_asset| {
+                // The reward liquidation target is never swapped
+                if reward_asset == cfg.reward_liquidation_target {
+                    return Ok(RewardRouteResponse {
+                        reward_asset,
+                        path: None,
+                    });
+                }
+
+                // We map the error here for the same reason as in
+                // `ConfigUnchecked::check`, so that the query error is readable.
+                let path = cfg
+                    .router
+                    .query_path_for_pair(
+                        &deps.querier,
+                        &reward_asset,
+                        &cfg.reward_liquidation_target,
+                    )
+                    .map_err(|_| {
+                        StdError::generic_err(format!(
+                            "Could not read path in cw-dex-router for {:?} -> {:?}",
+                            reward_asset, cfg.reward_liquidation_target
+                        ))
+                    })?;
+
+                Ok(RewardRouteResponse {
+                    reward_asset,
+                    path: Some(path),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns entries from the change log of the vault. Optional arguments
+    /// `start_after` and `limit` can be used for pagination.
+    pub fn query_change_log(
+        &self,
+        deps: Deps,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<ChangeLogEntry>> {
+        self.change_log.query_entries(deps, start_after, limit)
+    }
 }
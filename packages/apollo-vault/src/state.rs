@@ -1,11 +1,13 @@
 use apollo_cw_asset::{AssetInfo, AssetInfoBase};
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    Addr, BlockInfo, Decimal, Deps, MessageInfo, Order, StdError, StdResult, Storage, Uint128,
+    Addr, BlockInfo, Decimal, Deps, Empty, MessageInfo, Order, StdError, StdResult, Storage,
+    Timestamp, Uint128,
 };
 use cw20::Expiration;
 use cw_dex_router::helpers::CwDexRouterBase;
-use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, Item, MultiIndex};
+use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use cw_utils::Duration;
 use cw_vault_standard::extensions::lockup::UnlockingPosition;
 use liquidity_helper::LiquidityHelperBase;
 use schemars::JsonSchema;
@@ -37,8 +39,53 @@ pub struct ConfigBase<T> {
     pub force_withdraw_whitelist: Vec<T>,
     /// Helper for providing liquidity with unbalanced assets.
     pub liquidity_helper: LiquidityHelperBase<T>,
+    /// Percentage of an unlocking position's base tokens that is charged as a
+    /// penalty when its owner withdraws it before it has finished unlocking.
+    /// Early exit is disabled if zero.
+    #[serde(default)]
+    pub early_exit_penalty: Decimal,
+    /// If true, the early exit penalty decays linearly from
+    /// `early_exit_penalty` at the start of the unlocking period to zero at
+    /// its end.
+    #[serde(default)]
+    pub early_exit_penalty_decay: bool,
+    /// If true, early exit penalties are sent to the treasury. Otherwise they
+    /// stay in the vault, increasing the value of the vault tokens.
+    #[serde(default)]
+    pub early_exit_penalty_to_treasury: bool,
+    /// Percentage of a matured unlocking position's base tokens that is paid
+    /// to the keeper that withdraws it to an owner who opted in to
+    /// auto-withdrawal. Capped at [`MAX_AUTO_WITHDRAW_KEEPER_FEE`].
+    #[serde(default)]
+    pub auto_withdraw_keeper_fee: Decimal,
+    /// Minimum amount of base tokens that can be unlocked in a single unlock.
+    #[serde(default)]
+    pub min_unlock_amount: Uint128,
+    /// Maximum number of unlocking positions that an owner can have open at
+    /// the same time. Unlimited if zero.
+    #[serde(default)]
+    pub max_unlocking_positions_per_owner: u32,
+    /// Percentage of the vault's base tokens that is kept unstaked in a buffer
+    /// from which vault tokens can be redeemed instantly. The buffer is
+    /// refilled when compounding and on deposits. Instant redemption is
+    /// disabled if zero. Capped at [`MAX_REDEMPTION_BUFFER_RATIO`].
+    #[serde(default)]
+    pub redemption_buffer_ratio: Decimal,
+    /// Percentage of the base tokens of an instant redemption that is charged
+    /// as a fee. The fee stays in the vault, increasing the value of the vault
+    /// tokens.
+    #[serde(default)]
+    pub instant_redemption_fee: Decimal,
 }
 
+/// The maximum keeper fee for auto-withdrawal of matured unlocking positions.
+pub const MAX_AUTO_WITHDRAW_KEEPER_FEE: Decimal = Decimal::raw(10_000_000_000_000_000); // 1%
+
+/// The maximum ratio of the vault's base tokens kept unstaked in the
+/// redemption buffer. Unlocks that are larger than the locked base tokens are
+/// partly taken from the buffer, so most of the base tokens must stay locked.
+pub const MAX_REDEMPTION_BUFFER_RATIO: Decimal = Decimal::raw(250_000_000_000_000_000); // 25%
+
 /// Config with non-validated addresses.
 pub type ConfigUnchecked = ConfigBase<String>;
 /// Config with validated addresses.
@@ -72,9 +119,63 @@ impl Config {
             liquidity_helper: updates
                 .liquidity_helper
                 .unwrap_or_else(|| self.liquidity_helper.into()),
+            early_exit_penalty: updates
+                .early_exit_penalty
+                .unwrap_or(self.early_exit_penalty),
+            early_exit_penalty_decay: updates
+                .early_exit_penalty_decay
+                .unwrap_or(self.early_exit_penalty_decay),
+            early_exit_penalty_to_treasury: updates
+                .early_exit_penalty_to_treasury
+                .unwrap_or(self.early_exit_penalty_to_treasury),
+            auto_withdraw_keeper_fee: updates
+                .auto_withdraw_keeper_fee
+                .unwrap_or(self.auto_withdraw_keeper_fee),
+            min_unlock_amount: updates.min_unlock_amount.unwrap_or(self.min_unlock_amount),
+            max_unlocking_positions_per_owner: updates
+                .max_unlocking_positions_per_owner
+                .unwrap_or(self.max_unlocking_positions_per_owner),
+            redemption_buffer_ratio: updates
+                .redemption_buffer_ratio
+                .unwrap_or(self.redemption_buffer_ratio),
+            instant_redemption_fee: updates
+                .instant_redemption_fee
+                .unwrap_or(self.instant_redemption_fee),
         }
         .check(deps)
     }
+
+    /// Returns the early exit penalty rate for an unlocking position that is
+    /// released at `release_at` after unlocking for `lockup_duration`. If the
+    /// penalty decays, it is scaled by the fraction of the unlocking period
+    /// that remains. The penalty does not decay if `release_at` and
+    /// `lockup_duration` are not in the same unit.
+    pub fn early_exit_penalty_rate(
+        &self,
+        release_at: &Expiration,
+        lockup_duration: &Duration,
+        block: &BlockInfo,
+    ) -> Decimal {
+        if !self.early_exit_penalty_decay {
+            return self.early_exit_penalty;
+        }
+
+        let (remaining, duration) = match (release_at, lockup_duration) {
+            (Expiration::AtTime(time), Duration::Time(duration)) => (
+                time.seconds().saturating_sub(block.time.seconds()),
+                *duration,
+            ),
+            (Expiration::AtHeight(height), Duration::Height(duration)) => {
+                (height.saturating_sub(block.height), *duration)
+            }
+            _ => return self.early_exit_penalty,
+        };
+        if duration == 0 {
+            return self.early_exit_penalty;
+        }
+
+        self.early_exit_penalty * Decimal::from_ratio(remaining.min(duration), duration)
+    }
 }
 
 impl ConfigUnchecked {
@@ -85,6 +186,28 @@ impl ConfigUnchecked {
                 "Performance fee cannot be greater than 100%",
             ));
         }
+        if self.early_exit_penalty > Decimal::one() {
+            return Err(StdError::generic_err(
+                "Early exit penalty cannot be greater than 100%",
+            ));
+        }
+        if self.redemption_buffer_ratio > MAX_REDEMPTION_BUFFER_RATIO {
+            return Err(StdError::generic_err(format!(
+                "Redemption buffer ratio cannot be greater than {}",
+                MAX_REDEMPTION_BUFFER_RATIO
+            )));
+        }
+        if self.instant_redemption_fee > Decimal::one() {
+            return Err(StdError::generic_err(
+                "Instant redemption fee cannot be greater than 100%",
+            ));
+        }
+        if self.auto_withdraw_keeper_fee > MAX_AUTO_WITHDRAW_KEEPER_FEE {
+            return Err(StdError::generic_err(format!(
+                "Auto withdraw keeper fee cannot be greater than {}",
+                MAX_AUTO_WITHDRAW_KEEPER_FEE
+            )));
+        }
 
         let reward_assets: Vec<AssetInfo> = self
             .reward_assets
@@ -128,6 +251,14 @@ impl ConfigUnchecked {
                 .map(|x| deps.api.addr_validate(x))
                 .collect::<StdResult<_>>()?,
             liquidity_helper: self.liquidity_helper.check(deps.api)?,
+            early_exit_penalty: self.early_exit_penalty,
+            early_exit_penalty_decay: self.early_exit_penalty_decay,
+            early_exit_penalty_to_treasury: self.early_exit_penalty_to_treasury,
+            auto_withdraw_keeper_fee: self.auto_withdraw_keeper_fee,
+            min_unlock_amount: self.min_unlock_amount,
+            max_unlocking_positions_per_owner: self.max_unlocking_positions_per_owner,
+            redemption_buffer_ratio: self.redemption_buffer_ratio,
+            instant_redemption_fee: self.instant_redemption_fee,
         })
     }
 }
@@ -138,6 +269,15 @@ impl ConfigUnchecked {
 
 // Settings for pagination
 const DEFAULT_LIMIT: u32 = 10;
+/// The maximum page size of paginated queries and of `claim_all_matured`.
+pub const MAX_LIMIT: u32 = 50;
+/// The default number of claims reindexed by [`Claims::reindex`].
+pub const DEFAULT_REINDEX_LIMIT: u32 = 100;
+
+/// Returns `limit` or [`DEFAULT_LIMIT`] if `None`, capped at [`MAX_LIMIT`].
+fn page_size(limit: Option<u32>) -> usize {
+    limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize
+}
 
 /// An unlockin position for a user that can be claimed once it has matured.
 pub type Claim = UnlockingPosition;
@@ -157,6 +297,33 @@ pub struct Claims<'a> {
     // increasing and is not decremented when a claim is removed. It represents the number of
     // claims that have been created since creation of the `Claims` instance.
     next_claim_id: Item<'a, u64>,
+    /// Operators that are approved to transfer the claims of an owner, keyed
+    /// by `(owner, operator)`, with the expiration of the approval as value.
+    operators: Map<'a, (&'a Addr, &'a Addr), Expiration>,
+    /// The total amount of base tokens in all claims. Increased when a claim
+    /// is committed and decreased when tokens are claimed or force claimed.
+    total_unlocking: Item<'a, Uint128>,
+    /// Owners that opted in to have their matured claims withdrawn to them by
+    /// keepers.
+    auto_withdraw: Map<'a, &'a Addr, Empty>,
+    /// Index of the claims of owners that opted in to auto-withdrawal, keyed
+    /// by the [`release_at_key`] of the claim followed by its ID, so that
+    /// keepers only read claims that can be auto-withdrawn.
+    auto_withdraw_claims: Map<'a, (u8, u64, u64), Empty>,
+    /// The ID of the next claim to reindex, if a reindex started with
+    /// [`Claims::start_reindex`] is in progress. Claims with a lower ID have
+    /// been reindexed and are included in `total_unlocking`.
+    reindex_cursor: Item<'a, u64>,
+    /// The lockup duration that each claim was unlocked with, keyed by claim
+    /// ID. Claims created before the duration was recorded have no entry.
+    lockup_durations: Map<'a, u64, Duration>,
+    /// The lockup duration of the pending claim, saved to `lockup_durations`
+    /// when the pending claim is committed.
+    pending_lockup_duration: Item<'a, Duration>,
+    /// The total amount of base tokens in claims maturing at each
+    /// [`release_at_key`]. Kept up to date together with `total_unlocking`,
+    /// so that totals can be read per maturity rather than per claim.
+    release_totals: Map<'a, (u8, u64), Uint128>,
 }
 
 /// Helper struct for indexing claims. Needed by the [`IndexedMap`]
@@ -164,15 +331,98 @@ pub struct Claims<'a> {
 pub struct ClaimIndexes<'a> {
     /// Index mapping an address to all claims for that address.
     pub owner: MultiIndex<'a, Addr, Claim, u64>,
+    /// Index of all claims ordered by when they mature. See
+    /// [`release_at_key`] for the format of the index key.
+    pub release_at: MultiIndex<'a, (u8, u64), Claim, u64>,
+    /// Index of the claims of each owner ordered by when they mature.
+    pub owner_release_at: MultiIndex<'a, (Addr, u8, u64), Claim, u64>,
 }
 
 impl<'a> IndexList<Claim> for ClaimIndexes<'a> {
     fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Claim>> + '_> {
-        let v: Vec<&dyn Index<Claim>> = vec![&self.owner];
+        let v: Vec<&dyn Index<Claim>> = vec![&self.owner, &self.release_at, &self.owner_release_at];
         Box::new(v.into_iter())
     }
 }
 
+/// Returns the key of an [`Expiration`] in the `release_at` index. Claims
+/// expiring at a block height are ordered before claims expiring at a time,
+/// which are ordered before claims that never expire.
+fn release_at_key(release_at: &Expiration) -> (u8, u64) {
+    match release_at {
+        Expiration::AtHeight(height) => (0, *height),
+        Expiration::AtTime(time) => (1, time.nanos()),
+        Expiration::Never {} => (2, 0),
+    }
+}
+
+/// Returns the inclusive ranges of `release_at` index keys of claims that have
+/// matured at `block`.
+fn matured_release_at_keys(block: &BlockInfo) -> [((u8, u64), (u8, u64)); 2] {
+    [
+        ((0, 0), (0, block.height)),
+        ((1, 0), (1, block.time.nanos())),
+    ]
+}
+
+/// Returns the inclusive ranges of `release_at` index keys of claims that have
+/// not matured at `block`.
+fn pending_release_at_keys(block: &BlockInfo) -> [((u8, u64), (u8, u64)); 3] {
+    [
+        ((0, block.height + 1), (0, u64::MAX)),
+        ((1, block.time.nanos() + 1), (1, u64::MAX)),
+        ((2, 0), (2, u64::MAX)),
+    ]
+}
+
+/// Filter for which unlocking positions to return in
+/// [`Claims::query_all_claims`].
+#[cw_serde]
+pub enum UnlockingPositionFilter {
+    /// Only positions that have matured and can be withdrawn.
+    Matured,
+    /// Only positions that have not yet matured.
+    Pending,
+}
+
+/// The place in the order of [`Claims::query_all_claims`] to continue after,
+/// taken from the last unlocking position of the previous page. It remains
+/// valid after that position has been withdrawn.
+#[cw_serde]
+pub struct UnlockingPositionCursor {
+    /// When the unlocking position matures.
+    pub release_at: Expiration,
+    /// The ID of the unlocking position.
+    pub id: u64,
+}
+
+/// Aggregate amounts of base tokens in unlocking positions.
+#[cw_serde]
+pub struct UnlockingTotals {
+    /// The base tokens in all positions that have not yet been withdrawn.
+    pub total: Uint128,
+    /// The base tokens in positions that have matured but not yet been
+    /// withdrawn.
+    pub matured: Uint128,
+    /// The base tokens in positions that have not yet matured.
+    pub pending: Uint128,
+    /// The base tokens maturing in consecutive time buckets starting at the
+    /// current block time. Positions maturing at a block height are not
+    /// included in any bucket.
+    pub buckets: Vec<UnlockingBucket>,
+}
+
+/// The base tokens in unlocking positions maturing in a time bucket.
+#[cw_serde]
+pub struct UnlockingBucket {
+    /// The time at which the bucket ends (inclusive). The bucket starts after
+    /// the end of the previous bucket, or after the current block time for the
+    /// first bucket.
+    pub end: Timestamp,
+    /// The base tokens in positions maturing in the bucket.
+    pub base_token_amount: Uint128,
+}
+
 impl<'a> Claims<'a> {
     /// Create a new Claims instance
     ///
@@ -180,11 +430,41 @@ impl<'a> Claims<'a> {
     /// * `claims_namespace` - The key to use for the the primary key (u64
     ///   lockup ID)
     /// * `num_claims_key` - The key to use for the index value (owner addr)
+    /// * `release_at_index_namespace` - The key to use for the index on
+    ///   `release_at`
+    /// * `operators_namespace` - The key to use for the map of approved
+    ///   operators
+    /// * `total_unlocking_key` - The key to use for the total amount of base
+    ///   tokens in all claims
+    /// * `auto_withdraw_namespace` - The key to use for the set of owners that
+    ///   opted in to auto-withdrawal
+    /// * `auto_withdraw_claims_namespace` - The key to use for the index of
+    ///   claims of owners that opted in to auto-withdrawal
+    /// * `reindex_cursor_key` - The key to use for the progress of a reindex
+    /// * `lockup_durations_namespace` - The key to use for the lockup duration
+    ///   of each claim
+    /// * `pending_lockup_duration_key` - The key to use for the lockup
+    ///   duration of the pending claim
+    /// * `owner_release_at_index_namespace` - The key to use for the index on
+    ///   owner and `release_at`
+    /// * `release_totals_namespace` - The key to use for the total amount of
+    ///   base tokens in claims maturing at each `release_at`
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         claims_namespace: &'a str,
         claims_index_namespace: &'a str,
         pending_claims_key: &'a str,
         num_claims_key: &'a str,
+        release_at_index_namespace: &'a str,
+        operators_namespace: &'a str,
+        total_unlocking_key: &'a str,
+        auto_withdraw_namespace: &'a str,
+        auto_withdraw_claims_namespace: &'a str,
+        reindex_cursor_key: &'a str,
+        lockup_durations_namespace: &'a str,
+        pending_lockup_duration_key: &'a str,
+        owner_release_at_index_namespace: &'a str,
+        release_totals_namespace: &'a str,
     ) -> Self {
         let indexes = ClaimIndexes {
             owner: MultiIndex::new(
@@ -192,15 +472,66 @@ impl<'a> Claims<'a> {
                 claims_namespace,
                 claims_index_namespace,
             ),
+            release_at: MultiIndex::new(
+                |_pk, d| release_at_key(&d.release_at),
+                claims_namespace,
+                release_at_index_namespace,
+            ),
+            owner_release_at: MultiIndex::new(
+                |_pk, d| {
+                    let (kind, value) = release_at_key(&d.release_at);
+                    (d.owner.clone(), kind, value)
+                },
+                claims_namespace,
+                owner_release_at_index_namespace,
+            ),
         };
 
         Self {
             claims: IndexedMap::new(claims_namespace, indexes),
             pending_claim: Item::new(pending_claims_key),
             next_claim_id: Item::new(num_claims_key),
+            operators: Map::new(operators_namespace),
+            total_unlocking: Item::new(total_unlocking_key),
+            auto_withdraw: Map::new(auto_withdraw_namespace),
+            auto_withdraw_claims: Map::new(auto_withdraw_claims_namespace),
+            reindex_cursor: Item::new(reindex_cursor_key),
+            lockup_durations: Map::new(lockup_durations_namespace),
+            pending_lockup_duration: Item::new(pending_lockup_duration_key),
+            release_totals: Map::new(release_totals_namespace),
+        }
+    }
+
+    /// Returns the key of `claim` in the `auto_withdraw_claims` index.
+    fn auto_withdraw_claims_key(claim: &Claim) -> (u8, u64, u64) {
+        let (kind, value) = release_at_key(&claim.release_at);
+        (kind, value, claim.id)
+    }
+
+    /// Saves `claim` to the claims map, and adds it to or removes it from the
+    /// index of auto-withdrawable claims depending on whether its owner opted
+    /// in to auto-withdrawal.
+    fn save_claim(&self, storage: &mut dyn Storage, claim: &Claim) -> StdResult<()> {
+        self.claims.save(storage, claim.id, claim)?;
+        let key = Self::auto_withdraw_claims_key(claim);
+        if self.is_auto_withdraw(storage, &claim.owner) {
+            self.auto_withdraw_claims.save(storage, key, &Empty {})
+        } else {
+            self.auto_withdraw_claims.remove(storage, key);
+            Ok(())
         }
     }
 
+    /// Removes `claim` from the claims map and from the index of
+    /// auto-withdrawable claims.
+    fn remove_claim(&self, storage: &mut dyn Storage, claim: &Claim) -> StdResult<()> {
+        self.claims.remove(storage, claim.id)?;
+        self.lockup_durations.remove(storage, claim.id);
+        self.auto_withdraw_claims
+            .remove(storage, Self::auto_withdraw_claims_key(claim));
+        Ok(())
+    }
+
     /// Create a pending claim that can be saved to the claims by calling
     /// `save_pending_claim`.
     ///
@@ -242,6 +573,26 @@ impl<'a> Claims<'a> {
         }
     }
 
+    /// Sets the lockup duration that the pending claim is unlocked with. It is
+    /// saved together with the claim when the pending claim is committed.
+    pub fn set_pending_lockup_duration(
+        &self,
+        storage: &mut dyn Storage,
+        lockup_duration: &Duration,
+    ) -> StdResult<()> {
+        self.pending_lockup_duration.save(storage, lockup_duration)
+    }
+
+    /// Returns the lockup duration that the claim with ID `lock_id` was
+    /// unlocked with, or `None` if it was not recorded.
+    pub fn lockup_duration(
+        &self,
+        storage: &dyn Storage,
+        lock_id: u64,
+    ) -> StdResult<Option<Duration>> {
+        self.lockup_durations.may_load(storage, lock_id)
+    }
+
     /// Sets the pending claim. This will overwrite any existing pending claim.
     pub fn set_pending_claim(&self, storage: &mut dyn Storage, claim: &Claim) -> StdResult<()> {
         self.pending_claim.save(storage, claim)
@@ -274,7 +625,13 @@ impl<'a> Claims<'a> {
             ))),
             None => {
                 self.pending_claim.remove(storage);
-                self.claims.save(storage, pending_claim.id, &pending_claim)
+                if let Some(lockup_duration) = self.pending_lockup_duration.may_load(storage)? {
+                    self.pending_lockup_duration.remove(storage);
+                    self.lockup_durations
+                        .save(storage, pending_claim.id, &lockup_duration)?;
+                }
+                self.add_total_unlocking(storage, &pending_claim, pending_claim.base_token_amount)?;
+                self.save_claim(storage, &pending_claim)
             }
         }
     }
@@ -309,7 +666,8 @@ impl<'a> Claims<'a> {
         }
 
         // Remove the claim from the map
-        self.claims.remove(storage, lock_id)?;
+        self.remove_claim(storage, &claim)?;
+        self.sub_total_unlocking(storage, &claim, claim.base_token_amount)?;
 
         Ok(claim.base_token_amount)
     }
@@ -346,14 +704,407 @@ impl<'a> Claims<'a> {
 
         if left_after_claim > Uint128::zero() {
             lockup.base_token_amount = left_after_claim;
-            self.claims.save(storage, lock_id, &lockup)?;
+            self.save_claim(storage, &lockup)?;
         } else {
-            self.claims.remove(storage, lock_id)?;
+            self.remove_claim(storage, &lockup)?;
         }
+        self.sub_total_unlocking(storage, &lockup, claimed)?;
 
         Ok(claimed)
     }
 
+    /// Decrease the amount of the claim with ID `lock_id` by `amount`, deleting
+    /// the claim if nothing is left. Does not check the owner of the claim or
+    /// whether it has matured.
+    pub fn decrease_claim(
+        &self,
+        storage: &mut dyn Storage,
+        lock_id: u64,
+        amount: Uint128,
+    ) -> StdResult<()> {
+        let mut claim = self.claims.load(storage, lock_id)?;
+        claim.base_token_amount = claim.base_token_amount.checked_sub(amount)?;
+        if claim.base_token_amount.is_zero() {
+            self.remove_claim(storage, &claim)?;
+        } else {
+            self.save_claim(storage, &claim)?;
+        }
+        self.sub_total_unlocking(storage, &claim, amount)
+    }
+
+    /// Redeem all matured claims of `owner` for the underlying tokens, in the
+    /// order in which they matured. Only matured claims of `owner` are read,
+    /// so claims that have not matured do not add to the cost.
+    ///
+    /// ## Arguments
+    /// * `owner` - The owner of the claims
+    /// * `limit` - Optional maximum number of claims to redeem. Defaults to
+    ///   [`DEFAULT_LIMIT`] and is capped at [`MAX_LIMIT`].
+    ///
+    /// ## Returns
+    /// Returns the IDs of the redeemed claims and the total amount of tokens
+    /// redeemed. Returns an error if `owner` has no matured claims.
+    pub fn claim_all_matured(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        owner: &Addr,
+        limit: Option<u32>,
+    ) -> StdResult<(Vec<u64>, Uint128)> {
+        let limit = page_size(limit);
+
+        let mut matured = vec![];
+        for ((kind, _), (_, hi)) in matured_release_at_keys(block) {
+            matured.extend(
+                self.claims
+                    .idx
+                    .owner_release_at
+                    .sub_prefix((owner.clone(), kind))
+                    .range(
+                        storage,
+                        None,
+                        Some(Bound::inclusive((hi, u64::MAX))),
+                        Order::Ascending,
+                    )
+                    .take(limit - matured.len())
+                    .collect::<StdResult<Vec<_>>>()?,
+            );
+        }
+
+        if matured.is_empty() {
+            return Err(StdError::generic_err("No matured claims."));
+        }
+
+        // Remove the claims from the map
+        let mut total = Uint128::zero();
+        let mut ids = Vec::with_capacity(matured.len());
+        for (id, claim) in matured {
+            self.remove_claim(storage, &claim)?;
+            self.sub_total_unlocking(storage, &claim, claim.base_token_amount)?;
+            total = total.checked_add(claim.base_token_amount)?;
+            ids.push(id);
+        }
+
+        Ok((ids, total))
+    }
+
+    /// Transfer ownership of a claim to `new_owner`.
+    ///
+    /// ## Arguments
+    /// * `sender` - The address transferring the claim. Must be the owner of
+    ///   the claim or an operator approved by the owner.
+    /// * `lock_id` - The id of the claim
+    /// * `new_owner` - The new owner of the claim
+    ///
+    /// ## Returns
+    /// Returns the claim with the updated owner. Returns an error if the claim
+    /// does not exist or if `sender` is neither the owner nor an approved
+    /// operator.
+    pub fn transfer_claim(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        sender: &Addr,
+        lock_id: u64,
+        new_owner: &Addr,
+    ) -> StdResult<Claim> {
+        let mut claim = self.claims.load(storage, lock_id)?;
+
+        // Ensure the claim is owned by the sender, or that the sender is approved
+        // by the owner
+        if claim.owner != *sender && !self.is_operator(storage, block, &claim.owner, sender)? {
+            return Err(StdError::generic_err("Claim not owned by sender"));
+        }
+
+        // Saving the claim also updates the owner index
+        claim.owner = new_owner.clone();
+        self.save_claim(storage, &claim)?;
+
+        Ok(claim)
+    }
+
+    /// Set the owner of the claim with ID `lock_id` without any checks.
+    /// Returns an error if the claim does not exist.
+    pub fn set_claim_owner(
+        &self,
+        storage: &mut dyn Storage,
+        lock_id: u64,
+        owner: &Addr,
+    ) -> StdResult<()> {
+        let mut claim = self.claims.load(storage, lock_id)?;
+        if claim.owner != *owner {
+            claim.owner = owner.clone();
+            self.save_claim(storage, &claim)?;
+        }
+        Ok(())
+    }
+
+    /// Approve `operator` to transfer all claims of `owner` until `expires`.
+    /// Overwrites any existing approval of `operator`.
+    pub fn approve_operator(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        owner: &Addr,
+        operator: &Addr,
+        expires: Expiration,
+    ) -> StdResult<()> {
+        if owner == operator {
+            return Err(StdError::generic_err("Cannot approve self as operator"));
+        }
+        if expires.is_expired(block) {
+            return Err(StdError::generic_err(
+                "Approval expiration is already expired",
+            ));
+        }
+        self.operators.save(storage, (owner, operator), &expires)
+    }
+
+    /// Revoke the approval of `operator` to transfer the claims of `owner`.
+    pub fn revoke_operator(
+        &self,
+        storage: &mut dyn Storage,
+        owner: &Addr,
+        operator: &Addr,
+    ) -> StdResult<()> {
+        if !self.operators.has(storage, (owner, operator)) {
+            return Err(StdError::generic_err("Operator not approved"));
+        }
+        self.operators.remove(storage, (owner, operator));
+        Ok(())
+    }
+
+    /// Returns true if `operator` has an unexpired approval to transfer the
+    /// claims of `owner`.
+    pub fn is_operator(
+        &self,
+        storage: &dyn Storage,
+        block: &BlockInfo,
+        owner: &Addr,
+        operator: &Addr,
+    ) -> StdResult<bool> {
+        Ok(matches!(
+            self.operators.may_load(storage, (owner, operator))?,
+            Some(expires) if !expires.is_expired(block)
+        ))
+    }
+
+    /// Opt `owner` in to or out of auto-withdrawal of matured claims. The
+    /// existing claims of `owner` are added to or removed from the index of
+    /// auto-withdrawable claims.
+    pub fn set_auto_withdraw(
+        &self,
+        storage: &mut dyn Storage,
+        owner: &Addr,
+        enabled: bool,
+    ) -> StdResult<()> {
+        if enabled {
+            self.auto_withdraw.save(storage, owner, &Empty {})?;
+        } else {
+            self.auto_withdraw.remove(storage, owner);
+        }
+
+        let keys = self
+            .claims
+            .idx
+            .owner
+            .prefix(owner.clone())
+            .range(storage, None, None, Order::Ascending)
+            .map(|res| res.map(|(_, claim)| Self::auto_withdraw_claims_key(&claim)))
+            .collect::<StdResult<Vec<_>>>()?;
+        for key in keys {
+            if enabled {
+                self.auto_withdraw_claims.save(storage, key, &Empty {})?;
+            } else {
+                self.auto_withdraw_claims.remove(storage, key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if `owner` opted in to auto-withdrawal of matured claims.
+    pub fn is_auto_withdraw(&self, storage: &dyn Storage, owner: &Addr) -> bool {
+        self.auto_withdraw.has(storage, owner)
+    }
+
+    /// Reads up to `limit` matured claims of owners that opted in to
+    /// auto-withdrawal, in the order in which they matured. The claims are not
+    /// removed. Only the index of auto-withdrawable claims is read, so claims
+    /// of other owners do not add to the cost of this function.
+    pub fn matured_auto_withdraw_claims(
+        &self,
+        storage: &dyn Storage,
+        block: &BlockInfo,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<Claim>> {
+        let limit = page_size(limit);
+
+        let mut ids = vec![];
+        for ((lo_kind, lo), (hi_kind, hi)) in matured_release_at_keys(block) {
+            ids.extend(
+                self.auto_withdraw_claims
+                    .keys(
+                        storage,
+                        Some(Bound::inclusive((lo_kind, lo, 0))),
+                        Some(Bound::inclusive((hi_kind, hi, u64::MAX))),
+                        Order::Ascending,
+                    )
+                    .take(limit - ids.len())
+                    .map(|res| res.map(|(_, _, id)| id))
+                    .collect::<StdResult<Vec<_>>>()?,
+            );
+        }
+        ids.into_iter()
+            .map(|id| self.claims.load(storage, id))
+            .collect()
+    }
+
+    /// Returns the total amount of base tokens in all claims.
+    pub fn total_unlocking(&self, storage: &dyn Storage) -> StdResult<Uint128> {
+        Ok(self.total_unlocking.may_load(storage)?.unwrap_or_default())
+    }
+
+    /// Returns true if the claim with ID `lock_id` is included in
+    /// `total_unlocking`. While a reindex is in progress, claims that have not
+    /// been reindexed yet are added to the total when they are reindexed, so
+    /// changes to them must not be applied to the total.
+    fn is_in_total(&self, storage: &dyn Storage, lock_id: u64) -> StdResult<bool> {
+        Ok(match self.reindex_cursor.may_load(storage)? {
+            Some(cursor) => lock_id < cursor,
+            None => true,
+        })
+    }
+
+    /// Adds `amount` to `total_unlocking` and to the release total of
+    /// `claim`.
+    fn add_to_totals(
+        &self,
+        storage: &mut dyn Storage,
+        claim: &Claim,
+        amount: Uint128,
+    ) -> StdResult<()> {
+        let total = self.total_unlocking(storage)?.checked_add(amount)?;
+        self.total_unlocking.save(storage, &total)?;
+        self.release_totals.update(
+            storage,
+            release_at_key(&claim.release_at),
+            |release_total| -> StdResult<_> {
+                Ok(release_total.unwrap_or_default().checked_add(amount)?)
+            },
+        )?;
+        Ok(())
+    }
+
+    fn add_total_unlocking(
+        &self,
+        storage: &mut dyn Storage,
+        claim: &Claim,
+        amount: Uint128,
+    ) -> StdResult<()> {
+        if !self.is_in_total(storage, claim.id)? {
+            return Ok(());
+        }
+        self.add_to_totals(storage, claim, amount)
+    }
+
+    fn sub_total_unlocking(
+        &self,
+        storage: &mut dyn Storage,
+        claim: &Claim,
+        amount: Uint128,
+    ) -> StdResult<()> {
+        if !self.is_in_total(storage, claim.id)? {
+            return Ok(());
+        }
+        let total = self.total_unlocking(storage)?.checked_sub(amount)?;
+        self.total_unlocking.save(storage, &total)?;
+
+        let key = release_at_key(&claim.release_at);
+        let release_total = self
+            .release_totals
+            .may_load(storage, key)?
+            .unwrap_or_default()
+            .checked_sub(amount)?;
+        if release_total.is_zero() {
+            self.release_totals.remove(storage, key);
+        } else {
+            self.release_totals.save(storage, key, &release_total)?;
+        }
+        Ok(())
+    }
+
+    /// Starts saving all claims again, so that indexes added after the claims
+    /// were created are populated, and recomputing `total_unlocking` from the
+    /// stored claims. The claims are processed in batches with
+    /// [`Self::reindex`], so that vaults with many claims can be migrated
+    /// without exceeding the gas limit. Used in migrations.
+    ///
+    /// Until the reindex has finished, the indexes and the total only include
+    /// the claims that have been reindexed or changed since it started.
+    pub fn start_reindex(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        let keys = self
+            .release_totals
+            .keys(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for key in keys {
+            self.release_totals.remove(storage, key);
+        }
+        self.reindex_cursor.save(storage, &0)?;
+        self.total_unlocking.save(storage, &Uint128::zero())
+    }
+
+    /// Reindexes up to `limit` claims of the reindex started with
+    /// [`Self::start_reindex`], in the order of their IDs. Returns true if all
+    /// claims have been reindexed, and an error if no reindex is in progress.
+    pub fn reindex(&self, storage: &mut dyn Storage, limit: u32) -> StdResult<bool> {
+        let cursor = self
+            .reindex_cursor
+            .may_load(storage)?
+            .ok_or_else(|| StdError::generic_err("No reindex in progress"))?;
+        let limit = limit.max(1) as usize;
+
+        let claims = self
+            .claims
+            .range(
+                storage,
+                Some(Bound::inclusive(cursor)),
+                None,
+                Order::Ascending,
+            )
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        for (_, claim) in &claims {
+            self.save_claim(storage, claim)?;
+            self.add_to_totals(storage, claim, claim.base_token_amount)?;
+        }
+
+        match claims.last() {
+            Some((id, _)) if claims.len() == limit => {
+                self.reindex_cursor.save(storage, &(id + 1))?;
+                Ok(false)
+            }
+            _ => {
+                self.reindex_cursor.remove(storage);
+                Ok(true)
+            }
+        }
+    }
+
+    /// Returns the ID of the next claim to reindex, or `None` if no reindex is
+    /// in progress.
+    pub fn reindex_cursor(&self, storage: &dyn Storage) -> StdResult<Option<u64>> {
+        self.reindex_cursor.may_load(storage)
+    }
+
+    /// Returns the highest ID of all claims, or `None` if there are no claims.
+    pub fn last_claim_id(&self, storage: &dyn Storage) -> StdResult<Option<u64>> {
+        self.claims
+            .keys(storage, None, None, Order::Descending)
+            .next()
+            .transpose()
+    }
+
     // ========== Query functions ==========
 
     /// Query lockup by id
@@ -361,6 +1112,22 @@ impl<'a> Claims<'a> {
         self.claims.load(deps.storage, lockup_id)
     }
 
+    /// Counts the claims of `owner`, stopping at `limit` claims.
+    pub fn count_claims_for_owner(
+        &self,
+        storage: &dyn Storage,
+        owner: &Addr,
+        limit: usize,
+    ) -> usize {
+        self.claims
+            .idx
+            .owner
+            .prefix(owner.clone())
+            .keys_raw(storage, None, None, Order::Ascending)
+            .take(limit)
+            .count()
+    }
+
     /// Reads all claims for an owner. The optional arguments `start_after` and
     /// `limit` can be used for pagination if there are too many claims to
     /// return in one query.
@@ -376,7 +1143,7 @@ impl<'a> Claims<'a> {
         start_after: Option<u64>,
         limit: Option<u32>,
     ) -> StdResult<Vec<(u64, Claim)>> {
-        let limit = limit.unwrap_or(DEFAULT_LIMIT) as usize;
+        let limit = page_size(limit);
         let start: Option<Bound<u64>> = start_after.map(Bound::exclusive);
 
         self.claims
@@ -387,39 +1154,677 @@ impl<'a> Claims<'a> {
             .take(limit)
             .collect::<StdResult<Vec<_>>>()
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use cosmwasm_std::testing::{
-        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
-    };
-    use cosmwasm_std::{Addr, OwnedDeps, Uint128};
-    use cw_utils::Expiration;
 
-    use test_case::test_case;
+    /// Reads the claims of all owners, ordered by when they mature. The optional
+    /// argument `filter` can be used to only return matured or pending claims,
+    /// and the optional arguments `start_after` and `limit` can be used for
+    /// pagination.
+    ///
+    /// # Arguments
+    /// - `filter` - Optional filter on whether the claims have matured
+    /// - `start_after` - Optional cursor of the claim to start the query after.
+    ///   The claim does not need to exist anymore.
+    /// - `limit` - Optional maximum number of claims to return
+    pub fn query_all_claims(
+        &self,
+        deps: Deps,
+        block: &BlockInfo,
+        filter: Option<UnlockingPositionFilter>,
+        start_after: Option<UnlockingPositionCursor>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<Claim>> {
+        let limit = page_size(limit);
+        let start_after = start_after.map(|cursor| (release_at_key(&cursor.release_at), cursor.id));
+
+        // Inclusive ranges of index keys to iterate, in ascending order
+        let segments = match filter {
+            Some(UnlockingPositionFilter::Matured) => matured_release_at_keys(block).to_vec(),
+            Some(UnlockingPositionFilter::Pending) => pending_release_at_keys(block).to_vec(),
+            None => vec![((0, 0), (2, u64::MAX))],
+        };
 
-    use super::*;
+        let mut claims = vec![];
+        for (lo, hi) in segments {
+            if claims.len() >= limit {
+                break;
+            }
+            let min = match start_after {
+                Some(key) if key >= (lo, 0) => Bound::exclusive(key),
+                _ => Bound::inclusive((lo, 0)),
+            };
+            let max = Bound::inclusive((hi, u64::MAX));
+            for res in self
+                .claims
+                .idx
+                .release_at
+                .range(deps.storage, Some(min), Some(max), Order::Ascending)
+                .take(limit - claims.len())
+            {
+                claims.push(res?.1);
+            }
+        }
+        Ok(claims)
+    }
 
-    const OWNER: &str = "owner";
-    const NOT_OWNER: &str = "not_owner";
+    /// Returns the aggregate base tokens in all claims, and in claims maturing
+    /// in `num_buckets` consecutive time buckets of `bucket_duration` seconds
+    /// each, starting at the current block time. `num_buckets` is capped at
+    /// [`MAX_LIMIT`].
+    ///
+    /// Only the release totals of claims that have not matured are read, one
+    /// entry per time at which claims mature. The matured amount is the
+    /// running total minus the pending amount, so neither the number of claims
+    /// nor matured claims that were not withdrawn add to the cost.
+    pub fn query_totals(
+        &self,
+        deps: Deps,
+        block: &BlockInfo,
+        bucket_duration: u64,
+        num_buckets: u32,
+    ) -> StdResult<UnlockingTotals> {
+        let total = self.total_unlocking(deps.storage)?;
+
+        let num_buckets = num_buckets.min(MAX_LIMIT) as u64;
+        let mut buckets: Vec<UnlockingBucket> = (1..=num_buckets)
+            .map(|i| UnlockingBucket {
+                end: block.time.plus_seconds(bucket_duration.saturating_mul(i)),
+                base_token_amount: Uint128::zero(),
+            })
+            .collect();
+
+        let mut pending = Uint128::zero();
+        for (lo, hi) in pending_release_at_keys(block) {
+            for res in self.release_totals.range(
+                deps.storage,
+                Some(Bound::inclusive(lo)),
+                Some(Bound::inclusive(hi)),
+                Order::Ascending,
+            ) {
+                let ((kind, value), amount) = res?;
+                pending = pending.checked_add(amount)?;
+                if kind != 1 {
+                    continue;
+                }
+                if let Some(bucket) = buckets
+                    .iter_mut()
+                    .find(|bucket| value <= bucket.end.nanos())
+                {
+                    bucket.base_token_amount = bucket.base_token_amount.checked_add(amount)?;
+                }
+            }
+        }
 
-    const CLAIMS: &str = "claims";
-    const CLAIMS_INDEX: &str = "claims_index";
-    const PENDING_CLAIMS: &str = "pending_claims";
-    const NUM_CLAIMS: &str = "num_claims";
-    const BASE_TOKEN_AMOUNT: Uint128 = Uint128::new(100);
-    const EXPIRATION: Expiration = Expiration::AtHeight(100);
+        Ok(UnlockingTotals {
+            total,
+            matured: total.checked_sub(pending)?,
+            pending,
+            buckets,
+        })
+    }
 
-    fn setup_pending_claim(
-        lock_id: Option<u64>,
-    ) -> (
+    /// Reads all operators approved by `owner`, including expired approvals,
+    /// together with the expiration of each approval. The optional arguments
+    /// `start_after` and `limit` can be used for pagination.
+    ///
+    /// # Arguments
+    /// - `owner` - The owner that approved the operators
+    /// - `start_after` - Optional operator address to start the query after
+    /// - `limit` - Optional maximum number of operators to return
+    pub fn query_operators(
+        &self,
+        deps: Deps,
+        owner: &Addr,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<(Addr, Expiration)>> {
+        let limit = page_size(limit);
+        let start = start_after.as_ref().map(Bound::exclusive);
+
+        self.operators
+            .prefix(owner)
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .collect()
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Unlock batches
+//--------------------------------------------------------------------------------------------------
+
+/// The status of an [`UnlockBatch`].
+#[cw_serde]
+pub enum UnlockBatchStatus {
+    /// The batch accepts new unlock requests.
+    Open,
+    /// The base tokens of the batch are unlocking in the claim with ID
+    /// `lockup_id`, which is owned by the vault.
+    Unlocking {
+        /// The ID of the claim of the batch.
+        lockup_id: u64,
+        /// The time or block height at which the base tokens can be withdrawn.
+        release_at: Expiration,
+    },
+}
+
+/// A batch of unlock requests made during one epoch, whose base tokens are
+/// unlocked together.
+#[cw_serde]
+pub struct UnlockBatch {
+    /// The ID of the batch.
+    pub id: u64,
+    /// The end of the epoch, after which the batch can be unlocked.
+    pub epoch_end: Timestamp,
+    /// The total shares of the owners in the batch that have not been
+    /// withdrawn.
+    pub total_shares: Uint128,
+    /// The base tokens in the batch that have not been withdrawn.
+    pub base_token_amount: Uint128,
+    /// The status of the batch.
+    pub status: UnlockBatchStatus,
+}
+
+/// A struct for handling unlock requests that are batched per epoch, as well
+/// as the shares of the owners in each batch.
+pub struct UnlockBatches<'a> {
+    /// The duration of an epoch in seconds. Epoch mode is disabled if not set.
+    epoch_duration: Item<'a, u64>,
+    /// All batches that have not been fully withdrawn.
+    batches: Map<'a, u64, UnlockBatch>,
+    /// The ID of the batch that currently accepts unlock requests, if any.
+    open_batch: Item<'a, u64>,
+    /// The ID of the next batch to be created.
+    next_batch_id: Item<'a, u64>,
+    /// The shares of each owner in each batch, keyed by `(owner, batch_id)`.
+    shares: Map<'a, (&'a Addr, u64), Uint128>,
+}
+
+impl<'a> UnlockBatches<'a> {
+    /// Create a new UnlockBatches instance
+    pub fn new(
+        epoch_duration_key: &'a str,
+        batches_namespace: &'a str,
+        open_batch_key: &'a str,
+        next_batch_id_key: &'a str,
+        shares_namespace: &'a str,
+    ) -> Self {
+        Self {
+            epoch_duration: Item::new(epoch_duration_key),
+            batches: Map::new(batches_namespace),
+            open_batch: Item::new(open_batch_key),
+            next_batch_id: Item::new(next_batch_id_key),
+            shares: Map::new(shares_namespace),
+        }
+    }
+
+    /// Enables epoch mode with epochs of `epoch_duration` seconds.
+    pub fn set_epoch_duration(
+        &self,
+        storage: &mut dyn Storage,
+        epoch_duration: u64,
+    ) -> StdResult<()> {
+        if epoch_duration == 0 {
+            return Err(StdError::generic_err("Epoch duration must be positive"));
+        }
+        self.epoch_duration.save(storage, &epoch_duration)
+    }
+
+    /// Disables epoch mode. A batch that is still open can be unlocked and
+    /// withdrawn from as usual, and is reused if epoch mode is enabled again.
+    pub fn remove_epoch_duration(&self, storage: &mut dyn Storage) {
+        self.epoch_duration.remove(storage)
+    }
+
+    /// Returns the duration of an epoch in seconds, or `None` if epoch mode is
+    /// disabled.
+    pub fn epoch_duration(&self, storage: &dyn Storage) -> StdResult<Option<u64>> {
+        self.epoch_duration.may_load(storage)
+    }
+
+    /// Adds a request of `owner` to unlock `base_token_amount` base tokens to
+    /// the open batch, creating a new batch that ends at the end of the
+    /// current epoch if there is no open batch. Requests are added to the open
+    /// batch until it is unlocked, even after its epoch has ended.
+    ///
+    /// ## Returns
+    /// Returns the updated batch and the shares issued to `owner`.
+    pub fn add_request(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        owner: &Addr,
+        base_token_amount: Uint128,
+    ) -> StdResult<(UnlockBatch, Uint128)> {
+        let epoch_duration = self
+            .epoch_duration(storage)?
+            .ok_or_else(|| StdError::generic_err("Epoch mode is not enabled"))?;
+
+        let mut batch = match self.open_batch.may_load(storage)? {
+            Some(id) => self.batches.load(storage, id)?,
+            None => {
+                let id = self.next_batch_id.may_load(storage)?.unwrap_or_default();
+                self.next_batch_id.save(storage, &(id + 1))?;
+                self.open_batch.save(storage, &id)?;
+                let epoch = block.time.seconds() / epoch_duration;
+                UnlockBatch {
+                    id,
+                    epoch_end: Timestamp::from_seconds((epoch + 1) * epoch_duration),
+                    total_shares: Uint128::zero(),
+                    base_token_amount: Uint128::zero(),
+                    status: UnlockBatchStatus::Open,
+                }
+            }
+        };
+
+        // Shares are issued one to one for base tokens while the batch is open
+        let shares = base_token_amount;
+        batch.total_shares = batch.total_shares.checked_add(shares)?;
+        batch.base_token_amount = batch.base_token_amount.checked_add(base_token_amount)?;
+        self.batches.save(storage, batch.id, &batch)?;
+        self.shares
+            .update(storage, (owner, batch.id), |x| -> StdResult<_> {
+                Ok(x.unwrap_or_default().checked_add(shares)?)
+            })?;
+
+        Ok((batch, shares))
+    }
+
+    /// Returns the batch that currently accepts unlock requests, if any.
+    pub fn open_batch(&self, storage: &dyn Storage) -> StdResult<Option<UnlockBatch>> {
+        self.open_batch
+            .may_load(storage)?
+            .map(|id| self.batches.load(storage, id))
+            .transpose()
+    }
+
+    /// Closes the open batch so that it can be unlocked. Returns an error if
+    /// there is no open batch or if its epoch has not ended.
+    pub fn close_open_batch(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+    ) -> StdResult<UnlockBatch> {
+        let id = self
+            .open_batch
+            .may_load(storage)?
+            .ok_or_else(|| StdError::generic_err("No open unlock batch"))?;
+        let batch = self.batches.load(storage, id)?;
+        if block.time < batch.epoch_end {
+            return Err(StdError::generic_err(format!(
+                "Epoch of unlock batch {} ends at {}",
+                id, batch.epoch_end
+            )));
+        }
+        self.open_batch.remove(storage);
+        Ok(batch)
+    }
+
+    /// Sets the status of the batch with ID `batch_id` to unlocking in the
+    /// claim `claim`.
+    pub fn set_unlocking(
+        &self,
+        storage: &mut dyn Storage,
+        batch_id: u64,
+        claim: &Claim,
+    ) -> StdResult<UnlockBatch> {
+        let mut batch = self.batches.load(storage, batch_id)?;
+        if batch.status != UnlockBatchStatus::Open {
+            return Err(StdError::generic_err(format!(
+                "Unlock batch {} is already unlocking",
+                batch_id
+            )));
+        }
+        batch.status = UnlockBatchStatus::Unlocking {
+            lockup_id: claim.id,
+            release_at: claim.release_at,
+        };
+        self.batches.save(storage, batch_id, &batch)?;
+        Ok(batch)
+    }
+
+    /// Removes the shares of `owner` in the batch with ID `batch_id` and
+    /// returns the owner's pro-rata share of the base tokens in the batch,
+    /// together with the ID of the claim of the batch. Deletes the batch if
+    /// all shares have been withdrawn.
+    ///
+    /// Returns an error if the owner has no shares in the batch, or if the
+    /// base tokens of the batch have not finished unlocking.
+    pub fn withdraw(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        batch_id: u64,
+        owner: &Addr,
+    ) -> StdResult<(Uint128, u64)> {
+        let mut batch = self.batches.load(storage, batch_id)?;
+        let lockup_id = match batch.status {
+            UnlockBatchStatus::Unlocking {
+                lockup_id,
+                release_at,
+            } if release_at.is_expired(block) => lockup_id,
+            _ => {
+                return Err(StdError::generic_err(format!(
+                    "Unlock batch {} has not yet matured.",
+                    batch_id
+                )))
+            }
+        };
+
+        let shares = self
+            .shares
+            .may_load(storage, (owner, batch_id))?
+            .ok_or_else(|| StdError::generic_err("No shares in unlock batch"))?;
+        self.shares.remove(storage, (owner, batch_id));
+
+        let amount = batch
+            .base_token_amount
+            .multiply_ratio(shares, batch.total_shares);
+        batch.total_shares = batch.total_shares.checked_sub(shares)?;
+        batch.base_token_amount = batch.base_token_amount.checked_sub(amount)?;
+        if batch.total_shares.is_zero() {
+            self.batches.remove(storage, batch_id);
+        } else {
+            self.batches.save(storage, batch_id, &batch)?;
+        }
+
+        Ok((amount, lockup_id))
+    }
+
+    /// Query a batch by ID.
+    pub fn query_batch(&self, deps: Deps, batch_id: u64) -> StdResult<UnlockBatch> {
+        self.batches.load(deps.storage, batch_id)
+    }
+
+    /// Reads all batches that have not been fully withdrawn. The optional
+    /// arguments `start_after` and `limit` can be used for pagination.
+    pub fn query_batches(
+        &self,
+        deps: Deps,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<UnlockBatch>> {
+        self.batches
+            .range(
+                deps.storage,
+                start_after.map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(page_size(limit))
+            .map(|res| res.map(|(_, batch)| batch))
+            .collect()
+    }
+
+    /// Reads the shares of `owner` in all batches, as `(batch_id, shares)`.
+    /// The optional arguments `start_after` and `limit` can be used for
+    /// pagination.
+    pub fn query_shares_for_owner(
+        &self,
+        deps: Deps,
+        owner: &Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<(u64, Uint128)>> {
+        self.shares
+            .prefix(owner)
+            .range(
+                deps.storage,
+                start_after.map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(page_size(limit))
+            .collect()
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Admin transfer
+//--------------------------------------------------------------------------------------------------
+
+/// The number of seconds after which an admin transfer proposal expires if no
+/// expiration is given when it is proposed. Set to 7 days.
+pub const DEFAULT_ADMIN_TRANSFER_EXPIRATION: u64 = 7 * 24 * 60 * 60;
+
+/// The maximum number of seconds from now that an admin transfer proposal can
+/// expire at. Set to 30 days.
+pub const MAX_ADMIN_TRANSFER_EXPIRATION: u64 = 30 * 24 * 60 * 60;
+
+/// A pending change of the vault admin, proposed by the current admin.
+#[cw_serde]
+pub struct AdminTransferProposal {
+    /// The address that must accept the transfer. `None` means that the admin
+    /// has proposed to renounce admin rights, which must be confirmed by the
+    /// admin via `ConfirmRenounceAdmin`.
+    pub new_admin: Option<Addr>,
+    /// The point after which the proposal can no longer be accepted.
+    pub expires: Expiration,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Change log
+//--------------------------------------------------------------------------------------------------
+
+/// A privileged change made to the vault, together with the values before and
+/// after the change.
+#[cw_serde]
+pub enum ChangeLogAction {
+    /// The config was updated via `UpdateConfig`.
+    UpdateConfig {
+        /// The config before the update.
+        before: Box<Config>,
+        /// The config after the update.
+        after: Box<Config>,
+    },
+    /// An admin transfer was initiated via `UpdateAdmin`, or an admin
+    /// renunciation was initiated via `RenounceAdmin`.
+    ProposeAdminTransfer {
+        /// The admin that proposed the transfer.
+        admin: Option<Addr>,
+        /// The pending admin transfer before the proposal, if any.
+        previous_proposal: Option<AdminTransferProposal>,
+        /// The new pending admin transfer.
+        proposal: AdminTransferProposal,
+    },
+    /// An admin transfer was accepted via `AcceptAdminTransfer`.
+    AcceptAdminTransfer {
+        /// The admin before the transfer.
+        previous_admin: Option<Addr>,
+        /// The admin after the transfer.
+        new_admin: Addr,
+    },
+    /// The admin renounced admin rights via `ConfirmRenounceAdmin`, leaving
+    /// the vault without an admin.
+    RenounceAdmin {
+        /// The admin before the renunciation.
+        previous_admin: Option<Addr>,
+    },
+    /// A pending admin transfer was dropped via `DropAdminTransfer`.
+    DropAdminTransfer {
+        /// The admin transfer that was dropped, if any.
+        proposal: Option<AdminTransferProposal>,
+    },
+    /// The force withdraw whitelist was updated.
+    UpdateForceWithdrawWhitelist {
+        /// The whitelist before the update.
+        before: Vec<Addr>,
+        /// The whitelist after the update.
+        after: Vec<Addr>,
+    },
+    /// The duration of an unlock epoch in seconds was updated via
+    /// `UpdateUnlockEpochDuration`. `None` means epoch mode is disabled.
+    UpdateUnlockEpochDuration {
+        /// The epoch duration before the update.
+        before: Option<u64>,
+        /// The epoch duration after the update.
+        after: Option<u64>,
+    },
+    /// A change to a parameter that only some vaults have, recorded by the
+    /// contract that made it, e.g. the validator of a superfluid vault.
+    Custom {
+        /// The name of the execute message that made the change.
+        action: String,
+        /// The value of the parameter before the change.
+        before: String,
+        /// The value of the parameter after the change.
+        after: String,
+    },
+}
+
+/// An entry in the [`ChangeLog`].
+#[cw_serde]
+pub struct ChangeLogEntry {
+    /// The ID of the entry. IDs are assigned sequentially starting from 0.
+    pub id: u64,
+    /// The block height at which the change was made.
+    pub height: u64,
+    /// The block time at which the change was made.
+    pub time: Timestamp,
+    /// The address that made the change.
+    pub sender: Addr,
+    /// The change that was made.
+    pub action: ChangeLogAction,
+}
+
+/// An append-only history of privileged changes to the vault, such as config
+/// updates, admin transfers and whitelist changes. Entries are never modified
+/// or removed once recorded.
+pub struct ChangeLog<'a> {
+    /// All recorded entries, keyed by ID.
+    entries: Map<'a, u64, ChangeLogEntry>,
+    /// The ID that will be assigned to the next recorded entry.
+    next_id: Item<'a, u64>,
+}
+
+impl<'a> ChangeLog<'a> {
+    /// Create a new ChangeLog instance
+    ///
+    /// ## Arguments
+    /// * `entries_namespace` - The key to use for the map of entries
+    /// * `next_id_key` - The key to use for the ID counter
+    pub fn new(entries_namespace: &'a str, next_id_key: &'a str) -> Self {
+        Self {
+            entries: Map::new(entries_namespace),
+            next_id: Item::new(next_id_key),
+        }
+    }
+
+    /// Append a new entry to the change log.
+    ///
+    /// ## Returns
+    /// Returns the ID of the recorded entry.
+    pub fn record(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        sender: &Addr,
+        action: ChangeLogAction,
+    ) -> StdResult<u64> {
+        let id = self.next_id.may_load(storage)?.unwrap_or_default();
+
+        self.entries.save(
+            storage,
+            id,
+            &ChangeLogEntry {
+                id,
+                height: block.height,
+                time: block.time,
+                sender: sender.clone(),
+                action,
+            },
+        )?;
+        self.next_id.save(storage, &(id + 1))?;
+
+        Ok(id)
+    }
+
+    // ========== Query functions ==========
+
+    /// Reads entries from the change log in the order they were recorded. The
+    /// optional arguments `start_after` and `limit` can be used for
+    /// pagination.
+    ///
+    /// # Arguments
+    /// - `start_after` - Optional id of the entry to start the query after
+    /// - `limit` - Optional maximum number of entries to return
+    pub fn query_entries(
+        &self,
+        deps: Deps,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<ChangeLogEntry>> {
+        let limit = page_size(limit);
+        let start: Option<Bound<u64>> = start_after.map(Bound::exclusive);
+
+        self.entries
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|x| x.map(|(_, entry)| entry))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+        MOCK_CONTRACT_ADDR,
+    };
+    use cosmwasm_std::{Addr, Env, OwnedDeps, Uint128};
+    use cw_utils::Expiration;
+
+    use test_case::test_case;
+
+    use super::*;
+
+    const OWNER: &str = "owner";
+    const NOT_OWNER: &str = "not_owner";
+    const NEW_OWNER: &str = "new_owner";
+    const OPERATOR: &str = "operator";
+
+    const CLAIMS: &str = "claims";
+    const CLAIMS_INDEX: &str = "claims_index";
+    const PENDING_CLAIMS: &str = "pending_claims";
+    const NUM_CLAIMS: &str = "num_claims";
+    const RELEASE_AT_INDEX: &str = "claims_release_at_index";
+    const OPERATORS: &str = "operators";
+    const TOTAL_UNLOCKING: &str = "total_unlocking";
+    const AUTO_WITHDRAW: &str = "auto_withdraw";
+    const AUTO_WITHDRAW_CLAIMS: &str = "auto_withdraw_claims";
+    const REINDEX_CURSOR: &str = "reindex_cursor";
+    const LOCKUP_DURATIONS: &str = "lockup_durations";
+    const PENDING_LOCKUP_DURATION: &str = "pending_lockup_duration";
+    const OWNER_RELEASE_AT_INDEX: &str = "claims_owner_release_at_index";
+    const RELEASE_TOTALS: &str = "claims_release_totals";
+    const CHANGE_LOG: &str = "change_log";
+    const CHANGE_LOG_NEXT_ID: &str = "change_log_next_id";
+    const BASE_TOKEN_AMOUNT: Uint128 = Uint128::new(100);
+    const EXPIRATION: Expiration = Expiration::AtHeight(100);
+
+    fn setup_pending_claim(
+        lock_id: Option<u64>,
+    ) -> (
         OwnedDeps<MockStorage, MockApi, MockQuerier>,
         Claims<'static>,
     ) {
         let mut deps = mock_dependencies();
 
-        let claims = Claims::new(CLAIMS, CLAIMS_INDEX, PENDING_CLAIMS, NUM_CLAIMS);
+        let claims = Claims::new(
+            CLAIMS,
+            CLAIMS_INDEX,
+            PENDING_CLAIMS,
+            NUM_CLAIMS,
+            RELEASE_AT_INDEX,
+            OPERATORS,
+            TOTAL_UNLOCKING,
+            AUTO_WITHDRAW,
+            AUTO_WITHDRAW_CLAIMS,
+            REINDEX_CURSOR,
+            LOCKUP_DURATIONS,
+            PENDING_LOCKUP_DURATION,
+            OWNER_RELEASE_AT_INDEX,
+            RELEASE_TOTALS,
+        );
 
         // Create pending claim without specifying lock_id
         claims
@@ -587,6 +1992,55 @@ mod tests {
         assert_eq!(pending_claim.id, 1);
     }
 
+    #[test]
+    fn test_claim_lockup_duration() {
+        let (mut deps, claims) = setup_pending_claim(Some(1));
+        let info = mock_info(OWNER, &[]);
+
+        // The lockup duration of the pending claim is saved when it is
+        // committed
+        claims
+            .set_pending_lockup_duration(&mut deps.storage, &Duration::Height(50))
+            .unwrap();
+        claims.commit_pending_claim(&mut deps.storage).unwrap();
+        assert!(claims
+            .pending_lockup_duration
+            .may_load(&deps.storage)
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            claims.lockup_duration(&deps.storage, 1).unwrap(),
+            Some(Duration::Height(50))
+        );
+
+        // A claim committed without a lockup duration has none
+        claims
+            .create_pending_claim(
+                &mut deps.storage,
+                &Addr::unchecked(OWNER),
+                BASE_TOKEN_AMOUNT,
+                EXPIRATION,
+                None,
+            )
+            .unwrap();
+        claims.commit_pending_claim(&mut deps.storage).unwrap();
+        assert_eq!(claims.lockup_duration(&deps.storage, 2).unwrap(), None);
+
+        // The lockup duration is kept when part of the claim is force claimed
+        // and removed with the claim
+        claims
+            .force_claim(&mut deps.storage, &info, 1, Some(Uint128::new(1)))
+            .unwrap();
+        assert_eq!(
+            claims.lockup_duration(&deps.storage, 1).unwrap(),
+            Some(Duration::Height(50))
+        );
+        claims
+            .force_claim(&mut deps.storage, &info, 1, None)
+            .unwrap();
+        assert_eq!(claims.lockup_duration(&deps.storage, 1).unwrap(), None);
+    }
+
     #[test_case(100, NOT_OWNER => Err(StdError::generic_err("Claim not owned by sender")); "claim not owned by sender")]
     #[test_case(100, OWNER => Ok(BASE_TOKEN_AMOUNT) ; "claim owned by sender")]
     #[test_case(99, OWNER => Err(StdError::generic_err("Claim has not yet matured.")); "claim not yet matured")]
@@ -666,7 +2120,7 @@ mod tests {
         let mut claims = Vec::new();
         for i in start_id..(start_id + n as u64) {
             claims.push(Claim {
-                id: i as u64,
+                id: i,
                 owner: Addr::unchecked(OWNER),
                 base_token_amount: BASE_TOKEN_AMOUNT,
                 release_at: EXPIRATION,
@@ -679,6 +2133,7 @@ mod tests {
     #[test_case(OWNER, None, Some(31) => Ok(claims(0, 31)); "pagination with limit")]
     #[test_case(OWNER, Some(1), None => Ok(claims(2, DEFAULT_LIMIT)); "pagination with start id")]
     #[test_case(OWNER, Some(1), Some(31) => Ok(claims(2, 31)); "pagination with start id and limit")]
+    #[test_case(OWNER, None, Some(75) => Ok(claims(0, MAX_LIMIT)); "limit is capped at max limit")]
     fn test_query_claims_for_owner(
         owner: &str,
         start_after: Option<u64>,
@@ -687,7 +2142,22 @@ mod tests {
         let mut deps = mock_dependencies();
 
         // Create 100 claims for owner
-        let claims = Claims::new(CLAIMS, CLAIMS_INDEX, PENDING_CLAIMS, NUM_CLAIMS);
+        let claims = Claims::new(
+            CLAIMS,
+            CLAIMS_INDEX,
+            PENDING_CLAIMS,
+            NUM_CLAIMS,
+            RELEASE_AT_INDEX,
+            OPERATORS,
+            TOTAL_UNLOCKING,
+            AUTO_WITHDRAW,
+            AUTO_WITHDRAW_CLAIMS,
+            REINDEX_CURSOR,
+            LOCKUP_DURATIONS,
+            PENDING_LOCKUP_DURATION,
+            OWNER_RELEASE_AT_INDEX,
+            RELEASE_TOTALS,
+        );
         let owner = Addr::unchecked(owner);
         for _ in 0..100 {
             claims
@@ -707,4 +2177,1027 @@ mod tests {
             .query_claims_for_owner(deps.as_ref(), &owner, start_after, limit)
             .map(|claims| claims.iter().map(|c| c.1.clone()).collect())
     }
+
+    #[test_case(99, None => Err(StdError::generic_err("No matured claims.")); "no matured claims")]
+    #[test_case(100, None => Ok((vec![0, 2], Uint128::new(200))); "some matured claims")]
+    #[test_case(103, None => Ok((vec![0, 2, 3, 5], Uint128::new(400))); "all claims matured")]
+    #[test_case(103, Some(3) => Ok((vec![0, 2, 3], Uint128::new(300))); "limit")]
+    fn test_claim_all_matured(
+        block_height: u64,
+        limit: Option<u32>,
+    ) -> StdResult<(Vec<u64>, Uint128)> {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.height = block_height;
+        let owner = Addr::unchecked(OWNER);
+
+        // Create claims for owner that mature at different heights, and one
+        // matured claim for another address
+        let claims = Claims::new(
+            CLAIMS,
+            CLAIMS_INDEX,
+            PENDING_CLAIMS,
+            NUM_CLAIMS,
+            RELEASE_AT_INDEX,
+            OPERATORS,
+            TOTAL_UNLOCKING,
+            AUTO_WITHDRAW,
+            AUTO_WITHDRAW_CLAIMS,
+            REINDEX_CURSOR,
+            LOCKUP_DURATIONS,
+            PENDING_LOCKUP_DURATION,
+            OWNER_RELEASE_AT_INDEX,
+            RELEASE_TOTALS,
+        );
+        for (claim_owner, height) in [
+            (OWNER, 100),
+            (NOT_OWNER, 100),
+            (OWNER, 100),
+            (OWNER, 101),
+            (OWNER, 200),
+            (OWNER, 103),
+        ] {
+            claims
+                .create_pending_claim(
+                    &mut deps.storage,
+                    &Addr::unchecked(claim_owner),
+                    BASE_TOKEN_AMOUNT,
+                    Expiration::AtHeight(height),
+                    None,
+                )
+                .unwrap();
+            claims.commit_pending_claim(&mut deps.storage).unwrap();
+        }
+
+        let res = claims.claim_all_matured(&mut deps.storage, &env.block, &owner, limit);
+
+        // Assert that exactly the claimed claims were removed
+        let remaining: Vec<u64> = claims
+            .claims
+            .keys(&deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()
+            .unwrap();
+        let claimed = res.as_ref().map(|(ids, _)| ids.clone()).unwrap_or_default();
+        assert_eq!(
+            remaining,
+            (0..6)
+                .filter(|id| !claimed.contains(id))
+                .collect::<Vec<_>>()
+        );
+        let claimed_amount = res.as_ref().map(|(_, amount)| *amount).unwrap_or_default();
+        assert_eq!(
+            claims.total_unlocking(&deps.storage).unwrap(),
+            Uint128::new(600) - claimed_amount
+        );
+
+        res
+    }
+
+    #[test]
+    fn test_total_unlocking() {
+        let (mut deps, claims, env) = setup_claims_with_release_at();
+        let info = mock_info(OWNER, &[]);
+        let total = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>| {
+            claims.total_unlocking(&deps.storage).unwrap()
+        };
+
+        // Committing claims increases the total
+        assert_eq!(total(&deps), BASE_TOKEN_AMOUNT * Uint128::new(8));
+
+        // Claiming a matured claim decreases the total
+        claims
+            .claim_tokens(&mut deps.storage, &env.block, &info, 5)
+            .unwrap();
+        assert_eq!(total(&deps), BASE_TOKEN_AMOUNT * Uint128::new(7));
+
+        // Force claiming part of a claim decreases the total by the claimed amount
+        claims
+            .force_claim(&mut deps.storage, &info, 0, Some(Uint128::new(40)))
+            .unwrap();
+        assert_eq!(total(&deps), Uint128::new(660));
+
+        // Force claiming the rest of the claim
+        claims
+            .force_claim(&mut deps.storage, &info, 0, None)
+            .unwrap();
+        assert_eq!(total(&deps), BASE_TOKEN_AMOUNT * Uint128::new(6));
+
+        // Failed claims leave the total unchanged
+        claims
+            .claim_tokens(&mut deps.storage, &env.block, &info, 2)
+            .unwrap_err();
+        assert_eq!(total(&deps), BASE_TOKEN_AMOUNT * Uint128::new(6));
+
+        // Recomputing the total from the stored claims gives the same result
+        claims.total_unlocking.remove(&mut deps.storage);
+        claims.start_reindex(&mut deps.storage).unwrap();
+        assert!(claims.reindex(&mut deps.storage, 100).unwrap());
+        assert_eq!(total(&deps), BASE_TOKEN_AMOUNT * Uint128::new(6));
+    }
+
+    /// Creates claims for different owners that mature at a height, at a time
+    /// or never, in an order that differs from the order of their IDs.
+    fn setup_claims_with_release_at() -> (
+        OwnedDeps<MockStorage, MockApi, MockQuerier>,
+        Claims<'static>,
+        Env,
+    ) {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let now = env.block.time;
+        let claims = Claims::new(
+            CLAIMS,
+            CLAIMS_INDEX,
+            PENDING_CLAIMS,
+            NUM_CLAIMS,
+            RELEASE_AT_INDEX,
+            OPERATORS,
+            TOTAL_UNLOCKING,
+            AUTO_WITHDRAW,
+            AUTO_WITHDRAW_CLAIMS,
+            REINDEX_CURSOR,
+            LOCKUP_DURATIONS,
+            PENDING_LOCKUP_DURATION,
+            OWNER_RELEASE_AT_INDEX,
+            RELEASE_TOTALS,
+        );
+
+        for (owner, release_at) in [
+            (OWNER, Expiration::AtTime(now.plus_seconds(100))),
+            (NOT_OWNER, Expiration::AtTime(now.minus_seconds(100))),
+            (OWNER, Expiration::AtHeight(env.block.height + 1)),
+            (NOT_OWNER, Expiration::Never {}),
+            (OWNER, Expiration::AtTime(now.plus_seconds(50))),
+            (OWNER, Expiration::AtHeight(env.block.height)),
+            (NOT_OWNER, Expiration::AtTime(now)),
+            (OWNER, Expiration::AtTime(now.plus_seconds(250))),
+        ] {
+            claims
+                .create_pending_claim(
+                    &mut deps.storage,
+                    &Addr::unchecked(owner),
+                    BASE_TOKEN_AMOUNT,
+                    release_at,
+                    None,
+                )
+                .unwrap();
+            claims.commit_pending_claim(&mut deps.storage).unwrap();
+        }
+
+        (deps, claims, env)
+    }
+
+    #[test_case(None, None, None => vec![5, 2, 1, 6, 4, 0, 7, 3]; "all claims ordered by release_at")]
+    #[test_case(Some(UnlockingPositionFilter::Matured), None, None => vec![5, 1, 6]; "matured")]
+    #[test_case(Some(UnlockingPositionFilter::Pending), None, None => vec![2, 4, 0, 7, 3]; "pending")]
+    #[test_case(None, Some(6), Some(2) => vec![4, 0]; "pagination")]
+    #[test_case(Some(UnlockingPositionFilter::Matured), Some(5), None => vec![1, 6]; "matured with start id")]
+    #[test_case(Some(UnlockingPositionFilter::Pending), Some(4), Some(10) => vec![0, 7, 3]; "pending with start id")]
+    #[test_case(Some(UnlockingPositionFilter::Pending), Some(6), None => vec![4, 0, 7, 3]; "pending with start id that matured")]
+    fn test_query_all_claims(
+        filter: Option<UnlockingPositionFilter>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> Vec<u64> {
+        let (deps, claims, env) = setup_claims_with_release_at();
+        let start_after =
+            start_after.map(|id| cursor(&claims.claims.load(&deps.storage, id).unwrap()));
+
+        claims
+            .query_all_claims(deps.as_ref(), &env.block, filter, start_after, limit)
+            .unwrap()
+            .into_iter()
+            .map(|claim| claim.id)
+            .collect()
+    }
+
+    fn cursor(claim: &Claim) -> UnlockingPositionCursor {
+        UnlockingPositionCursor {
+            release_at: claim.release_at,
+            id: claim.id,
+        }
+    }
+
+    #[test]
+    fn test_query_all_claims_after_removed_claim() {
+        let (mut deps, claims, env) = setup_claims_with_release_at();
+        let page = claims
+            .query_all_claims(deps.as_ref(), &env.block, None, None, Some(3))
+            .unwrap();
+        assert_eq!(page.iter().map(|c| c.id).collect::<Vec<_>>(), vec![5, 2, 1]);
+
+        // The last claim of the page is withdrawn before the next page is read
+        claims
+            .claim_tokens(&mut deps.storage, &env.block, &mock_info(NOT_OWNER, &[]), 1)
+            .unwrap();
+
+        let ids: Vec<u64> = claims
+            .query_all_claims(
+                deps.as_ref(),
+                &env.block,
+                None,
+                Some(cursor(&page[2])),
+                Some(3),
+            )
+            .unwrap()
+            .into_iter()
+            .map(|claim| claim.id)
+            .collect();
+        assert_eq!(ids, vec![6, 4, 0]);
+    }
+
+    #[test_case(&[], None => Vec::<u64>::new(); "no owners opted in")]
+    #[test_case(&[OWNER], None => vec![5]; "one owner opted in")]
+    #[test_case(&[NOT_OWNER], None => vec![1, 6]; "other owner opted in")]
+    #[test_case(&[OWNER, NOT_OWNER], None => vec![5, 1, 6]; "all owners opted in")]
+    #[test_case(&[OWNER, NOT_OWNER], Some(2) => vec![5, 1]; "with limit")]
+    fn test_matured_auto_withdraw_claims(owners: &[&str], limit: Option<u32>) -> Vec<u64> {
+        let (mut deps, claims, env) = setup_claims_with_release_at();
+        for owner in owners {
+            claims
+                .set_auto_withdraw(&mut deps.storage, &Addr::unchecked(*owner), true)
+                .unwrap();
+        }
+
+        claims
+            .matured_auto_withdraw_claims(&deps.storage, &env.block, limit)
+            .unwrap()
+            .into_iter()
+            .map(|claim| claim.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_set_auto_withdraw() {
+        let (mut deps, claims, env) = setup_claims_with_release_at();
+        let owner = Addr::unchecked(OWNER);
+        assert!(!claims.is_auto_withdraw(&deps.storage, &owner));
+
+        claims
+            .set_auto_withdraw(&mut deps.storage, &owner, true)
+            .unwrap();
+        assert!(claims.is_auto_withdraw(&deps.storage, &owner));
+
+        // Opting out removes the owner's claims from auto-withdrawal
+        claims
+            .set_auto_withdraw(&mut deps.storage, &owner, false)
+            .unwrap();
+        assert!(!claims.is_auto_withdraw(&deps.storage, &owner));
+        assert!(claims
+            .matured_auto_withdraw_claims(&deps.storage, &env.block, None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_auto_withdraw_claims_index() {
+        let (mut deps, claims, env) = setup_claims_with_release_at();
+        let owner = Addr::unchecked(OWNER);
+        let matured_ids = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>| {
+            claims
+                .matured_auto_withdraw_claims(&deps.storage, &env.block, None)
+                .unwrap()
+                .into_iter()
+                .map(|claim| claim.id)
+                .collect::<Vec<_>>()
+        };
+
+        claims
+            .set_auto_withdraw(&mut deps.storage, &owner, true)
+            .unwrap();
+        assert_eq!(matured_ids(&deps), vec![5]);
+
+        // Claims committed after opting in are indexed
+        claims
+            .create_pending_claim(
+                &mut deps.storage,
+                &owner,
+                BASE_TOKEN_AMOUNT,
+                Expiration::AtHeight(env.block.height - 1),
+                None,
+            )
+            .unwrap();
+        claims.commit_pending_claim(&mut deps.storage).unwrap();
+        assert_eq!(matured_ids(&deps), vec![8, 5]);
+
+        // Transferring a claim to an owner that did not opt in removes it from
+        // the index, and transferring it to an owner that did adds it
+        claims
+            .transfer_claim(
+                &mut deps.storage,
+                &env.block,
+                &owner,
+                8,
+                &Addr::unchecked(NOT_OWNER),
+            )
+            .unwrap();
+        assert_eq!(matured_ids(&deps), vec![5]);
+        claims
+            .transfer_claim(
+                &mut deps.storage,
+                &env.block,
+                &Addr::unchecked(NOT_OWNER),
+                6,
+                &owner,
+            )
+            .unwrap();
+        assert_eq!(matured_ids(&deps), vec![5, 6]);
+
+        // Withdrawn claims are removed from the index
+        claims
+            .claim_tokens(&mut deps.storage, &env.block, &mock_info(OWNER, &[]), 5)
+            .unwrap();
+        assert_eq!(matured_ids(&deps), vec![6]);
+    }
+
+    #[test_case(OWNER, 10 => 5; "all claims of owner")]
+    #[test_case(NOT_OWNER, 10 => 3; "all claims of other owner")]
+    #[test_case(OWNER, 2 => 2; "stops at limit")]
+    #[test_case("no_claims", 10 => 0; "owner without claims")]
+    fn test_count_claims_for_owner(owner: &str, limit: usize) -> usize {
+        let (deps, claims, _env) = setup_claims_with_release_at();
+
+        claims.count_claims_for_owner(&deps.storage, &Addr::unchecked(owner), limit)
+    }
+
+    #[test]
+    fn test_last_claim_id() {
+        let (mut deps, claims, env) = setup_claims_with_release_at();
+        assert_eq!(claims.last_claim_id(&deps.storage).unwrap(), Some(7));
+
+        // Removing the last claim lowers the last claim ID
+        let info = mock_info(OWNER, &[]);
+        let mut block = env.block;
+        block.time = block.time.plus_seconds(250);
+        claims
+            .claim_tokens(&mut deps.storage, &block, &info, 7)
+            .unwrap();
+        assert_eq!(claims.last_claim_id(&deps.storage).unwrap(), Some(6));
+
+        let empty = mock_dependencies();
+        assert_eq!(claims.last_claim_id(&empty.storage).unwrap(), None);
+    }
+
+    #[test]
+    fn test_query_totals() {
+        let (deps, claims, env) = setup_claims_with_release_at();
+        let now = env.block.time;
+
+        // Claims maturing at the same time share a release total
+        assert_eq!(
+            claims
+                .release_totals
+                .keys(&deps.storage, None, None, Order::Ascending)
+                .count(),
+            8
+        );
+
+        let totals = claims
+            .query_totals(deps.as_ref(), &env.block, 100, 3)
+            .unwrap();
+
+        assert_eq!(
+            totals,
+            UnlockingTotals {
+                total: BASE_TOKEN_AMOUNT * Uint128::new(8),
+                matured: BASE_TOKEN_AMOUNT * Uint128::new(3),
+                pending: BASE_TOKEN_AMOUNT * Uint128::new(5),
+                buckets: vec![
+                    UnlockingBucket {
+                        end: now.plus_seconds(100),
+                        base_token_amount: BASE_TOKEN_AMOUNT * Uint128::new(2),
+                    },
+                    UnlockingBucket {
+                        end: now.plus_seconds(200),
+                        base_token_amount: Uint128::zero(),
+                    },
+                    UnlockingBucket {
+                        end: now.plus_seconds(300),
+                        base_token_amount: BASE_TOKEN_AMOUNT,
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_query_totals_after_changes() {
+        let (mut deps, claims, mut env) = setup_claims_with_release_at();
+        let info = mock_info(OWNER, &[]);
+        let now = env.block.time;
+
+        // A second claim maturing with claim 4 is added to its release total
+        claims
+            .create_pending_claim(
+                &mut deps.storage,
+                &Addr::unchecked(OWNER),
+                Uint128::new(30),
+                Expiration::AtTime(now.plus_seconds(50)),
+                None,
+            )
+            .unwrap();
+        claims.commit_pending_claim(&mut deps.storage).unwrap();
+
+        // Force claiming part of claim 0 and all of claim 3 reduces their
+        // release totals
+        claims
+            .force_claim(&mut deps.storage, &info, 0, Some(Uint128::new(40)))
+            .unwrap();
+        claims
+            .force_claim(&mut deps.storage, &mock_info(NOT_OWNER, &[]), 3, None)
+            .unwrap();
+        assert_eq!(
+            claims
+                .release_totals
+                .may_load(&deps.storage, release_at_key(&Expiration::Never {}))
+                .unwrap(),
+            None
+        );
+
+        let totals = claims
+            .query_totals(deps.as_ref(), &env.block, 100, 1)
+            .unwrap();
+        assert_eq!(totals.total, Uint128::new(690));
+        assert_eq!(totals.matured, BASE_TOKEN_AMOUNT * Uint128::new(3));
+        assert_eq!(totals.pending, Uint128::new(390));
+        assert_eq!(totals.buckets[0].base_token_amount, Uint128::new(190));
+
+        // Claims that mature are counted as matured without being withdrawn
+        env.block.time = now.plus_seconds(100);
+        env.block.height += 1;
+        let totals = claims
+            .query_totals(deps.as_ref(), &env.block, 100, 1)
+            .unwrap();
+        assert_eq!(totals.matured, Uint128::new(590));
+        assert_eq!(totals.pending, BASE_TOKEN_AMOUNT);
+    }
+
+    /// Returns the prefix of all storage keys in `namespace`, which is the
+    /// namespace prefixed with its length as used by cw-storage-plus.
+    fn namespace_prefix(namespace: &str) -> Vec<u8> {
+        let mut prefix = (namespace.len() as u16).to_be_bytes().to_vec();
+        prefix.extend_from_slice(namespace.as_bytes());
+        prefix
+    }
+
+    #[test]
+    fn test_reindex() {
+        let (mut deps, claims, env) = setup_claims_with_release_at();
+
+        // Remove the release_at index, as if the claims were created before it
+        // was added
+        let keys: Vec<Vec<u8>> = deps
+            .storage
+            .range(None, None, Order::Ascending)
+            .map(|(k, _)| k)
+            .filter(|k| {
+                [RELEASE_AT_INDEX, OWNER_RELEASE_AT_INDEX, RELEASE_TOTALS]
+                    .iter()
+                    .any(|namespace| k.starts_with(&namespace_prefix(namespace)))
+            })
+            .collect();
+        assert!(!keys.is_empty());
+        for key in keys {
+            deps.storage.remove(&key);
+        }
+        assert!(claims
+            .query_all_claims(deps.as_ref(), &env.block, None, None, None)
+            .unwrap()
+            .is_empty());
+
+        claims.start_reindex(&mut deps.storage).unwrap();
+        assert_eq!(claims.reindex_cursor(&deps.storage).unwrap(), Some(0));
+
+        // Claims are reindexed in batches in the order of their IDs
+        assert!(!claims.reindex(&mut deps.storage, 3).unwrap());
+        assert_eq!(claims.reindex_cursor(&deps.storage).unwrap(), Some(3));
+        let ids: Vec<u64> = claims
+            .query_all_claims(deps.as_ref(), &env.block, None, None, None)
+            .unwrap()
+            .into_iter()
+            .map(|claim| claim.id)
+            .collect();
+        assert_eq!(ids, vec![2, 1, 0]);
+
+        assert!(!claims.reindex(&mut deps.storage, 5).unwrap());
+        assert!(claims.reindex(&mut deps.storage, 5).unwrap());
+        assert_eq!(claims.reindex_cursor(&deps.storage).unwrap(), None);
+        claims.reindex(&mut deps.storage, 5).unwrap_err();
+
+        let ids: Vec<u64> = claims
+            .query_all_claims(deps.as_ref(), &env.block, None, None, None)
+            .unwrap()
+            .into_iter()
+            .map(|claim| claim.id)
+            .collect();
+        assert_eq!(ids, vec![5, 2, 1, 6, 4, 0, 7, 3]);
+        assert_eq!(
+            claims.total_unlocking(&deps.storage).unwrap(),
+            BASE_TOKEN_AMOUNT * Uint128::new(8)
+        );
+    }
+
+    #[test]
+    fn test_total_unlocking_during_reindex() {
+        let (mut deps, claims, env) = setup_claims_with_release_at();
+        let info = mock_info(OWNER, &[]);
+
+        claims.start_reindex(&mut deps.storage).unwrap();
+        claims.reindex(&mut deps.storage, 2).unwrap();
+        assert_eq!(
+            claims.total_unlocking(&deps.storage).unwrap(),
+            BASE_TOKEN_AMOUNT * Uint128::new(2)
+        );
+
+        // Changes to reindexed claims are applied to the total
+        claims
+            .force_claim(&mut deps.storage, &info, 0, Some(Uint128::new(40)))
+            .unwrap();
+        assert_eq!(
+            claims.total_unlocking(&deps.storage).unwrap(),
+            Uint128::new(160)
+        );
+
+        // Changes to claims that are not reindexed yet are not, since the
+        // claims are added to the total when they are reindexed
+        claims
+            .force_claim(&mut deps.storage, &info, 4, None)
+            .unwrap();
+        claims
+            .create_pending_claim(
+                &mut deps.storage,
+                &Addr::unchecked(OWNER),
+                BASE_TOKEN_AMOUNT,
+                Expiration::AtHeight(env.block.height + 10),
+                Some(8),
+            )
+            .unwrap();
+        claims.commit_pending_claim(&mut deps.storage).unwrap();
+        assert_eq!(
+            claims.total_unlocking(&deps.storage).unwrap(),
+            Uint128::new(160)
+        );
+
+        assert!(claims.reindex(&mut deps.storage, 100).unwrap());
+        assert_eq!(
+            claims.total_unlocking(&deps.storage).unwrap(),
+            Uint128::new(60) + BASE_TOKEN_AMOUNT * Uint128::new(7)
+        );
+    }
+
+    #[test_case(OWNER, None, 150 => Ok(()); "sender is owner")]
+    #[test_case(NOT_OWNER, None, 150 => Err(StdError::generic_err("Claim not owned by sender")); "sender is not owner")]
+    #[test_case(OPERATOR, Some(200), 150 => Ok(()); "sender is approved operator")]
+    #[test_case(OPERATOR, Some(200), 200 => Err(StdError::generic_err("Claim not owned by sender")); "operator approval expired")]
+    #[test_case(OPERATOR, None, 150 => Err(StdError::generic_err("Claim not owned by sender")); "operator not approved")]
+    fn test_transfer_claim(
+        sender: &str,
+        approval_expires_at: Option<u64>,
+        block_height: u64,
+    ) -> StdResult<()> {
+        let mut env = mock_env();
+        env.block.height = 150;
+        let owner = Addr::unchecked(OWNER);
+        let new_owner = Addr::unchecked(NEW_OWNER);
+
+        let (mut deps, claims) = setup_pending_claim(None);
+        claims.commit_pending_claim(&mut deps.storage).unwrap();
+
+        if let Some(height) = approval_expires_at {
+            claims
+                .approve_operator(
+                    &mut deps.storage,
+                    &env.block,
+                    &owner,
+                    &Addr::unchecked(OPERATOR),
+                    Expiration::AtHeight(height),
+                )
+                .unwrap();
+        }
+
+        env.block.height = block_height;
+        let res = claims.transfer_claim(
+            &mut deps.storage,
+            &env.block,
+            &Addr::unchecked(sender),
+            0,
+            &new_owner,
+        );
+
+        let owner_claims = claims
+            .query_claims_for_owner(deps.as_ref(), &owner, None, None)
+            .unwrap();
+        let new_owner_claims = claims
+            .query_claims_for_owner(deps.as_ref(), &new_owner, None, None)
+            .unwrap();
+        match res {
+            Ok(claim) => {
+                // Assert that the claim and the owner index were updated
+                assert_eq!(claim.owner, new_owner);
+                assert_eq!(claims.claims.load(&deps.storage, 0).unwrap(), claim);
+                assert!(owner_claims.is_empty());
+                assert_eq!(new_owner_claims, vec![(0, claim)]);
+                Ok(())
+            }
+            Err(err) => {
+                // Assert that the claim was not transferred
+                assert_eq!(owner_claims.len(), 1);
+                assert!(new_owner_claims.is_empty());
+                Err(err)
+            }
+        }
+    }
+
+    #[test]
+    fn test_approve_and_revoke_operator() {
+        let env = mock_env();
+        let owner = Addr::unchecked(OWNER);
+        let operator = Addr::unchecked(OPERATOR);
+        let expires = Expiration::AtHeight(env.block.height + 1);
+
+        let (mut deps, claims) = setup_pending_claim(None);
+
+        // Owner can not approve themselves
+        let err = claims
+            .approve_operator(&mut deps.storage, &env.block, &owner, &owner, expires)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Cannot approve self as operator")
+        );
+
+        // Approval must not already be expired
+        let err = claims
+            .approve_operator(
+                &mut deps.storage,
+                &env.block,
+                &owner,
+                &operator,
+                Expiration::AtHeight(env.block.height),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Approval expiration is already expired")
+        );
+
+        claims
+            .approve_operator(&mut deps.storage, &env.block, &owner, &operator, expires)
+            .unwrap();
+        assert!(claims
+            .is_operator(&deps.storage, &env.block, &owner, &operator)
+            .unwrap());
+        assert_eq!(
+            claims
+                .query_operators(deps.as_ref(), &owner, None, None)
+                .unwrap(),
+            vec![(operator.clone(), expires)]
+        );
+
+        claims
+            .revoke_operator(&mut deps.storage, &owner, &operator)
+            .unwrap();
+        assert!(!claims
+            .is_operator(&deps.storage, &env.block, &owner, &operator)
+            .unwrap());
+        assert!(claims
+            .query_operators(deps.as_ref(), &owner, None, None)
+            .unwrap()
+            .is_empty());
+
+        // Revoking an operator that is not approved fails
+        let err = claims
+            .revoke_operator(&mut deps.storage, &owner, &operator)
+            .unwrap_err();
+        assert_eq!(err, StdError::generic_err("Operator not approved"));
+    }
+
+    fn early_exit_config(penalty: Decimal, decay: bool) -> Config {
+        Config {
+            performance_fee: Decimal::zero(),
+            treasury: Addr::unchecked("treasury"),
+            router: CwDexRouterBase(Addr::unchecked("router")),
+            reward_assets: vec![],
+            reward_liquidation_target: AssetInfo::Native("uosmo".to_string()),
+            force_withdraw_whitelist: vec![],
+            liquidity_helper: LiquidityHelperBase(Addr::unchecked("liquidity_helper")),
+            early_exit_penalty: penalty,
+            early_exit_penalty_decay: decay,
+            early_exit_penalty_to_treasury: false,
+            auto_withdraw_keeper_fee: Decimal::zero(),
+            min_unlock_amount: Uint128::zero(),
+            max_unlocking_positions_per_owner: 0,
+            redemption_buffer_ratio: Decimal::zero(),
+            instant_redemption_fee: Decimal::zero(),
+        }
+    }
+
+    #[test_case(false, Expiration::AtTime(Timestamp::from_seconds(1100)), Duration::Time(1000) => Decimal::percent(10); "no decay")]
+    #[test_case(true, Expiration::AtTime(Timestamp::from_seconds(2000)), Duration::Time(1000) => Decimal::percent(10); "decay at start of unlocking")]
+    #[test_case(true, Expiration::AtTime(Timestamp::from_seconds(1250)), Duration::Time(1000) => Decimal::permille(25); "decay by time")]
+    #[test_case(true, Expiration::AtHeight(600), Duration::Height(200) => Decimal::percent(5); "decay by height")]
+    #[test_case(true, Expiration::AtTime(Timestamp::from_seconds(3000)), Duration::Time(1000) => Decimal::percent(10); "remaining time is capped at duration")]
+    #[test_case(true, Expiration::AtTime(Timestamp::from_seconds(900)), Duration::Time(1000) => Decimal::zero(); "matured")]
+    #[test_case(true, Expiration::AtHeight(600), Duration::Time(1000) => Decimal::percent(10); "no decay for mismatched units")]
+    fn test_early_exit_penalty_rate(
+        decay: bool,
+        release_at: Expiration,
+        lockup_duration: Duration,
+    ) -> Decimal {
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(1000);
+        env.block.height = 500;
+
+        early_exit_config(Decimal::percent(10), decay).early_exit_penalty_rate(
+            &release_at,
+            &lockup_duration,
+            &env.block,
+        )
+    }
+
+    #[test]
+    fn test_decrease_claim() {
+        let (mut deps, claims) = setup_pending_claim(Some(3));
+        claims.commit_pending_claim(&mut deps.storage).unwrap();
+
+        // Decreasing part of the claim keeps the rest
+        claims
+            .decrease_claim(&mut deps.storage, 3, Uint128::new(40))
+            .unwrap();
+        let claim = claims.query_claim_by_id(deps.as_ref(), 3).unwrap();
+        assert_eq!(claim.base_token_amount, Uint128::new(60));
+        assert_eq!(
+            claims.total_unlocking(&deps.storage).unwrap(),
+            Uint128::new(60)
+        );
+
+        // Decreasing more than the claim fails
+        claims
+            .decrease_claim(&mut deps.storage, 3, Uint128::new(61))
+            .unwrap_err();
+
+        // Decreasing the rest of the claim deletes it
+        claims
+            .decrease_claim(&mut deps.storage, 3, Uint128::new(60))
+            .unwrap();
+        claims.query_claim_by_id(deps.as_ref(), 3).unwrap_err();
+        assert_eq!(
+            claims.total_unlocking(&deps.storage).unwrap(),
+            Uint128::zero()
+        );
+    }
+
+    const EPOCH_DURATION: u64 = 100;
+
+    fn setup_unlock_batches() -> (
+        OwnedDeps<MockStorage, MockApi, MockQuerier>,
+        UnlockBatches<'static>,
+        Env,
+    ) {
+        let deps = mock_dependencies();
+        let unlock_batches = UnlockBatches::new(
+            "unlock_epoch_duration",
+            "unlock_batches",
+            "open_unlock_batch",
+            "next_unlock_batch_id",
+            "unlock_batch_shares",
+        );
+        (deps, unlock_batches, mock_env())
+    }
+
+    #[test]
+    fn test_unlock_batches_epoch_mode() {
+        let (mut deps, unlock_batches, env) = setup_unlock_batches();
+        let owner = Addr::unchecked(OWNER);
+
+        // Epoch mode is disabled by default
+        assert_eq!(unlock_batches.epoch_duration(&deps.storage).unwrap(), None);
+        let err = unlock_batches
+            .add_request(&mut deps.storage, &env.block, &owner, BASE_TOKEN_AMOUNT)
+            .unwrap_err();
+        assert_eq!(err, StdError::generic_err("Epoch mode is not enabled"));
+
+        // Epochs must have a positive duration
+        let err = unlock_batches
+            .set_epoch_duration(&mut deps.storage, 0)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Epoch duration must be positive")
+        );
+
+        unlock_batches
+            .set_epoch_duration(&mut deps.storage, EPOCH_DURATION)
+            .unwrap();
+        assert_eq!(
+            unlock_batches.epoch_duration(&deps.storage).unwrap(),
+            Some(EPOCH_DURATION)
+        );
+
+        // An open batch can still be closed after epoch mode is disabled, and
+        // is reused if epoch mode is enabled again
+        let (batch, _) = unlock_batches
+            .add_request(&mut deps.storage, &env.block, &owner, BASE_TOKEN_AMOUNT)
+            .unwrap();
+        unlock_batches.remove_epoch_duration(&mut deps.storage);
+        assert_eq!(unlock_batches.epoch_duration(&deps.storage).unwrap(), None);
+        assert_eq!(
+            unlock_batches.open_batch(&deps.storage).unwrap(),
+            Some(batch.clone())
+        );
+        unlock_batches
+            .set_epoch_duration(&mut deps.storage, EPOCH_DURATION * 2)
+            .unwrap();
+        let (reused_batch, _) = unlock_batches
+            .add_request(&mut deps.storage, &env.block, &owner, BASE_TOKEN_AMOUNT)
+            .unwrap();
+        assert_eq!(reused_batch.id, batch.id);
+        assert_eq!(reused_batch.epoch_end, batch.epoch_end);
+    }
+
+    #[test]
+    fn test_unlock_batches() {
+        let (mut deps, unlock_batches, mut env) = setup_unlock_batches();
+        let owner = Addr::unchecked(OWNER);
+        let new_owner = Addr::unchecked(NEW_OWNER);
+        unlock_batches
+            .set_epoch_duration(&mut deps.storage, EPOCH_DURATION)
+            .unwrap();
+
+        // Requests in the same epoch are added to the same batch, which ends at
+        // the end of the epoch
+        unlock_batches
+            .add_request(&mut deps.storage, &env.block, &owner, Uint128::new(30))
+            .unwrap();
+        let (batch, shares) = unlock_batches
+            .add_request(&mut deps.storage, &env.block, &new_owner, Uint128::new(70))
+            .unwrap();
+        let epoch_end = (env.block.time.seconds() / EPOCH_DURATION + 1) * EPOCH_DURATION;
+        assert_eq!(shares, Uint128::new(70));
+        assert_eq!(
+            batch,
+            UnlockBatch {
+                id: 0,
+                epoch_end: Timestamp::from_seconds(epoch_end),
+                total_shares: Uint128::new(100),
+                base_token_amount: Uint128::new(100),
+                status: UnlockBatchStatus::Open,
+            }
+        );
+
+        assert_eq!(
+            unlock_batches.open_batch(&deps.storage).unwrap(),
+            Some(batch.clone())
+        );
+
+        // The batch can't be closed before the end of the epoch
+        unlock_batches
+            .close_open_batch(&mut deps.storage, &env.block)
+            .unwrap_err();
+
+        env.block.time = Timestamp::from_seconds(epoch_end);
+        let closed = unlock_batches
+            .close_open_batch(&mut deps.storage, &env.block)
+            .unwrap();
+        assert_eq!(closed, batch);
+        assert_eq!(unlock_batches.open_batch(&deps.storage).unwrap(), None);
+        let err = unlock_batches
+            .close_open_batch(&mut deps.storage, &env.block)
+            .unwrap_err();
+        assert_eq!(err, StdError::generic_err("No open unlock batch"));
+
+        // Requests after closing the batch are added to a new batch
+        let (next_batch, _) = unlock_batches
+            .add_request(&mut deps.storage, &env.block, &new_owner, Uint128::new(50))
+            .unwrap();
+        assert_eq!(next_batch.id, 1);
+        assert_eq!(
+            next_batch.epoch_end,
+            Timestamp::from_seconds(epoch_end + EPOCH_DURATION)
+        );
+
+        // Shares can't be withdrawn before the batch is unlocking
+        unlock_batches
+            .withdraw(&mut deps.storage, &env.block, 0, &owner)
+            .unwrap_err();
+
+        let claim = Claim {
+            id: 7,
+            owner: Addr::unchecked(MOCK_CONTRACT_ADDR),
+            base_token_amount: Uint128::new(100),
+            release_at: Expiration::AtHeight(env.block.height + 10),
+        };
+        unlock_batches
+            .set_unlocking(&mut deps.storage, 0, &claim)
+            .unwrap();
+        unlock_batches
+            .set_unlocking(&mut deps.storage, 0, &claim)
+            .unwrap_err();
+
+        // Shares can't be withdrawn before the claim of the batch has matured
+        unlock_batches
+            .withdraw(&mut deps.storage, &env.block, 0, &owner)
+            .unwrap_err();
+
+        env.block.height += 10;
+        let err = unlock_batches
+            .withdraw(
+                &mut deps.storage,
+                &env.block,
+                0,
+                &Addr::unchecked(NOT_OWNER),
+            )
+            .unwrap_err();
+        assert_eq!(err, StdError::generic_err("No shares in unlock batch"));
+
+        // Owners withdraw their pro-rata share of the batch
+        assert_eq!(
+            unlock_batches
+                .withdraw(&mut deps.storage, &env.block, 0, &owner)
+                .unwrap(),
+            (Uint128::new(30), 7)
+        );
+        let batch = unlock_batches.query_batch(deps.as_ref(), 0).unwrap();
+        assert_eq!(batch.total_shares, Uint128::new(70));
+        assert_eq!(batch.base_token_amount, Uint128::new(70));
+        unlock_batches
+            .withdraw(&mut deps.storage, &env.block, 0, &owner)
+            .unwrap_err();
+
+        // The batch is deleted once all shares have been withdrawn
+        assert_eq!(
+            unlock_batches
+                .withdraw(&mut deps.storage, &env.block, 0, &new_owner)
+                .unwrap(),
+            (Uint128::new(70), 7)
+        );
+        unlock_batches.query_batch(deps.as_ref(), 0).unwrap_err();
+        assert_eq!(
+            unlock_batches
+                .query_batches(deps.as_ref(), None, None)
+                .unwrap(),
+            vec![next_batch]
+        );
+        assert_eq!(
+            unlock_batches
+                .query_shares_for_owner(deps.as_ref(), &new_owner, None, None)
+                .unwrap(),
+            vec![(1, Uint128::new(50))]
+        );
+    }
+
+    fn change_log_entries(start_id: u64, n: u32) -> Vec<ChangeLogEntry> {
+        let env = mock_env();
+        (start_id..(start_id + n as u64))
+            .map(|id| ChangeLogEntry {
+                id,
+                height: env.block.height + id,
+                time: env.block.time,
+                sender: Addr::unchecked(OWNER),
+                action: ChangeLogAction::DropAdminTransfer {
+                    proposal: Some(AdminTransferProposal {
+                        new_admin: Some(Addr::unchecked(NOT_OWNER)),
+                        expires: EXPIRATION,
+                    }),
+                },
+            })
+            .collect()
+    }
+
+    #[test_case(None, None => Ok(change_log_entries(0, DEFAULT_LIMIT)); "default pagination")]
+    #[test_case(None, Some(31) => Ok(change_log_entries(0, 31)); "pagination with limit")]
+    #[test_case(Some(1), None => Ok(change_log_entries(2, DEFAULT_LIMIT)); "pagination with start id")]
+    #[test_case(Some(95), Some(31) => Ok(change_log_entries(96, 4)); "pagination past last entry")]
+    fn test_change_log_query_entries(
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<ChangeLogEntry>> {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let change_log = ChangeLog::new(CHANGE_LOG, CHANGE_LOG_NEXT_ID);
+
+        // Record 100 entries, one per block
+        let start_height = env.block.height;
+        for i in 0..100u64 {
+            env.block.height = start_height + i;
+            let id = change_log
+                .record(
+                    &mut deps.storage,
+                    &env.block,
+                    &Addr::unchecked(OWNER),
+                    ChangeLogAction::DropAdminTransfer {
+                        proposal: Some(AdminTransferProposal {
+                            new_admin: Some(Addr::unchecked(NOT_OWNER)),
+                            expires: EXPIRATION,
+                        }),
+                    },
+                )
+                .unwrap();
+
+            // Assert that IDs are assigned sequentially
+            assert_eq!(id, i);
+        }
+
+        change_log.query_entries(deps.as_ref(), start_after, limit)
+    }
 }
@@ -0,0 +1,193 @@
+use cosmwasm_std::{
+    to_binary, Addr, Deps, DepsMut, Env, Event, Reply, Response, StdResult, SubMsg, WasmMsg,
+};
+#[cfg(feature = "lockup")]
+use cosmwasm_std::{CosmosMsg, Empty};
+#[cfg(feature = "lockup")]
+use cw721::OwnerOfResponse;
+#[cfg(feature = "lockup")]
+use cw721_base::MintMsg;
+use cw_utils::parse_reply_instantiate_data;
+
+use crate::error::ContractError;
+use crate::msg::{
+    UnlockingPositionMetadata, UnlockingPositionNftExtensionMsg,
+    UnlockingPositionNftInstantiateInfo,
+};
+#[cfg(feature = "lockup")]
+use crate::state::Claim;
+use crate::AutocompoundingVault;
+
+/// Reply ID for the instantiation of the unlocking position NFT contract.
+pub const UNLOCKING_POSITION_NFT_INSTANTIATE_REPLY_ID: u64 = 934;
+
+/// The execute message of the unlocking position NFT contract, used to mint,
+/// burn and update unlocking position NFTs.
+pub type UnlockingPositionNftExecuteMsg =
+    cw721_base::ExecuteMsg<UnlockingPositionMetadata, UnlockingPositionNftExtensionMsg>;
+
+/// Functions for the optional mode in which every unlocking position is
+/// represented by a cw721 token minted by the vault. In this mode the holder of
+/// the token is the owner of the unlocking position.
+impl<'a, S, P, V> AutocompoundingVault<'a, S, P, V> {
+    /// Returns a SubMsg that instantiates the unlocking position NFT contract
+    /// with the vault as minter. The reply must be handled with
+    /// [`Self::reply_instantiate_unlocking_position_nft`].
+    pub fn instantiate_unlocking_position_nft(
+        &self,
+        env: &Env,
+        info: UnlockingPositionNftInstantiateInfo,
+    ) -> StdResult<SubMsg> {
+        Ok(SubMsg::reply_on_success(
+            WasmMsg::Instantiate {
+                admin: info.admin,
+                code_id: info.code_id,
+                msg: to_binary(&cw721_base::InstantiateMsg {
+                    name: info.name,
+                    symbol: info.symbol,
+                    minter: env.contract.address.to_string(),
+                })?,
+                funds: vec![],
+                label: info.label,
+            },
+            UNLOCKING_POSITION_NFT_INSTANTIATE_REPLY_ID,
+        ))
+    }
+
+    /// Saves the address of the instantiated unlocking position NFT contract.
+    pub fn reply_instantiate_unlocking_position_nft(
+        &self,
+        deps: DepsMut,
+        reply: Reply,
+    ) -> Result<Response, ContractError> {
+        let res = parse_reply_instantiate_data(reply)?;
+        let nft_addr = deps.api.addr_validate(&res.contract_address)?;
+        self.unlocking_position_nft.save(deps.storage, &nft_addr)?;
+
+        let event = Event::new("apollo/vaults/unlocking_position_nft")
+            .add_attribute("action", "reply_instantiate_unlocking_position_nft")
+            .add_attribute("nft_contract", nft_addr);
+        Ok(Response::new().add_event(event))
+    }
+
+    /// Returns the address of the unlocking position NFT contract, or `None` if
+    /// unlocking positions are not represented as NFTs.
+    pub fn query_unlocking_position_nft(&self, deps: Deps) -> StdResult<Option<Addr>> {
+        self.unlocking_position_nft.may_load(deps.storage)
+    }
+}
+
+/// Functions used by the unlocking logic of lockup vaults to keep the NFTs in
+/// sync with the claims.
+#[cfg(feature = "lockup")]
+impl<'a, S, P, V> AutocompoundingVault<'a, S, P, V> {
+    /// Returns a message that mints an NFT representing `claim` to the owner
+    /// of the claim, or `None` if unlocking positions are not represented as
+    /// NFTs.
+    pub(crate) fn mint_unlocking_position_nft_msg(
+        &self,
+        deps: Deps,
+        claim: &Claim,
+    ) -> StdResult<Option<CosmosMsg>> {
+        self.unlocking_position_nft
+            .may_load(deps.storage)?
+            .map(|nft_addr| {
+                Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: nft_addr.to_string(),
+                    msg: to_binary(&UnlockingPositionNftExecuteMsg::Mint(MintMsg {
+                        token_id: claim.id.to_string(),
+                        owner: claim.owner.to_string(),
+                        token_uri: None,
+                        extension: UnlockingPositionMetadata {
+                            base_token_amount: claim.base_token_amount,
+                            release_at: claim.release_at,
+                        },
+                    }))?,
+                    funds: vec![],
+                }))
+            })
+            .transpose()
+    }
+
+    /// Returns a message that updates the metadata of the NFT representing
+    /// `claim` after base tokens were withdrawn from it, or `None` if
+    /// unlocking positions are not represented as NFTs.
+    #[cfg(feature = "force-unlock")]
+    pub(crate) fn update_unlocking_position_nft_msg(
+        &self,
+        deps: Deps,
+        claim: &Claim,
+    ) -> StdResult<Option<CosmosMsg>> {
+        self.unlocking_position_nft
+            .may_load(deps.storage)?
+            .map(|nft_addr| {
+                Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: nft_addr.to_string(),
+                    msg: to_binary(&UnlockingPositionNftExecuteMsg::Extension {
+                        msg: UnlockingPositionNftExtensionMsg::UpdateMetadata {
+                            token_id: claim.id.to_string(),
+                            metadata: UnlockingPositionMetadata {
+                                base_token_amount: claim.base_token_amount,
+                                release_at: claim.release_at,
+                            },
+                        },
+                    })?,
+                    funds: vec![],
+                }))
+            })
+            .transpose()
+    }
+
+    /// Returns a message that burns the NFT representing the claim with ID
+    /// `lockup_id`, or `None` if unlocking positions are not represented as
+    /// NFTs. The unlocking position NFT contract lets the vault as minter burn
+    /// any NFT, so the holder does not need to approve the vault.
+    pub(crate) fn burn_unlocking_position_nft_msg(
+        &self,
+        deps: Deps,
+        lockup_id: u64,
+    ) -> StdResult<Option<CosmosMsg>> {
+        self.unlocking_position_nft
+            .may_load(deps.storage)?
+            .map(|nft_addr| {
+                Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: nft_addr.to_string(),
+                    msg: to_binary(&UnlockingPositionNftExecuteMsg::Burn {
+                        token_id: lockup_id.to_string(),
+                    })?,
+                    funds: vec![],
+                }))
+            })
+            .transpose()
+    }
+
+    /// Sets the owner of the claim with ID `lockup_id` to the current holder
+    /// of its NFT, since the NFT may have been transferred since the claim was
+    /// created. Does nothing if unlocking positions are not represented as
+    /// NFTs.
+    pub(crate) fn sync_claim_owner_with_nft(&self, deps: DepsMut, lockup_id: u64) -> StdResult<()> {
+        if let Some(nft_addr) = self.unlocking_position_nft.may_load(deps.storage)? {
+            let res: OwnerOfResponse = deps.querier.query_wasm_smart(
+                nft_addr,
+                &cw721_base::QueryMsg::<Empty>::OwnerOf {
+                    token_id: lockup_id.to_string(),
+                    include_expired: None,
+                },
+            )?;
+            let owner = deps.api.addr_validate(&res.owner)?;
+            self.claims
+                .set_claim_owner(deps.storage, lockup_id, &owner)?;
+        }
+        Ok(())
+    }
+
+    /// Returns an error if unlocking positions are represented as NFTs, for
+    /// functionality that relies on the owner stored in the claims rather
+    /// than the current holder of the NFT.
+    pub(crate) fn assert_no_unlocking_position_nft(&self, deps: Deps) -> Result<(), ContractError> {
+        match self.unlocking_position_nft.may_load(deps.storage)? {
+            Some(_) => Err(ContractError::UnlockingPositionIsNft {}),
+            None => Ok(()),
+        }
+    }
+}